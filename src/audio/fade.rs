@@ -491,4 +491,42 @@ mod tests {
         assert_eq!(options.audio_bitrate, "320k");
         assert_eq!(options.sample_rate, 48000);
     }
+
+    #[test]
+    fn test_fade_in_command() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let options = FadeOptions::new().preserve_video(false).audio_codec("flac");
+        fade_in(
+            &ffmpeg,
+            "in.wav",
+            "out.flac",
+            2.5,
+            FadeType::Exponential,
+            &options,
+        )
+        .unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(
+            calls[0].option_value("-filter:a"),
+            Some("afade=t=in:st=0:d=2.5:curve=exp")
+        );
+        assert_eq!(calls[0].option_value("-c:a"), Some("flac"));
+        assert!(!calls[0].has_arg("-c:v"));
+        assert_eq!(calls[0].last_arg(), Some("out.flac"));
+
+        assert!(
+            fade_in(
+                &ffmpeg,
+                "in.wav",
+                "out.flac",
+                0.0,
+                FadeType::Linear,
+                &options
+            )
+            .is_err()
+        );
+        assert_eq!(executor.ffmpeg_calls().len(), 1);
+    }
 }
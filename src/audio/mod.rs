@@ -26,6 +26,7 @@ pub mod error;
 pub mod extractor;
 pub mod fade;
 pub mod replacer;
+pub mod silence;
 pub mod volume;
 
 /// Common audio processing constants and utilities.
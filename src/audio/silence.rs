@@ -0,0 +1,187 @@
+/// Silence detection.
+///
+/// This module finds the silent parts of an audio stream with `FFmpeg`'s
+/// `silencedetect` filter, e.g. to cut the pauses out of a recording.
+use std::path::Path;
+
+use crate::audio::error::{Error, Result};
+use crate::ffmpeg::FFmpeg;
+use crate::ffmpeg::analysis::{self, AnalysisScope, AnalyzedMedia};
+use crate::utility::time::{Duration, TimePosition};
+
+/// Options of silence detection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SilenceOptions {
+    /// Level below which audio counts as silent, in dB.
+    pub threshold_db: f64,
+    /// Shortest silence to report, in seconds.
+    pub min_duration: f64,
+    /// What part of the input to analyze.
+    pub scope: AnalysisScope,
+}
+
+impl Default for SilenceOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -30.0,
+            min_duration: 0.5,
+            scope: AnalysisScope::default(),
+        }
+    }
+}
+
+impl SilenceOptions {
+    /// Creates options with a threshold and a shortest silence.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold_db` - Level below which audio counts as silent, in dB
+    /// * `min_duration` - Shortest silence to report, in seconds
+    #[must_use]
+    pub fn new(threshold_db: f64, min_duration: f64) -> Self {
+        Self {
+            threshold_db,
+            min_duration,
+            ..Self::default()
+        }
+    }
+
+    /// Sets what part of the input to analyze.
+    #[must_use]
+    pub fn with_scope(mut self, scope: AnalysisScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Checks that the options are valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threshold is above 0 dB or the shortest
+    /// silence is not positive.
+    pub fn validate(&self) -> Result<()> {
+        if self.threshold_db > 0.0 {
+            return Err(Error::ProcessingError(format!(
+                "Silence threshold must not be above 0 dB: {}",
+                self.threshold_db
+            )));
+        }
+        if self.min_duration <= 0.0 {
+            return Err(Error::ProcessingError(format!(
+                "Shortest silence must be positive: {}",
+                self.min_duration
+            )));
+        }
+        Ok(())
+    }
+
+    /// Builds the detection filter.
+    fn filter(&self) -> String {
+        format!(
+            "silencedetect=noise={}dB:d={}",
+            self.threshold_db, self.min_duration
+        )
+    }
+}
+
+/// A silent part of an input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceInterval {
+    /// Start of the silence in the input.
+    pub start: TimePosition,
+    /// End of the silence in the input.
+    pub end: TimePosition,
+}
+
+impl SilenceInterval {
+    /// Gets the length of the silence.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Detects the silent parts of an audio stream.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` instance to use
+/// * `input` - Path to the input file
+/// * `options` - Threshold, shortest silence and scope of the detection
+///
+/// # Returns
+///
+/// The silences in time order. A silence that lasts until the end of the
+/// analyzed range ends there.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid or the file can't be
+/// processed.
+pub fn detect_silence<P: AsRef<Path>>(
+    ffmpeg: &FFmpeg,
+    input: P,
+    options: &SilenceOptions,
+) -> Result<Vec<SilenceInterval>> {
+    options.validate()?;
+    let mut log = analysis::SpanLog::new("silence_start:", "silence_end:");
+    analysis::run_pass(
+        ffmpeg,
+        input.as_ref(),
+        &options.scope,
+        AnalyzedMedia::Audio,
+        &options.filter(),
+        |line| log.feed(line),
+    )?;
+
+    Ok(log
+        .finish()
+        .into_iter()
+        .map(|(start, end)| SilenceInterval {
+            start: options.scope.input_time(start),
+            end: options.scope.input_time(end),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::ExecutionOutput;
+
+    #[test]
+    fn test_detect_silence() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "silencedetect",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[silencedetect @ 0x55] silence_start: -0.01\n\
+                 [silencedetect @ 0x55] silence_end: 1.2 | silence_duration: 1.21\n\
+                 out_time_us=2000000\n\
+                 [silencedetect @ 0x55] silence_start: 4.5\n\
+                 [silencedetect @ 0x55] silence_end: 5.25 | silence_duration: 0.75\n\
+                 [silencedetect @ 0x55] silence_start: 8\n\
+                 out_time_us=9500000\n",
+            ),
+        );
+        let input = std::env::current_exe().unwrap();
+        let options = SilenceOptions::new(-35.0, 0.5)
+            .with_scope(AnalysisScope::new().with_range(TimePosition::from_seconds(20.0), 10.0));
+
+        let silences = detect_silence(&ffmpeg, &input, &options).unwrap();
+        let spans: Vec<(f64, f64)> = silences
+            .iter()
+            .map(|s| (s.start.as_seconds(), s.end.as_seconds()))
+            .collect();
+        assert_eq!(spans, vec![(20.0, 21.2), (24.5, 25.25), (28.0, 29.5)]);
+
+        let call = &executor.ffmpeg_calls()[0];
+        assert_eq!(call.option_value("-map"), Some("0:a:0"));
+        assert_eq!(
+            call.option_value("-af"),
+            Some("silencedetect=noise=-35dB:d=0.5")
+        );
+        assert!(call.has_arg("-vn"));
+        assert!(SilenceOptions::new(3.0, 0.5).validate().is_err());
+    }
+}
@@ -283,4 +283,39 @@ mod tests {
         let adj = VolumeAdjustment::decibel(30.0);
         assert_eq!(adj.as_db(), 20.0); // Clamped to 20.0
     }
+
+    #[test]
+    fn test_adjust_volume_command() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        adjust_volume(
+            &ffmpeg,
+            "in.mp4",
+            "out.mp4",
+            VolumeAdjustment::decibel(-6.0),
+        )
+        .unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].option_value("-i"), Some("in.mp4"));
+        assert_eq!(calls[0].option_value("-filter:a"), Some("volume=-6dB"));
+        assert_eq!(calls[0].option_value("-c:v"), Some("copy"));
+        assert!(calls[0].has_arg("-y"));
+        assert_eq!(calls[0].last_arg(), Some("out.mp4"));
+    }
+
+    #[test]
+    fn test_adjust_volume_reports_ffmpeg_error() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "ffmpeg",
+            crate::ffmpeg::ExecutionOutput::failure(
+                1,
+                "Stream map '0:a' matches no streams.\nInvalid argument",
+            ),
+        );
+
+        let result = adjust_volume(&ffmpeg, "in.mp4", "out.mp4", VolumeAdjustment::linear(0.5));
+        assert!(result.is_err());
+    }
 }
@@ -5,7 +5,7 @@
 /// and coordination between various components of the application.
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use log::debug;
 
 use crate::core::console::ConsoleLogger;
@@ -76,6 +76,14 @@ pub enum Commands {
         /// End time in seconds
         #[arg(short, long)]
         end: f64,
+
+        /// Re-encode the whole range for a precise cut
+        #[arg(long, conflicts_with = "smart")]
+        recompress: bool,
+
+        /// Re-encode only the partial GOPs at the cut points and copy the rest
+        #[arg(long)]
+        smart: bool,
     },
 
     /// Displays information about a video file
@@ -94,6 +102,188 @@ pub enum Commands {
         /// Output file path
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Render settings
+        #[command(flatten)]
+        settings: Box<RenderArgs>,
+    },
+
+    /// Lists the built-in and user render presets
+    Presets,
+
+    /// Renders a single frame of a project to an image file
+    Still {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Time to render: seconds, HH:MM:SS[.mmm] or a HH:MM:SS:FF timecode
+        #[arg(short, long)]
+        time: String,
+
+        /// Output image path (the extension selects the format, e.g. png, jpg)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Frame width in pixels
+        #[arg(long, default_value_t = 1920)]
+        width: u32,
+
+        /// Frame height in pixels
+        #[arg(long, default_value_t = 1080)]
+        height: u32,
+
+        /// Frame rate used to read timecode frame numbers
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+    },
+
+    /// Generates thumbnails, a track strip or a contact sheet
+    Thumbnails {
+        /// Media file to generate thumbnails of
+        #[arg(short, long, conflicts_with = "project")]
+        input: Option<PathBuf>,
+
+        /// Project file; without --asset or --track the whole timeline is used
+        #[arg(short, long)]
+        project: Option<PathBuf>,
+
+        /// Asset ID in the project to generate thumbnails of
+        #[arg(long, requires = "project", conflicts_with = "track")]
+        asset: Option<String>,
+
+        /// Track index in the project to render a thumbnail strip of
+        #[arg(long, requires = "project")]
+        track: Option<usize>,
+
+        /// Output image path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Number of evenly spaced thumbnails (maximum with --scenes)
+        #[arg(long, default_value_t = 10)]
+        count: u32,
+
+        /// Place thumbnails at scene changes above this score (0.0-1.0)
+        #[arg(long)]
+        scenes: Option<f64>,
+
+        /// Thumbnail width in pixels
+        #[arg(long, default_value_t = 160)]
+        width: u32,
+
+        /// Thumbnail height in pixels
+        #[arg(long, default_value_t = 90)]
+        height: u32,
+
+        /// Number of thumbnails per contact sheet row
+        #[arg(long, default_value_t = 5)]
+        columns: u32,
+
+        /// Frame rate used for the timecode labels
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+    },
+
+    /// Detects scene changes and optionally splits clips or adds markers at them
+    DetectScenes {
+        /// Media file to analyze
+        #[arg(short, long, conflicts_with = "project")]
+        input: Option<PathBuf>,
+
+        /// Project file to analyze a track of
+        #[arg(short, long, requires = "track")]
+        project: Option<PathBuf>,
+
+        /// Track index in the project to analyze
+        #[arg(long, requires = "project")]
+        track: Option<usize>,
+
+        /// Split the clips (split) or add markers (markers) at the changes
+        #[arg(long, requires = "project")]
+        apply: Option<String>,
+
+        /// Smallest score of a scene change (0.0-1.0)
+        #[arg(long, default_value_t = 0.4)]
+        threshold: f64,
+
+        /// Detector to use (select or scdet)
+        #[arg(long, default_value = "select")]
+        detector: String,
+
+        /// Smallest time between two changes in seconds
+        #[arg(long, default_value_t = 0.5)]
+        min_gap: f64,
+
+        /// Frame rate used for the timecodes
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+    },
+
+    /// Checks a file or clip for black segments, frozen video and borders
+    Qc {
+        /// Media file to check
+        #[arg(short, long, conflicts_with = "project")]
+        input: Option<PathBuf>,
+
+        /// Project file containing the clip to check
+        #[arg(short, long, requires_all = ["track", "clip"])]
+        project: Option<PathBuf>,
+
+        /// Track index of the clip in the project
+        #[arg(long, requires = "project")]
+        track: Option<usize>,
+
+        /// Clip index in the track
+        #[arg(long, requires = "project")]
+        clip: Option<usize>,
+
+        /// Crop the clip to the detected picture area and save the project
+        #[arg(long, requires = "project")]
+        apply_crop: bool,
+
+        /// Checks to run (comma separated: black, freeze, crop)
+        #[arg(long, default_value = "black,freeze,crop")]
+        checks: String,
+
+        /// Shortest black segment to report in seconds
+        #[arg(long, default_value_t = 2.0)]
+        black_min: f64,
+
+        /// Shortest freeze to report in seconds
+        #[arg(long, default_value_t = 2.0)]
+        freeze_min: f64,
+
+        /// Brightness up to which borders count as black (0-255)
+        #[arg(long, default_value_t = 24)]
+        crop_limit: u32,
+
+        /// Report format (text or json)
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Write the report to a file instead of the console
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Frame rate used for the timecodes
+        #[arg(long, default_value_t = 30.0)]
+        fps: f64,
+    },
+
+    /// Imports a numbered image sequence into a project as one asset
+    ImportSequence {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Frame pattern, e.g. plates/shot_%05d.png
+        #[arg(long)]
+        pattern: PathBuf,
+
+        /// Frame rate of the sequence (fps)
+        #[arg(long, default_value_t = 24.0)]
+        fps: f64,
     },
 
     /// Undoes the last edit in a project
@@ -143,6 +333,230 @@ pub enum Commands {
         #[arg(long)]
         fps: Option<f64>,
     },
+
+    /// Shows, clears or prunes the render cache
+    Cache {
+        /// Cache operation to perform
+        #[clap(subcommand)]
+        action: CacheAction,
+
+        /// Cache directory (defaults to the render cache directory)
+        #[arg(long, global = true)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Manages the render queue for batch rendering
+    Queue {
+        /// Queue operation to perform
+        #[clap(subcommand)]
+        action: QueueAction,
+
+        /// Queue file (defaults to render_queue.json in the working directory)
+        #[arg(long, global = true)]
+        queue: Option<PathBuf>,
+    },
+}
+
+/// Operations on the render queue.
+#[derive(Subcommand)]
+pub enum QueueAction {
+    /// Adds a render job to the queue
+    Add {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Output file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Render settings
+        #[command(flatten)]
+        settings: Box<RenderArgs>,
+    },
+
+    /// Lists the jobs in the queue and their status
+    List,
+
+    /// Renders all pending jobs
+    Run {
+        /// Maximum number of jobs rendered at the same time
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+    },
+
+    /// Queues failed jobs to be rendered again
+    Retry {
+        /// ID (or ID prefix) of the job to retry; retries all failed jobs if omitted
+        id: Option<String>,
+    },
+}
+
+/// Render preset and settings overrides shared by render commands.
+#[derive(Args, Debug, Default)]
+pub struct RenderArgs {
+    /// Render preset to start from (see `edv presets`)
+    #[arg(long)]
+    preset: Option<String>,
+
+    /// Video width in pixels
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Video height in pixels
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Frame rate (fps)
+    #[arg(long)]
+    fps: Option<f64>,
+
+    /// Video codec (h264, h265, vp9, av1, prores, prores4444, dnxhr, ffv1, copy)
+    #[arg(long)]
+    video_codec: Option<String>,
+
+    /// Video quality (1-100)
+    #[arg(long)]
+    video_quality: Option<u32>,
+
+    /// Rate control: crf=N, cbr=KBPS, vbr=KBPS:MAX_KBPS, 2pass=KBPS or
+    /// size=BYTES (with optional K/M/G suffix) for a two-pass target size
+    #[arg(long)]
+    rate_control: Option<String>,
+
+    /// Audio codec (aac, opus, mp3, vorbis, pcm, flac, copy)
+    #[arg(long)]
+    audio_codec: Option<String>,
+
+    /// Audio quality (1-100)
+    #[arg(long)]
+    audio_quality: Option<u32>,
+
+    /// Container format (mp4, webm, mov, mkv, gif, mxf, wav, flac, mp3, m4a, hls, dash,
+    /// png-sequence, tiff-sequence)
+    #[arg(long)]
+    format: Option<String>,
+
+    /// Number of render threads
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Duration of the segments rendered in parallel (seconds, 0 renders in a
+    /// single pass; timelines of 10 minutes or more are segmented by default)
+    #[arg(long)]
+    segment_duration: Option<f64>,
+
+    /// Encoding ladder of HLS/DASH output, e.g. 1920x1080@5000,1280x720@2800
+    #[arg(long)]
+    ladder: Option<String>,
+
+    /// Media segment duration of HLS/DASH output (seconds)
+    #[arg(long)]
+    stream_segment: Option<f64>,
+
+    /// Number of the first frame of image sequence output
+    #[arg(long)]
+    start_number: Option<u32>,
+
+    /// Keep the alpha channel in image sequence output
+    #[arg(long)]
+    alpha: bool,
+
+    /// Render per-track audio stems with the output (with-mix) or instead of it (only)
+    #[arg(long)]
+    stems: Option<String>,
+
+    /// File format of the audio stems (wav, flac)
+    #[arg(long)]
+    stem_format: Option<String>,
+
+    /// Directory of the audio stems (default: <output name>_stems next to the output)
+    #[arg(long)]
+    stem_dir: Option<PathBuf>,
+
+    /// Tracks mixed into one stem, e.g. dialogue=0,2 (repeatable)
+    #[arg(long)]
+    stem_group: Vec<String>,
+
+    /// Probe the output after rendering and compare it with the settings:
+    /// report mismatches (report) or fail the render on them (strict)
+    #[arg(long)]
+    verify: Option<String>,
+}
+
+impl RenderArgs {
+    /// Converts the settings into command arguments.
+    ///
+    /// # Returns
+    ///
+    /// Option/value pairs for the options that are set.
+    fn to_args(&self) -> Vec<String> {
+        let options = [
+            ("--preset", self.preset.clone()),
+            ("--width", self.width.map(|v| v.to_string())),
+            ("--height", self.height.map(|v| v.to_string())),
+            ("--fps", self.fps.map(|v| v.to_string())),
+            ("--video-codec", self.video_codec.clone()),
+            ("--video-quality", self.video_quality.map(|v| v.to_string())),
+            ("--rate-control", self.rate_control.clone()),
+            ("--audio-codec", self.audio_codec.clone()),
+            ("--audio-quality", self.audio_quality.map(|v| v.to_string())),
+            ("--format", self.format.clone()),
+            ("--threads", self.threads.map(|v| v.to_string())),
+            (
+                "--segment-duration",
+                self.segment_duration.map(|v| v.to_string()),
+            ),
+            ("--ladder", self.ladder.clone()),
+            (
+                "--stream-segment",
+                self.stream_segment.map(|v| v.to_string()),
+            ),
+            ("--start-number", self.start_number.map(|v| v.to_string())),
+            ("--alpha", self.alpha.then(|| "true".to_string())),
+            ("--stems", self.stems.clone()),
+            ("--stem-format", self.stem_format.clone()),
+            (
+                "--stem-dir",
+                self.stem_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+            ),
+            ("--verify", self.verify.clone()),
+        ];
+
+        options
+            .into_iter()
+            .filter_map(|(option, value)| value.map(|value| [option.to_string(), value]))
+            .chain(
+                self.stem_group
+                    .iter()
+                    .map(|group| ["--stem-group".to_string(), group.clone()]),
+            )
+            .flatten()
+            .collect()
+    }
+}
+
+/// Operations on the render cache.
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Shows the number and size of the cached files
+    Stats,
+
+    /// Removes all cached files
+    Clear,
+
+    /// Removes damaged, expired and unreferenced files and enforces the size limit
+    Prune {
+        /// Removes entries that have not been used for this many days
+        #[arg(long)]
+        max_age_days: Option<u64>,
+
+        /// Evicts least recently used entries until the cache fits this size in megabytes
+        #[arg(long)]
+        max_size_mb: Option<u64>,
+    },
 }
 
 impl App {
@@ -201,6 +615,26 @@ impl App {
         self.command_registry
             .register(Box::new(commands::RenderCommand::new()))?;
 
+        // Register still command
+        self.command_registry
+            .register(Box::new(commands::StillCommand::new()))?;
+
+        // Register thumbnails command
+        self.command_registry
+            .register(Box::new(commands::ThumbnailsCommand::new()))?;
+
+        // Register detect scenes command
+        self.command_registry
+            .register(Box::new(commands::DetectScenesCommand::new()))?;
+
+        // Register quality check command
+        self.command_registry
+            .register(Box::new(commands::QcCommand::new()))?;
+
+        // Register import sequence command
+        self.command_registry
+            .register(Box::new(commands::ImportSequenceCommand::new()))?;
+
         // Register project undo command
         self.command_registry
             .register(Box::new(commands::ProjectUndoCommand::new()))?;
@@ -217,6 +651,18 @@ impl App {
         self.command_registry
             .register(Box::new(GuiPlayCommand::new()))?;
 
+        // Register presets command
+        self.command_registry
+            .register(Box::new(commands::PresetsCommand::new()))?;
+
+        // Register cache command
+        self.command_registry
+            .register(Box::new(commands::CacheCommand::new()))?;
+
+        // Register queue command
+        self.command_registry
+            .register(Box::new(commands::QueueCommand::new()))?;
+
         Ok(())
     }
 
@@ -240,6 +686,8 @@ impl App {
                 output,
                 start,
                 end,
+                recompress,
+                smart,
             } => {
                 self.logger.debug(&format!(
                     "Executing trim command: input={}, output={}, start={}, end={}",
@@ -256,8 +704,14 @@ impl App {
                         input.to_string_lossy().to_string(),
                         output.to_string_lossy().to_string(),
                     ];
-                    args.push(start.to_string());
-                    args.push(end.to_string());
+                    args.extend(["--start".to_string(), start.to_string()]);
+                    args.extend(["--end".to_string(), end.to_string()]);
+                    if recompress {
+                        args.push("--recompress".to_string());
+                    }
+                    if smart {
+                        args.push("--smart".to_string());
+                    }
 
                     // Execute the command with arguments and the already created context
                     trim_cmd.execute(&context, &args)?;
@@ -282,7 +736,11 @@ impl App {
                     return Err(super::Error::UnknownCommand("info".to_string()));
                 }
             }
-            Commands::Render { project, output } => {
+            Commands::Render {
+                project,
+                output,
+                settings,
+            } => {
                 self.logger.debug(&format!(
                     "Executing render command: project={}, output={}",
                     project.display(),
@@ -293,9 +751,12 @@ impl App {
                 if let Ok(render_cmd) = self.command_registry.get("render") {
                     // Build the arguments list
                     let mut args = vec![
+                        "--project".to_string(),
                         project.to_string_lossy().to_string(),
+                        "--output".to_string(),
                         output.to_string_lossy().to_string(),
                     ];
+                    args.extend(settings.to_args());
 
                     // Execute the command with arguments and the already created context
                     render_cmd.execute(&context, &args)?;
@@ -303,6 +764,202 @@ impl App {
                     return Err(super::Error::UnknownCommand("render".to_string()));
                 }
             }
+            Commands::Presets => {
+                self.logger.debug("Executing presets command");
+
+                // Get the PresetsCommand from the registry and execute it
+                if let Ok(presets_cmd) = self.command_registry.get("presets") {
+                    presets_cmd.execute(&context, &[])?;
+                } else {
+                    return Err(super::Error::UnknownCommand("presets".to_string()));
+                }
+            }
+            Commands::Still {
+                project,
+                time,
+                output,
+                width,
+                height,
+                fps,
+            } => {
+                self.logger.debug(&format!(
+                    "Executing still command: project={}, time={time}, output={}",
+                    project.display(),
+                    output.display()
+                ));
+
+                if let Ok(still_cmd) = self.command_registry.get("still") {
+                    let args = vec![
+                        project.to_string_lossy().to_string(),
+                        time,
+                        output.to_string_lossy().to_string(),
+                        width.to_string(),
+                        height.to_string(),
+                        fps.to_string(),
+                    ];
+
+                    still_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("still".to_string()));
+                }
+            }
+            Commands::Thumbnails {
+                input,
+                project,
+                asset,
+                track,
+                output,
+                count,
+                scenes,
+                width,
+                height,
+                columns,
+                fps,
+            } => {
+                self.logger.debug(&format!(
+                    "Executing thumbnails command: output={}",
+                    output.display()
+                ));
+
+                if let Ok(thumbnails_cmd) = self.command_registry.get("thumbnails") {
+                    let options = [
+                        ("--input", input.map(|v| v.to_string_lossy().to_string())),
+                        (
+                            "--project",
+                            project.map(|v| v.to_string_lossy().to_string()),
+                        ),
+                        ("--asset", asset),
+                        ("--track", track.map(|v| v.to_string())),
+                        ("--output", Some(output.to_string_lossy().to_string())),
+                        ("--count", Some(count.to_string())),
+                        ("--scenes", scenes.map(|v| v.to_string())),
+                        ("--width", Some(width.to_string())),
+                        ("--height", Some(height.to_string())),
+                        ("--columns", Some(columns.to_string())),
+                        ("--fps", Some(fps.to_string())),
+                    ];
+                    let args: Vec<String> = options
+                        .into_iter()
+                        .filter_map(|(option, value)| {
+                            value.map(|value| [option.to_string(), value])
+                        })
+                        .flatten()
+                        .collect();
+
+                    thumbnails_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("thumbnails".to_string()));
+                }
+            }
+            Commands::DetectScenes {
+                input,
+                project,
+                track,
+                apply,
+                threshold,
+                detector,
+                min_gap,
+                fps,
+            } => {
+                self.logger.debug("Executing detect scenes command");
+
+                if let Ok(detect_cmd) = self.command_registry.get("detect-scenes") {
+                    let options = [
+                        ("--input", input.map(|v| v.to_string_lossy().to_string())),
+                        (
+                            "--project",
+                            project.map(|v| v.to_string_lossy().to_string()),
+                        ),
+                        ("--track", track.map(|v| v.to_string())),
+                        ("--apply", apply),
+                        ("--threshold", Some(threshold.to_string())),
+                        ("--detector", Some(detector)),
+                        ("--min-gap", Some(min_gap.to_string())),
+                        ("--fps", Some(fps.to_string())),
+                    ];
+                    let args: Vec<String> = options
+                        .into_iter()
+                        .filter_map(|(option, value)| {
+                            value.map(|value| [option.to_string(), value])
+                        })
+                        .flatten()
+                        .collect();
+
+                    detect_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("detect-scenes".to_string()));
+                }
+            }
+            Commands::Qc {
+                input,
+                project,
+                track,
+                clip,
+                apply_crop,
+                checks,
+                black_min,
+                freeze_min,
+                crop_limit,
+                format,
+                output,
+                fps,
+            } => {
+                self.logger.debug("Executing qc command");
+
+                if let Ok(qc_cmd) = self.command_registry.get("qc") {
+                    let options = [
+                        ("--input", input.map(|v| v.to_string_lossy().to_string())),
+                        (
+                            "--project",
+                            project.map(|v| v.to_string_lossy().to_string()),
+                        ),
+                        ("--track", track.map(|v| v.to_string())),
+                        ("--clip", clip.map(|v| v.to_string())),
+                        ("--apply-crop", Some(apply_crop.to_string())),
+                        ("--checks", Some(checks)),
+                        ("--black-min", Some(black_min.to_string())),
+                        ("--freeze-min", Some(freeze_min.to_string())),
+                        ("--crop-limit", Some(crop_limit.to_string())),
+                        ("--format", Some(format)),
+                        ("--output", output.map(|v| v.to_string_lossy().to_string())),
+                        ("--fps", Some(fps.to_string())),
+                    ];
+                    let args: Vec<String> = options
+                        .into_iter()
+                        .filter_map(|(option, value)| {
+                            value.map(|value| [option.to_string(), value])
+                        })
+                        .flatten()
+                        .collect();
+
+                    qc_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("qc".to_string()));
+                }
+            }
+            Commands::ImportSequence {
+                project,
+                pattern,
+                fps,
+            } => {
+                self.logger.debug(&format!(
+                    "Executing import sequence command: project={}, pattern={}",
+                    project.display(),
+                    pattern.display()
+                ));
+
+                if let Ok(import_cmd) = self.command_registry.get("import-sequence") {
+                    let args = vec![
+                        project.to_string_lossy().to_string(),
+                        pattern.to_string_lossy().to_string(),
+                        fps.to_string(),
+                    ];
+
+                    import_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("import-sequence".to_string()));
+                }
+            }
             Commands::ProjectUndo { project } => {
                 self.logger.debug(&format!(
                     "Executing project undo command: project={}",
@@ -406,6 +1063,96 @@ impl App {
                     return Err(super::Error::UnknownCommand("gui-play".to_string()));
                 }
             }
+            Commands::Cache { action, dir } => {
+                self.logger.debug(&format!(
+                    "Executing cache command: dir={:?}",
+                    dir.as_ref().map(|d| d.display().to_string())
+                ));
+
+                // Get the CacheCommand from the registry and execute it
+                if let Ok(cache_cmd) = self.command_registry.get("cache") {
+                    // Build the arguments list
+                    let mut args = Vec::new();
+
+                    match action {
+                        CacheAction::Stats => args.push("stats".to_string()),
+                        CacheAction::Clear => args.push("clear".to_string()),
+                        CacheAction::Prune {
+                            max_age_days,
+                            max_size_mb,
+                        } => {
+                            args.push("prune".to_string());
+
+                            if let Some(days) = max_age_days {
+                                args.push("--max-age-days".to_string());
+                                args.push(days.to_string());
+                            }
+
+                            if let Some(megabytes) = max_size_mb {
+                                args.push("--max-size-mb".to_string());
+                                args.push(megabytes.to_string());
+                            }
+                        }
+                    }
+
+                    if let Some(dir) = dir {
+                        args.push("--dir".to_string());
+                        args.push(dir.to_string_lossy().to_string());
+                    }
+
+                    // Execute the command with arguments and the already created context
+                    cache_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("cache".to_string()));
+                }
+            }
+            Commands::Queue { action, queue } => {
+                self.logger.debug(&format!(
+                    "Executing queue command: queue={:?}",
+                    queue.as_ref().map(|q| q.display().to_string())
+                ));
+
+                // Get the QueueCommand from the registry and execute it
+                if let Ok(queue_cmd) = self.command_registry.get("queue") {
+                    // Build the arguments list
+                    let mut args = Vec::new();
+
+                    match action {
+                        QueueAction::Add {
+                            project,
+                            output,
+                            settings,
+                        } => {
+                            args.push("add".to_string());
+                            args.push("--project".to_string());
+                            args.push(project.to_string_lossy().to_string());
+                            args.push("--output".to_string());
+                            args.push(output.to_string_lossy().to_string());
+                            args.extend(settings.to_args());
+                        }
+                        QueueAction::List => args.push("list".to_string()),
+                        QueueAction::Run { concurrency } => {
+                            args.push("run".to_string());
+                            args.push("--concurrency".to_string());
+                            args.push(concurrency.to_string());
+                        }
+                        QueueAction::Retry { id } => {
+                            args.push("retry".to_string());
+                            args.extend(id);
+                        }
+                    }
+
+                    if let Some(queue) = queue {
+                        args.push("--queue".to_string());
+                        args.push(queue.to_string_lossy().to_string());
+                    }
+
+                    // Execute the command with arguments and the already created context
+                    queue_cmd.execute(&context, &args)?;
+                } else {
+                    return Err(super::Error::UnknownCommand("queue".to_string()));
+                }
+            }
         }
 
         Ok(())
@@ -1,6 +1,9 @@
 use crate::cli::output::{OutputFormatter, ProgressReporter};
 use crate::cli::utils::TimePosition;
-use crate::ffmpeg::{FFmpeg, MediaInfo};
+use crate::ffmpeg::{
+    BlackOptions, CropOptions, FFmpeg, FreezeOptions, MediaInfo, QcOptions, QcReport,
+    SceneDetector, SceneOptions, TimeSpan,
+};
 use chrono::{DateTime, Utc};
 use mime_guess::MimeGuess;
 /// Command definitions and registry for the CLI application.
@@ -12,10 +15,16 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 use crate::core::Context;
+use crate::project::rendering::{
+    PresetRegistry, RenderCache, RenderConfig, RenderError, RenderQueue, RenderSettings,
+    StreamingOptions, ThumbnailOptions, ThumbnailSpacing, render_project, thumbnails,
+};
+use crate::project::{AssetId, AssetMetadata, AssetReference, Project};
+use crate::utility::time::Duration;
 
 use super::{Error, Result};
 
@@ -165,6 +174,210 @@ impl CommandRegistry {
     }
 }
 
+/// Timeline length in seconds from which segmented rendering is enabled by default.
+const AUTO_SEGMENT_THRESHOLD_SECONDS: f64 = 600.0;
+
+/// Render options shared by the render and queue commands.
+#[derive(Debug, Default)]
+struct RenderOptions {
+    /// Project file to render.
+    project: Option<PathBuf>,
+    /// Output file path.
+    output: Option<PathBuf>,
+    /// Name of the render preset to start from.
+    preset: Option<String>,
+    /// Settings that override the preset.
+    overrides: RenderSettings,
+}
+
+impl RenderOptions {
+    /// Parses render options from command arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Option/value pairs
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is unknown or its value is invalid.
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let overrides = &mut options.overrides;
+
+        let mut i = 0;
+        while i < args.len() {
+            let option = args[i].as_str();
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))?;
+
+            match option {
+                "--project" => options.project = Some(PathBuf::from(value)),
+                "--output" => options.output = Some(PathBuf::from(value)),
+                "--preset" => options.preset = Some(value.clone()),
+                "--width" => overrides.width = Some(Self::parse_value(option, value)?),
+                "--height" => overrides.height = Some(Self::parse_value(option, value)?),
+                "--fps" => overrides.frame_rate = Some(Self::parse_value(option, value)?),
+                "--video-codec" => overrides.video_codec = Some(Self::parse_value(option, value)?),
+                "--video-quality" => {
+                    overrides.video_quality = Some(Self::parse_value(option, value)?);
+                }
+                "--rate-control" => {
+                    overrides.rate_control = Some(Self::parse_value(option, value)?);
+                }
+                "--audio-codec" => overrides.audio_codec = Some(Self::parse_value(option, value)?),
+                "--audio-quality" => {
+                    overrides.audio_quality = Some(Self::parse_value(option, value)?);
+                }
+                "--format" => overrides.format = Some(Self::parse_value(option, value)?),
+                "--threads" => overrides.threads = Some(Self::parse_value(option, value)?),
+                "--ladder" => {
+                    overrides.ladder = Some(
+                        StreamingOptions::parse_ladder(value).map_err(Error::InvalidArgument)?,
+                    );
+                }
+                "--segment-duration" => {
+                    let seconds: f64 = Self::parse_value(option, value)?;
+                    overrides.segment_duration = Some(Duration::from_seconds(seconds));
+                }
+                "--stream-segment" => {
+                    let seconds: f64 = Self::parse_value(option, value)?;
+                    overrides.stream_segment_duration = Some(Duration::from_seconds(seconds));
+                }
+                "--start-number" => {
+                    overrides.start_number = Some(Self::parse_value(option, value)?);
+                }
+                "--alpha" => overrides.alpha = Some(Self::parse_value(option, value)?),
+                "--stems" => overrides.stems = Some(Self::parse_value(option, value)?),
+                "--stem-format" => {
+                    overrides.stem_format = Some(Self::parse_value(option, value)?);
+                }
+                "--stem-dir" => overrides.stem_directory = Some(PathBuf::from(value)),
+                "--verify" => {
+                    let (verify, fail_on_mismatch) = match value.as_str() {
+                        "off" => (false, false),
+                        "report" => (true, false),
+                        "strict" => (true, true),
+                        _ => {
+                            return Err(Error::InvalidArgument(format!(
+                                "Invalid value for {option}: {value} (expected off, report or strict)"
+                            )));
+                        }
+                    };
+                    overrides.verify = Some(verify);
+                    overrides.fail_on_mismatch = Some(fail_on_mismatch);
+                }
+                "--stem-group" => {
+                    let group = value.parse().map_err(Error::InvalidArgument)?;
+                    overrides
+                        .stem_groups
+                        .get_or_insert_with(Vec::new)
+                        .push(group);
+                }
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown argument: {option}"
+                    )));
+                }
+            }
+            i += 2;
+        }
+
+        Ok(options)
+    }
+
+    /// Parses the value of an option.
+    fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+
+    /// Gets the project file, checking that it exists.
+    fn project(&self) -> Result<PathBuf> {
+        let project = self
+            .project
+            .clone()
+            .ok_or_else(|| Error::MissingArgument("--project".to_string()))?;
+        if !project.exists() {
+            return Err(Error::InvalidPath(format!(
+                "Project file not found: {}",
+                project.display()
+            )));
+        }
+        Ok(project)
+    }
+
+    /// Builds the rendering configuration.
+    ///
+    /// The preset settings are applied first and the overrides on top. Without
+    /// a preset the overrides are applied to the default configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The execution context
+    /// * `timeline_duration` - Duration of the timeline to render
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output is missing, the preset cannot be found,
+    /// or the resulting configuration is invalid.
+    fn to_config(&self, context: &Context, timeline_duration: Duration) -> Result<RenderConfig> {
+        let output = self
+            .output
+            .clone()
+            .ok_or_else(|| Error::MissingArgument("--output".to_string()))?;
+
+        let (mut config, segment_duration) = match &self.preset {
+            Some(name) => {
+                let registry = PresetRegistry::load(&context.config.config_dir)
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                let preset = registry
+                    .get(name)
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                let config = preset
+                    .to_config(output, &self.overrides)
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                let segment_duration = self
+                    .overrides
+                    .segment_duration
+                    .or(preset.settings.segment_duration);
+                (config, segment_duration)
+            }
+            None => {
+                let mut config = RenderConfig::new(output);
+                self.overrides.apply(&mut config);
+                config.validate().map_err(Error::RenderError)?;
+                (config, self.overrides.segment_duration)
+            }
+        };
+
+        if segment_duration.is_none() {
+            Self::segment_long_timeline(&mut config, timeline_duration);
+        }
+
+        Ok(config)
+    }
+
+    /// Enables segmented rendering for long timelines.
+    ///
+    /// Timelines of at least `AUTO_SEGMENT_THRESHOLD_SECONDS` are split into segments
+    /// of the default duration. Two-pass outputs are left in a single pass,
+    /// since their bitrate is planned over the whole output.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration without an explicit segment duration
+    /// * `timeline_duration` - Duration of the timeline to render
+    fn segment_long_timeline(config: &mut RenderConfig, timeline_duration: Duration) {
+        if timeline_duration.as_seconds() >= AUTO_SEGMENT_THRESHOLD_SECONDS
+            && !config.rate_control.is_two_pass()
+        {
+            config.parallel_segments = true;
+        }
+    }
+}
+
 /// Renders a project to a video file.
 #[derive(Debug)]
 pub struct RenderCommand;
@@ -187,16 +400,96 @@ impl Command for RenderCommand {
     }
 
     fn usage(&self) -> &str {
-        "render --project <project_file> --output <output_file> [options]"
+        "render --project <project_file> --output <output_file> [--preset <name>] [--width <px>] [--height <px>] [--fps <fps>] [--video-codec <codec>] [--video-quality <1-100>] [--rate-control <crf=N|cbr=KBPS|vbr=KBPS:MAX|2pass=KBPS|size=BYTES[KMG]>] [--audio-codec <codec>] [--audio-quality <1-100>] [--format <format>] [--threads <n>] [--segment-duration <seconds>] [--ladder <WxH@KBPS,...>] [--stream-segment <seconds>] [--start-number <n>] [--alpha <true|false>] [--stems <with-mix|only>] [--stem-format <wav|flac>] [--stem-dir <dir>] [--stem-group <name=track,...>]... [--verify <off|report|strict>]"
     }
 
     fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
-        // For now, return a simple not implemented error
-        // The actual implementation will be added in a separate PR
-        context.logger.info("Render command received");
-        context.logger.info(&format!("Args: {:?}", args));
+        let options = RenderOptions::parse(args)?;
+        let project_path = options.project()?;
+
+        let project = Project::load(&project_path).map_err(|e| {
+            Error::ProjectError(format!(
+                "Failed to load project {}: {e}",
+                project_path.display()
+            ))
+        })?;
+        let config = options.to_config(context, project.timeline.duration())?;
+
+        context.logger.info(&format!(
+            "Rendering {} -> {} ({}x{} @ {} fps)",
+            project_path.display(),
+            config.output_path.display(),
+            config.width,
+            config.height,
+            config.frame_rate
+        ));
+
+        let result =
+            render_project(project, config).map_err(|e| Error::RenderError(e.to_string()))?;
+
+        context.logger.info(&format!(
+            "Rendered {} frames to {} in {:.1}s",
+            result.total_frames,
+            result.output_path.display(),
+            result.render_time.as_secs_f64()
+        ));
+        for stem in &result.stems {
+            context.logger.info(&format!("Stem: {}", stem.display()));
+        }
+        if let Some(report) = &result.verification {
+            if report.passed() {
+                context.logger.info(&format!("Verified {report}"));
+            } else {
+                context
+                    .logger
+                    .warning(&format!("Verification mismatch in {report}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the available render presets.
+#[derive(Debug)]
+pub struct PresetsCommand;
+
+impl PresetsCommand {
+    /// Creates a new presets command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Command for PresetsCommand {
+    fn name(&self) -> &str {
+        "presets"
+    }
+
+    fn description(&self) -> &str {
+        "Lists the built-in and user render presets"
+    }
+
+    fn usage(&self) -> &str {
+        "presets"
+    }
+
+    fn execute(&self, context: &Context, _args: &[String]) -> Result<()> {
+        let registry = PresetRegistry::load(&context.config.config_dir)
+            .map_err(|e| Error::RenderError(e.to_string()))?;
+
+        for preset in registry.presets() {
+            let source = preset
+                .source
+                .as_ref()
+                .map_or_else(|| "built-in".to_string(), |path| path.display().to_string());
+            context.logger.info(&format!(
+                "{:<24} {}  [{source}]",
+                preset.name, preset.description
+            ));
+        }
 
-        // Return success for now - this is just a stub until fully implemented
         Ok(())
     }
 }
@@ -586,8 +879,10 @@ fn parse_frame_rate(frame_rate: &str) -> Result<(u64, u64)> {
 /// Command for trimming a video file.
 ///
 /// This command trims a video file to a specified start and end time,
-/// creating a new file with the trimmed content. It supports both
-/// stream copy (fast) and re-encoding (higher quality for precise cuts).
+/// creating a new file with the trimmed content. It supports stream copy
+/// (fast, but cuts snap to keyframes), re-encoding (precise, but slow) and
+/// smart cuts, which re-encode only the partial groups of pictures at the
+/// cut points and copy the rest.
 #[derive(Debug)]
 pub struct TrimCommand;
 
@@ -689,7 +984,7 @@ impl Command for TrimCommand {
     }
 
     fn usage(&self) -> &str {
-        "trim --input <input_file> --output <output_file> [--start <time>] [--end <time>] [--recompress]"
+        "trim --input <input_file> --output <output_file> [--start <time>] [--end <time>] [--recompress | --smart]"
     }
 
     fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
@@ -706,6 +1001,7 @@ impl Command for TrimCommand {
         let mut start_time = None;
         let mut end_time = None;
         let mut recompress = false;
+        let mut smart = false;
         let mut show_frames = true; // デフォルトでフレーム表示を有効
 
         let mut i = 2;
@@ -733,6 +1029,10 @@ impl Command for TrimCommand {
                     recompress = true;
                     i += 1;
                 }
+                "--smart" => {
+                    smart = true;
+                    i += 1;
+                }
                 "--no-frames" => {
                     show_frames = false;
                     i += 1;
@@ -746,6 +1046,12 @@ impl Command for TrimCommand {
             }
         }
 
+        if recompress && smart {
+            return Err(Error::InvalidArgument(
+                "--recompress and --smart cannot be used together".to_string(),
+            ));
+        }
+
         // Validate input file
         self.check_input_file(input_file)?;
 
@@ -758,7 +1064,7 @@ impl Command for TrimCommand {
             .info(&format!("Analyzing media file: {input_file}"));
         let media_info = ffmpeg
             .get_media_info(input_file)
-            .map_err(|e| Error::FFmpegError(format!("Failed to get media info: {e}")))?;
+            .map_err(|e| Error::ffmpeg("Failed to get media info", &e))?;
 
         // Get duration from format section
         let duration_str = media_info.format.duration.as_ref().ok_or_else(|| {
@@ -780,6 +1086,22 @@ impl Command for TrimCommand {
             format_duration(end_pos.as_seconds() - start_pos.as_seconds())
         ));
 
+        if smart {
+            // エッジの GOP だけを再エンコードし、中間はコピーする
+            let plan = ffmpeg
+                .smart_trim(input_file, output_file, start_pos, end_pos)
+                .map_err(|e| Error::ffmpeg("Smart trim failed", &e))?;
+            context.logger.info(&format!(
+                "Re-encoded {:.2}s at the cut points and copied {:.2}s",
+                plan.encoded_duration(),
+                plan.copied_duration()
+            ));
+            context
+                .logger
+                .info(&format!("Trimmed video saved to: {output_file}"));
+            return Ok(());
+        }
+
         let mut ffmpeg_cmd = ffmpeg.command();
 
         // Add input options
@@ -812,7 +1134,7 @@ impl Command for TrimCommand {
                     progress.update(time_pos as usize);
                 }
             })
-            .map_err(|e| Error::FFmpegError(format!("FFmpeg execution failed: {e}")))?;
+            .map_err(|e| Error::ffmpeg("FFmpeg execution failed", &e))?;
 
         progress.complete();
         context
@@ -865,6 +1187,695 @@ fn extract_time_from_progress(line: &str) -> Option<f64> {
     None
 }
 
+/// Renders a single frame of a project to an image file.
+#[derive(Debug)]
+pub struct StillCommand;
+
+impl StillCommand {
+    /// Creates a new still command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Command for StillCommand {
+    fn name(&self) -> &str {
+        "still"
+    }
+
+    fn description(&self) -> &str {
+        "Renders a single frame of a project to an image file"
+    }
+
+    fn usage(&self) -> &str {
+        "still <project_file> <time> <output_image> [width] [height] [fps]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let [project_path, time, output, rest @ ..] = args else {
+            return Err(Error::MissingArgument(
+                "Project file, time and output image".to_string(),
+            ));
+        };
+        let parse_number = |index: usize, name: &str, default: f64| -> Result<f64> {
+            rest.get(index).map_or(Ok(default), |value| {
+                value
+                    .parse()
+                    .map_err(|_| Error::InvalidArgument(format!("Invalid {name}: {value}")))
+            })
+        };
+        let width = parse_number(0, "width", 1920.0)? as u32;
+        let height = parse_number(1, "height", 1080.0)? as u32;
+        let fps = parse_number(2, "frame rate", 30.0)?;
+
+        let time = crate::utility::time::TimePosition::parse_timecode(time, fps)
+            .map_err(Error::InvalidArgument)?;
+        let project = crate::project::Project::load(Path::new(project_path))
+            .map_err(|e| Error::ProjectError(format!("Failed to load project: {e}")))?;
+
+        context.logger.info(&format!(
+            "Rendering frame at {} ({width}x{height})",
+            time.to_timecode(fps)
+        ));
+        let frame = project
+            .render_frame(time, (width, height))
+            .map_err(|e| Error::RenderError(format!("Failed to render frame: {e}")))?;
+
+        frame
+            .save(output)
+            .map_err(|e| Error::CommandExecution(format!("Failed to write {output}: {e}")))?;
+        context.logger.info(&format!("Frame written to {output}"));
+        Ok(())
+    }
+}
+
+/// Generates thumbnails, a track strip or a contact sheet.
+#[derive(Debug)]
+pub struct ThumbnailsCommand;
+
+impl ThumbnailsCommand {
+    /// Creates a new thumbnails command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses the value of an option.
+    fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+}
+
+impl Command for ThumbnailsCommand {
+    fn name(&self) -> &str {
+        "thumbnails"
+    }
+
+    fn description(&self) -> &str {
+        "Generates thumbnails, a track strip or a contact sheet"
+    }
+
+    fn usage(&self) -> &str {
+        "thumbnails (--input <file> | --project <project_file> [--asset <id> | --track <index>]) --output <image> [--count <n>] [--scenes <0.0-1.0>] [--width <px>] [--height <px>] [--columns <n>] [--fps <fps>]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let mut input = None;
+        let mut project_path = None;
+        let mut asset_id = None;
+        let mut track_index = None;
+        let mut output = None;
+        let mut count = 10;
+        let mut scenes = None;
+        let mut width = 160;
+        let mut height = 90;
+        let mut columns = 5;
+        let mut fps = 30.0;
+
+        for pair in args.chunks(2) {
+            let option = pair[0].as_str();
+            let value = pair
+                .get(1)
+                .ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))?;
+            match option {
+                "--input" => input = Some(PathBuf::from(value)),
+                "--project" => project_path = Some(PathBuf::from(value)),
+                "--asset" => asset_id = Some(Self::parse_value::<AssetId>(option, value)?),
+                "--track" => track_index = Some(Self::parse_value::<usize>(option, value)?),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--count" => count = Self::parse_value(option, value)?,
+                "--scenes" => scenes = Some(Self::parse_value(option, value)?),
+                "--width" => width = Self::parse_value(option, value)?,
+                "--height" => height = Self::parse_value(option, value)?,
+                "--columns" => columns = Self::parse_value(option, value)?,
+                "--fps" => fps = Self::parse_value(option, value)?,
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown argument: {option}"
+                    )));
+                }
+            }
+        }
+        let output = output.ok_or_else(|| Error::MissingArgument("--output".to_string()))?;
+
+        let spacing = match scenes {
+            Some(threshold) => ThumbnailSpacing::Scenes {
+                threshold,
+                max_count: count,
+            },
+            None => ThumbnailSpacing::Even(count),
+        };
+        let options = ThumbnailOptions::default()
+            .with_size(width, height)
+            .with_spacing(spacing);
+        let render_error = |e: RenderError| Error::RenderError(e.to_string());
+
+        let image = if let Some(input) = input {
+            // 単体のファイルはプロジェクトに属さないため、キャッシュしない
+            let ffmpeg = FFmpeg::detect().map_err(|e| Error::FFmpegError(e.to_string()))?;
+            let asset = AssetReference {
+                id: AssetId::new(),
+                path: input,
+                metadata: AssetMetadata {
+                    duration: None,
+                    dimensions: None,
+                    asset_type: "video".to_string(),
+                    extra: HashMap::new(),
+                },
+            };
+            let thumbnails = thumbnails::asset_thumbnails(&ffmpeg, &asset, &options, None)
+                .map_err(render_error)?;
+            thumbnails::contact_sheet(&thumbnails, columns, fps)
+        } else {
+            let project_path = project_path
+                .ok_or_else(|| Error::MissingArgument("--input or --project".to_string()))?;
+            let project = Project::load(&project_path)
+                .map_err(|e| Error::ProjectError(format!("Failed to load project: {e}")))?;
+            let mut cache =
+                RenderCache::new(RenderCache::default_dir(), None).map_err(render_error)?;
+
+            if let Some(asset_id) = asset_id {
+                let asset = project.get_asset(asset_id).ok_or_else(|| {
+                    Error::InvalidArgument(format!("Asset not found: {asset_id}"))
+                })?;
+                let ffmpeg = FFmpeg::detect().map_err(|e| Error::FFmpegError(e.to_string()))?;
+                let thumbnails =
+                    thumbnails::asset_thumbnails(&ffmpeg, asset, &options, Some(&mut cache))
+                        .map_err(render_error)?;
+                thumbnails::contact_sheet(&thumbnails, columns, fps)
+            } else if let Some(track_index) = track_index {
+                let track = project
+                    .timeline
+                    .get_tracks()
+                    .get(track_index)
+                    .ok_or_else(|| {
+                        Error::InvalidArgument(format!("Track index out of range: {track_index}"))
+                    })?;
+                options.validate().map_err(render_error)?;
+                thumbnails::track_strip(
+                    &project,
+                    track.id(),
+                    count,
+                    (width, height),
+                    Some(&mut cache),
+                )
+                .map_err(render_error)?
+            } else {
+                options.validate().map_err(render_error)?;
+                let thumbnails = thumbnails::timeline_thumbnails(
+                    &project,
+                    count,
+                    (width, height),
+                    Some(&mut cache),
+                )
+                .map_err(render_error)?;
+                thumbnails::contact_sheet(&thumbnails, columns, fps)
+            }
+        };
+
+        image.save(&output).map_err(|e| {
+            Error::CommandExecution(format!("Failed to write {}: {e}", output.display()))
+        })?;
+        context
+            .logger
+            .info(&format!("Thumbnails written to {}", output.display()));
+        Ok(())
+    }
+}
+
+/// Detects scene changes of a file or of the clips on a project track.
+#[derive(Debug)]
+pub struct DetectScenesCommand;
+
+impl DetectScenesCommand {
+    /// Creates a new detect scenes command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses the value of an option.
+    fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+}
+
+impl Command for DetectScenesCommand {
+    fn name(&self) -> &str {
+        "detect-scenes"
+    }
+
+    fn description(&self) -> &str {
+        "Detects scene changes and optionally splits clips or adds markers at them"
+    }
+
+    fn usage(&self) -> &str {
+        "detect-scenes (--input <file> | --project <project_file> --track <index> [--apply <split|markers>]) [--threshold <0.0-1.0>] [--detector <select|scdet>] [--min-gap <seconds>] [--fps <fps>]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let mut input = None;
+        let mut project_path = None;
+        let mut track_index = None;
+        let mut apply = None;
+        let mut options = SceneOptions::default();
+        let mut fps = 30.0;
+
+        for pair in args.chunks(2) {
+            let option = pair[0].as_str();
+            let value = pair
+                .get(1)
+                .ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))?;
+            match option {
+                "--input" => input = Some(PathBuf::from(value)),
+                "--project" => project_path = Some(PathBuf::from(value)),
+                "--track" => {
+                    track_index = Some(Self::parse_value::<usize>(option, value)?);
+                }
+                "--apply" => match value.as_str() {
+                    "split" | "markers" => apply = Some(value.clone()),
+                    _ => {
+                        return Err(Error::InvalidArgument(format!(
+                            "Invalid value for --apply: {value} (expected split or markers)"
+                        )));
+                    }
+                },
+                "--threshold" => options.threshold = Self::parse_value(option, value)?,
+                "--detector" => {
+                    options.detector = match value.as_str() {
+                        "select" => SceneDetector::Select,
+                        "scdet" => SceneDetector::Scdet,
+                        _ => {
+                            return Err(Error::InvalidArgument(format!(
+                                "Invalid value for --detector: {value} (expected select or scdet)"
+                            )));
+                        }
+                    };
+                }
+                "--min-gap" => options.min_gap = Self::parse_value(option, value)?,
+                "--fps" => fps = Self::parse_value(option, value)?,
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown argument: {option}"
+                    )));
+                }
+            }
+        }
+        options
+            .validate()
+            .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+
+        let ffmpeg = FFmpeg::detect().map_err(|e| Error::FFmpegError(e.to_string()))?;
+        if let Some(input) = input {
+            let cuts = ffmpeg
+                .detect_scenes(&input, &options)
+                .map_err(|e| Error::FFmpegError(e.to_string()))?;
+            context.logger.info(&format!(
+                "{} scene changes in {}",
+                cuts.len(),
+                input.display()
+            ));
+            for cut in cuts {
+                context.logger.info(&format!(
+                    "  {} (score {:.2})",
+                    cut.time.to_timecode(fps),
+                    cut.score
+                ));
+            }
+            return Ok(());
+        }
+
+        let project_path = project_path
+            .ok_or_else(|| Error::MissingArgument("--input or --project".to_string()))?;
+        let track_index =
+            track_index.ok_or_else(|| Error::MissingArgument("--track".to_string()))?;
+        let mut project = Project::load(&project_path)
+            .map_err(|e| Error::ProjectError(format!("Failed to load project: {e}")))?;
+        let track_id = project
+            .timeline
+            .get_tracks()
+            .get(track_index)
+            .map(|track| track.id())
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!("Track index out of range: {track_index}"))
+            })?;
+
+        let cuts = project
+            .detect_scenes(&ffmpeg, track_id, &options)
+            .map_err(|e| Error::ProjectError(e.to_string()))?;
+        context.logger.info(&format!(
+            "{} scene changes on track {track_index}",
+            cuts.len()
+        ));
+        for cut in &cuts {
+            context.logger.info(&format!(
+                "  {} (source {}, score {:.2})",
+                cut.position.to_timecode(fps),
+                cut.source_time.to_timecode(fps),
+                cut.score
+            ));
+        }
+
+        match apply.as_deref() {
+            Some("split") => {
+                let created = project
+                    .split_at_scenes(track_id, &cuts)
+                    .map_err(|e| Error::ProjectError(e.to_string()))?;
+                context
+                    .logger
+                    .info(&format!("Split clips into {} new clips", created.len()));
+            }
+            Some(_) => {
+                project.mark_scenes(&cuts);
+                context
+                    .logger
+                    .info(&format!("Added {} markers", cuts.len()));
+            }
+            None => return Ok(()),
+        }
+        project
+            .save(&project_path)
+            .map_err(|e| Error::ProjectError(format!("Failed to save project: {e}")))?;
+        Ok(())
+    }
+}
+
+/// Runs quality checks on a file or a project clip and reports the findings.
+#[derive(Debug)]
+pub struct QcCommand;
+
+impl QcCommand {
+    /// Creates a new quality check command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses the value of an option.
+    fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+
+    /// Builds the JSON form of a report.
+    fn json_report(source: &str, report: &QcReport, clean: bool) -> serde_json::Value {
+        let spans = |spans: &[TimeSpan]| -> Vec<serde_json::Value> {
+            spans
+                .iter()
+                .map(|span| {
+                    serde_json::json!({
+                        "start": span.start.as_seconds(),
+                        "end": span.end.as_seconds(),
+                        "duration": span.duration().as_seconds(),
+                    })
+                })
+                .collect()
+        };
+        serde_json::json!({
+            "source": source,
+            "clean": clean,
+            "black": spans(&report.black),
+            "freezes": spans(&report.freezes),
+            "crop": report.crop.map(|crop| serde_json::json!({
+                "width": crop.width,
+                "height": crop.height,
+                "x": crop.x,
+                "y": crop.y,
+            })),
+        })
+    }
+
+    /// Builds the text form of a report.
+    fn text_report(source: &str, report: &QcReport, clean: bool, fps: f64) -> Vec<String> {
+        let mut lines = vec![format!(
+            "QC of {source}: {}",
+            if clean { "passed" } else { "issues found" }
+        )];
+        for (name, spans) in [("Black", &report.black), ("Frozen", &report.freezes)] {
+            for span in spans {
+                lines.push(format!(
+                    "  {name}: {} - {} ({:.2}s)",
+                    span.start.to_timecode(fps),
+                    span.end.to_timecode(fps),
+                    span.duration().as_seconds()
+                ));
+            }
+        }
+        if let Some(crop) = report.crop {
+            lines.push(format!("  Picture area: {crop}"));
+        }
+        lines
+    }
+}
+
+impl Command for QcCommand {
+    fn name(&self) -> &str {
+        "qc"
+    }
+
+    fn description(&self) -> &str {
+        "Checks a file or clip for black segments, frozen video and borders"
+    }
+
+    fn usage(&self) -> &str {
+        "qc (--input <file> | --project <project_file> --track <index> --clip <index> [--apply-crop <true|false>]) [--checks <black,freeze,crop>] [--black-min <seconds>] [--freeze-min <seconds>] [--crop-limit <0-255>] [--format <text|json>] [--output <report_file>] [--fps <fps>]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let mut input = None;
+        let mut project_path = None;
+        let mut track_index = None;
+        let mut clip_index = None;
+        let mut apply_crop = false;
+        let mut checks = vec![
+            "black".to_string(),
+            "freeze".to_string(),
+            "crop".to_string(),
+        ];
+        let mut black = BlackOptions::default();
+        let mut freeze = FreezeOptions::default();
+        let mut crop = CropOptions::default();
+        let mut json = false;
+        let mut output = None;
+        let mut fps = 30.0;
+
+        for pair in args.chunks(2) {
+            let option = pair[0].as_str();
+            let value = pair
+                .get(1)
+                .ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))?;
+            match option {
+                "--input" => input = Some(PathBuf::from(value)),
+                "--project" => project_path = Some(PathBuf::from(value)),
+                "--track" => track_index = Some(Self::parse_value::<usize>(option, value)?),
+                "--clip" => clip_index = Some(Self::parse_value::<usize>(option, value)?),
+                "--apply-crop" => apply_crop = Self::parse_value(option, value)?,
+                "--checks" => {
+                    checks = value.split(',').map(|c| c.trim().to_string()).collect();
+                }
+                "--black-min" => black.min_duration = Self::parse_value(option, value)?,
+                "--freeze-min" => freeze.min_duration = Self::parse_value(option, value)?,
+                "--crop-limit" => crop.limit = Self::parse_value(option, value)?,
+                "--format" => {
+                    json = match value.as_str() {
+                        "text" => false,
+                        "json" => true,
+                        _ => {
+                            return Err(Error::InvalidArgument(format!(
+                                "Invalid value for --format: {value} (expected text or json)"
+                            )));
+                        }
+                    };
+                }
+                "--output" => output = Some(PathBuf::from(value)),
+                "--fps" => fps = Self::parse_value(option, value)?,
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown argument: {option}"
+                    )));
+                }
+            }
+        }
+
+        let mut options = QcOptions::default();
+        for check in &checks {
+            match check.as_str() {
+                "black" => options.black = Some(black),
+                "freeze" => options.freeze = Some(freeze),
+                "crop" => options.crop = Some(crop),
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown check: {check} (expected black, freeze or crop)"
+                    )));
+                }
+            }
+        }
+        options
+            .validate()
+            .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+        if apply_crop && options.crop.is_none() {
+            return Err(Error::InvalidArgument(
+                "--apply-crop requires the crop check".to_string(),
+            ));
+        }
+
+        let ffmpeg = FFmpeg::detect().map_err(|e| Error::FFmpegError(e.to_string()))?;
+        let (source, report, dimensions) = if let Some(input) = input {
+            let report = ffmpeg
+                .quality_check(&input, &options)
+                .map_err(|e| Error::FFmpegError(e.to_string()))?;
+            // サイズが分からない場合は、枠の有無を判定しない
+            let dimensions = ffmpeg.get_media_info(&input).ok().and_then(|info| {
+                let stream = info.video_streams().into_iter().next()?;
+                Some((
+                    u32::try_from(stream.width?).ok()?,
+                    u32::try_from(stream.height?).ok()?,
+                ))
+            });
+            (input.display().to_string(), report, dimensions)
+        } else {
+            let project_path = project_path
+                .ok_or_else(|| Error::MissingArgument("--input or --project".to_string()))?;
+            let track_index =
+                track_index.ok_or_else(|| Error::MissingArgument("--track".to_string()))?;
+            let clip_index =
+                clip_index.ok_or_else(|| Error::MissingArgument("--clip".to_string()))?;
+            let mut project = Project::load(&project_path)
+                .map_err(|e| Error::ProjectError(format!("Failed to load project: {e}")))?;
+            let track = project
+                .timeline
+                .get_tracks()
+                .get(track_index)
+                .ok_or_else(|| {
+                    Error::InvalidArgument(format!("Track index out of range: {track_index}"))
+                })?;
+            let track_id = track.id();
+            let clip = track.get_clips().get(clip_index).ok_or_else(|| {
+                Error::InvalidArgument(format!("Clip index out of range: {clip_index}"))
+            })?;
+            let clip_id = clip.id();
+            let dimensions = project
+                .get_asset(clip.asset_id())
+                .and_then(|asset| asset.metadata.dimensions);
+
+            let report = project
+                .quality_check_clip(&ffmpeg, track_id, clip_id, &options)
+                .map_err(|e| Error::ProjectError(e.to_string()))?;
+
+            if apply_crop && let Some(crop) = report.crop {
+                let applied = project
+                    .apply_detected_crop(track_id, clip_id, crop)
+                    .map_err(|e| Error::ProjectError(e.to_string()))?;
+                if applied {
+                    project
+                        .save(&project_path)
+                        .map_err(|e| Error::ProjectError(format!("Failed to save project: {e}")))?;
+                    context
+                        .logger
+                        .info(&format!("Cropped clip {clip_index} to {crop}"));
+                }
+            }
+            (
+                format!("clip {clip_index} of track {track_index}"),
+                report,
+                dimensions,
+            )
+        };
+
+        let (width, height) = dimensions.unzip();
+        let clean = report.is_clean(width, height);
+        let text = if json {
+            serde_json::to_string_pretty(&Self::json_report(&source, &report, clean))
+                .map_err(|e| Error::CommandExecution(format!("Failed to write report: {e}")))?
+        } else {
+            Self::text_report(&source, &report, clean, fps).join("\n")
+        };
+
+        if let Some(output) = output {
+            fs::write(&output, format!("{text}\n")).map_err(|e| {
+                Error::CommandExecution(format!("Failed to write {}: {e}", output.display()))
+            })?;
+            context
+                .logger
+                .info(&format!("QC report written to {}", output.display()));
+        } else if json {
+            writeln!(io::stdout(), "{text}")
+                .map_err(|e| Error::CommandExecution(format!("Failed to write report: {e}")))?;
+        } else {
+            for line in text.lines() {
+                context.logger.info(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Imports a numbered image sequence into a project.
+#[derive(Debug)]
+pub struct ImportSequenceCommand;
+
+impl ImportSequenceCommand {
+    /// Creates a new import sequence command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Command for ImportSequenceCommand {
+    fn name(&self) -> &str {
+        "import-sequence"
+    }
+
+    fn description(&self) -> &str {
+        "Imports a numbered image sequence into a project as one asset"
+    }
+
+    fn usage(&self) -> &str {
+        "import-sequence <project_file> <pattern> <fps>"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let [project_path, pattern, fps] = args else {
+            return Err(Error::MissingArgument(
+                "Project file, frame pattern and frame rate".to_string(),
+            ));
+        };
+        let project_path = PathBuf::from(project_path);
+        let fps: f64 = fps
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid frame rate: {fps}")))?;
+
+        let mut project = crate::project::Project::load(&project_path)
+            .map_err(|e| Error::ProjectError(format!("Failed to load project: {e}")))?;
+
+        let asset_id = project
+            .add_image_sequence(PathBuf::from(pattern), fps)
+            .map_err(|e| Error::ProjectError(format!("Failed to import sequence: {e}")))?;
+
+        project
+            .save(&project_path)
+            .map_err(|e| Error::ProjectError(format!("Failed to save project: {e}")))?;
+
+        if let Some(asset) = project.get_asset(asset_id) {
+            context.logger.info(&format!(
+                "Imported {pattern} as asset {asset_id} ({:.2}s)",
+                asset
+                    .metadata
+                    .duration
+                    .map_or(0.0, |duration| duration.as_seconds())
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// Undoes the last edit in a project.
 #[derive(Debug)]
 pub struct ProjectUndoCommand;
@@ -1180,7 +2191,7 @@ impl Command for PlayCommand {
             .info(&format!("Analyzing media file: {input_file}"));
         let media_info = ffmpeg
             .get_media_info(input_file)
-            .map_err(|e| Error::FFmpegError(format!("Failed to get media info: {e}")))?;
+            .map_err(|e| Error::ffmpeg("Failed to get media info", &e))?;
 
         // Get duration from format section
         let duration_str = media_info.format.duration.as_ref().ok_or_else(|| {
@@ -1300,6 +2311,315 @@ impl Command for PlayCommand {
     }
 }
 
+/// Inspects and maintains the render cache.
+#[derive(Debug)]
+pub struct CacheCommand;
+
+impl CacheCommand {
+    /// Creates a new cache command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses a numeric option value.
+    fn parse_number(option: &str, value: Option<&String>) -> Result<u64> {
+        let value =
+            value.ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))?;
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+
+    /// Prints the statistics of the cache.
+    fn print_stats(context: &Context, cache_dir: &Path, cache: &RenderCache) {
+        let stats = cache.stats();
+
+        context
+            .logger
+            .info(&format!("Cache directory: {}", cache_dir.display()));
+        context.logger.info(&format!(
+            "Entries: {} ({} assets, {} segments)",
+            stats.entry_count(),
+            stats.asset_entries,
+            stats.segment_entries
+        ));
+
+        let limit = stats
+            .max_size
+            .map_or_else(|| "unlimited".to_string(), format_file_size);
+        context.logger.info(&format!(
+            "Size: {} (limit: {limit})",
+            format_file_size(stats.total_size)
+        ));
+
+        if let (Some(oldest), Some(newest)) = (stats.oldest_access, stats.newest_access) {
+            let format_time = |time| {
+                DateTime::<Utc>::from(time)
+                    .format("%Y-%m-%d %H:%M:%S UTC")
+                    .to_string()
+            };
+            context.logger.info(&format!(
+                "Last used: {} (least recent: {})",
+                format_time(newest),
+                format_time(oldest)
+            ));
+        }
+    }
+}
+
+impl Command for CacheCommand {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    fn description(&self) -> &str {
+        "Shows, clears or prunes the render cache"
+    }
+
+    fn usage(&self) -> &str {
+        "cache <stats|clear|prune> [--dir <cache_dir>] [--max-age-days <days>] [--max-size-mb <megabytes>]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let action = args
+            .first()
+            .ok_or_else(|| Error::MissingArgument("cache action".to_string()))?;
+
+        // Parse remaining arguments
+        let mut cache_dir = RenderCache::default_dir();
+        let mut max_age_days = None;
+        let mut max_size_mb = None;
+
+        let mut i = 1;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--dir" => {
+                    let dir = args.get(i + 1).ok_or_else(|| {
+                        Error::InvalidArgument("--dir requires a value".to_string())
+                    })?;
+                    cache_dir = PathBuf::from(dir);
+                }
+                "--max-age-days" => {
+                    max_age_days = Some(Self::parse_number("--max-age-days", args.get(i + 1))?);
+                }
+                "--max-size-mb" => {
+                    max_size_mb = Some(Self::parse_number("--max-size-mb", args.get(i + 1))?);
+                }
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown argument: {}",
+                        args[i]
+                    )));
+                }
+            }
+            i += 2;
+        }
+
+        let mut cache = RenderCache::new(cache_dir.clone(), None)
+            .map_err(|e| Error::RenderError(e.to_string()))?;
+
+        match action.as_str() {
+            "stats" => Self::print_stats(context, &cache_dir, &cache),
+            "clear" => {
+                let stats = cache.stats();
+                cache
+                    .clear()
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                context.logger.info(&format!(
+                    "Removed {} cache entries ({})",
+                    stats.entry_count(),
+                    format_file_size(stats.total_size)
+                ));
+            }
+            "prune" => {
+                cache.set_max_age(
+                    max_age_days.map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60)),
+                );
+                cache.set_max_size(max_size_mb.map(|megabytes| megabytes * 1024 * 1024));
+
+                let report = cache
+                    .prune()
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                context.logger.info(&format!(
+                    "Removed {} damaged, {} expired and {} least recently used entries, {} orphaned files",
+                    report.invalid_entries,
+                    report.expired_entries,
+                    report.evicted_entries,
+                    report.orphaned_files
+                ));
+                context
+                    .logger
+                    .info(&format!("Freed {}", format_file_size(report.freed_bytes)));
+            }
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "Unknown cache action: {action} (expected stats, clear or prune)"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Manages the persisted render queue.
+#[derive(Debug)]
+pub struct QueueCommand;
+
+impl QueueCommand {
+    /// Creates a new queue command.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Gets the value following an option.
+    fn option_value<'a>(args: &'a [String], index: usize, option: &str) -> Result<&'a String> {
+        args.get(index + 1)
+            .ok_or_else(|| Error::InvalidArgument(format!("{option} requires a value")))
+    }
+
+    /// Parses the value following an option.
+    fn parse_option<T: std::str::FromStr>(
+        args: &[String],
+        index: usize,
+        option: &str,
+    ) -> Result<T> {
+        let value = Self::option_value(args, index, option)?;
+        value
+            .parse()
+            .map_err(|_| Error::InvalidArgument(format!("Invalid value for {option}: {value}")))
+    }
+
+    /// Adds a job to the queue.
+    fn add(context: &Context, queue: &mut RenderQueue, args: &[String]) -> Result<()> {
+        let options = RenderOptions::parse(args)?;
+        let project = options.project()?;
+        let timeline_duration = Project::load(&project)
+            .map_err(|e| {
+                Error::ProjectError(format!("Failed to load project {}: {e}", project.display()))
+            })?
+            .timeline
+            .duration();
+        let config = options.to_config(context, timeline_duration)?;
+
+        let job = queue
+            .add(project, config)
+            .map_err(|e| Error::RenderError(e.to_string()))?;
+        context.logger.info(&format!(
+            "Queued job {} -> {}",
+            job.short_id(),
+            job.config.output_path.display()
+        ));
+        Ok(())
+    }
+
+    /// Lists the jobs in the queue.
+    fn list(context: &Context, queue: &RenderQueue) {
+        if queue.jobs().is_empty() {
+            context.logger.info("The render queue is empty");
+            return;
+        }
+
+        for job in queue.jobs() {
+            let mut line = format!(
+                "{}  {:<9}  {} -> {}",
+                job.short_id(),
+                job.status.as_str(),
+                job.project_path.display(),
+                job.config.output_path.display()
+            );
+            if job.attempts > 1 {
+                line.push_str(&format!("  (attempt {})", job.attempts));
+            }
+            if let Some(error) = &job.error {
+                line.push_str(&format!("  error: {error}"));
+            }
+            context.logger.info(&line);
+        }
+    }
+}
+
+impl Command for QueueCommand {
+    fn name(&self) -> &str {
+        "queue"
+    }
+
+    fn description(&self) -> &str {
+        "Adds, lists, runs and retries queued render jobs"
+    }
+
+    fn usage(&self) -> &str {
+        "queue <add|list|run|retry> [--queue <queue_file>] [add: --project <project_file> --output <output_file> [--preset <name>] [render options]] [run: --concurrency <n>] [retry: <job_id>]"
+    }
+
+    fn execute(&self, context: &Context, args: &[String]) -> Result<()> {
+        let action = args
+            .first()
+            .ok_or_else(|| Error::MissingArgument("queue action".to_string()))?;
+
+        // --queue はすべての操作で共通
+        let mut queue_path = context.config.working_dir.join("render_queue.json");
+        let mut action_args = Vec::new();
+        let mut i = 1;
+        while i < args.len() {
+            if args[i] == "--queue" {
+                queue_path = PathBuf::from(Self::option_value(args, i, "--queue")?);
+                i += 2;
+            } else {
+                action_args.push(args[i].clone());
+                i += 1;
+            }
+        }
+
+        let mut queue =
+            RenderQueue::open(&queue_path).map_err(|e| Error::RenderError(e.to_string()))?;
+
+        match action.as_str() {
+            "add" => Self::add(context, &mut queue, &action_args)?,
+            "list" => Self::list(context, &queue),
+            "run" => {
+                let concurrency = match action_args.as_slice() {
+                    [] => 1,
+                    [option, _] if option == "--concurrency" => {
+                        Self::parse_option(&action_args, 0, "--concurrency")?
+                    }
+                    _ => {
+                        return Err(Error::InvalidArgument(format!(
+                            "Unknown arguments: {}",
+                            action_args.join(" ")
+                        )));
+                    }
+                };
+
+                let summary = queue
+                    .run(concurrency)
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                context.logger.info(&format!(
+                    "Render queue finished: {} completed, {} failed",
+                    summary.completed, summary.failed
+                ));
+            }
+            "retry" => {
+                let count = queue
+                    .retry(action_args.first().map(String::as_str))
+                    .map_err(|e| Error::RenderError(e.to_string()))?;
+                context
+                    .logger
+                    .info(&format!("Queued {count} failed jobs for retry"));
+            }
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "Unknown queue action: {action} (expected add, list, run or retry)"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1341,6 +2661,260 @@ mod tests {
         }
     }
 
+    /// Creates a context that only logs errors.
+    fn quiet_context() -> Context {
+        Context::new(
+            crate::core::Config::new(),
+            Box::new(crate::core::console::ConsoleLogger::new(
+                crate::core::LogLevel::Error,
+            )),
+        )
+    }
+
+    #[test]
+    fn test_trim_command_runs_ffmpeg() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.mp4");
+        fs::write(&input, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "in.mp4", "duration": "10.0"}, "streams": []}"#,
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+
+        let input_arg = input.to_string_lossy().into_owned();
+        let args: Vec<String> = [&input_arg, "out.mp4", "--start", "2", "--end", "7"]
+            .map(String::from)
+            .to_vec();
+        TrimCommand::new().execute(&quiet_context(), &args).unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].option_value("-ss"), Some("2.000s"));
+        assert_eq!(calls[0].option_value("-i"), Some(input_arg.as_str()));
+        assert_eq!(calls[0].option_value("-t"), Some("5"));
+        assert_eq!(calls[0].option_value("-c"), Some("copy"));
+        assert_eq!(calls[0].last_arg(), Some("out.mp4"));
+
+        // 長さを超える範囲は FFmpeg を実行せずに拒否する
+        let args: Vec<String> = [&input_arg, "out.mp4", "--end", "12"]
+            .map(String::from)
+            .to_vec();
+        assert!(TrimCommand::new().execute(&quiet_context(), &args).is_err());
+        assert_eq!(executor.ffmpeg_calls().len(), 1);
+    }
+
+    #[test]
+    fn test_trim_command_smart_cut() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.mp4");
+        fs::write(&input, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "in.mp4", "duration": "10.0"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                             "r_frame_rate": "25/1"}]}"#,
+        );
+        executor.respond(
+            "packet=",
+            crate::ffmpeg::ExecutionOutput::success(
+                r#"{"packets": [{"pts_time": "0.0", "flags": "K_"},
+                                {"pts_time": "4.0", "flags": "K_"},
+                                {"pts_time": "8.0", "flags": "K_"}]}"#,
+            ),
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+
+        let input_arg = input.to_string_lossy().into_owned();
+        let args: Vec<String> = [
+            &input_arg, "out.mp4", "--start", "2", "--end", "9", "--smart",
+        ]
+        .map(String::from)
+        .to_vec();
+        TrimCommand::new().execute(&quiet_context(), &args).unwrap();
+
+        // 先頭と末尾をエンコードし、中間をコピーしてから結合する
+        let calls = executor.ffmpeg_calls();
+        let codecs: Vec<_> = calls.iter().map(|c| c.option_value("-c:v")).collect();
+        assert_eq!(
+            codecs,
+            vec![Some("libx264"), Some("copy"), Some("libx264"), None]
+        );
+        assert_eq!(calls[3].last_arg(), Some("out.mp4"));
+
+        let args: Vec<String> = [&input_arg, "out.mp4", "--smart", "--recompress"]
+            .map(String::from)
+            .to_vec();
+        assert!(TrimCommand::new().execute(&quiet_context(), &args).is_err());
+    }
+
+    #[test]
+    fn test_trim_command_reports_failure_cause() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.mp4");
+        fs::write(&input, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "in.mp4", "duration": "10.0"}, "streams": []}"#,
+        );
+        executor.respond(
+            "-progress",
+            crate::ffmpeg::ExecutionOutput::failure(
+                1,
+                "[out#0/mp4 @ 0x5581] Error opening output /srv/out.mp4: Permission denied\n\
+                 Error opening output file /srv/out.mp4.\n",
+            ),
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+
+        let input_arg = input.to_string_lossy().into_owned();
+        let args: Vec<String> = [input_arg.as_str(), "/srv/out.mp4", "--recompress"]
+            .map(String::from)
+            .to_vec();
+        let error = TrimCommand::new()
+            .execute(&quiet_context(), &args)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "FFmpeg error: permission denied: /srv/out.mp4\n\
+             hint: Check the permissions of the file and its directory"
+        );
+    }
+
+    /// Saves a project with one 10 second 1280x720 video clip.
+    fn save_project_with_clip() -> tempfile::NamedTempFile {
+        let mut project = Project::new("analysis");
+        let asset = project.add_asset(
+            std::env::current_exe().unwrap(),
+            AssetMetadata {
+                duration: Some(Duration::from_seconds(30.0)),
+                dimensions: Some((1280, 720)),
+                asset_type: "video".to_string(),
+                extra: HashMap::new(),
+            },
+        );
+        let track = project.timeline.add_track(crate::project::TrackKind::Video);
+        let clip = crate::project::timeline::Clip::new(
+            crate::project::ClipId::new(),
+            asset,
+            crate::utility::time::TimePosition::from_seconds(0.0),
+            Duration::from_seconds(10.0),
+            crate::utility::time::TimePosition::from_seconds(0.0),
+            crate::utility::time::TimePosition::from_seconds(10.0),
+        );
+        project.timeline.add_clip(track, clip).unwrap();
+        let project_file = tempfile::NamedTempFile::new().unwrap();
+        project.save(project_file.path()).unwrap();
+        project_file
+    }
+
+    #[test]
+    fn test_detect_scenes_command_adds_markers() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            crate::ffmpeg::ExecutionOutput::success(Vec::new()).with_stderr(
+                "[Parsed_metadata_1 @ 0x55] frame:0 pts:48 pts_time:1.5\n\
+                 [Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.8\n",
+            ),
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let project_file = save_project_with_clip();
+
+        let project_arg = project_file.path().to_string_lossy().into_owned();
+        let args: Vec<String> = [
+            "--project",
+            &project_arg,
+            "--track",
+            "0",
+            "--apply",
+            "markers",
+        ]
+        .map(String::from)
+        .to_vec();
+        DetectScenesCommand::new()
+            .execute(&quiet_context(), &args)
+            .unwrap();
+
+        let saved = Project::load(project_file.path()).unwrap();
+        let markers = saved.timeline.markers();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].position.as_seconds(), 1.5);
+
+        let args: Vec<String> = ["--input", "in.mp4", "--detector", "histogram"]
+            .map(String::from)
+            .to_vec();
+        assert!(
+            DetectScenesCommand::new()
+                .execute(&quiet_context(), &args)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_qc_command_writes_report_and_crops_clip() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            crate::ffmpeg::ExecutionOutput::success(Vec::new()).with_stderr(
+                "[freezedetect @ 0x56] lavfi.freezedetect.freeze_start: 4\n\
+                 [freezedetect @ 0x56] lavfi.freezedetect.freeze_end: 7\n\
+                 [Parsed_cropdetect_1 @ 0x57] x:0 y:88 pts:2 t:1.0 crop=1280:544:0:88\n",
+            ),
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let project_file = save_project_with_clip();
+        let report_file = tempfile::NamedTempFile::new().unwrap();
+
+        let project_arg = project_file.path().to_string_lossy().into_owned();
+        let report_arg = report_file.path().to_string_lossy().into_owned();
+        let args: Vec<String> = [
+            "--project",
+            &project_arg,
+            "--track",
+            "0",
+            "--clip",
+            "0",
+            "--apply-crop",
+            "true",
+            "--checks",
+            "freeze,crop",
+            "--format",
+            "json",
+            "--output",
+            &report_arg,
+        ]
+        .map(String::from)
+        .to_vec();
+        QcCommand::new().execute(&quiet_context(), &args).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(report_file.path()).unwrap()).unwrap();
+        assert_eq!(report["clean"], false);
+        assert_eq!(report["black"].as_array().unwrap().len(), 0);
+        assert_eq!(report["freezes"][0]["duration"], 3.0);
+        assert_eq!(report["crop"]["height"], 544);
+        assert_eq!(
+            executor.ffmpeg_calls()[0].option_value("-vf"),
+            Some("freezedetect=n=-60dB:d=2,cropdetect=limit=24:round=2:reset=0")
+        );
+
+        let saved = Project::load(project_file.path()).unwrap();
+        let clip = &saved.timeline.get_tracks()[0].get_clips()[0];
+        assert_eq!(
+            clip.crop().map(|crop| crop.to_string()).as_deref(),
+            Some("1280:544:0:88")
+        );
+
+        let args: Vec<String> = ["--input", "in.mp4", "--checks", "audio"]
+            .map(String::from)
+            .to_vec();
+        assert!(QcCommand::new().execute(&quiet_context(), &args).is_err());
+    }
+
     #[test]
     fn test_register_and_get_command() {
         let mut registry = CommandRegistry::new();
@@ -1407,4 +2981,27 @@ mod tests {
         assert!(names.contains(&"test1".to_string()));
         assert!(names.contains(&"test2".to_string()));
     }
+
+    #[test]
+    fn test_render_options_segment_duration() {
+        let args: Vec<String> = ["--segment-duration", "30"].map(String::from).to_vec();
+        let options = RenderOptions::parse(&args).unwrap();
+        let mut config = RenderConfig::new(PathBuf::from("out.mp4"));
+        options.overrides.apply(&mut config);
+        assert!(config.parallel_segments);
+        assert_eq!(config.segment_duration, Duration::from_seconds(30.0));
+
+        // 指定がなければ長いタイムラインだけを分割する
+        let mut config = RenderConfig::new(PathBuf::from("out.mp4"));
+        RenderOptions::segment_long_timeline(&mut config, Duration::from_seconds(60.0));
+        assert!(!config.parallel_segments);
+        RenderOptions::segment_long_timeline(&mut config, Duration::from_seconds(3600.0));
+        assert!(config.parallel_segments);
+
+        let mut config = RenderConfig::new(PathBuf::from("out.mp4"));
+        config.rate_control =
+            crate::project::rendering::RateControl::TwoPassBitrate { bitrate: 5000 };
+        RenderOptions::segment_long_timeline(&mut config, Duration::from_seconds(3600.0));
+        assert!(!config.parallel_segments);
+    }
 }
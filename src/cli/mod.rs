@@ -72,5 +72,24 @@ pub enum Error {
     FFmpegError(String),
 }
 
+impl Error {
+    /// Creates the error of a failed `FFmpeg` operation.
+    ///
+    /// If the cause of the failure is recognized, the error consists of the
+    /// cause and a hint how to fix it instead of the log of `FFmpeg`.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - What was being done, e.g. `Failed to get media info`
+    /// * `error` - The `FFmpeg` error
+    #[must_use]
+    pub fn ffmpeg(context: &str, error: &crate::ffmpeg::Error) -> Self {
+        match error.failure_cause() {
+            Some(cause) => Self::FFmpegError(format!("{cause}\nhint: {}", cause.hint())),
+            None => Self::FFmpegError(format!("{context}: {error}")),
+        }
+    }
+}
+
 /// Result type for CLI operations
 pub type Result<T> = std::result::Result<T, Error>;
@@ -86,6 +86,8 @@ pub struct Config {
     pub cache_dir: PathBuf,
     /// Default output directory
     pub output_dir: PathBuf,
+    /// Directory for user configuration such as render presets
+    pub config_dir: PathBuf,
 }
 
 impl Config {
@@ -97,9 +99,27 @@ impl Config {
             working_dir: PathBuf::from("."),
             cache_dir: PathBuf::from(".cache"),
             output_dir: PathBuf::from("output"),
+            config_dir: Self::default_config_dir(),
         }
     }
 
+    /// Gets the default directory for user configuration
+    ///
+    /// This is `$XDG_CONFIG_HOME/edv`, falling back to `~/.config/edv` and,
+    /// if no home directory is known, to `.edv` in the current directory.
+    ///
+    /// # Returns
+    ///
+    /// Default configuration directory
+    #[must_use]
+    pub fn default_config_dir() -> PathBuf {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map_or_else(|| PathBuf::from(".edv"), |dir| dir.join("edv"))
+    }
+
     /// Loads configuration from the specified file
     ///
     /// # Arguments
@@ -126,9 +146,10 @@ impl Config {
             )));
         }
 
-        // Set working directory to the parent of the config file
+        // Set working and configuration directories to the parent of the config file
         if let Some(parent) = path.parent() {
             config.working_dir = parent.to_path_buf();
+            config.config_dir = parent.to_path_buf();
         }
 
         Ok(config)
@@ -216,6 +237,21 @@ impl Config {
         self.output_dir = dir;
         self
     }
+
+    /// Sets the configuration directory
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - New configuration directory
+    ///
+    /// # Returns
+    ///
+    /// Updated configuration
+    #[must_use]
+    pub fn with_config_dir(mut self, dir: PathBuf) -> Self {
+        self.config_dir = dir;
+        self
+    }
 }
 
 /// Application context
@@ -0,0 +1,456 @@
+/// Media analysis with `FFmpeg` detection filters.
+///
+/// An analysis pass decodes one stream of an input through a detection
+/// filter into the `null` muxer, and reads what the filter detected from
+/// the log. The scope of a pass (input options, range and stream) is shared
+/// by all detectors; times are reported in the input's timeline, also when
+/// only a range of it is analyzed.
+///
+/// Scene changes are detected with either the `select` filter's scene score
+/// or the `scdet` filter.
+use std::path::Path;
+
+use crate::ffmpeg::{Error, FFmpeg, Result};
+use crate::utility::time::TimePosition;
+
+/// What part of an input an analysis pass decodes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalysisScope {
+    /// Options placed before the input (e.g. of an image sequence).
+    pub input_options: Vec<String>,
+    /// Start of the analyzed range, or `None` for the start of the input.
+    pub start: Option<TimePosition>,
+    /// Duration of the analyzed range in seconds, or `None` for the rest of
+    /// the input.
+    pub duration: Option<f64>,
+    /// Index of the stream among the streams of its type.
+    pub stream: usize,
+}
+
+impl AnalysisScope {
+    /// Creates a scope covering the first stream of the whole input.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the options placed before the input.
+    #[must_use]
+    pub fn with_input_options(mut self, options: Vec<String>) -> Self {
+        self.input_options = options;
+        self
+    }
+
+    /// Restricts the pass to a range of the input.
+    #[must_use]
+    pub fn with_range(mut self, start: TimePosition, duration: f64) -> Self {
+        self.start = Some(start);
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Sets the index of the stream among the streams of its type.
+    #[must_use]
+    pub fn with_stream(mut self, stream: usize) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Converts a time of the pass output into a time of the input.
+    #[must_use]
+    pub fn input_time(&self, seconds: f64) -> TimePosition {
+        let offset = self.start.map_or(0.0, |start| start.as_seconds());
+        TimePosition::from_seconds(offset + seconds)
+    }
+}
+
+/// The type of stream an analysis pass decodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnalyzedMedia {
+    /// A video stream, filtered with `-vf`.
+    Video,
+    /// An audio stream, filtered with `-af`.
+    Audio,
+}
+
+/// Runs an analysis pass and passes every log line to a parser.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` instance to run
+/// * `input` - Path to the input
+/// * `scope` - What part of the input to decode
+/// * `media` - The type of stream to decode
+/// * `filter` - The detection filter graph
+/// * `on_line` - Called with every log line
+///
+/// # Errors
+///
+/// Returns an error if the input does not exist or `FFmpeg` fails.
+pub(crate) fn run_pass(
+    ffmpeg: &FFmpeg,
+    input: &Path,
+    scope: &AnalysisScope,
+    media: AnalyzedMedia,
+    filter: &str,
+    on_line: impl FnMut(&str),
+) -> Result<()> {
+    if scope.input_options.is_empty() && !input.exists() {
+        return Err(Error::InvalidPath(format!(
+            "File not found: {}",
+            input.display()
+        )));
+    }
+
+    let mut command = ffmpeg.command();
+    command.input_options(scope.input_options.iter());
+    if let Some(start) = scope.start {
+        command.add_input_option("-ss", start.as_seconds().to_string());
+    }
+    command.input(input);
+    if let Some(duration) = scope.duration {
+        command.add_output_option("-t", duration.to_string());
+    }
+
+    let (map, filter_option, disabled) = match media {
+        AnalyzedMedia::Video => (format!("0:v:{}", scope.stream), "-vf", "-an"),
+        AnalyzedMedia::Audio => (format!("0:a:{}", scope.stream), "-af", "-vn"),
+    };
+    command
+        .add_output_option("-map", map)
+        .add_output_option(filter_option, filter)
+        .output_options([disabled, "-sn"])
+        .add_output_option("-f", "null")
+        .set_output("-");
+    command.execute_with_progress(on_line)
+}
+
+/// Reads the word following a key in a log line.
+pub(crate) fn token_after<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    Some(
+        line.split(key)
+            .nth(1)?
+            .split_whitespace()
+            .next()?
+            .trim_end_matches([',', ';']),
+    )
+}
+
+/// Reads the number following a key in a log line.
+pub(crate) fn value_after(line: &str, key: &str) -> Option<f64> {
+    token_after(line, key)?.parse().ok()
+}
+
+/// Collects time spans that a filter logs as a start and an end.
+#[derive(Debug)]
+pub(crate) struct SpanLog {
+    /// Key preceding the start of a span.
+    start_key: &'static str,
+    /// Key preceding the end of a span.
+    end_key: &'static str,
+    /// Start of the span that has not ended yet.
+    pending_start: Option<f64>,
+    /// Output time of the last progress report.
+    last_time: f64,
+    /// The spans read so far, in pass output time.
+    spans: Vec<(f64, f64)>,
+}
+
+impl SpanLog {
+    /// Creates a log reading spans between two keys.
+    pub(crate) fn new(start_key: &'static str, end_key: &'static str) -> Self {
+        Self {
+            start_key,
+            end_key,
+            pending_start: None,
+            last_time: 0.0,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Reads a log line.
+    pub(crate) fn feed(&mut self, line: &str) {
+        // blackdetect は開始と終了を同じ行に出力する
+        if let Some(start) = value_after(line, self.start_key) {
+            // 先頭の区間は負の時刻で報告されることがある
+            self.pending_start = Some(start.max(0.0));
+        }
+        if let Some(end) = value_after(line, self.end_key) {
+            let start = self.pending_start.take().unwrap_or(0.0);
+            self.spans.push((start, end));
+        } else if let Some(micros) = value_after(line, "out_time_us=") {
+            self.last_time = self.last_time.max(micros / 1_000_000.0);
+        }
+    }
+
+    /// Ends the pass, closing a span that lasts until the end.
+    pub(crate) fn finish(mut self) -> Vec<(f64, f64)> {
+        if let Some(start) = self.pending_start.take()
+            && self.last_time > start
+        {
+            self.spans.push((start, self.last_time));
+        }
+        self.spans
+    }
+}
+
+/// How scene changes are detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SceneDetector {
+    /// The scene score of the `select` filter.
+    #[default]
+    Select,
+    /// The `scdet` filter, which compares frames over a sliding window.
+    Scdet,
+}
+
+/// Options of scene change detection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneOptions {
+    /// Smallest score of a scene change, from 0.0 to 1.0.
+    pub threshold: f64,
+    /// The detector to use.
+    pub detector: SceneDetector,
+    /// Smallest time between two reported changes in seconds.
+    pub min_gap: f64,
+    /// What part of the input to analyze.
+    pub scope: AnalysisScope,
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.4,
+            detector: SceneDetector::default(),
+            min_gap: 0.5,
+            scope: AnalysisScope::default(),
+        }
+    }
+}
+
+impl SceneOptions {
+    /// Creates options with a threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Smallest score of a scene change, from 0.0 to 1.0
+    #[must_use]
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the detector.
+    #[must_use]
+    pub fn with_detector(mut self, detector: SceneDetector) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    /// Sets the smallest time between two reported changes.
+    #[must_use]
+    pub fn with_min_gap(mut self, seconds: f64) -> Self {
+        self.min_gap = seconds;
+        self
+    }
+
+    /// Sets what part of the input to analyze.
+    #[must_use]
+    pub fn with_scope(mut self, scope: AnalysisScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Checks that the options are valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threshold is outside 0.0 to 1.0 or the gap
+    /// is negative.
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(Error::InvalidArgument(
+                "Scene threshold must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+        if self.min_gap < 0.0 {
+            return Err(Error::InvalidArgument(
+                "Scene gap must not be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds the detection filter.
+    fn filter(&self) -> String {
+        match self.detector {
+            SceneDetector::Select => format!(
+                "select='gt(scene,{})',metadata=print:key=lavfi.scene_score",
+                self.threshold
+            ),
+            // scdet のしきい値は 0〜100
+            SceneDetector::Scdet => format!("scdet=threshold={}", self.threshold * 100.0),
+        }
+    }
+}
+
+/// A detected scene change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneCut {
+    /// Time of the first frame of the new scene in the input.
+    pub time: TimePosition,
+    /// How different the frame is from the previous one, from 0.0 to 1.0.
+    pub score: f64,
+}
+
+/// Collects scene changes from the log of a detection pass.
+#[derive(Debug, Default)]
+struct SceneLog {
+    /// Time of the last frame printed by the `metadata` filter.
+    pending_time: Option<f64>,
+    /// The changes read so far, in pass output time.
+    cuts: Vec<(f64, f64)>,
+}
+
+impl SceneLog {
+    /// Reads a log line.
+    fn feed(&mut self, line: &str) {
+        // scdet: "lavfi.scd.score: 60.093, lavfi.scd.time: 2.04"
+        if let Some(score) = value_after(line, "lavfi.scd.score:") {
+            if let Some(time) = value_after(line, "lavfi.scd.time:") {
+                self.cuts.push((time, score / 100.0));
+            }
+            return;
+        }
+        // metadata=print はフレームの時刻とスコアを別の行に出力する
+        if !line.contains("Parsed_metadata") {
+            return;
+        }
+        if let Some(time) = value_after(line, "pts_time:") {
+            self.pending_time = Some(time);
+        } else if let Some(score) = value_after(line, "lavfi.scene_score=")
+            && let Some(time) = self.pending_time.take()
+        {
+            self.cuts.push((time, score));
+        }
+    }
+}
+
+/// Drops changes closer than a gap to the previous kept change.
+fn spaced(mut cuts: Vec<SceneCut>, min_gap: f64) -> Vec<SceneCut> {
+    cuts.sort_by_key(|cut| cut.time);
+    let mut kept: Vec<SceneCut> = Vec::with_capacity(cuts.len());
+    for cut in cuts {
+        match kept.last() {
+            Some(last) if (cut.time - last.time).as_seconds() < min_gap => {}
+            _ => kept.push(cut),
+        }
+    }
+    kept
+}
+
+impl FFmpeg {
+    /// Detects the scene changes of a video.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Path to the video
+    /// * `options` - Threshold, detector and scope of the detection
+    ///
+    /// # Returns
+    ///
+    /// The scene changes in time order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are invalid or `FFmpeg` fails.
+    pub fn detect_scenes<P: AsRef<Path>>(
+        &self,
+        input: P,
+        options: &SceneOptions,
+    ) -> Result<Vec<SceneCut>> {
+        options.validate()?;
+        let mut log = SceneLog::default();
+        run_pass(
+            self,
+            input.as_ref(),
+            &options.scope,
+            AnalyzedMedia::Video,
+            &options.filter(),
+            |line| log.feed(line),
+        )?;
+
+        let cuts = log
+            .cuts
+            .into_iter()
+            .filter(|(_, score)| *score >= options.threshold)
+            .map(|(time, score)| SceneCut {
+                time: options.scope.input_time(time),
+                score,
+            })
+            .collect();
+        Ok(spaced(cuts, options.min_gap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::ExecutionOutput;
+
+    #[test]
+    fn test_scene_log_reads_both_detectors() {
+        let mut log = SceneLog::default();
+        for line in [
+            "[Parsed_metadata_1 @ 0x55] frame:0    pts:48      pts_time:2.002",
+            "[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.612345",
+            "[Parsed_metadata_1 @ 0x55] frame:1    pts:120     pts_time:5.005",
+            "[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.450000",
+            "[scdet @ 0x56] lavfi.scd.score: 72.500, lavfi.scd.time: 7.5",
+            "frame=  180 fps=0.0 q=-0.0 size=N/A time=00:00:07.50",
+        ] {
+            log.feed(line);
+        }
+        assert_eq!(
+            log.cuts,
+            vec![(2.002, 0.612_345), (5.005, 0.45), (7.5, 0.725)]
+        );
+    }
+
+    #[test]
+    fn test_detect_scenes() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[Parsed_metadata_1 @ 0x55] frame:0 pts:48 pts_time:2.0\n\
+                 [Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.6\n\
+                 [Parsed_metadata_1 @ 0x55] frame:1 pts:50 pts_time:2.2\n\
+                 [Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.9\n\
+                 [Parsed_metadata_1 @ 0x55] frame:2 pts:96 pts_time:4.0\n\
+                 [Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.5\n",
+            ),
+        );
+        let input = std::env::current_exe().unwrap();
+        let options = SceneOptions::new(0.4)
+            .with_min_gap(1.0)
+            .with_scope(AnalysisScope::new().with_range(TimePosition::from_seconds(10.0), 30.0));
+
+        let cuts = ffmpeg.detect_scenes(&input, &options).unwrap();
+        let times: Vec<f64> = cuts.iter().map(|cut| cut.time.as_seconds()).collect();
+        assert_eq!(times, vec![12.0, 14.0]);
+        assert_eq!(cuts[0].score, 0.6);
+
+        let call = &executor.ffmpeg_calls()[0];
+        assert_eq!(call.option_value("-ss"), Some("10"));
+        assert_eq!(call.option_value("-t"), Some("30"));
+        assert_eq!(call.option_value("-map"), Some("0:v:0"));
+        assert_eq!(
+            call.option_value("-vf"),
+            Some("select='gt(scene,0.4)',metadata=print:key=lavfi.scene_score")
+        );
+        assert!(SceneOptions::new(1.5).validate().is_err());
+    }
+}
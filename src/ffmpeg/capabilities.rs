@@ -0,0 +1,374 @@
+/// Introspection of the components an `FFmpeg` build supports.
+///
+/// `FFmpeg` builds differ in the encoders, filters and hardware acceleration
+/// methods they are compiled with. The lists printed by `ffmpeg -encoders`,
+/// `-decoders`, `-filters`, `-muxers` and `-hwaccels` are parsed once per
+/// executable and cached for the lifetime of the process, so that a render
+/// can be checked for missing components before it starts instead of failing
+/// halfway through.
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::ffmpeg::{Error, FFmpeg, Result};
+
+/// Kind of an `FFmpeg` component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComponentKind {
+    /// An encoder (e.g. `libx264`).
+    Encoder,
+    /// A decoder (e.g. `h264`).
+    Decoder,
+    /// A filter (e.g. `overlay`).
+    Filter,
+    /// A muxer (e.g. `mp4`).
+    Muxer,
+    /// A hardware acceleration method (e.g. `cuda`).
+    HwAccel,
+}
+
+impl ComponentKind {
+    /// Gets the `ffmpeg` option that lists the components of this kind.
+    fn list_option(self) -> &'static str {
+        match self {
+            Self::Encoder => "-encoders",
+            Self::Decoder => "-decoders",
+            Self::Filter => "-filters",
+            Self::Muxer => "-muxers",
+            Self::HwAccel => "-hwaccels",
+        }
+    }
+}
+
+impl fmt::Display for ComponentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Encoder => "encoder",
+            Self::Decoder => "decoder",
+            Self::Filter => "filter",
+            Self::Muxer => "muxer",
+            Self::HwAccel => "hardware acceleration method",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The components supported by an `FFmpeg` build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Names of the available encoders.
+    pub encoders: BTreeSet<String>,
+    /// Names of the available decoders.
+    pub decoders: BTreeSet<String>,
+    /// Names of the available filters.
+    pub filters: BTreeSet<String>,
+    /// Names of the available muxers.
+    pub muxers: BTreeSet<String>,
+    /// Names of the available hardware acceleration methods.
+    pub hwaccels: BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// Queries the components of an `FFmpeg` executable.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` installation to query
+    ///
+    /// # Returns
+    ///
+    /// The supported components.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `FFmpeg` cannot be run.
+    pub fn query(ffmpeg: &FFmpeg) -> Result<Self> {
+        let list = |kind: ComponentKind| -> Result<String> {
+            let args = [OsString::from("-hide_banner"), kind.list_option().into()];
+            let output = ffmpeg
+                .executor()
+                .run(ffmpeg.path(), &args)
+                .map_err(|e| Error::ExecutionError(format!("Failed to execute FFmpeg: {e}")))?;
+            if !output.success {
+                return Err(Error::ProcessTerminated {
+                    exit_code: output.exit_code,
+                    message: format!("ffmpeg {} failed: {}", kind.list_option(), output.stderr),
+                });
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        Ok(Self {
+            encoders: parse_codec_list(&list(ComponentKind::Encoder)?),
+            decoders: parse_codec_list(&list(ComponentKind::Decoder)?),
+            filters: parse_filter_list(&list(ComponentKind::Filter)?),
+            muxers: parse_format_list(&list(ComponentKind::Muxer)?),
+            hwaccels: parse_hwaccel_list(&list(ComponentKind::HwAccel)?),
+        })
+    }
+
+    /// Gets the capabilities of an `FFmpeg` executable, querying them only
+    /// the first time they are requested for that executable.
+    ///
+    /// Instances with a custom executor are queried every time.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` installation to query
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capabilities are not cached and cannot be queried.
+    pub fn cached(ffmpeg: &FFmpeg) -> Result<Arc<Self>> {
+        // 実プロセス以外（テスト用の実行器など）の結果はキャッシュしない
+        if !ffmpeg.runs_processes() {
+            return Ok(Arc::new(Self::query(ffmpeg)?));
+        }
+
+        let path = ffmpeg.path();
+        static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<Capabilities>>>> = OnceLock::new();
+        let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+
+        if let Some(capabilities) = cache.lock().ok().and_then(|c| c.get(path).cloned()) {
+            return Ok(capabilities);
+        }
+
+        // 問い合わせ中はロックを保持しない（並行した問い合わせは結果が同じ）
+        let capabilities = Arc::new(Self::query(ffmpeg)?);
+        if let Ok(mut cache) = cache.lock() {
+            cache.insert(path.to_path_buf(), Arc::clone(&capabilities));
+        }
+        Ok(capabilities)
+    }
+
+    /// Determines whether a component is available.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of the component
+    /// * `name` - Name of the component
+    #[must_use]
+    pub fn has(&self, kind: ComponentKind, name: &str) -> bool {
+        let names = match kind {
+            ComponentKind::Encoder => &self.encoders,
+            ComponentKind::Decoder => &self.decoders,
+            ComponentKind::Filter => &self.filters,
+            ComponentKind::Muxer => &self.muxers,
+            ComponentKind::HwAccel => &self.hwaccels,
+        };
+        names.contains(name)
+    }
+
+    /// Checks that a component is available.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of the component
+    /// * `name` - Name of the component
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingComponent` naming the component if it is not available.
+    pub fn require(&self, kind: ComponentKind, name: &str) -> Result<()> {
+        if self.has(kind, name) {
+            Ok(())
+        } else {
+            Err(Error::MissingComponent {
+                kind,
+                name: name.to_string(),
+            })
+        }
+    }
+
+    /// Checks that all components are available.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - Kinds and names of the components
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingComponent` naming the first missing component.
+    pub fn require_all<'a>(
+        &self,
+        components: impl IntoIterator<Item = (ComponentKind, &'a str)>,
+    ) -> Result<()> {
+        components
+            .into_iter()
+            .try_for_each(|(kind, name)| self.require(kind, name))
+    }
+}
+
+/// Gets the names of the filters used in a filtergraph.
+///
+/// # Arguments
+///
+/// * `filtergraph` - A filtergraph such as `[0:v] scale=640:360,setsar=1 [v0]`
+///
+/// # Returns
+///
+/// The filter names, without pad labels and options.
+#[must_use]
+pub fn filter_names(filtergraph: &str) -> BTreeSet<String> {
+    filtergraph
+        .split([';', ','])
+        .filter_map(|filter| {
+            // 先頭の入力ラベル [..] を取り除く
+            let mut filter = filter.trim_start();
+            while let Some(rest) = filter.strip_prefix('[') {
+                filter = rest
+                    .split_once(']')
+                    .map_or("", |(_, rest)| rest)
+                    .trim_start();
+            }
+            let name: String = filter
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            (!name.is_empty()).then_some(name)
+        })
+        .collect()
+}
+
+/// Parses the output of `ffmpeg -encoders` or `ffmpeg -decoders`.
+///
+/// The entries follow a `------` separator line and consist of a flag
+/// column such as `V....D` and the codec name.
+fn parse_codec_list(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the output of `ffmpeg -filters`.
+///
+/// Entries consist of a flag column such as `TSC` or `...`, the filter name
+/// and its input and output pad types such as `V->V`; the legend lines
+/// before them contain `=` instead.
+fn parse_filter_list(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let flags = fields.next()?;
+            let name = fields.next()?;
+            let pads = fields.next()?;
+            (flags
+                .chars()
+                .all(|c| matches!(c, 'T' | 'S' | 'C' | '.' | 'A' | '|'))
+                && pads.contains("->"))
+            .then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Parses the output of `ffmpeg -muxers`.
+///
+/// The entries follow a `--` separator line and consist of the `E` flag and
+/// one or more comma-separated format names.
+fn parse_format_list(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .skip_while(|line| line.trim() != "--")
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the output of `ffmpeg -hwaccels`, one method per line after the
+/// `Hardware acceleration methods:` heading.
+fn parse_hwaccel_list(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.ends_with(':'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codec_list() {
+        let output = "Encoders:
+ V..... = Video
+ A..... = Audio
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+ A....D aac                  AAC (Advanced Audio Coding)
+";
+        let encoders = parse_codec_list(output);
+        assert_eq!(
+            encoders.into_iter().collect::<Vec<_>>(),
+            vec!["aac".to_string(), "libx264".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_filter_names() {
+        let names = filter_names(
+            "[0:v] format=yuva420p ,scale=1920:1080,setsar=1 [v0];[v0][v1] overlay=shortest=1 [v2];[a0][a1] amix=inputs=2 [aout]",
+        );
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            vec!["amix", "format", "overlay", "scale", "setsar"]
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_list() {
+        let output = "Filters:
+  T.. = Timeline support
+  | = Source or sink filter
+ TSC overlay           VV->V      Overlay a video source on top of the input.
+ ... amix              N->A       Audio mixing.
+ ... anullsrc          |->A       Null audio source, return empty audio frames.
+";
+        let filters = parse_filter_list(output);
+        assert!(filters.contains("overlay"));
+        assert!(filters.contains("amix"));
+        assert!(filters.contains("anullsrc"));
+        assert_eq!(filters.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_format_and_hwaccel_lists() {
+        let muxers = parse_format_list(
+            "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+  E mp4             MP4 (MPEG-4 Part 14)
+  E image2,image2pipe image2 sequence
+",
+        );
+        assert!(muxers.contains("mp4"));
+        assert!(muxers.contains("image2pipe"));
+
+        let hwaccels = parse_hwaccel_list("Hardware acceleration methods:\ncuda\nvaapi\n\n");
+        assert_eq!(hwaccels.len(), 2);
+
+        let capabilities = Capabilities {
+            hwaccels,
+            ..Capabilities::default()
+        };
+        assert!(capabilities.require(ComponentKind::HwAccel, "cuda").is_ok());
+        let error = capabilities
+            .require(ComponentKind::HwAccel, "qsv")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "FFmpeg is missing the hardware acceleration method qsv"
+        );
+    }
+}
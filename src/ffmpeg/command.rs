@@ -2,8 +2,8 @@
 ///
 /// This module provides a simplified interface for building `FFmpeg` commands.
 use crate::ffmpeg::{Error, FFmpeg, Result};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 
 /// Represents an `FFmpeg` command.
 #[derive(Debug, Clone)]
@@ -12,8 +12,8 @@ pub struct FFmpegCommand<'a> {
     ffmpeg: &'a FFmpeg,
     /// Input options to apply before specifying inputs.
     input_options: Vec<String>,
-    /// Input files for the command.
-    inputs: Vec<PathBuf>,
+    /// Input files for the command, each with the options that apply to it alone.
+    inputs: Vec<(Vec<String>, PathBuf)>,
     /// Filter complex to apply (if any).
     filter_complex: Option<String>,
     /// Output options to apply before specifying output.
@@ -84,7 +84,35 @@ impl<'a> FFmpegCommand<'a> {
     ///
     /// Self for method chaining
     pub fn input<P: AsRef<Path>>(&mut self, input: P) -> &mut Self {
-        self.inputs.push(input.as_ref().to_path_buf());
+        self.inputs.push((Vec::new(), input.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Adds an input file with options that apply to this input only.
+    ///
+    /// Unlike `input_options`, which come before the first input, the options
+    /// are placed right before this input's `-i`, e.g. to seek each input of
+    /// a filtergraph separately.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the input
+    /// * `input` - The input file path
+    ///
+    /// # Returns
+    ///
+    /// Self for method chaining
+    pub fn input_with_options<S, I, P>(&mut self, options: I, input: P) -> &mut Self
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+        P: AsRef<Path>,
+    {
+        let options = options
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect();
+        self.inputs.push((options, input.as_ref().to_path_buf()));
         self
     }
 
@@ -198,27 +226,17 @@ impl<'a> FFmpegCommand<'a> {
     ///
     /// Self for method chaining
     pub fn add_input<P: AsRef<Path>>(&mut self, input: P) -> &mut Self {
-        self.inputs.push(input.as_ref().to_path_buf());
+        self.inputs.push((Vec::new(), input.as_ref().to_path_buf()));
         self
     }
 
-    /// Executes the `FFmpeg` command.
-    ///
-    /// Returns an error if:
-    /// * No output file is specified
-    /// * No input files are specified
-    /// * The `FFmpeg` process fails to start or returns a non-zero exit code
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the command fails to execute or returns a non-zero exit code.
-    pub fn execute(&self) -> Result<()> {
-        // バリデーションチェック
-        if self.output.is_none() {
+    /// Checks that the command has an output and at least one input.
+    fn validate(&self) -> Result<&Path> {
+        let Some(output) = self.output.as_deref() else {
             return Err(Error::MissingArgument(
                 "No output file specified".to_string(),
             ));
-        }
+        };
 
         if self.inputs.is_empty() {
             return Err(Error::MissingArgument(
@@ -226,62 +244,136 @@ impl<'a> FFmpegCommand<'a> {
             ));
         }
 
-        // コマンドの構築
-        let mut command = Command::new(self.ffmpeg.path());
+        Ok(output)
+    }
 
-        // まず、ストリーム処理を効率化するためにバッファリングを設定
-        command.stdout(Stdio::piped());
-        command.stderr(Stdio::piped());
+    /// Builds the arguments `execute` passes to `FFmpeg`.
+    ///
+    /// # Returns
+    ///
+    /// The arguments, in the order input options, inputs, filter complex,
+    /// output options, overwrite flag and output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no output or no input is specified.
+    pub fn args(&self) -> Result<Vec<OsString>> {
+        let output = self.validate()?;
+        let mut args: Vec<OsString> = Vec::new();
 
         // 入力オプションとファイル
-        if !self.input_options.is_empty() {
-            command.args(&self.input_options);
-        }
-
-        for input in &self.inputs {
-            command.arg("-i").arg(input);
+        args.extend(self.input_options.iter().map(OsString::from));
+        for (options, input) in &self.inputs {
+            args.extend(options.iter().map(OsString::from));
+            args.push("-i".into());
+            args.push(input.into());
         }
 
         // フィルター複合体がある場合
         if let Some(filter) = &self.filter_complex {
-            command.arg("-filter_complex").arg(filter);
+            args.push("-filter_complex".into());
+            args.push(filter.into());
         }
 
         // 出力オプション
-        if !self.output_options.is_empty() {
-            command.args(&self.output_options);
-        }
+        args.extend(self.output_options.iter().map(OsString::from));
 
         // 上書きフラグがある場合は-yフラグを追加
         if self.overwrite {
-            command.arg("-y");
+            args.push("-y".into());
         }
 
         // 最後に出力ファイルを追加
-        // (unwrapは問題ありません。既に上で出力が存在するか確認しているため)
-        command.arg(self.output.as_ref().unwrap());
+        args.push(output.into());
+        Ok(args)
+    }
+
+    /// Builds the arguments `execute_with_progress` passes to `FFmpeg`,
+    /// which write progress reports to standard error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no output or no input is specified.
+    pub fn progress_args(&self) -> Result<Vec<OsString>> {
+        let output = self.validate()?;
+        let mut args: Vec<OsString> = Vec::new();
+
+        // Add global options
+        if self.overwrite {
+            args.push("-y".into());
+        }
+
+        // Add progress option for stderr output
+        args.push("-progress".into());
+        args.push("pipe:2".into());
+
+        // Add input options before the first input, then the inputs
+        args.extend(self.input_options.iter().map(OsString::from));
+        for (options, input) in &self.inputs {
+            args.extend(options.iter().map(OsString::from));
+            args.push("-i".into());
+            args.push(input.into());
+        }
+
+        // Add filter complex if specified
+        if let Some(filter) = &self.filter_complex {
+            args.push("-filter_complex".into());
+            args.push(filter.into());
+        }
+
+        // Add output options and output
+        args.extend(self.output_options.iter().map(OsString::from));
+        args.push(output.into());
+        Ok(args)
+    }
+
+    /// Executes the `FFmpeg` command.
+    ///
+    /// The command is run by the executor of the `FFmpeg` instance.
+    ///
+    /// Returns an error if:
+    /// * No output file is specified
+    /// * No input files are specified
+    /// * The `FFmpeg` process fails to start or returns a non-zero exit code
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails to execute or returns a non-zero exit code.
+    pub fn execute(&self) -> Result<()> {
+        let args = self.args()?;
 
         // コマンドを実行
-        let output = command.output().map_err(|e| Error::IoError(e))?;
+        let output = self
+            .ffmpeg
+            .executor()
+            .run(self.ffmpeg.path(), &args)
+            .map_err(Error::IoError)?;
 
         // 終了コードをチェック
-        if !output.status.success() {
-            // FFmpegのエラーメッセージを取得して返す
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let error_message = stderr
-                .lines()
-                .filter(|line| line.contains("Error") || line.contains("Invalid"))
-                .collect::<Vec<_>>()
-                .join("\n");
-
-            if !error_message.is_empty() {
-                return Err(Error::ExecutionError(error_message));
-            }
-
-            return Err(Error::ProcessTerminated {
-                exit_code: output.status.code(),
-                message: format!("FFmpeg process failed with exit code: {}", output.status),
-            });
+        if !output.success {
+            // 原因が分からない場合はFFmpegのエラーメッセージを取得して返す
+            return Err(Error::from_failed_run(&output, || {
+                let error_message = output
+                    .stderr
+                    .lines()
+                    .filter(|line| line.contains("Error") || line.contains("Invalid"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if error_message.is_empty() {
+                    Error::ProcessTerminated {
+                        exit_code: output.exit_code,
+                        message: format!(
+                            "FFmpeg process failed with exit code: {}",
+                            output
+                                .exit_code
+                                .map_or_else(|| "none".to_string(), |code| code.to_string())
+                        ),
+                    }
+                } else {
+                    Error::ExecutionError(error_message)
+                }
+            }));
         }
 
         Ok(())
@@ -304,86 +396,23 @@ impl<'a> FFmpegCommand<'a> {
     where
         F: FnMut(&str),
     {
-        // Check that we have inputs and an output
-        if self.inputs.is_empty() {
-            return Err(Error::MissingArgument(
-                "No input files specified".to_string(),
-            ));
-        }
+        let args = self.progress_args()?;
 
-        if self.output.is_none() {
-            return Err(Error::MissingArgument(
-                "No output file specified".to_string(),
-            ));
-        }
-
-        // Build the command
-        let mut cmd = Command::new(self.ffmpeg.path());
-
-        // Add global options
-        if self.overwrite {
-            cmd.arg("-y");
-        }
-
-        // Add progress option for stderr output
-        cmd.arg("-progress").arg("pipe:2");
-
-        // Add input options and inputs
-        for i in 0..self.inputs.len() {
-            // Add input options for this input
-            if i == 0 && !self.input_options.is_empty() {
-                for option in &self.input_options {
-                    cmd.arg(option);
-                }
-            }
-
-            // Add the input
-            cmd.arg("-i").arg(&self.inputs[i]);
-        }
-
-        // Add filter complex if specified
-        if let Some(filter) = &self.filter_complex {
-            cmd.arg("-filter_complex").arg(filter);
-        }
-
-        // Add output options
-        for option in &self.output_options {
-            cmd.arg(option);
-        }
-
-        // Add output
-        cmd.arg(self.output.as_ref().unwrap());
-
-        // Execute the command
-        let mut child = cmd
-            .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
+        // Execute the command, passing progress updates from stderr to the callback
+        let output = self
+            .ffmpeg
+            .executor()
+            .run_streaming(self.ffmpeg.path(), &args, &mut progress_callback)
             .map_err(Error::IoError)?;
 
-        // Read progress updates from stderr
-        if let Some(stderr) = child.stderr.take() {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
-
-            // Process each line as it becomes available
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    progress_callback(&line);
-                }
-            }
-        }
-
-        // Wait for the process to finish
-        let output = child.wait_with_output().map_err(Error::IoError)?;
-
         // Check for success
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::ProcessTerminated {
-                exit_code: output.status.code(),
-                message: format!("FFmpeg process failed: {stderr}"),
-            });
+        if !output.success {
+            return Err(Error::from_failed_run(&output, || {
+                Error::ProcessTerminated {
+                    exit_code: output.exit_code,
+                    message: format!("FFmpeg process failed: {}", output.stderr),
+                }
+            }));
         }
 
         Ok(())
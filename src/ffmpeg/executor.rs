@@ -0,0 +1,695 @@
+/// Execution of `FFmpeg` and `ffprobe` processes.
+///
+/// `FFmpegCommand::execute`, `FFmpeg::get_media_info` and the capability
+/// queries do not spawn processes themselves but go through the
+/// `FFmpegExecutor` of their `FFmpeg` instance. The default
+/// `ProcessExecutor` runs the programs; `RecordingExecutor` is a test double
+/// that records every argument vector and answers with scripted outputs, so
+/// code built on `FFmpeg` can be tested without an `FFmpeg` installation.
+///
+/// Code that looks up `FFmpeg` with `FFmpeg::detect` can be pointed at a test
+/// double with `FFmpeg::scoped_default`. The override is per thread; the
+/// renderer passes it on to the worker threads it starts.
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use crate::ffmpeg::{FFmpeg, Version};
+
+/// The outcome of running a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionOutput {
+    /// Exit code of the process, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// Whether the process exited successfully.
+    pub success: bool,
+    /// Standard output of the process.
+    pub stdout: Vec<u8>,
+    /// Standard error of the process.
+    pub stderr: String,
+}
+
+impl ExecutionOutput {
+    /// Creates the output of a successful run.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdout` - Standard output of the process
+    #[must_use]
+    pub fn success(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            exit_code: Some(0),
+            success: true,
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Creates the output of a failed run.
+    ///
+    /// # Arguments
+    ///
+    /// * `exit_code` - Exit code of the process
+    /// * `stderr` - Standard error of the process
+    #[must_use]
+    pub fn failure(exit_code: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            exit_code: Some(exit_code),
+            success: false,
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// Sets the standard error of the output.
+    #[must_use]
+    pub fn with_stderr(mut self, stderr: impl Into<String>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+}
+
+/// Runs `FFmpeg` and `ffprobe` processes.
+pub trait FFmpegExecutor: fmt::Debug + Send + Sync {
+    /// Runs a program to completion and collects its output.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Path to the program
+    /// * `args` - Arguments of the program
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program cannot be started.
+    fn run(&self, program: &Path, args: &[OsString]) -> io::Result<ExecutionOutput>;
+
+    /// Runs a program to completion, passing every line of its standard
+    /// error to a callback as it is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Path to the program
+    /// * `args` - Arguments of the program
+    /// * `on_stderr_line` - Callback receiving the standard error lines
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program cannot be started.
+    fn run_streaming(
+        &self,
+        program: &Path,
+        args: &[OsString],
+        on_stderr_line: &mut dyn FnMut(&str),
+    ) -> io::Result<ExecutionOutput>;
+
+    /// Starts a program without waiting for it, so that its standard input
+    /// can be written and its standard output and error read while it runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Path to the program
+    /// * `args` - Arguments of the program
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program cannot be started.
+    fn spawn(&self, program: &Path, args: &[OsString]) -> io::Result<SpawnedProcess>;
+}
+
+/// Exit status of a spawned process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessStatus {
+    /// Exit code of the process, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// Whether the process exited successfully.
+    pub success: bool,
+}
+
+/// Waits for or stops a spawned process.
+pub trait ProcessHandle: Send {
+    /// Waits for the process to exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be waited for.
+    fn wait(&mut self) -> io::Result<ProcessStatus>;
+
+    /// Stops the process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be stopped.
+    fn kill(&mut self) -> io::Result<()>;
+}
+
+impl ProcessHandle for Child {
+    fn wait(&mut self) -> io::Result<ProcessStatus> {
+        let status = Child::wait(self)?;
+        Ok(ProcessStatus {
+            exit_code: status.code(),
+            success: status.success(),
+        })
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self)
+    }
+}
+
+/// A running process started by `FFmpegExecutor::spawn`.
+pub struct SpawnedProcess {
+    /// Standard input of the process, until it is taken; dropping it closes
+    /// the input.
+    pub stdin: Option<Box<dyn Write + Send>>,
+    /// Standard output of the process, until it is taken.
+    pub stdout: Option<Box<dyn Read + Send>>,
+    /// Standard error of the process, until it is taken.
+    pub stderr: Option<Box<dyn Read + Send>>,
+    /// Waits for or stops the process.
+    handle: Box<dyn ProcessHandle>,
+}
+
+impl SpawnedProcess {
+    /// Creates a spawned process from its pipes and handle.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdin` - Standard input of the process
+    /// * `stdout` - Standard output of the process
+    /// * `stderr` - Standard error of the process
+    /// * `handle` - Waits for or stops the process
+    #[must_use]
+    pub fn new(
+        stdin: Box<dyn Write + Send>,
+        stdout: Box<dyn Read + Send>,
+        stderr: Box<dyn Read + Send>,
+        handle: Box<dyn ProcessHandle>,
+    ) -> Self {
+        Self {
+            stdin: Some(stdin),
+            stdout: Some(stdout),
+            stderr: Some(stderr),
+            handle,
+        }
+    }
+
+    /// Waits for the process to exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be waited for.
+    pub fn wait(&mut self) -> io::Result<ProcessStatus> {
+        self.handle.wait()
+    }
+
+    /// Stops the process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be stopped.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.handle.kill()
+    }
+}
+
+impl fmt::Debug for SpawnedProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnedProcess")
+            .field("stdin", &self.stdin.is_some())
+            .field("stdout", &self.stdout.is_some())
+            .field("stderr", &self.stderr.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs programs as child processes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessExecutor;
+
+impl ProcessExecutor {
+    /// Gets the process executor shared by all `FFmpeg` instances that do
+    /// not set their own.
+    #[must_use]
+    pub fn shared() -> Arc<dyn FFmpegExecutor> {
+        static SHARED: OnceLock<Arc<dyn FFmpegExecutor>> = OnceLock::new();
+        Arc::clone(SHARED.get_or_init(|| Arc::new(Self)))
+    }
+}
+
+impl FFmpegExecutor for ProcessExecutor {
+    fn run(&self, program: &Path, args: &[OsString]) -> io::Result<ExecutionOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        Ok(ExecutionOutput {
+            exit_code: output.status.code(),
+            success: output.status.success(),
+            stdout: output.stdout,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    fn run_streaming(
+        &self,
+        program: &Path,
+        args: &[OsString],
+        on_stderr_line: &mut dyn FnMut(&str),
+    ) -> io::Result<ExecutionOutput> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // 標準エラーは行ごとにコールバックへ渡し、エラー報告用にも残す
+        let mut stderr = String::new();
+        if let Some(pipe) = child.stderr.take() {
+            for line in BufReader::new(pipe)
+                .lines()
+                .map_while(std::result::Result::ok)
+            {
+                on_stderr_line(&line);
+                stderr.push_str(&line);
+                stderr.push('\n');
+            }
+        }
+
+        let output = child.wait_with_output()?;
+        Ok(ExecutionOutput {
+            exit_code: output.status.code(),
+            success: output.status.success(),
+            stdout: output.stdout,
+            stderr,
+        })
+    }
+
+    fn spawn(&self, program: &Path, args: &[OsString]) -> io::Result<SpawnedProcess> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("stdin not piped"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("stdout not piped"))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| io::Error::other("stderr not piped"))?;
+        Ok(SpawnedProcess::new(
+            Box::new(stdin),
+            Box::new(stdout),
+            Box::new(stderr),
+            Box::new(child),
+        ))
+    }
+}
+
+/// A writer whose contents are shared with its clones.
+#[derive(Debug, Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Gets the written bytes.
+    fn contents(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .map(|buffer| buffer.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|_| io::Error::other("stdin buffer poisoned"))?
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The process `RecordingExecutor::spawn` pretends to start, which has
+/// already exited with the scripted status.
+#[derive(Debug, Clone, Copy)]
+struct ScriptedProcess {
+    /// The scripted exit status.
+    status: ProcessStatus,
+}
+
+impl ProcessHandle for ScriptedProcess {
+    fn wait(&mut self) -> io::Result<ProcessStatus> {
+        Ok(self.status)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A program run recorded by `RecordingExecutor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// Path to the program.
+    pub program: PathBuf,
+    /// Arguments of the program.
+    pub args: Vec<String>,
+}
+
+impl RecordedCall {
+    /// Determines whether the program is `ffprobe`.
+    #[must_use]
+    pub fn is_ffprobe(&self) -> bool {
+        self.program
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy() == "ffprobe")
+    }
+
+    /// Determines whether an argument was passed.
+    #[must_use]
+    pub fn has_arg(&self, arg: &str) -> bool {
+        self.args.iter().any(|a| a == arg)
+    }
+
+    /// Gets the value passed after an option.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The option, e.g. `-c:v`
+    ///
+    /// # Returns
+    ///
+    /// The argument following the first occurrence of the option.
+    #[must_use]
+    pub fn option_value(&self, option: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|a| a == option)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    /// Gets the values passed after every occurrence of an option.
+    #[must_use]
+    pub fn option_values(&self, option: &str) -> Vec<&str> {
+        self.args
+            .windows(2)
+            .filter(|pair| pair[0] == option)
+            .map(|pair| pair[1].as_str())
+            .collect()
+    }
+
+    /// Gets the last argument, which is the output of an `FFmpeg` command.
+    #[must_use]
+    pub fn last_arg(&self) -> Option<&str> {
+        self.args.last().map(String::as_str)
+    }
+}
+
+/// A scripted response of `RecordingExecutor`.
+#[derive(Debug, Clone)]
+struct Response {
+    /// Text the joined command line must contain.
+    pattern: String,
+    /// The output to answer with.
+    output: ExecutionOutput,
+}
+
+/// A test double that records the programs it is asked to run and answers
+/// with scripted outputs instead of running them.
+///
+/// Responses are matched against the command line (the program followed by
+/// its arguments, separated by spaces); the most recently added matching
+/// response wins. Runs without a matching response succeed with no output.
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    /// The recorded runs, in order.
+    calls: Mutex<Vec<RecordedCall>>,
+    /// The scripted responses.
+    responses: Mutex<Vec<Response>>,
+    /// What was written to the standard input of spawned processes, by the
+    /// index of their call.
+    inputs: Mutex<Vec<(usize, SharedBuffer)>>,
+}
+
+impl RecordingExecutor {
+    /// Creates a recording executor without scripted responses.
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Scripts the output of runs whose command line contains a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Text the command line must contain, e.g. `ffprobe`
+    /// * `output` - The output to answer with
+    pub fn respond(&self, pattern: impl Into<String>, output: ExecutionOutput) {
+        if let Ok(mut responses) = self.responses.lock() {
+            responses.push(Response {
+                pattern: pattern.into(),
+                output,
+            });
+        }
+    }
+
+    /// Scripts the JSON `ffprobe` prints for every probed file.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The output of `ffprobe -print_format json -show_format -show_streams`
+    pub fn respond_media_info(&self, json: &str) {
+        self.respond("ffprobe", ExecutionOutput::success(json));
+    }
+
+    /// Gets the recorded runs.
+    #[must_use]
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// Gets what was written to the standard input of a spawned process.
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - Index of the call in `calls`
+    #[must_use]
+    pub fn stdin(&self, call: usize) -> Vec<u8> {
+        self.inputs
+            .lock()
+            .ok()
+            .and_then(|inputs| {
+                inputs
+                    .iter()
+                    .find(|(index, _)| *index == call)
+                    .map(|(_, buffer)| buffer.contents())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets the recorded `FFmpeg` runs, leaving out `ffprobe`.
+    #[must_use]
+    pub fn ffmpeg_calls(&self) -> Vec<RecordedCall> {
+        self.calls()
+            .into_iter()
+            .filter(|call| !call.is_ffprobe())
+            .collect()
+    }
+
+    /// Records a run and looks up its response.
+    ///
+    /// # Returns
+    ///
+    /// The index of the recorded call and the response to the run.
+    fn record(&self, program: &Path, args: &[OsString]) -> (usize, ExecutionOutput) {
+        let call = RecordedCall {
+            program: program.to_path_buf(),
+            args: args
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        };
+        let command_line = std::iter::once(call.program.to_string_lossy().into_owned())
+            .chain(call.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // 並行する実行に割り込まれないよう、追加と同じロックの中で番号を決める
+        let index = {
+            let mut calls = self.calls.lock().unwrap_or_else(PoisonError::into_inner);
+            calls.push(call);
+            calls.len() - 1
+        };
+
+        let output = self
+            .responses
+            .lock()
+            .ok()
+            .and_then(|responses| {
+                responses
+                    .iter()
+                    .rev()
+                    .find(|response| command_line.contains(&response.pattern))
+                    .map(|response| response.output.clone())
+            })
+            .unwrap_or_else(|| ExecutionOutput::success(Vec::new()));
+        (index, output)
+    }
+}
+
+impl FFmpegExecutor for RecordingExecutor {
+    fn run(&self, program: &Path, args: &[OsString]) -> io::Result<ExecutionOutput> {
+        Ok(self.record(program, args).1)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &Path,
+        args: &[OsString],
+        on_stderr_line: &mut dyn FnMut(&str),
+    ) -> io::Result<ExecutionOutput> {
+        let (_, output) = self.record(program, args);
+        for line in output.stderr.lines() {
+            on_stderr_line(line);
+        }
+        Ok(output)
+    }
+
+    fn spawn(&self, program: &Path, args: &[OsString]) -> io::Result<SpawnedProcess> {
+        let (index, output) = self.record(program, args);
+        let status = ProcessStatus {
+            exit_code: output.exit_code,
+            success: output.success,
+        };
+        // 標準入力に書き込まれた内容は記録し、テストから参照できるようにする
+        let stdin = SharedBuffer::default();
+        if let Ok(mut inputs) = self.inputs.lock() {
+            inputs.push((index, stdin.clone()));
+        }
+        Ok(SpawnedProcess::new(
+            Box::new(stdin),
+            Box::new(Cursor::new(output.stdout)),
+            Box::new(Cursor::new(output.stderr.into_bytes())),
+            Box::new(ScriptedProcess { status }),
+        ))
+    }
+}
+
+impl FFmpeg {
+    /// Creates an `FFmpeg` instance that runs nothing and records every
+    /// command in a `RecordingExecutor`.
+    ///
+    /// # Returns
+    ///
+    /// The instance and its executor, for scripting responses and
+    /// inspecting the recorded commands.
+    #[must_use]
+    pub fn recording() -> (Self, Arc<RecordingExecutor>) {
+        let executor = RecordingExecutor::new();
+        let ffmpeg = Self::new(PathBuf::from("ffmpeg"), Version::new(7, 0, 0))
+            .with_executor(Arc::clone(&executor) as Arc<dyn FFmpegExecutor>);
+        (ffmpeg, executor)
+    }
+
+    /// Makes `FFmpeg::detect` return the given instance on the current
+    /// thread until the returned guard is dropped.
+    ///
+    /// This points code that looks up `FFmpeg` itself, such as the render
+    /// pipeline and the CLI commands, at a test double.
+    ///
+    /// The override only applies to the current thread. The segment and
+    /// track thread pools, the render queue workers and `render_async`
+    /// install the instance of the thread that starts them on their own
+    /// threads; any other thread spawned while the guard is alive detects
+    /// the installed `FFmpeg` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The instance `detect` returns
+    #[must_use = "the default is restored when the guard is dropped"]
+    pub fn scoped_default(ffmpeg: Self) -> ScopedDefault {
+        let previous = DEFAULT.with(|default| default.replace(Some(ffmpeg)));
+        ScopedDefault { previous }
+    }
+
+    /// Gets the instance set with `scoped_default` on the current thread.
+    pub(crate) fn scoped() -> Option<Self> {
+        DEFAULT.with(|default| default.borrow().clone())
+    }
+}
+
+thread_local! {
+    /// The instance `FFmpeg::detect` returns on this thread, if any.
+    static DEFAULT: RefCell<Option<FFmpeg>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous `FFmpeg::detect` result when dropped.
+#[derive(Debug)]
+pub struct ScopedDefault {
+    /// The instance that was set before.
+    previous: Option<FFmpeg>,
+}
+
+impl Drop for ScopedDefault {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        DEFAULT.with(|default| *default.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_executor_scripts_and_records() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond("-i broken.mp4", ExecutionOutput::failure(1, "Invalid data"));
+
+        let mut command = ffmpeg.command();
+        command
+            .input("in.mp4")
+            .add_output_option("-c:v", "libx264")
+            .set_output("out.mp4");
+        assert!(command.execute().is_ok());
+
+        let mut broken = ffmpeg.command();
+        broken.input("broken.mp4").set_output("out.mp4");
+        assert!(broken.execute().is_err());
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].option_value("-i"), Some("in.mp4"));
+        assert_eq!(calls[0].option_value("-c:v"), Some("libx264"));
+        assert_eq!(calls[0].last_arg(), Some("out.mp4"));
+    }
+
+    #[test]
+    fn test_scoped_default_overrides_detect() {
+        let (ffmpeg, _executor) = FFmpeg::recording();
+        {
+            let _default = FFmpeg::scoped_default(ffmpeg);
+            assert_eq!(FFmpeg::detect().unwrap().path(), Path::new("ffmpeg"));
+        }
+        assert!(FFmpeg::scoped().is_none());
+    }
+}
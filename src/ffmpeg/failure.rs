@@ -0,0 +1,476 @@
+/// Classification of `FFmpeg` failures.
+///
+/// When `FFmpeg` exits with an error, the reason is only available as log
+/// lines on standard error. This module recognizes the common causes in
+/// those lines, so that callers can react to them (e.g. fall back to
+/// software encoding when a hardware device cannot be opened) and users get
+/// a short message with a hint instead of the whole log.
+use std::fmt;
+
+use crate::ffmpeg::ComponentKind;
+
+/// A recognized cause of an `FFmpeg` failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureCause {
+    /// An encoder, decoder, filter or muxer is not part of the `FFmpeg` build.
+    MissingComponent {
+        /// Kind of the missing component.
+        kind: ComponentKind,
+        /// Name of the missing component.
+        name: String,
+    },
+    /// A filter was given an option it does not accept, or an invalid value.
+    InvalidFilterOption {
+        /// Name of the filter (e.g. `scale`).
+        filter: String,
+        /// Name of the offending option, if `FFmpeg` named it.
+        option: Option<String>,
+        /// The message `FFmpeg` logged.
+        message: String,
+    },
+    /// An input file does not exist or cannot be decoded.
+    UnreadableInput {
+        /// Path of the input.
+        path: String,
+        /// Why the input cannot be read.
+        reason: String,
+    },
+    /// A file cannot be opened because of its permissions.
+    PermissionDenied {
+        /// Path of the file.
+        path: String,
+    },
+    /// The output device has no space left.
+    DiskFull,
+    /// A hardware acceleration device or encoder could not be initialized.
+    HwAccelInit {
+        /// The message `FFmpeg` logged.
+        message: String,
+    },
+}
+
+impl FailureCause {
+    /// Recognizes the cause of a failure from the standard error of `FFmpeg`.
+    ///
+    /// Causes are checked from the most to the least specific, since one
+    /// failure often produces several error lines (a failed hardware device,
+    /// for example, is followed by a generic error opening the encoder).
+    ///
+    /// # Arguments
+    ///
+    /// * `stderr` - The standard error of the failed `FFmpeg` process
+    ///
+    /// # Returns
+    ///
+    /// The cause, or `None` if it is not recognized.
+    #[must_use]
+    pub fn classify(stderr: &str) -> Option<Self> {
+        let lines: Vec<&str> = stderr.lines().map(str::trim).collect();
+
+        find_hwaccel_failure(&lines)
+            .or_else(|| find_missing_component(&lines))
+            .or_else(|| find_invalid_filter_option(&lines))
+            .or_else(|| {
+                lines
+                    .iter()
+                    .any(|line| line.contains("No space left on device"))
+                    .then_some(Self::DiskFull)
+            })
+            .or_else(|| find_permission_denied(&lines))
+            .or_else(|| find_unreadable_input(&lines))
+    }
+
+    /// Determines whether the failure was caused by hardware acceleration,
+    /// i.e. whether the same command could succeed in software.
+    #[must_use]
+    pub fn is_hardware_related(&self) -> bool {
+        match self {
+            Self::HwAccelInit { .. } => true,
+            Self::MissingComponent { kind, name } => {
+                matches!(kind, ComponentKind::Encoder | ComponentKind::HwAccel)
+                    && HARDWARE_ENCODER_SUFFIXES
+                        .iter()
+                        .any(|suffix| name.ends_with(suffix))
+            }
+            _ => false,
+        }
+    }
+
+    /// Gets a short suggestion of how to fix the failure.
+    #[must_use]
+    pub fn hint(&self) -> String {
+        match self {
+            Self::MissingComponent { kind, name } => match kind {
+                ComponentKind::Encoder | ComponentKind::Decoder => format!(
+                    "Install an FFmpeg build with {name} support or choose a different codec"
+                ),
+                ComponentKind::Muxer => {
+                    format!(
+                        "Install an FFmpeg build with {name} support or choose a different format"
+                    )
+                }
+                _ => format!("Install an FFmpeg build that includes the {kind} {name}"),
+            },
+            Self::InvalidFilterOption { filter, .. } => {
+                format!("Check the options of the filter with `ffmpeg -h filter={filter}`")
+            }
+            Self::UnreadableInput { .. } => {
+                "Check that the file exists and is a media file FFmpeg can decode".to_string()
+            }
+            Self::PermissionDenied { .. } => {
+                "Check the permissions of the file and its directory".to_string()
+            }
+            Self::DiskFull => {
+                "Free some disk space or write the output to another disk".to_string()
+            }
+            Self::HwAccelInit { .. } => {
+                "Check the GPU drivers, or render without hardware acceleration".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for FailureCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingComponent { kind, name } => {
+                write!(f, "the {kind} {name} is not available in this FFmpeg build")
+            }
+            Self::InvalidFilterOption {
+                filter,
+                option: Some(option),
+                ..
+            } => write!(f, "invalid option {option} for the {filter} filter"),
+            Self::InvalidFilterOption {
+                filter, message, ..
+            } => write!(f, "invalid options for the {filter} filter: {message}"),
+            Self::UnreadableInput { path, reason } => write!(f, "cannot read {path}: {reason}"),
+            Self::PermissionDenied { path } => write!(f, "permission denied: {path}"),
+            Self::DiskFull => write!(f, "no space left on the output device"),
+            Self::HwAccelInit { message } => {
+                write!(f, "hardware acceleration failed to initialize: {message}")
+            }
+        }
+    }
+}
+
+/// Suffixes of the names of hardware encoders.
+const HARDWARE_ENCODER_SUFFIXES: [&str; 6] =
+    ["_nvenc", "_qsv", "_vaapi", "_amf", "_videotoolbox", "_mf"];
+
+/// Messages logged when a hardware device or encoder cannot be initialized.
+const HWACCEL_FAILURES: [&str; 12] = [
+    "Device creation failed",
+    "Failed to initialise VAAPI connection",
+    "No VA display found",
+    "hwaccel initialisation returned error",
+    "Failed setup for format",
+    "Cannot load libcuda",
+    "Cannot load libnvidia-encode",
+    "No NVENC capable devices found",
+    "OpenEncodeSessionEx failed",
+    "Error creating a MFX session",
+    "Error initializing a MFX session",
+    "Could not create hardware device",
+];
+
+/// Removes the `[name @ 0x...]` context prefixes of a log line.
+fn strip_context(mut line: &str) -> &str {
+    while let Some(rest) = line.strip_prefix('[') {
+        line = rest
+            .split_once(']')
+            .map_or(rest, |(_, rest)| rest)
+            .trim_start();
+    }
+    line
+}
+
+/// Gets the filter name from a log context such as `[Parsed_scale_0 @ 0x55d0]`.
+fn context_filter(line: &str) -> Option<String> {
+    let context = line.strip_prefix("[Parsed_")?.split(" @").next()?;
+    // 末尾のインデックス（_0 など）を取り除く
+    let (name, index) = context.rsplit_once('_')?;
+    index
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| name.to_string())
+}
+
+/// Gets the text between the first pair of quotes after a marker.
+fn quoted_after(line: &str, marker: &str) -> Option<String> {
+    let rest = &line[line.find(marker)? + marker.len()..];
+    let rest = rest.trim_start().strip_prefix(['\'', '"'])?;
+    let end = rest.find(['\'', '"'])?;
+    Some(rest[..end].to_string())
+}
+
+/// Gets the text of a line before a suffix, without log context.
+fn subject_before<'a>(line: &'a str, suffix: &str) -> Option<&'a str> {
+    let subject = strip_context(line).strip_suffix(suffix)?.trim_end();
+    let subject = [
+        "Error opening output file ",
+        "Error opening output ",
+        "Error opening input file ",
+        "Error opening input ",
+    ]
+    .iter()
+    .find_map(|prefix| subject.strip_prefix(prefix))
+    .unwrap_or(subject);
+    (!subject.is_empty()).then_some(subject)
+}
+
+/// Finds a failure to initialize hardware acceleration.
+fn find_hwaccel_failure(lines: &[&str]) -> Option<FailureCause> {
+    lines
+        .iter()
+        .find(|line| {
+            HWACCEL_FAILURES
+                .iter()
+                .any(|failure| line.contains(failure))
+        })
+        .map(|line| FailureCause::HwAccelInit {
+            message: strip_context(line).to_string(),
+        })
+}
+
+/// Finds a missing encoder, decoder, filter or muxer.
+fn find_missing_component(lines: &[&str]) -> Option<FailureCause> {
+    lines.iter().find_map(|line| {
+        let missing = |kind, name: Option<String>| {
+            name.map(|name| FailureCause::MissingComponent { kind, name })
+        };
+
+        if line.contains("Unknown encoder") {
+            missing(
+                ComponentKind::Encoder,
+                quoted_after(line, "Unknown encoder"),
+            )
+        } else if line.contains("Unknown decoder") {
+            missing(
+                ComponentKind::Decoder,
+                quoted_after(line, "Unknown decoder"),
+            )
+        } else if line.contains("No such filter") {
+            missing(ComponentKind::Filter, quoted_after(line, "No such filter:"))
+        } else if line.contains("Requested output format") {
+            missing(
+                ComponentKind::Muxer,
+                quoted_after(line, "Requested output format"),
+            )
+        } else if let Some(rest) = line.split("coder (codec ").nth(1) {
+            // "Encoder (codec hevc) not found for output stream #0:0"
+            let kind = if line.contains("Decoder (codec") {
+                ComponentKind::Decoder
+            } else {
+                ComponentKind::Encoder
+            };
+            let name = rest.split(')').next().map(str::to_string);
+            missing(kind, name.filter(|_| line.contains("not found")))
+        } else {
+            None
+        }
+    })
+}
+
+/// Finds an invalid filter option.
+fn find_invalid_filter_option(lines: &[&str]) -> Option<FailureCause> {
+    lines.iter().find_map(|line| {
+        let message = strip_context(line).to_string();
+
+        // "Error applying option 'foo' to filter 'scale': Option not found"
+        if line.contains("Error applying option") {
+            return Some(FailureCause::InvalidFilterOption {
+                filter: quoted_after(line, "to filter")?,
+                option: quoted_after(line, "Error applying option"),
+                message,
+            });
+        }
+        // "Error initializing filter 'scale' with args 'w=abc'"
+        if line.contains("Error initializing filter") {
+            return Some(FailureCause::InvalidFilterOption {
+                filter: quoted_after(line, "Error initializing filter")?,
+                option: None,
+                message,
+            });
+        }
+        // "[Parsed_scale_0 @ 0x55d0] Option 'foo' not found"
+        let filter = context_filter(line)?;
+        let option = quoted_after(line, "Option");
+        (option.is_some() || line.contains("Unable to parse") || line.contains("Invalid"))
+            .then_some(FailureCause::InvalidFilterOption {
+                filter,
+                option,
+                message,
+            })
+    })
+}
+
+/// Finds a file that could not be opened because of its permissions.
+fn find_permission_denied(lines: &[&str]) -> Option<FailureCause> {
+    lines.iter().find_map(|line| {
+        subject_before(line, ": Permission denied").map(|path| FailureCause::PermissionDenied {
+            path: path.to_string(),
+        })
+    })
+}
+
+/// Finds an input that does not exist or cannot be decoded.
+fn find_unreadable_input(lines: &[&str]) -> Option<FailureCause> {
+    const REASONS: [&str; 2] = [
+        "No such file or directory",
+        "Invalid data found when processing input",
+    ];
+
+    // 出力先が開けない場合は入力の問題ではない
+    if lines
+        .iter()
+        .any(|line| line.contains("Error opening output"))
+    {
+        return None;
+    }
+
+    let reason = REASONS
+        .iter()
+        .find(|reason| lines.iter().any(|line| line.contains(*reason)))?;
+
+    // FFmpeg 7 names the input on a separate line
+    let path = lines
+        .iter()
+        .find_map(|line| {
+            strip_context(line)
+                .strip_prefix("Error opening input file ")
+                .map(|path| path.trim_end_matches('.'))
+        })
+        .or_else(|| {
+            lines
+                .iter()
+                .find_map(|line| subject_before(line, &format!(": {reason}")))
+                .filter(|path| !path.starts_with("Error"))
+        })?;
+
+    Some(FailureCause::UnreadableInput {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_missing_components() {
+        assert_eq!(
+            FailureCause::classify("Unknown encoder 'libx265'\n"),
+            Some(FailureCause::MissingComponent {
+                kind: ComponentKind::Encoder,
+                name: "libx265".to_string()
+            })
+        );
+        assert_eq!(
+            FailureCause::classify(
+                "[vost#0:0 @ 0x5581] Encoder (codec hevc) not found for output stream #0:0"
+            ),
+            Some(FailureCause::MissingComponent {
+                kind: ComponentKind::Encoder,
+                name: "hevc".to_string()
+            })
+        );
+        assert_eq!(
+            FailureCause::classify(
+                "[AVFilterGraph @ 0x55] No such filter: 'zscale'\nError initializing complex filters."
+            ),
+            Some(FailureCause::MissingComponent {
+                kind: ComponentKind::Filter,
+                name: "zscale".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_classify_invalid_filter_option() {
+        let cause = FailureCause::classify(
+            "[Parsed_scale_0 @ 0x55d0c] Option 'wdith' not found\n\
+             [AVFilterGraph @ 0x55d0b] Error initializing filter 'scale' with args 'wdith=640'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cause.to_string(),
+            "invalid option wdith for the scale filter"
+        );
+        assert_eq!(
+            cause.hint(),
+            "Check the options of the filter with `ffmpeg -h filter=scale`"
+        );
+
+        let cause = FailureCause::classify(
+            "[fc#0 @ 0x1] Error applying option 'size' to filter 'drawtext': Option not found",
+        );
+        assert!(matches!(
+            cause,
+            Some(FailureCause::InvalidFilterOption { filter, option: Some(option), .. })
+                if filter == "drawtext" && option == "size"
+        ));
+    }
+
+    #[test]
+    fn test_classify_file_errors() {
+        assert_eq!(
+            FailureCause::classify("clips/a.mp4: No such file or directory\n"),
+            Some(FailureCause::UnreadableInput {
+                path: "clips/a.mp4".to_string(),
+                reason: "No such file or directory".to_string()
+            })
+        );
+        assert_eq!(
+            FailureCause::classify(
+                "[in#0 @ 0x1] Error opening input: Invalid data found when processing input\n\
+                 Error opening input file broken.mp4.\n\
+                 Error opening input files: Invalid data found when processing input\n"
+            ),
+            Some(FailureCause::UnreadableInput {
+                path: "broken.mp4".to_string(),
+                reason: "Invalid data found when processing input".to_string()
+            })
+        );
+        assert_eq!(
+            FailureCause::classify(
+                "[out#0/mp4 @ 0x1] Error opening output /srv/out.mp4: Permission denied\n"
+            ),
+            Some(FailureCause::PermissionDenied {
+                path: "/srv/out.mp4".to_string()
+            })
+        );
+        assert_eq!(
+            FailureCause::classify(
+                "[out#0/mp4 @ 0x1] Error opening output out/x.mp4: No such file or directory"
+            ),
+            None
+        );
+        assert_eq!(
+            FailureCause::classify("av_interleaved_write_frame(): No space left on device"),
+            Some(FailureCause::DiskFull)
+        );
+    }
+
+    #[test]
+    fn test_classify_hwaccel_failure() {
+        let cause = FailureCause::classify(
+            "[h264_nvenc @ 0x55] Cannot load libcuda.so.1\n\
+             [vost#0:0/h264_nvenc @ 0x55] Error while opening encoder - maybe incorrect parameters\n",
+        )
+        .unwrap();
+        assert!(cause.is_hardware_related());
+        assert_eq!(
+            cause.to_string(),
+            "hardware acceleration failed to initialize: Cannot load libcuda.so.1"
+        );
+
+        let missing = FailureCause::MissingComponent {
+            kind: ComponentKind::Encoder,
+            name: "hevc_vaapi".to_string(),
+        };
+        assert!(missing.is_hardware_related());
+        assert!(FailureCause::classify("Something unexpected happened").is_none());
+    }
+}
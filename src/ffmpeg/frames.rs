@@ -0,0 +1,697 @@
+/// Decoding video into raw frames in memory.
+///
+/// `FFmpeg::decode_frames` starts an `FFmpeg` process that decodes a video
+/// stream, scales it and writes the frames as `rawvideo` to its standard
+/// output. `FrameDecoder` reads them from the pipe one at a time and yields
+/// them with their presentation timestamps, so that pixel work such as
+/// scopes or effects can be done without writing images to disk.
+///
+/// The timestamps come from the `showinfo` filter, which `FFmpeg` logs to
+/// standard error for every frame it passes on.
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+use crate::ffmpeg::executor::SpawnedProcess;
+use crate::ffmpeg::{Error, ExecutionOutput, FFmpeg, Result};
+use crate::utility::time::TimePosition;
+
+/// Pixel format of decoded frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PixelFormat {
+    /// 8-bit RGBA, one interleaved plane.
+    #[default]
+    Rgba,
+    /// 8-bit YUV 4:2:0, a full resolution Y plane followed by U and V
+    /// planes at half the width and height.
+    Yuv420p,
+}
+
+impl PixelFormat {
+    /// Gets the `FFmpeg` name of the pixel format.
+    #[must_use]
+    pub fn as_ffmpeg_str(&self) -> &'static str {
+        match self {
+            Self::Rgba => "rgba",
+            Self::Yuv420p => "yuv420p",
+        }
+    }
+
+    /// Gets the sizes of the planes of a frame in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frame in pixels
+    /// * `height` - Height of the frame in pixels
+    #[must_use]
+    pub fn plane_sizes(&self, width: u32, height: u32) -> Vec<usize> {
+        let (width, height) = (width as usize, height as usize);
+        match self {
+            Self::Rgba => vec![width * height * 4],
+            Self::Yuv420p => {
+                // 奇数サイズでは色差面の大きさを切り上げる
+                let chroma = width.div_ceil(2) * height.div_ceil(2);
+                vec![width * height, chroma, chroma]
+            }
+        }
+    }
+
+    /// Gets the size of a frame in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frame in pixels
+    /// * `height` - Height of the frame in pixels
+    #[must_use]
+    pub fn frame_size(&self, width: u32, height: u32) -> usize {
+        self.plane_sizes(width, height).iter().sum()
+    }
+}
+
+/// Options of `FFmpeg::decode_frames`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DecodeOptions {
+    /// Pixel format of the frames.
+    pub pixel_format: PixelFormat,
+    /// Width the frames are scaled to; if only the height is set, the width
+    /// follows from the aspect ratio of the video.
+    pub width: Option<u32>,
+    /// Height the frames are scaled to; if only the width is set, the height
+    /// follows from the aspect ratio of the video.
+    pub height: Option<u32>,
+    /// Position decoding starts at.
+    pub start: Option<TimePosition>,
+    /// How long to decode for (default: to the end of the video).
+    pub duration: Option<f64>,
+    /// Frame rate the video is resampled to (default: the frames of the video).
+    pub frame_rate: Option<f64>,
+    /// Index of the video stream among the video streams of the input.
+    pub stream_index: usize,
+}
+
+impl DecodeOptions {
+    /// Creates decode options that decode the whole first video stream at
+    /// its original size.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel_format` - Pixel format of the frames
+    #[must_use]
+    pub fn new(pixel_format: PixelFormat) -> Self {
+        Self {
+            pixel_format,
+            ..Self::default()
+        }
+    }
+
+    /// Scales the frames to the given size.
+    #[must_use]
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Scales the frames to the given width, keeping the aspect ratio.
+    #[must_use]
+    pub fn with_width(mut self, width: u32) -> Self {
+        self.width = Some(width);
+        self.height = None;
+        self
+    }
+
+    /// Starts decoding at the given position.
+    #[must_use]
+    pub fn with_start(mut self, start: TimePosition) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Decodes only the given number of seconds.
+    #[must_use]
+    pub fn with_duration(mut self, seconds: f64) -> Self {
+        self.duration = Some(seconds);
+        self
+    }
+
+    /// Resamples the video to the given frame rate.
+    #[must_use]
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Decodes the video stream with the given index.
+    #[must_use]
+    pub fn with_stream_index(mut self, stream_index: usize) -> Self {
+        self.stream_index = stream_index;
+        self
+    }
+
+    /// Validates the decode options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a size, the duration or the frame rate is not positive.
+    pub fn validate(&self) -> Result<()> {
+        if self.width == Some(0) || self.height == Some(0) {
+            return Err(Error::InvalidArgument(
+                "Frame size must be positive".to_string(),
+            ));
+        }
+        if self.duration.is_some_and(|d| d <= 0.0) {
+            return Err(Error::InvalidArgument(
+                "Decode duration must be positive".to_string(),
+            ));
+        }
+        if self.frame_rate.is_some_and(|fps| fps <= 0.0) {
+            return Err(Error::InvalidArgument(
+                "Frame rate must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A decoded video frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Index of the frame since decoding started.
+    pub index: u64,
+    /// Presentation time of the frame in the input.
+    pub timestamp: TimePosition,
+    /// Width of the frame in pixels.
+    pub width: u32,
+    /// Height of the frame in pixels.
+    pub height: u32,
+    /// Pixel format of the frame.
+    pub pixel_format: PixelFormat,
+    /// The pixels, plane after plane.
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Gets a plane of the frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the plane (0 for RGBA; 0 to 2 for Y, U and V)
+    ///
+    /// # Returns
+    ///
+    /// The bytes of the plane, or `None` if the format has no such plane.
+    #[must_use]
+    pub fn plane(&self, index: usize) -> Option<&[u8]> {
+        let sizes = self.pixel_format.plane_sizes(self.width, self.height);
+        let size = *sizes.get(index)?;
+        let offset: usize = sizes[..index].iter().sum();
+        self.data.get(offset..offset + size)
+    }
+}
+
+/// Reads the frames decoded by an `FFmpeg` process.
+///
+/// The process is stopped when the decoder is dropped before the end of
+/// the video.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    /// The `FFmpeg` installation decoding the frames.
+    ffmpeg: FFmpeg,
+    /// The decoded video.
+    input: PathBuf,
+    /// The options the decoder was started with.
+    options: DecodeOptions,
+    /// Width of the frames.
+    width: u32,
+    /// Height of the frames.
+    height: u32,
+    /// The running `FFmpeg` process, whose standard output is read.
+    process: SpawnedProcess,
+    /// Timestamps of the frames, in seconds since the start of decoding.
+    timestamps: Receiver<f64>,
+    /// Collects standard error, apart from the frame timestamps.
+    log: Option<JoinHandle<String>>,
+    /// Index of the next frame.
+    next_index: u64,
+    /// Timestamp of the last frame.
+    last_timestamp: TimePosition,
+    /// Whether the process has exited.
+    finished: bool,
+}
+
+impl FrameDecoder {
+    /// Starts decoding a video.
+    fn start(ffmpeg: &FFmpeg, input: &Path, options: DecodeOptions) -> Result<Self> {
+        options.validate()?;
+        let (width, height) = match (options.width, options.height) {
+            (Some(width), Some(height)) => (width, height),
+            (width, height) => {
+                let (source_width, source_height) =
+                    probe_size(ffmpeg, input, options.stream_index)?;
+                scaled_size(source_width, source_height, width, height)
+            }
+        };
+
+        let args = decode_args(input, &options, width, height);
+        let mut process = ffmpeg
+            .executor()
+            .spawn(ffmpeg.path(), &args)
+            .map_err(Error::IoError)?;
+        // 入力は使わないので閉じる
+        process.stdin = None;
+        if process.stdout.is_none() {
+            return Err(Error::ExecutionError(
+                "FFmpeg output is not piped".to_string(),
+            ));
+        }
+        let stderr = process
+            .stderr
+            .take()
+            .ok_or_else(|| Error::ExecutionError("FFmpeg log is not piped".to_string()))?;
+
+        // 標準エラーは別スレッドで読み、各フレームの時刻を取り出す
+        let (sender, timestamps) = mpsc::channel();
+        let log = thread::spawn(move || {
+            let mut log = String::new();
+            for line in BufReader::new(stderr)
+                .lines()
+                .map_while(std::io::Result::ok)
+            {
+                match parse_showinfo_time(&line) {
+                    Some(time) => {
+                        let _ = sender.send(time);
+                    }
+                    None => {
+                        log.push_str(&line);
+                        log.push('\n');
+                    }
+                }
+            }
+            log
+        });
+
+        let start = options.start.unwrap_or_else(TimePosition::zero);
+        Ok(Self {
+            ffmpeg: ffmpeg.clone(),
+            input: input.to_path_buf(),
+            options,
+            width,
+            height,
+            process,
+            timestamps,
+            log: Some(log),
+            next_index: 0,
+            last_timestamp: start,
+            finished: false,
+        })
+    }
+
+    /// Gets the width of the frames.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the frames.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the pixel format of the frames.
+    #[must_use]
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.options.pixel_format
+    }
+
+    /// Continues decoding from another position.
+    ///
+    /// The running process is stopped and a new one is started at the
+    /// position; the next frame is the first frame at or after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position in the input
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `FFmpeg` cannot be started.
+    pub fn seek(&mut self, position: TimePosition) -> Result<()> {
+        let options = DecodeOptions {
+            start: Some(position),
+            width: Some(self.width),
+            height: Some(self.height),
+            ..self.options.clone()
+        };
+        // 新しいプロセスを起動できてから古いプロセスを止める
+        *self = Self::start(&self.ffmpeg, &self.input, options)?;
+        Ok(())
+    }
+
+    /// Stops the process if it is still running.
+    fn stop(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    /// Waits for the process after the last frame and reports its failure.
+    fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        let status = self.process.wait().map_err(Error::IoError)?;
+        let stderr = self
+            .log
+            .take()
+            .and_then(|log| log.join().ok())
+            .unwrap_or_default();
+
+        if status.success {
+            return Ok(());
+        }
+        let output = ExecutionOutput {
+            exit_code: status.exit_code,
+            success: false,
+            stdout: Vec::new(),
+            stderr,
+        };
+        Err(Error::from_failed_run(&output, || {
+            Error::ProcessTerminated {
+                exit_code: output.exit_code,
+                message: format!("FFmpeg decoder failed: {}", output.stderr.trim()),
+            }
+        }))
+    }
+
+    /// Reads the next frame from the pipe.
+    ///
+    /// # Returns
+    ///
+    /// The pixels, or `None` at the end of the output.
+    fn read_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let size = self
+            .options
+            .pixel_format
+            .frame_size(self.width, self.height);
+        let Some(stdout) = self.process.stdout.as_mut() else {
+            return Ok(None);
+        };
+        let mut data = vec![0; size];
+        let mut filled = 0;
+        while filled < size {
+            match stdout.read(&mut data[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::IoError(e)),
+            }
+        }
+
+        match filled {
+            0 => Ok(None),
+            _ if filled < size => Err(Error::OutputParseError(format!(
+                "Truncated frame: expected {size} bytes, got {filled}"
+            ))),
+            _ => Ok(Some(data)),
+        }
+    }
+}
+
+impl Iterator for FrameDecoder {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let data = match self.read_frame() {
+            Ok(Some(data)) => data,
+            Ok(None) => return self.finish().err().map(Err),
+            Err(e) => {
+                self.stop();
+                return Some(Err(e));
+            }
+        };
+
+        // 時刻は showinfo の出力から得る（出力前に必ず記録される）
+        let start = self.options.start.map_or(0.0, |start| start.as_seconds());
+        let timestamp = self.timestamps.recv().map_or(self.last_timestamp, |time| {
+            TimePosition::from_seconds(start + time)
+        });
+        self.last_timestamp = timestamp;
+
+        let frame = Frame {
+            index: self.next_index,
+            timestamp,
+            width: self.width,
+            height: self.height,
+            pixel_format: self.options.pixel_format,
+            data,
+        };
+        self.next_index += 1;
+        Some(Ok(frame))
+    }
+}
+
+impl Drop for FrameDecoder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl FFmpeg {
+    /// Decodes a video into raw frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Path to the video
+    /// * `options` - Pixel format, size, range and frame rate of the frames
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the decoded frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are invalid, the size of the video
+    /// cannot be probed, or `FFmpeg` cannot be started. Decoding errors are
+    /// returned by the iterator.
+    pub fn decode_frames<P: AsRef<Path>>(
+        &self,
+        input: P,
+        options: DecodeOptions,
+    ) -> Result<FrameDecoder> {
+        FrameDecoder::start(self, input.as_ref(), options)
+    }
+}
+
+/// Probes the displayed size of a video stream.
+///
+/// `FFmpeg` rotates the decoded frames by the rotation of the stream, so the
+/// width and height are swapped for streams rotated by 90 or 270 degrees.
+fn probe_size(ffmpeg: &FFmpeg, input: &Path, stream_index: usize) -> Result<(u32, u32)> {
+    let info = ffmpeg.get_media_info(input)?;
+    info.streams
+        .iter()
+        .filter(|stream| stream.codec_type == "video")
+        .nth(stream_index)
+        .and_then(|stream| {
+            let width = u32::try_from(stream.width?).ok()?;
+            let height = u32::try_from(stream.height?).ok()?;
+            let quarter_turns = (stream.rotation().unwrap_or(0.0) / 90.0).round() as i64;
+            if quarter_turns % 2 == 1 {
+                Some((height, width))
+            } else {
+                Some((width, height))
+            }
+        })
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "{} has no video stream {stream_index}",
+                input.display()
+            ))
+        })
+}
+
+/// Computes the size of the frames from the size of the video and the
+/// requested width or height.
+fn scaled_size(
+    source_width: u32,
+    source_height: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> (u32, u32) {
+    let scale = |value: u32, numerator: u32, denominator: u32| {
+        ((f64::from(value) * f64::from(numerator) / f64::from(denominator)).round() as u32).max(1)
+    };
+    match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, scale(width, source_height, source_width)),
+        (None, Some(height)) => (scale(height, source_width, source_height), height),
+        (None, None) => (source_width, source_height),
+    }
+}
+
+/// Builds the arguments of the decoding `FFmpeg` process.
+fn decode_args(input: &Path, options: &DecodeOptions, width: u32, height: u32) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["-hide_banner", "-nostdin", "-nostats"]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+
+    // 入力側で位置合わせすると、キーフレームから正確な位置まで復号される
+    if let Some(start) = options.start {
+        args.push("-ss".into());
+        args.push(start.as_seconds().to_string().into());
+    }
+    args.push("-i".into());
+    args.push(input.into());
+    if let Some(duration) = options.duration {
+        args.push("-t".into());
+        args.push(duration.to_string().into());
+    }
+
+    // 出力サイズを固定し、フレームの大きさが常に一致するようにする
+    let mut filters = Vec::new();
+    if let Some(frame_rate) = options.frame_rate {
+        filters.push(format!("fps={frame_rate}"));
+    }
+    filters.push(format!("scale={width}:{height}"));
+    filters.push("showinfo".to_string());
+
+    let map = format!("0:v:{}", options.stream_index);
+    for arg in [
+        "-map",
+        map.as_str(),
+        "-an",
+        "-sn",
+        "-vf",
+        filters.join(",").as_str(),
+        "-vsync",
+        "passthrough",
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        options.pixel_format.as_ffmpeg_str(),
+        "pipe:1",
+    ] {
+        args.push(arg.into());
+    }
+    args
+}
+
+/// Parses the timestamp of a frame from a `showinfo` log line such as
+/// `[Parsed_showinfo_1 @ 0x55] n:   3 pts:  3072 pts_time:0.1 duration: ...`.
+fn parse_showinfo_time(line: &str) -> Option<f64> {
+    if !line.contains("showinfo") {
+        return None;
+    }
+    line.split_whitespace()
+        .find_map(|field| field.strip_prefix("pts_time:"))
+        .and_then(|time| time.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_sizes() {
+        assert_eq!(PixelFormat::Rgba.frame_size(4, 2), 32);
+        assert_eq!(PixelFormat::Yuv420p.plane_sizes(5, 3), vec![15, 6, 6]);
+        assert_eq!(scaled_size(1920, 1080, Some(640), None), (640, 360));
+        assert_eq!(scaled_size(1920, 1080, None, Some(540)), (960, 540));
+    }
+
+    #[test]
+    fn test_parse_showinfo_time() {
+        assert_eq!(
+            parse_showinfo_time(
+                "[Parsed_showinfo_1 @ 0x5581] n:   2 pts:   2048 pts_time:0.0833333 duration:512"
+            ),
+            Some(0.083_333_3)
+        );
+        assert_eq!(
+            parse_showinfo_time("[Parsed_showinfo_1 @ 0x5581] config in time_base: 1/12288"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_frames_reads_pipe() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let frame_size = PixelFormat::Yuv420p.frame_size(4, 2);
+        let mut stdout = vec![1; frame_size];
+        stdout.extend(vec![2; frame_size]);
+        executor.respond(
+            "rawvideo",
+            ExecutionOutput::success(stdout).with_stderr(
+                "[Parsed_showinfo_2 @ 0x1] n:   0 pts:      0 pts_time:0    duration:1\n\
+                 [Parsed_showinfo_2 @ 0x1] n:   1 pts:      1 pts_time:0.04 duration:1\n",
+            ),
+        );
+
+        let options = DecodeOptions::new(PixelFormat::Yuv420p)
+            .with_size(4, 2)
+            .with_start(TimePosition::from_seconds(10.0))
+            .with_frame_rate(25.0);
+        let frames: Vec<Frame> = ffmpeg
+            .decode_frames("in.mp4", options)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].index, 1);
+        assert!((frames[1].timestamp.as_seconds() - 10.04).abs() < 1e-9);
+        assert_eq!(frames[1].plane(0), Some(&[2u8; 8][..]));
+        assert_eq!(frames[1].plane(2).map(<[u8]>::len), Some(2));
+
+        let call = &executor.calls()[0];
+        assert_eq!(call.option_value("-ss"), Some("10"));
+        assert_eq!(call.option_value("-vf"), Some("fps=25,scale=4:2,showinfo"));
+        assert_eq!(call.option_value("-pix_fmt"), Some("yuv420p"));
+        assert_eq!(call.last_arg(), Some("pipe:1"));
+    }
+
+    #[test]
+    fn test_decode_frames_probes_rotated_size() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "phone.mp4", "duration": "10.0"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                             "width": 1920, "height": 1080,
+                             "side_data_list": [
+                                {"side_data_type": "Display Matrix", "rotation": -90}
+                             ]}]}"#,
+        );
+
+        let options = DecodeOptions::new(PixelFormat::Rgba).with_width(540);
+        let decoder = ffmpeg.decode_frames("phone.mp4", options).unwrap();
+        drop(decoder);
+
+        let call = executor.ffmpeg_calls().pop().unwrap();
+        assert_eq!(call.option_value("-vf"), Some("scale=540:960,showinfo"));
+    }
+
+    #[test]
+    fn test_decode_frames_reports_failure() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "rawvideo",
+            ExecutionOutput::failure(1, "missing.mp4: No such file or directory\n"),
+        );
+
+        let mut decoder = ffmpeg
+            .decode_frames("missing.mp4", DecodeOptions::default().with_size(2, 2))
+            .unwrap();
+        let error = decoder.next().unwrap().unwrap_err();
+        assert!(matches!(error, Error::Failed { .. }));
+        assert!(decoder.next().is_none());
+    }
+}
@@ -9,12 +9,38 @@ use std::fmt::{self, Display};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::{env, io};
 use thiserror::Error;
 
 // Submodules
+pub mod analysis;
+pub mod capabilities;
 pub mod command;
 pub mod error;
+pub mod executor;
+pub mod failure;
+pub mod frames;
+pub mod probe;
+pub mod quality;
+pub mod smart_cut;
+
+pub use analysis::{AnalysisScope, SceneCut, SceneDetector, SceneOptions};
+pub use capabilities::{Capabilities, ComponentKind};
+pub use executor::{
+    ExecutionOutput, FFmpegExecutor, ProcessExecutor, ProcessHandle, ProcessStatus, RecordedCall,
+    RecordingExecutor, SpawnedProcess,
+};
+pub use failure::FailureCause;
+pub use frames::{DecodeOptions, Frame, FrameDecoder, PixelFormat};
+pub use probe::{
+    ChapterInfo, ContentLightLevel, Disposition, HdrMetadata, HdrTransfer, KeyframeIndex,
+    KeyframeScan, MasteringDisplay, PacketInfo, ProgramInfo, ProgramStream, SideData,
+};
+pub use quality::{
+    BlackOptions, CropOptions, CropRect, FreezeOptions, QcOptions, QcReport, TimeSpan,
+};
+pub use smart_cut::{CutSegment, CutSegmentKind, SmartCutPlan};
 
 /// Errors that can occur in the `FFmpeg` module.
 #[derive(Error, Debug)]
@@ -68,6 +94,67 @@ pub enum Error {
     /// Invalid argument provided.
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
+
+    /// A component required by an operation is not part of the `FFmpeg` build.
+    #[error("FFmpeg is missing the {kind} {name}")]
+    MissingComponent {
+        /// Kind of the missing component.
+        kind: ComponentKind,
+        /// Name of the missing component.
+        name: String,
+    },
+
+    /// `FFmpeg` failed for a recognized reason.
+    #[error("{cause}")]
+    Failed {
+        /// The recognized cause of the failure.
+        cause: FailureCause,
+        /// The exit code of the process, if available.
+        exit_code: Option<i32>,
+        /// The standard error of the process.
+        stderr: String,
+    },
+}
+
+impl Error {
+    /// Creates the error of a failed `FFmpeg` or `ffprobe` run.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The output of the failed run
+    /// * `unrecognized` - Creates the error if the cause is not recognized
+    ///
+    /// # Returns
+    ///
+    /// `Error::Failed` if the cause of the failure is recognized in the
+    /// standard error, otherwise the error of `unrecognized`.
+    pub fn from_failed_run(output: &ExecutionOutput, unrecognized: impl FnOnce() -> Self) -> Self {
+        match FailureCause::classify(&output.stderr) {
+            Some(cause) => Self::Failed {
+                cause,
+                exit_code: output.exit_code,
+                stderr: output.stderr.clone(),
+            },
+            None => unrecognized(),
+        }
+    }
+
+    /// Gets the recognized cause of the error.
+    ///
+    /// # Returns
+    ///
+    /// The cause of a failed run, or a missing component found before running.
+    #[must_use]
+    pub fn failure_cause(&self) -> Option<FailureCause> {
+        match self {
+            Self::Failed { cause, .. } => Some(cause.clone()),
+            Self::MissingComponent { kind, name } => Some(FailureCause::MissingComponent {
+                kind: *kind,
+                name: name.clone(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Result type for `FFmpeg` operations.
@@ -161,6 +248,8 @@ pub struct FFmpeg {
     path: PathBuf,
     /// The `FFmpeg` version.
     version: Version,
+    /// Runs `FFmpeg` and `ffprobe` for this instance.
+    executor: Arc<dyn FFmpegExecutor>,
 }
 
 impl FFmpeg {
@@ -183,7 +272,34 @@ impl FFmpeg {
     /// A new `FFmpeg` instance.
     #[must_use]
     pub fn new(path: PathBuf, version: Version) -> Self {
-        Self { path, version }
+        Self {
+            path,
+            version,
+            executor: ProcessExecutor::shared(),
+        }
+    }
+
+    /// Sets the executor that runs `FFmpeg` and `ffprobe` for this instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The executor, e.g. a `RecordingExecutor` in tests
+    #[must_use]
+    pub fn with_executor(mut self, executor: Arc<dyn FFmpegExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Gets the executor that runs `FFmpeg` and `ffprobe` for this instance.
+    #[must_use]
+    pub fn executor(&self) -> &dyn FFmpegExecutor {
+        self.executor.as_ref()
+    }
+
+    /// Determines whether this instance runs real processes, i.e. whether
+    /// results of its commands may be cached per executable.
+    pub(crate) fn runs_processes(&self) -> bool {
+        Arc::ptr_eq(&self.executor, &ProcessExecutor::shared())
     }
 
     /// Gets the path to the `FFmpeg` executable.
@@ -222,6 +338,11 @@ impl FFmpeg {
     ///
     /// Returns an error if FFmpeg cannot be found or is not executable.
     pub fn detect() -> Result<Self> {
+        // 0. An instance set with `scoped_default` takes precedence
+        if let Some(ffmpeg) = Self::scoped() {
+            return Ok(ffmpeg);
+        }
+
         // 1. First check the current executable directory
         if let Ok(exe_path) = std::env::current_exe() {
             if let Some(exe_dir) = exe_path.parent() {
@@ -389,6 +510,22 @@ impl FFmpeg {
         locations
     }
 
+    /// Gets the encoders, decoders, filters, muxers and hardware acceleration
+    /// methods of this `FFmpeg` build.
+    ///
+    /// The components are queried once per executable and cached.
+    ///
+    /// # Returns
+    ///
+    /// The supported components.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `FFmpeg` cannot be queried.
+    pub fn capabilities(&self) -> Result<Arc<Capabilities>> {
+        Capabilities::cached(self)
+    }
+
     /// Creates a new FFmpeg command with this FFmpeg instance.
     ///
     /// # Returns
@@ -498,7 +635,11 @@ impl FFmpeg {
             .map_err(|_| Error::OutputParseError(format!("Failed to parse version: {version_str}")))
     }
 
-    /// Gets information about a media file.
+    /// Gets information about a media file, including its chapters and
+    /// programs.
+    ///
+    /// The file is probed once per version (size and modification time)
+    /// and the result is cached.
     ///
     /// # Arguments
     ///
@@ -521,41 +662,60 @@ impl FFmpeg {
             )));
         }
 
-        // Try to get ffprobe path from environment variable
-        let ffprobe_path = if let Ok(path) = std::env::var("FFPROBE_PATH") {
-            PathBuf::from(path)
-        } else {
-            // Try to find ffprobe in the same directory as ffmpeg
-            let ffmpeg_dir = self.path().parent();
-            let ffprobe_name = if cfg!(windows) {
-                "ffprobe.exe"
-            } else {
-                "ffprobe"
-            };
-
-            if let Some(dir) = ffmpeg_dir {
-                let possible_path = dir.join(ffprobe_name);
-                if possible_path.exists() {
-                    possible_path
-                } else {
-                    PathBuf::from(ffprobe_name) // Fall back to PATH
-                }
-            } else {
-                PathBuf::from(ffprobe_name) // Fall back to PATH
-            }
-        };
+        self.cached_media_info(path)
+            .map(|media_info| media_info.as_ref().clone())
+    }
 
+    /// Probes a media file with `ffprobe`, without the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the media file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be analyzed.
+    fn probe_media_info(&self, path: &Path) -> Result<MediaInfo> {
         // Use ffprobe to get media information
-        let mut cmd = std::process::Command::new(&ffprobe_path);
-        cmd.arg("-v")
-            .arg("quiet")
-            .arg("-print_format")
-            .arg("json")
-            .arg("-show_format")
-            .arg("-show_streams")
-            .arg(path);
-
-        let output = match cmd.output() {
+        let args: Vec<std::ffi::OsString> = vec![
+            "-v".into(),
+            "quiet".into(),
+            "-print_format".into(),
+            "json".into(),
+            "-show_format".into(),
+            "-show_streams".into(),
+            "-show_chapters".into(),
+            "-show_programs".into(),
+            path.into(),
+        ];
+        let output = self.run_ffprobe(&args)?;
+
+        // Parse the JSON output
+        let output_str = String::from_utf8_lossy(&output);
+
+        match serde_json::from_str(&output_str) {
+            Ok(media_info) => Ok(media_info),
+            Err(e) => Err(Error::OutputParseError(format!(
+                "Failed to parse ffprobe output: {e}"
+            ))),
+        }
+    }
+
+    /// Runs `ffprobe` next to this `FFmpeg`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments of `ffprobe`
+    ///
+    /// # Returns
+    ///
+    /// The standard output of `ffprobe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ffprobe` cannot be run or fails.
+    pub(crate) fn run_ffprobe(&self, args: &[std::ffi::OsString]) -> Result<Vec<u8>> {
+        let output = match self.executor.run(&self.ffprobe_path(), args) {
             Ok(output) => output,
             Err(e) => {
                 return Err(Error::ExecutionError(format!(
@@ -564,22 +724,38 @@ impl FFmpeg {
             }
         };
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::ProcessTerminated {
-                exit_code: output.status.code(),
-                message: format!("ffprobe process failed: {stderr}"),
-            });
+        if !output.success {
+            return Err(Error::from_failed_run(&output, || {
+                Error::ProcessTerminated {
+                    exit_code: output.exit_code,
+                    message: format!("ffprobe process failed: {}", output.stderr),
+                }
+            }));
         }
+        Ok(output.stdout)
+    }
 
-        // Parse the JSON output
-        let output_str = String::from_utf8_lossy(&output.stdout);
+    /// Gets the path of `ffprobe`.
+    ///
+    /// # Returns
+    ///
+    /// `FFPROBE_PATH` if set, else `ffprobe` next to `FFmpeg` if it exists,
+    /// else `ffprobe` looked up in `PATH`.
+    fn ffprobe_path(&self) -> PathBuf {
+        // Try to get ffprobe path from environment variable
+        if let Ok(path) = std::env::var("FFPROBE_PATH") {
+            return PathBuf::from(path);
+        }
 
-        match serde_json::from_str(&output_str) {
-            Ok(media_info) => Ok(media_info),
-            Err(e) => Err(Error::OutputParseError(format!(
-                "Failed to parse ffprobe output: {e}"
-            ))),
+        // Try to find ffprobe in the same directory as ffmpeg
+        let ffprobe_name = if cfg!(windows) {
+            "ffprobe.exe"
+        } else {
+            "ffprobe"
+        };
+        match self.path().parent() {
+            Some(dir) if dir.join(ffprobe_name).exists() => dir.join(ffprobe_name),
+            _ => PathBuf::from(ffprobe_name), // Fall back to PATH
         }
     }
 
@@ -678,6 +854,24 @@ pub struct StreamInfo {
     /// The bit rate (for audio/video streams).
     #[serde(default)]
     pub bit_rate: Option<String>,
+    /// The color range (for video streams).
+    #[serde(default)]
+    pub color_range: Option<String>,
+    /// The color space (for video streams).
+    #[serde(default)]
+    pub color_space: Option<String>,
+    /// The transfer characteristics (for video streams).
+    #[serde(default)]
+    pub color_transfer: Option<String>,
+    /// The color primaries (for video streams).
+    #[serde(default)]
+    pub color_primaries: Option<String>,
+    /// The dispositions (default, forced, ...).
+    #[serde(default)]
+    pub disposition: Disposition,
+    /// The side data (display matrix, HDR metadata, ...).
+    #[serde(default)]
+    pub side_data_list: Vec<SideData>,
     /// Additional tags.
     #[serde(default)]
     pub tags: Option<std::collections::HashMap<String, String>>,
@@ -690,6 +884,12 @@ pub struct MediaInfo {
     pub format: FormatInfo,
     /// Information about the streams (video, audio, subtitle, etc.).
     pub streams: Vec<StreamInfo>,
+    /// The chapters, in order.
+    #[serde(default)]
+    pub chapters: Vec<ChapterInfo>,
+    /// The programs (e.g. of an MPEG-TS broadcast).
+    #[serde(default)]
+    pub programs: Vec<ProgramInfo>,
 }
 
 impl MediaInfo {
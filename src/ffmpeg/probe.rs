@@ -0,0 +1,837 @@
+/// Extended `ffprobe` model and per-file probe cache.
+///
+/// `MediaInfo` covers the container and its streams. This module adds what
+/// `ffprobe` reports next to them: chapters, programs, stream dispositions,
+/// side data (display matrix rotation, mastering display and content light
+/// levels) and the HDR metadata derived from it. It also builds keyframe
+/// indexes of video streams, either from the packet flags (fast, no
+/// decoding) or by decoding only the keyframes with `-skip_frame nokey`.
+///
+/// Probe results are cached per file. A file is identified by its canonical
+/// path, size and modification time, so a rewritten file is probed again.
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::ffmpeg::{Error, FFmpeg, MediaInfo, Result};
+use crate::utility::time::TimePosition;
+
+/// Number of entries a probe cache keeps before it is emptied.
+const MAX_CACHED_FILES: usize = 256;
+
+/// A chapter of a media file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChapterInfo {
+    /// The chapter ID.
+    #[serde(default)]
+    pub id: i64,
+    /// The time base of `start` and `end`.
+    #[serde(default)]
+    pub time_base: String,
+    /// The start in time base units.
+    #[serde(default)]
+    pub start: i64,
+    /// The start time in seconds.
+    #[serde(default)]
+    pub start_time: Option<String>,
+    /// The end in time base units.
+    #[serde(default)]
+    pub end: i64,
+    /// The end time in seconds.
+    #[serde(default)]
+    pub end_time: Option<String>,
+    /// Additional tags (e.g. `title`).
+    #[serde(default)]
+    pub tags: Option<HashMap<String, String>>,
+}
+
+impl ChapterInfo {
+    /// Gets the start time in seconds.
+    #[must_use]
+    pub fn start_seconds(&self) -> Option<f64> {
+        self.start_time.as_ref().and_then(|s| s.parse().ok())
+    }
+
+    /// Gets the end time in seconds.
+    #[must_use]
+    pub fn end_seconds(&self) -> Option<f64> {
+        self.end_time.as_ref().and_then(|s| s.parse().ok())
+    }
+
+    /// Gets the title of the chapter.
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        tag(self.tags.as_ref(), "title")
+    }
+}
+
+/// A program of a media file (e.g. a service of an MPEG-TS broadcast).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProgramInfo {
+    /// The program ID.
+    #[serde(default)]
+    pub program_id: i32,
+    /// The program number.
+    #[serde(default)]
+    pub program_num: i32,
+    /// The number of streams of the program.
+    #[serde(default)]
+    pub nb_streams: i32,
+    /// The PID of the program map table.
+    #[serde(default)]
+    pub pmt_pid: Option<i32>,
+    /// The PID carrying the program clock reference.
+    #[serde(default)]
+    pub pcr_pid: Option<i32>,
+    /// Additional tags (e.g. `service_name`).
+    #[serde(default)]
+    pub tags: Option<HashMap<String, String>>,
+    /// The streams of the program.
+    #[serde(default)]
+    pub streams: Vec<ProgramStream>,
+}
+
+impl ProgramInfo {
+    /// Gets the indexes of the streams of the program.
+    #[must_use]
+    pub fn stream_indexes(&self) -> Vec<i32> {
+        self.streams.iter().map(|stream| stream.index).collect()
+    }
+
+    /// Gets the service name of the program.
+    #[must_use]
+    pub fn service_name(&self) -> Option<&str> {
+        tag(self.tags.as_ref(), "service_name")
+    }
+}
+
+/// A stream listed in a program.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProgramStream {
+    /// The index of the stream in the file.
+    pub index: i32,
+}
+
+/// The dispositions of a stream.
+///
+/// `ffprobe` reports them as 0 or 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct Disposition {
+    /// The stream is selected by default.
+    #[serde(deserialize_with = "flag")]
+    pub default: bool,
+    /// The stream is a dub.
+    #[serde(deserialize_with = "flag")]
+    pub dub: bool,
+    /// The stream is in the original language.
+    #[serde(deserialize_with = "flag")]
+    pub original: bool,
+    /// The stream is a commentary.
+    #[serde(deserialize_with = "flag")]
+    pub comment: bool,
+    /// The subtitles are forced.
+    #[serde(deserialize_with = "flag")]
+    pub forced: bool,
+    /// The stream is for the hearing impaired.
+    #[serde(deserialize_with = "flag")]
+    pub hearing_impaired: bool,
+    /// The stream is for the visually impaired.
+    #[serde(deserialize_with = "flag")]
+    pub visual_impaired: bool,
+    /// The stream is an attached picture (e.g. cover art).
+    #[serde(deserialize_with = "flag")]
+    pub attached_pic: bool,
+    /// The subtitles are captions.
+    #[serde(deserialize_with = "flag")]
+    pub captions: bool,
+    /// The stream is an audio description.
+    #[serde(deserialize_with = "flag")]
+    pub descriptions: bool,
+}
+
+/// Side data of a stream, such as a display matrix or HDR metadata.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SideData {
+    /// The type of the side data (e.g. `Display Matrix`).
+    #[serde(rename = "side_data_type", default)]
+    pub kind: String,
+    /// The values of the side data, as reported by `ffprobe`.
+    #[serde(flatten)]
+    pub values: HashMap<String, serde_json::Value>,
+}
+
+impl SideData {
+    /// Side data type of the display matrix.
+    pub const DISPLAY_MATRIX: &'static str = "Display Matrix";
+    /// Side data type of the mastering display metadata.
+    pub const MASTERING_DISPLAY: &'static str = "Mastering display metadata";
+    /// Side data type of the content light level metadata.
+    pub const CONTENT_LIGHT_LEVEL: &'static str = "Content light level metadata";
+    /// Side data type of the Dolby Vision configuration.
+    pub const DOLBY_VISION: &'static str = "DOVI configuration record";
+
+    /// Gets a numeric value, which `ffprobe` reports as a number, a
+    /// numeric string or a rational such as `34000/50000`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Name of the value
+    #[must_use]
+    pub fn number(&self, key: &str) -> Option<f64> {
+        match self.values.get(key)? {
+            serde_json::Value::Number(number) => number.as_f64(),
+            serde_json::Value::String(text) => parse_number(text),
+            _ => None,
+        }
+    }
+}
+
+/// HDR metadata of a video stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrMetadata {
+    /// The transfer function.
+    pub transfer: HdrTransfer,
+    /// The color primaries (e.g. `bt2020`).
+    pub primaries: Option<String>,
+    /// The color volume of the mastering display.
+    pub mastering_display: Option<MasteringDisplay>,
+    /// The content light levels.
+    pub content_light: Option<ContentLightLevel>,
+    /// Whether the stream carries a Dolby Vision configuration.
+    pub dolby_vision: bool,
+}
+
+/// Transfer function of an HDR stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrTransfer {
+    /// SMPTE ST 2084 perceptual quantizer (HDR10, HDR10+, Dolby Vision).
+    Pq,
+    /// ARIB STD-B67 hybrid log-gamma.
+    Hlg,
+    /// Another transfer function, with HDR side data.
+    Other,
+}
+
+/// Color volume of the display an HDR stream was mastered on (SMPTE ST 2086).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasteringDisplay {
+    /// CIE 1931 xy chromaticity of the red primary.
+    pub red: (f64, f64),
+    /// CIE 1931 xy chromaticity of the green primary.
+    pub green: (f64, f64),
+    /// CIE 1931 xy chromaticity of the blue primary.
+    pub blue: (f64, f64),
+    /// CIE 1931 xy chromaticity of the white point.
+    pub white_point: (f64, f64),
+    /// Minimum luminance in cd/m².
+    pub min_luminance: f64,
+    /// Maximum luminance in cd/m².
+    pub max_luminance: f64,
+}
+
+impl MasteringDisplay {
+    /// Reads the mastering display from its side data.
+    fn from_side_data(data: &SideData) -> Option<Self> {
+        let point = |x: &str, y: &str| Some((data.number(x)?, data.number(y)?));
+        Some(Self {
+            red: point("red_x", "red_y")?,
+            green: point("green_x", "green_y")?,
+            blue: point("blue_x", "blue_y")?,
+            white_point: point("white_point_x", "white_point_y")?,
+            min_luminance: data.number("min_luminance")?,
+            max_luminance: data.number("max_luminance")?,
+        })
+    }
+}
+
+/// Content light levels of an HDR stream (CTA-861.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLightLevel {
+    /// Maximum content light level (MaxCLL) in cd/m².
+    pub max_content: u32,
+    /// Maximum frame-average light level (MaxFALL) in cd/m².
+    pub max_average: u32,
+}
+
+impl ContentLightLevel {
+    /// Reads the light levels from their side data.
+    fn from_side_data(data: &SideData) -> Option<Self> {
+        Some(Self {
+            max_content: data.number("max_content")? as u32,
+            max_average: data.number("max_average")? as u32,
+        })
+    }
+}
+
+impl crate::ffmpeg::StreamInfo {
+    /// Gets the language of the stream, unless it is undetermined.
+    #[must_use]
+    pub fn language(&self) -> Option<&str> {
+        tag(self.tags.as_ref(), "language").filter(|language| *language != "und")
+    }
+
+    /// Gets the title of the stream.
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        tag(self.tags.as_ref(), "title")
+    }
+
+    /// Gets the side data of a type.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The side data type (e.g. `SideData::DISPLAY_MATRIX`)
+    #[must_use]
+    pub fn side_data(&self, kind: &str) -> Option<&SideData> {
+        self.side_data_list.iter().find(|data| data.kind == kind)
+    }
+
+    /// Gets the clockwise rotation to apply to the frames for display.
+    ///
+    /// The rotation comes from the display matrix, or from the `rotate`
+    /// tag written by older muxers.
+    ///
+    /// # Returns
+    ///
+    /// The rotation in degrees from 0 to 360, or `None` if the stream has
+    /// no rotation.
+    #[must_use]
+    pub fn rotation(&self) -> Option<f64> {
+        // 表示行列の rotation は反時計回りなので符号を反転する
+        let degrees = self
+            .side_data(SideData::DISPLAY_MATRIX)
+            .and_then(|data| data.number("rotation"))
+            .map(|rotation| -rotation)
+            .or_else(|| tag(self.tags.as_ref(), "rotate").and_then(parse_number))?;
+        let degrees = degrees.rem_euclid(360.0);
+        (degrees != 0.0).then_some(degrees)
+    }
+
+    /// Gets the HDR metadata of a video stream.
+    ///
+    /// # Returns
+    ///
+    /// The metadata, or `None` if the stream is not HDR.
+    #[must_use]
+    pub fn hdr(&self) -> Option<HdrMetadata> {
+        let mastering_display = self
+            .side_data(SideData::MASTERING_DISPLAY)
+            .and_then(MasteringDisplay::from_side_data);
+        let content_light = self
+            .side_data(SideData::CONTENT_LIGHT_LEVEL)
+            .and_then(ContentLightLevel::from_side_data);
+        let dolby_vision = self.side_data(SideData::DOLBY_VISION).is_some();
+
+        let transfer = match self.color_transfer.as_deref() {
+            Some("smpte2084") => HdrTransfer::Pq,
+            Some("arib-std-b67") => HdrTransfer::Hlg,
+            _ if mastering_display.is_some() || content_light.is_some() || dolby_vision => {
+                HdrTransfer::Other
+            }
+            _ => return None,
+        };
+        Some(HdrMetadata {
+            transfer,
+            primaries: self.color_primaries.clone(),
+            mastering_display,
+            content_light,
+            dolby_vision,
+        })
+    }
+}
+
+/// How a keyframe index is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum KeyframeScan {
+    /// Read the keyframe flags of the packets (`-show_packets`), without
+    /// decoding.
+    #[default]
+    Packets,
+    /// Decode only the keyframes (`-skip_frame nokey`), which gives their
+    /// presentation times after reordering.
+    Frames,
+}
+
+/// A packet (or decoded keyframe) of a video stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketInfo {
+    /// The presentation time in seconds.
+    pub pts: Option<f64>,
+    /// The decoding time in seconds.
+    pub dts: Option<f64>,
+    /// The duration in seconds.
+    pub duration: Option<f64>,
+    /// The size in bytes.
+    pub size: Option<u64>,
+    /// The byte position in the file.
+    pub pos: Option<u64>,
+    /// Whether the packet is a keyframe.
+    pub keyframe: bool,
+}
+
+/// The packets and keyframes of a video stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeIndex {
+    /// Index of the stream among the video streams of the file.
+    pub stream: usize,
+    /// How the index was built.
+    pub scan: KeyframeScan,
+    /// The packets in decoding order, or the keyframes for `Frames` scans.
+    pub packets: Vec<PacketInfo>,
+    /// The presentation times of the keyframes, sorted.
+    keyframes: Vec<TimePosition>,
+}
+
+impl KeyframeIndex {
+    /// Creates an index from the packets of a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Index of the stream among the video streams
+    /// * `scan` - How the packets were read
+    /// * `packets` - The packets in decoding order
+    #[must_use]
+    pub fn new(stream: usize, scan: KeyframeScan, packets: Vec<PacketInfo>) -> Self {
+        let mut keyframes: Vec<TimePosition> = packets
+            .iter()
+            .filter(|packet| packet.keyframe)
+            .filter_map(|packet| packet.pts.or(packet.dts))
+            .map(TimePosition::from_seconds)
+            .collect();
+        keyframes.sort();
+        keyframes.dedup();
+        Self {
+            stream,
+            scan,
+            packets,
+            keyframes,
+        }
+    }
+
+    /// Gets the presentation times of the keyframes, sorted.
+    #[must_use]
+    pub fn keyframes(&self) -> &[TimePosition] {
+        &self.keyframes
+    }
+
+    /// Gets the last keyframe at or before a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The time
+    #[must_use]
+    pub fn keyframe_at_or_before(&self, position: TimePosition) -> Option<TimePosition> {
+        let count = self
+            .keyframes
+            .partition_point(|keyframe| *keyframe <= position);
+        count.checked_sub(1).map(|index| self.keyframes[index])
+    }
+
+    /// Gets the first keyframe at or after a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The time
+    #[must_use]
+    pub fn keyframe_at_or_after(&self, position: TimePosition) -> Option<TimePosition> {
+        let index = self
+            .keyframes
+            .partition_point(|keyframe| *keyframe < position);
+        self.keyframes.get(index).copied()
+    }
+
+    /// Determines whether a keyframe lies within a tolerance of a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The time
+    /// * `tolerance` - The largest distance in seconds
+    #[must_use]
+    pub fn is_keyframe(&self, position: TimePosition, tolerance: f64) -> bool {
+        let near = |keyframe: Option<TimePosition>| {
+            keyframe.is_some_and(|k| (k.as_seconds() - position.as_seconds()).abs() <= tolerance)
+        };
+        near(self.keyframe_at_or_before(position)) || near(self.keyframe_at_or_after(position))
+    }
+}
+
+/// Output of `ffprobe -show_entries packet=...` or `frame=...`.
+#[derive(Debug, Default, Deserialize)]
+struct PacketList {
+    /// The packets.
+    #[serde(default)]
+    packets: Vec<RawPacket>,
+    /// The decoded frames.
+    #[serde(default)]
+    frames: Vec<RawFrame>,
+}
+
+/// A packet as reported by `ffprobe`.
+#[derive(Debug, Deserialize)]
+struct RawPacket {
+    #[serde(default)]
+    pts_time: Option<String>,
+    #[serde(default)]
+    dts_time: Option<String>,
+    #[serde(default)]
+    duration_time: Option<String>,
+    #[serde(default)]
+    size: Option<String>,
+    #[serde(default)]
+    pos: Option<String>,
+    #[serde(default)]
+    flags: String,
+}
+
+/// A decoded frame as reported by `ffprobe`.
+#[derive(Debug, Deserialize)]
+struct RawFrame {
+    #[serde(default, alias = "pkt_pts_time", alias = "best_effort_timestamp_time")]
+    pts_time: Option<String>,
+    #[serde(default)]
+    pkt_dts_time: Option<String>,
+    #[serde(default, alias = "pkt_duration_time")]
+    duration_time: Option<String>,
+    #[serde(default)]
+    pkt_size: Option<String>,
+    #[serde(default)]
+    pkt_pos: Option<String>,
+}
+
+impl PacketList {
+    /// Converts the reported packets or frames.
+    fn into_packets(self) -> Vec<PacketInfo> {
+        let number = |value: Option<String>| value.as_deref().and_then(parse_number);
+        let integer = |value: Option<String>| value.and_then(|v| v.parse().ok());
+
+        let packets = self.packets.into_iter().map(|packet| PacketInfo {
+            pts: number(packet.pts_time),
+            dts: number(packet.dts_time),
+            duration: number(packet.duration_time),
+            size: integer(packet.size),
+            pos: integer(packet.pos),
+            keyframe: packet.flags.starts_with('K'),
+        });
+        // -skip_frame nokey ではキーフレームだけがデコードされる
+        let frames = self.frames.into_iter().map(|frame| PacketInfo {
+            pts: number(frame.pts_time),
+            dts: number(frame.pkt_dts_time),
+            duration: number(frame.duration_time),
+            size: integer(frame.pkt_size),
+            pos: integer(frame.pkt_pos),
+            keyframe: true,
+        });
+        packets.chain(frames).collect()
+    }
+}
+
+/// Identifies a version of a file in the probe caches.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FileKey {
+    /// The canonical path.
+    path: PathBuf,
+    /// The size in bytes.
+    len: u64,
+    /// The modification time.
+    modified: Option<SystemTime>,
+}
+
+impl FileKey {
+    /// Identifies the current version of a file.
+    fn of(path: &Path) -> Option<Self> {
+        let path = fs::canonicalize(path).ok()?;
+        let metadata = fs::metadata(&path).ok()?;
+        Some(Self {
+            path,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// A probe cache of one kind of result.
+type ProbeCache<K, V> = OnceLock<Mutex<HashMap<K, Arc<V>>>>;
+
+/// Gets a probe result from a cache, or probes and caches it.
+fn cached<K: Eq + Hash, V>(
+    cache: &'static ProbeCache<K, V>,
+    key: Option<K>,
+    probe: impl FnOnce() -> Result<V>,
+) -> Result<Arc<V>> {
+    let Some(key) = key else {
+        return probe().map(Arc::new);
+    };
+    let cache = cache.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(value) = cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+        return Ok(value);
+    }
+
+    // 問い合わせ中はロックを保持しない
+    let value = Arc::new(probe()?);
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= MAX_CACHED_FILES {
+            cache.clear();
+        }
+        cache.insert(key, Arc::clone(&value));
+    }
+    Ok(value)
+}
+
+impl FFmpeg {
+    /// Gets information about a media file, probing it once per version of
+    /// the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the media file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be analyzed.
+    pub(crate) fn cached_media_info(&self, path: &Path) -> Result<Arc<MediaInfo>> {
+        static CACHE: ProbeCache<FileKey, MediaInfo> = OnceLock::new();
+        // 実プロセス以外（テスト用の実行器など）の結果はキャッシュしない
+        let key = self.runs_processes().then(|| FileKey::of(path)).flatten();
+        cached(&CACHE, key, || self.probe_media_info(path))
+    }
+
+    /// Builds the keyframe index of a video stream.
+    ///
+    /// The index is built once per version of the file and scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the media file
+    /// * `stream` - Index of the stream among the video streams
+    /// * `scan` - How to find the keyframes
+    ///
+    /// # Returns
+    ///
+    /// The packets and keyframes of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be analyzed.
+    pub fn keyframe_index<P: AsRef<Path>>(
+        &self,
+        path: P,
+        stream: usize,
+        scan: KeyframeScan,
+    ) -> Result<Arc<KeyframeIndex>> {
+        static CACHE: ProbeCache<(FileKey, usize, KeyframeScan), KeyframeIndex> = OnceLock::new();
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(Error::InvalidPath(format!(
+                "File not found: {}",
+                path.display()
+            )));
+        }
+
+        let key = self
+            .runs_processes()
+            .then(|| FileKey::of(path))
+            .flatten()
+            .map(|key| (key, stream, scan));
+        cached(&CACHE, key, || {
+            let output = self.run_ffprobe(&keyframe_args(path, stream, scan))?;
+            let list: PacketList = serde_json::from_slice(&output).map_err(|e| {
+                Error::OutputParseError(format!("Failed to parse ffprobe packets: {e}"))
+            })?;
+            Ok(KeyframeIndex::new(stream, scan, list.into_packets()))
+        })
+    }
+}
+
+/// Builds the `ffprobe` arguments listing the packets or keyframes of a
+/// video stream.
+fn keyframe_args(path: &Path, stream: usize, scan: KeyframeScan) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![
+        "-v".into(),
+        "error".into(),
+        "-select_streams".into(),
+        format!("v:{stream}").into(),
+    ];
+    match scan {
+        KeyframeScan::Packets => {
+            args.push("-show_entries".into());
+            args.push("packet=pts_time,dts_time,duration_time,size,pos,flags".into());
+        }
+        KeyframeScan::Frames => {
+            args.push("-skip_frame".into());
+            args.push("nokey".into());
+            args.push("-show_entries".into());
+            args.push("frame=pts_time,pkt_dts_time,duration_time,pkt_size,pkt_pos".into());
+        }
+    }
+    args.extend(["-print_format".into(), "json".into(), path.into()]);
+    args
+}
+
+/// Gets a tag, ignoring empty values.
+fn tag<'a>(tags: Option<&'a HashMap<String, String>>, key: &str) -> Option<&'a str> {
+    tags?
+        .get(key)
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+/// Parses a number or a rational such as `1001/30000`.
+fn parse_number(text: &str) -> Option<f64> {
+    match text.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.trim().parse().ok()?;
+            (denominator != 0.0).then_some(numerator.trim().parse::<f64>().ok()? / denominator)
+        }
+        None => text.trim().parse().ok(),
+    }
+}
+
+/// Deserializes a disposition flag reported as 0 or 1.
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<bool, D::Error> {
+    Ok(i64::deserialize(deserializer)? != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBED: &str = r#"{
+        "programs": [{"program_id": 1, "program_num": 1, "nb_streams": 2,
+                      "tags": {"service_name": "News"},
+                      "streams": [{"index": 0}, {"index": 1}]}],
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "hevc",
+             "color_transfer": "smpte2084", "color_primaries": "bt2020",
+             "disposition": {"default": 1, "forced": 0, "attached_pic": 0},
+             "side_data_list": [
+                {"side_data_type": "Display Matrix", "rotation": -90},
+                {"side_data_type": "Mastering display metadata",
+                 "red_x": "35400/50000", "red_y": "14600/50000",
+                 "green_x": "8500/50000", "green_y": "39850/50000",
+                 "blue_x": "6550/50000", "blue_y": "2300/50000",
+                 "white_point_x": "15635/50000", "white_point_y": "16450/50000",
+                 "min_luminance": "50/10000", "max_luminance": "10000000/10000"},
+                {"side_data_type": "Content light level metadata",
+                 "max_content": 1000, "max_average": 400}
+             ]},
+            {"index": 1, "codec_type": "audio", "codec_name": "aac",
+             "disposition": {"default": 0, "comment": 1},
+             "tags": {"language": "eng", "rotate": "90"}}
+        ],
+        "chapters": [
+            {"id": 0, "time_base": "1/1000", "start": 0, "start_time": "0.000000",
+             "end": 60000, "end_time": "60.000000", "tags": {"title": "Intro"}}
+        ],
+        "format": {"filename": "hdr.mkv", "duration": "60.0"}
+    }"#;
+
+    #[test]
+    fn test_extended_media_info() {
+        let info: MediaInfo = serde_json::from_str(PROBED).unwrap();
+
+        assert_eq!(info.chapters[0].title(), Some("Intro"));
+        assert_eq!(info.chapters[0].end_seconds(), Some(60.0));
+        assert_eq!(info.programs[0].service_name(), Some("News"));
+        assert_eq!(info.programs[0].stream_indexes(), vec![0, 1]);
+
+        let video = &info.streams[0];
+        assert!(video.disposition.default);
+        assert_eq!(video.rotation(), Some(90.0));
+        let hdr = video.hdr().unwrap();
+        assert_eq!(hdr.transfer, HdrTransfer::Pq);
+        assert_eq!(hdr.primaries.as_deref(), Some("bt2020"));
+        let display = hdr.mastering_display.unwrap();
+        assert!((display.red.0 - 0.708).abs() < 1e-9);
+        assert!((display.max_luminance - 1000.0).abs() < 1e-9);
+        assert_eq!(
+            hdr.content_light,
+            Some(ContentLightLevel {
+                max_content: 1000,
+                max_average: 400
+            })
+        );
+
+        let audio = &info.streams[1];
+        assert!(!audio.disposition.default && audio.disposition.comment);
+        assert_eq!(audio.language(), Some("eng"));
+        assert_eq!(audio.rotation(), Some(90.0));
+        assert!(audio.hdr().is_none());
+    }
+
+    #[test]
+    fn test_keyframe_index_from_packets() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "packet=",
+            crate::ffmpeg::ExecutionOutput::success(
+                r#"{"packets": [
+                    {"pts_time": "0.000000", "dts_time": "-0.040000", "size": "5120", "pos": "48", "flags": "K__"},
+                    {"pts_time": "0.080000", "dts_time": "0.000000", "size": "812", "pos": "5168", "flags": "___"},
+                    {"pts_time": "2.000000", "dts_time": "1.960000", "size": "4980", "pos": "90112", "flags": "K__"}
+                ]}"#,
+            ),
+        );
+        let input = std::env::current_exe().unwrap();
+
+        let index = ffmpeg
+            .keyframe_index(&input, 0, KeyframeScan::Packets)
+            .unwrap();
+        assert_eq!(index.packets.len(), 3);
+        assert_eq!(index.packets[1].pos, Some(5168));
+        assert_eq!(
+            index.keyframes(),
+            &[
+                TimePosition::from_seconds(0.0),
+                TimePosition::from_seconds(2.0)
+            ]
+        );
+        let at = TimePosition::from_seconds(1.5);
+        assert_eq!(
+            index.keyframe_at_or_before(at),
+            Some(TimePosition::from_seconds(0.0))
+        );
+        assert_eq!(
+            index.keyframe_at_or_after(at),
+            Some(TimePosition::from_seconds(2.0))
+        );
+        assert!(index.is_keyframe(TimePosition::from_seconds(1.99), 0.02));
+
+        let call = &executor.calls()[0];
+        assert!(call.is_ffprobe());
+        assert_eq!(call.option_value("-select_streams"), Some("v:0"));
+        assert!(!call.has_arg("-skip_frame"));
+    }
+
+    #[test]
+    fn test_keyframe_index_from_frames() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "nokey",
+            crate::ffmpeg::ExecutionOutput::success(
+                r#"{"frames": [{"pts_time": "0.500000", "pkt_pos": "48"},
+                               {"pts_time": "4.500000", "pkt_pos": "90112"}]}"#,
+            ),
+        );
+        let input = std::env::current_exe().unwrap();
+
+        let index = ffmpeg
+            .keyframe_index(&input, 1, KeyframeScan::Frames)
+            .unwrap();
+        assert_eq!(index.keyframes().len(), 2);
+        assert_eq!(
+            index.keyframe_at_or_before(TimePosition::from_seconds(0.2)),
+            None
+        );
+        assert_eq!(
+            executor.calls()[0].option_value("-select_streams"),
+            Some("v:1")
+        );
+    }
+}
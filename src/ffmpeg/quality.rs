@@ -0,0 +1,443 @@
+/// Quality checks of deliverables.
+///
+/// A quality check looks for black segments (`blackdetect`), frozen video
+/// (`freezedetect`) and letterbox or pillarbox borders (`cropdetect`). The
+/// enabled detectors are chained into one filter, so the video is decoded
+/// only once however many checks run.
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ffmpeg::analysis::{self, AnalysisScope, AnalyzedMedia, SpanLog};
+use crate::ffmpeg::{Error, FFmpeg, Result};
+use crate::utility::time::{Duration, TimePosition};
+
+/// Options of black segment detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlackOptions {
+    /// Shortest black segment to report, in seconds.
+    pub min_duration: f64,
+    /// Share of black pixels that makes a frame black, from 0.0 to 1.0.
+    pub picture_threshold: f64,
+    /// Brightness below which a pixel is black, from 0.0 to 1.0.
+    pub pixel_threshold: f64,
+}
+
+impl Default for BlackOptions {
+    fn default() -> Self {
+        Self {
+            min_duration: 2.0,
+            picture_threshold: 0.98,
+            pixel_threshold: 0.1,
+        }
+    }
+}
+
+impl BlackOptions {
+    /// Builds the detection filter.
+    fn filter(&self) -> String {
+        format!(
+            "blackdetect=d={}:pic_th={}:pix_th={}",
+            self.min_duration, self.picture_threshold, self.pixel_threshold
+        )
+    }
+}
+
+/// Options of frozen video detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreezeOptions {
+    /// Noise tolerance between frames, in dB.
+    pub noise_db: f64,
+    /// Shortest freeze to report, in seconds.
+    pub min_duration: f64,
+}
+
+impl Default for FreezeOptions {
+    fn default() -> Self {
+        Self {
+            noise_db: -60.0,
+            min_duration: 2.0,
+        }
+    }
+}
+
+impl FreezeOptions {
+    /// Builds the detection filter.
+    fn filter(&self) -> String {
+        format!("freezedetect=n={}dB:d={}", self.noise_db, self.min_duration)
+    }
+}
+
+/// Options of border detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropOptions {
+    /// Brightness up to which a border pixel counts as black, from 0 to 255.
+    pub limit: u32,
+    /// The crop size is rounded down to a multiple of this value.
+    pub round: u32,
+}
+
+impl Default for CropOptions {
+    fn default() -> Self {
+        Self {
+            limit: 24,
+            round: 2,
+        }
+    }
+}
+
+impl CropOptions {
+    /// Builds the detection filter.
+    fn filter(&self) -> String {
+        // reset=0 のため、最終的な値は全フレームを含む範囲になる
+        format!(
+            "cropdetect=limit={}:round={}:reset=0",
+            self.limit, self.round
+        )
+    }
+}
+
+/// Options of a quality check.
+///
+/// A detector set to `None` does not run.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QcOptions {
+    /// Black segment detection.
+    pub black: Option<BlackOptions>,
+    /// Frozen video detection.
+    pub freeze: Option<FreezeOptions>,
+    /// Border detection.
+    pub crop: Option<CropOptions>,
+    /// What part of the input to check.
+    pub scope: AnalysisScope,
+}
+
+impl QcOptions {
+    /// Creates options running all detectors with their defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            black: Some(BlackOptions::default()),
+            freeze: Some(FreezeOptions::default()),
+            crop: Some(CropOptions::default()),
+            scope: AnalysisScope::default(),
+        }
+    }
+
+    /// Sets the black segment detection.
+    #[must_use]
+    pub fn with_black(mut self, black: Option<BlackOptions>) -> Self {
+        self.black = black;
+        self
+    }
+
+    /// Sets the frozen video detection.
+    #[must_use]
+    pub fn with_freeze(mut self, freeze: Option<FreezeOptions>) -> Self {
+        self.freeze = freeze;
+        self
+    }
+
+    /// Sets the border detection.
+    #[must_use]
+    pub fn with_crop(mut self, crop: Option<CropOptions>) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Sets what part of the input to check.
+    #[must_use]
+    pub fn with_scope(mut self, scope: AnalysisScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Checks that the options are valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no detector is enabled or a threshold is out of
+    /// range.
+    pub fn validate(&self) -> Result<()> {
+        if self.black.is_none() && self.freeze.is_none() && self.crop.is_none() {
+            return Err(Error::InvalidArgument(
+                "At least one quality check must be enabled".to_string(),
+            ));
+        }
+        if let Some(black) = &self.black
+            && !((0.0..=1.0).contains(&black.picture_threshold)
+                && (0.0..=1.0).contains(&black.pixel_threshold))
+        {
+            return Err(Error::InvalidArgument(
+                "Black thresholds must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+        if let Some(crop) = &self.crop
+            && crop.limit > 255
+        {
+            return Err(Error::InvalidArgument(
+                "Crop limit must be between 0 and 255".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds the chain of the enabled detection filters.
+    fn filter(&self) -> String {
+        let filters: Vec<String> = [
+            self.black.map(|black| black.filter()),
+            self.freeze.map(|freeze| freeze.filter()),
+            self.crop.map(|crop| crop.filter()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        filters.join(",")
+    }
+}
+
+/// A span of time in an input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSpan {
+    /// Start of the span.
+    pub start: TimePosition,
+    /// End of the span.
+    pub end: TimePosition,
+}
+
+impl TimeSpan {
+    /// Gets the length of the span.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// A rectangle of a frame to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CropRect {
+    /// Width of the kept area in pixels.
+    pub width: u32,
+    /// Height of the kept area in pixels.
+    pub height: u32,
+    /// Distance of the kept area from the left edge in pixels.
+    pub x: u32,
+    /// Distance of the kept area from the top edge in pixels.
+    pub y: u32,
+}
+
+impl CropRect {
+    /// Checks whether the rectangle keeps a whole frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frame
+    /// * `height` - Height of the frame
+    #[must_use]
+    pub fn is_full_frame(&self, width: u32, height: u32) -> bool {
+        self.x == 0 && self.y == 0 && self.width >= width && self.height >= height
+    }
+
+    /// Gets the `crop` filter keeping the rectangle.
+    #[must_use]
+    pub fn filter(&self) -> String {
+        format!("crop={self}")
+    }
+}
+
+impl fmt::Display for CropRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for CropRect {
+    type Err = Error;
+
+    /// Parses a rectangle written as `width:height:x:y`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidArgument(format!("Invalid crop rectangle: {s}"));
+        let values: Vec<u32> = s
+            .split(':')
+            .map(|value| value.trim().parse().map_err(|_| invalid()))
+            .collect::<Result<_>>()?;
+        let [width, height, x, y] = values[..] else {
+            return Err(invalid());
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            width,
+            height,
+            x,
+            y,
+        })
+    }
+}
+
+/// Findings of a quality check.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QcReport {
+    /// Black segments, in time order.
+    pub black: Vec<TimeSpan>,
+    /// Frozen segments, in time order.
+    pub freezes: Vec<TimeSpan>,
+    /// Area without borders, or `None` if borders were not checked or no
+    /// frame was measured.
+    pub crop: Option<CropRect>,
+}
+
+impl QcReport {
+    /// Checks whether the check found nothing but an optional crop that
+    /// keeps a whole frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frames, if known
+    /// * `height` - Height of the frames, if known
+    #[must_use]
+    pub fn is_clean(&self, width: Option<u32>, height: Option<u32>) -> bool {
+        let crop_clean = match (self.crop, width, height) {
+            (Some(crop), Some(width), Some(height)) => crop.is_full_frame(width, height),
+            _ => true,
+        };
+        self.black.is_empty() && self.freezes.is_empty() && crop_clean
+    }
+}
+
+impl FFmpeg {
+    /// Runs a quality check on a video.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Path to the video
+    /// * `options` - Detectors and scope of the check
+    ///
+    /// # Returns
+    ///
+    /// The findings of the enabled detectors, with times in the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are invalid or `FFmpeg` fails.
+    pub fn quality_check<P: AsRef<Path>>(&self, input: P, options: &QcOptions) -> Result<QcReport> {
+        options.validate()?;
+        let mut black = SpanLog::new("black_start:", "black_end:");
+        let mut freezes = SpanLog::new("freeze_start:", "freeze_end:");
+        let mut crop = None;
+        analysis::run_pass(
+            self,
+            input.as_ref(),
+            &options.scope,
+            AnalyzedMedia::Video,
+            &options.filter(),
+            |line| {
+                black.feed(line);
+                freezes.feed(line);
+                if let Some(rect) = analysis::token_after(line, " crop=")
+                    .and_then(|value| value.parse::<CropRect>().ok())
+                {
+                    crop = Some(rect);
+                }
+            },
+        )?;
+
+        let spans = |log: SpanLog| -> Vec<TimeSpan> {
+            log.finish()
+                .into_iter()
+                .map(|(start, end)| TimeSpan {
+                    start: options.scope.input_time(start),
+                    end: options.scope.input_time(end),
+                })
+                .collect()
+        };
+        Ok(QcReport {
+            black: if options.black.is_some() {
+                spans(black)
+            } else {
+                Vec::new()
+            },
+            freezes: if options.freeze.is_some() {
+                spans(freezes)
+            } else {
+                Vec::new()
+            },
+            crop: options.crop.and(crop),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::ExecutionOutput;
+
+    #[test]
+    fn test_crop_rect_parsing() {
+        let rect: CropRect = "1920:800:0:140".parse().unwrap();
+        assert_eq!(
+            rect,
+            CropRect {
+                width: 1920,
+                height: 800,
+                x: 0,
+                y: 140
+            }
+        );
+        assert_eq!(rect.filter(), "crop=1920:800:0:140");
+        assert!(!rect.is_full_frame(1920, 1080));
+        assert!("1920:800:0".parse::<CropRect>().is_err());
+        assert!("0:800:0:0".parse::<CropRect>().is_err());
+    }
+
+    #[test]
+    fn test_quality_check() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[blackdetect @ 0x55] black_start:0 black_end:2.5 black_duration:2.5\n\
+                 [Parsed_cropdetect_2 @ 0x57] x1:0 x2:1919 y1:142 y2:937 w:1920 h:784 x:0 y:148 pts:1 t:0.04 limit:0.09 crop=1920:784:0:148\n\
+                 [freezedetect @ 0x56] lavfi.freezedetect.freeze_start: 10\n\
+                 [freezedetect @ 0x56] lavfi.freezedetect.freeze_duration: 4\n\
+                 [freezedetect @ 0x56] lavfi.freezedetect.freeze_end: 14\n\
+                 [Parsed_cropdetect_2 @ 0x57] x1:0 x2:1919 y1:138 y2:941 w:1920 h:800 x:0 y:140 pts:2 t:20.0 limit:0.09 crop=1920:800:0:140\n\
+                 [freezedetect @ 0x56] lavfi.freezedetect.freeze_start: 28\n\
+                 out_time_us=30000000\n",
+            ),
+        );
+        let input = std::env::current_exe().unwrap();
+
+        let report = ffmpeg.quality_check(&input, &QcOptions::new()).unwrap();
+        let spans = |spans: &[TimeSpan]| -> Vec<(f64, f64)> {
+            spans
+                .iter()
+                .map(|span| (span.start.as_seconds(), span.end.as_seconds()))
+                .collect()
+        };
+        assert_eq!(spans(&report.black), vec![(0.0, 2.5)]);
+        assert_eq!(spans(&report.freezes), vec![(10.0, 14.0), (28.0, 30.0)]);
+        assert_eq!(report.crop, Some("1920:800:0:140".parse().unwrap()));
+        assert!(!report.is_clean(Some(1920), Some(1080)));
+
+        let call = &executor.ffmpeg_calls()[0];
+        assert_eq!(
+            call.option_value("-vf"),
+            Some(
+                "blackdetect=d=2:pic_th=0.98:pix_th=0.1,freezedetect=n=-60dB:d=2,\
+                 cropdetect=limit=24:round=2:reset=0"
+            )
+        );
+
+        let options = QcOptions::default();
+        assert!(options.validate().is_err());
+        let options = QcOptions::default().with_crop(Some(CropOptions::default()));
+        let report = ffmpeg.quality_check(&input, &options).unwrap();
+        assert!(report.black.is_empty());
+        assert_eq!(
+            executor.ffmpeg_calls()[1].option_value("-vf"),
+            Some("cropdetect=limit=24:round=2:reset=0")
+        );
+    }
+}
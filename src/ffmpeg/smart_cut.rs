@@ -0,0 +1,564 @@
+/// Keyframe-aware trimming ("smart cut").
+///
+/// A stream copy can only start on a keyframe, so copying a range that
+/// starts between keyframes either starts early or drops frames until the
+/// next keyframe, and re-encoding the whole range is slow. A smart cut
+/// re-encodes only the partial groups of pictures at the edges of the
+/// range and stream-copies the keyframe-aligned middle:
+///
+/// ```text
+///   keyframes:  K-------K-------K-------K-------K
+///   range:         [start                  end]
+///   segments:      [enc][     copy      ][enc]
+/// ```
+///
+/// The edges are encoded with the encoder of the source codec, at the size
+/// and pixel format of the source, into the same intermediate container as
+/// the copied middle. The segments are joined with the concat demuxer and
+/// muxed with the audio of the range, which is stream copied.
+///
+/// Only the first video stream is cut this way; other video and subtitle
+/// streams are not kept.
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use crate::ffmpeg::{
+    Error, ExecutionOutput, FFmpeg, KeyframeIndex, KeyframeScan, PacketInfo, Result,
+};
+use crate::utility::time::TimePosition;
+
+/// How a segment of a smart cut is produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CutSegmentKind {
+    /// The segment is decoded and encoded again.
+    Encode,
+    /// The packets of the segment are copied.
+    Copy,
+}
+
+/// A segment of a smart cut.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CutSegment {
+    /// How the segment is produced.
+    pub kind: CutSegmentKind,
+    /// Start of the segment in the input.
+    pub start: TimePosition,
+    /// End of the segment in the input.
+    pub end: TimePosition,
+}
+
+impl CutSegment {
+    /// Gets the duration of the segment in seconds.
+    #[must_use]
+    pub fn duration(&self) -> f64 {
+        self.end.as_seconds() - self.start.as_seconds()
+    }
+}
+
+/// The segments of a smart cut, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartCutPlan {
+    /// The segments covering the range.
+    pub segments: Vec<CutSegment>,
+}
+
+impl SmartCutPlan {
+    /// Plans the cut of a range.
+    ///
+    /// A cut point within `tolerance` of a keyframe counts as on the
+    /// keyframe. A range without a keyframe-aligned middle is encoded as a
+    /// whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The keyframes of the video stream
+    /// * `start` - Start of the range
+    /// * `end` - End of the range
+    /// * `tolerance` - The largest distance in seconds from a keyframe
+    #[must_use]
+    pub fn new(
+        index: &KeyframeIndex,
+        start: TimePosition,
+        end: TimePosition,
+        tolerance: f64,
+    ) -> Self {
+        let shift = |position: TimePosition, seconds: f64| {
+            TimePosition::from_seconds(position.as_seconds() + seconds)
+        };
+        // 許容誤差内のキーフレームはカット位置とみなす
+        let copy_start = index.keyframe_at_or_after(shift(start, -tolerance));
+        let copy_end = index
+            .keyframe_at_or_before(shift(end, tolerance))
+            .map(|keyframe| {
+                if (keyframe.as_seconds() - end.as_seconds()).abs() <= tolerance {
+                    end
+                } else {
+                    keyframe
+                }
+            });
+
+        let (copy_start, copy_end) = match (copy_start, copy_end) {
+            (Some(copy_start), Some(copy_end)) if copy_start < copy_end => {
+                (copy_start.max(start), copy_end)
+            }
+            _ => {
+                return Self {
+                    segments: vec![CutSegment {
+                        kind: CutSegmentKind::Encode,
+                        start,
+                        end,
+                    }],
+                };
+            }
+        };
+
+        let mut segments = Vec::with_capacity(3);
+        if copy_start > start {
+            segments.push(CutSegment {
+                kind: CutSegmentKind::Encode,
+                start,
+                end: copy_start,
+            });
+        }
+        segments.push(CutSegment {
+            kind: CutSegmentKind::Copy,
+            start: copy_start,
+            end: copy_end,
+        });
+        if copy_end < end {
+            segments.push(CutSegment {
+                kind: CutSegmentKind::Encode,
+                start: copy_end,
+                end,
+            });
+        }
+        Self { segments }
+    }
+
+    /// Gets the duration re-encoded by the cut in seconds.
+    #[must_use]
+    pub fn encoded_duration(&self) -> f64 {
+        self.duration_of(CutSegmentKind::Encode)
+    }
+
+    /// Gets the duration copied by the cut in seconds.
+    #[must_use]
+    pub fn copied_duration(&self) -> f64 {
+        self.duration_of(CutSegmentKind::Copy)
+    }
+
+    /// Sums the durations of the segments of a kind.
+    fn duration_of(&self, kind: CutSegmentKind) -> f64 {
+        self.segments
+            .iter()
+            .filter(|segment| segment.kind == kind)
+            .map(CutSegment::duration)
+            .sum()
+    }
+}
+
+/// How the edges of a cut are encoded for a source codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EdgeEncoder {
+    /// The `FFmpeg` encoder.
+    encoder: &'static str,
+    /// High quality options of the encoder.
+    options: &'static [&'static str],
+    /// Extension of the intermediate container.
+    container: &'static str,
+}
+
+impl EdgeEncoder {
+    /// Gets the edge encoder of a source codec.
+    ///
+    /// MPEG-TS carries the parameter sets in-band, so segments whose
+    /// encoder settings differ still concatenate; the other codecs use
+    /// Matroska.
+    fn for_codec(codec: &str) -> Option<Self> {
+        let (encoder, options, container): (_, &'static [&'static str], _) = match codec {
+            "h264" => ("libx264", &["-crf", "16", "-preset", "medium"], "ts"),
+            "hevc" => ("libx265", &["-crf", "18", "-preset", "medium"], "ts"),
+            "mpeg2video" => ("mpeg2video", &["-q:v", "2"], "ts"),
+            "mpeg4" => ("mpeg4", &["-q:v", "2"], "mkv"),
+            "vp9" => ("libvpx-vp9", &["-crf", "20", "-b:v", "0"], "mkv"),
+            "av1" => ("libsvtav1", &["-crf", "24"], "mkv"),
+            "prores" => ("prores_ks", &["-profile:v", "3"], "mkv"),
+            "dnxhd" => ("dnxhd", &["-profile:v", "dnxhr_hq"], "mkv"),
+            "ffv1" => ("ffv1", &[], "mkv"),
+            _ => return None,
+        };
+        Some(Self {
+            encoder,
+            options,
+            container,
+        })
+    }
+}
+
+impl FFmpeg {
+    /// Trims a video frame-accurately, re-encoding only the partial groups
+    /// of pictures at the cut points.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Path to the video
+    /// * `output` - Path of the trimmed video
+    /// * `start` - Start of the range to keep
+    /// * `end` - End of the range to keep
+    ///
+    /// # Returns
+    ///
+    /// The plan the cut followed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input has no video stream, if the codec of
+    /// the video cannot be encoded again, or if `FFmpeg` fails.
+    pub fn smart_trim<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input: P,
+        output: Q,
+        start: TimePosition,
+        end: TimePosition,
+    ) -> Result<SmartCutPlan> {
+        let (input, output) = (input.as_ref(), output.as_ref());
+        if start >= end {
+            return Err(Error::InvalidArgument(
+                "Start time must be less than end time".to_string(),
+            ));
+        }
+
+        let media_info = self.get_media_info(input)?;
+        let video = media_info
+            .video_streams()
+            .first()
+            .copied()
+            .cloned()
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!("{} has no video stream", input.display()))
+            })?;
+        let edge_encoder = EdgeEncoder::for_codec(&video.codec_name).ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "Smart cut cannot re-encode {} video",
+                video.codec_name
+            ))
+        })?;
+        let frame_rate = video
+            .frame_rate
+            .as_deref()
+            .and_then(parse_rate)
+            .filter(|rate| *rate > 0.0);
+
+        let start_time = media_info
+            .format
+            .start_time
+            .as_deref()
+            .and_then(|time| time.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        let probed = self.keyframe_index(input, 0, KeyframeScan::Packets)?;
+        let index = rebase_index(&probed, start_time);
+        // 半フレーム以内ならキーフレーム上とみなす
+        let tolerance = frame_rate.map_or(0.001, |rate| 0.5 / rate);
+        let plan = SmartCutPlan::new(&index, start, end, tolerance);
+
+        let workspace = TempDir::new()?;
+        let mut list = String::from("ffconcat version 1.0\n");
+        for (number, segment) in plan.segments.iter().enumerate() {
+            let path = workspace
+                .path()
+                .join(format!("segment_{number:03}.{}", edge_encoder.container));
+            let args = segment_args(input, &path, segment, &video, edge_encoder, frame_rate);
+            self.run_cut_step(&args)?;
+            let _ = writeln!(list, "file '{}'", escape_concat_path(&path));
+        }
+
+        let list_path = workspace.path().join("segments.ffconcat");
+        fs::write(&list_path, list)?;
+        self.run_cut_step(&join_args(&list_path, input, start, end, output))?;
+        Ok(plan)
+    }
+
+    /// Runs one `FFmpeg` step of a smart cut.
+    fn run_cut_step(&self, args: &[OsString]) -> Result<()> {
+        let output: ExecutionOutput = self
+            .executor()
+            .run(self.path(), args)
+            .map_err(|e| Error::ExecutionError(format!("Failed to execute FFmpeg: {e}")))?;
+        if output.success {
+            return Ok(());
+        }
+        Err(Error::from_failed_run(&output, || {
+            Error::ProcessTerminated {
+                exit_code: output.exit_code,
+                message: format!("Smart cut failed: {}", output.stderr.trim()),
+            }
+        }))
+    }
+}
+
+/// Moves the times of a keyframe index onto the timeline of input seeking.
+///
+/// `ffprobe` reports packet times including the start time of the file,
+/// while `-ss` on an input counts from the start of the file.
+fn rebase_index(index: &KeyframeIndex, start_time: f64) -> KeyframeIndex {
+    let rebase = |time: Option<f64>| time.map(|time| time - start_time);
+    let packets = index
+        .packets
+        .iter()
+        .map(|&packet| PacketInfo {
+            pts: rebase(packet.pts),
+            dts: rebase(packet.dts),
+            ..packet
+        })
+        .collect();
+    KeyframeIndex::new(index.stream, index.scan, packets)
+}
+
+/// Builds the `FFmpeg` arguments producing a segment of a smart cut.
+fn segment_args(
+    input: &Path,
+    path: &Path,
+    segment: &CutSegment,
+    video: &crate::ffmpeg::StreamInfo,
+    edge_encoder: EdgeEncoder,
+    frame_rate: Option<f64>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![
+        "-hide_banner".into(),
+        "-nostdin".into(),
+        "-ss".into(),
+        seconds(segment.start).into(),
+        "-i".into(),
+        input.into(),
+        "-map".into(),
+        "0:v:0".into(),
+        "-an".into(),
+        "-sn".into(),
+    ];
+
+    match segment.kind {
+        CutSegmentKind::Copy => {
+            args.extend(["-t".into(), format!("{:.6}", segment.duration()).into()]);
+            args.extend(["-c:v".into(), "copy".into()]);
+        }
+        CutSegmentKind::Encode => {
+            // フレーム数で区切ると継ぎ目でフレームが重複・欠落しない
+            match frame_rate {
+                Some(rate) => {
+                    let frames = (segment.duration() * rate).round().max(1.0) as u64;
+                    args.extend(["-frames:v".into(), frames.to_string().into()]);
+                }
+                None => {
+                    args.extend(["-t".into(), format!("{:.6}", segment.duration()).into()]);
+                }
+            }
+            args.extend(["-c:v".into(), edge_encoder.encoder.into()]);
+            args.extend(edge_encoder.options.iter().map(OsString::from));
+            if let Some(pixel_format) = &video.pixel_format {
+                args.extend(["-pix_fmt".into(), pixel_format.into()]);
+            }
+        }
+    }
+
+    args.extend([
+        "-avoid_negative_ts".into(),
+        "make_zero".into(),
+        "-y".into(),
+        path.into(),
+    ]);
+    args
+}
+
+/// Builds the `FFmpeg` arguments joining the segments and muxing the audio
+/// of the range.
+fn join_args(
+    list: &Path,
+    input: &Path,
+    start: TimePosition,
+    end: TimePosition,
+    output: &Path,
+) -> Vec<OsString> {
+    vec![
+        "-hide_banner".into(),
+        "-nostdin".into(),
+        "-f".into(),
+        "concat".into(),
+        "-safe".into(),
+        "0".into(),
+        "-i".into(),
+        list.into(),
+        "-ss".into(),
+        seconds(start).into(),
+        "-t".into(),
+        format!("{:.6}", end.as_seconds() - start.as_seconds()).into(),
+        "-i".into(),
+        input.into(),
+        "-map".into(),
+        "0:v:0".into(),
+        "-map".into(),
+        "1:a?".into(),
+        "-c".into(),
+        "copy".into(),
+        "-avoid_negative_ts".into(),
+        "make_zero".into(),
+        "-y".into(),
+        output.into(),
+    ]
+}
+
+/// Formats a time for `FFmpeg` in seconds.
+fn seconds(position: TimePosition) -> String {
+    format!("{:.6}", position.as_seconds())
+}
+
+/// Escapes a path for a `file` line of a concat list.
+fn escape_concat_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\'', r"'\''")
+}
+
+/// Parses a frame rate such as `30000/1001`.
+fn parse_rate(rate: &str) -> Option<f64> {
+    match rate.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.parse().ok()?;
+            (denominator != 0.0).then_some(numerator.parse::<f64>().ok()? / denominator)
+        }
+        None => rate.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::PacketInfo;
+
+    /// Builds an index with keyframes at the given times.
+    fn index(keyframes: &[f64]) -> KeyframeIndex {
+        let packets = keyframes
+            .iter()
+            .map(|time| PacketInfo {
+                pts: Some(*time),
+                dts: Some(*time),
+                duration: None,
+                size: None,
+                pos: None,
+                keyframe: true,
+            })
+            .collect();
+        KeyframeIndex::new(0, KeyframeScan::Packets, packets)
+    }
+
+    fn at(seconds: f64) -> TimePosition {
+        TimePosition::from_seconds(seconds)
+    }
+
+    #[test]
+    fn test_plan_encodes_only_the_edges() {
+        let index = index(&[0.0, 2.0, 4.0, 6.0, 8.0]);
+
+        let plan = SmartCutPlan::new(&index, at(1.0), at(7.0), 0.02);
+        let kinds: Vec<_> = plan.segments.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CutSegmentKind::Encode,
+                CutSegmentKind::Copy,
+                CutSegmentKind::Encode
+            ]
+        );
+        assert_eq!(plan.segments[1].start, at(2.0));
+        assert_eq!(plan.segments[1].end, at(6.0));
+        assert!((plan.encoded_duration() - 2.0).abs() < 1e-9);
+        assert!((plan.copied_duration() - 4.0).abs() < 1e-9);
+
+        // キーフレーム上のカットはコピーだけで済む
+        let plan = SmartCutPlan::new(&index, at(2.01), at(6.0), 0.02);
+        assert_eq!(plan.segments.len(), 1);
+        assert_eq!(plan.segments[0].kind, CutSegmentKind::Copy);
+        assert_eq!(plan.segments[0].start, at(2.01));
+
+        // 範囲内にコピーできる区間がなければ全体をエンコードする
+        let plan = SmartCutPlan::new(&index, at(2.5), at(3.5), 0.02);
+        assert_eq!(plan.segments.len(), 1);
+        assert_eq!(plan.segments[0].kind, CutSegmentKind::Encode);
+    }
+
+    #[test]
+    fn test_smart_trim_runs_segments_and_join() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.mp4");
+        fs::write(&input, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "in.mp4", "duration": "10.0"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                             "pix_fmt": "yuv420p", "r_frame_rate": "25/1"}]}"#,
+        );
+        executor.respond(
+            "packet=",
+            ExecutionOutput::success(
+                r#"{"packets": [{"pts_time": "0.0", "flags": "K_"},
+                                {"pts_time": "2.0", "flags": "K_"},
+                                {"pts_time": "4.0", "flags": "K_"}]}"#,
+            ),
+        );
+
+        let plan = ffmpeg
+            .smart_trim(&input, "out.mp4", at(1.0), at(4.0))
+            .unwrap();
+        assert_eq!(plan.segments.len(), 2);
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].option_value("-ss"), Some("1.000000"));
+        assert_eq!(calls[0].option_value("-frames:v"), Some("25"));
+        assert_eq!(calls[0].option_value("-c:v"), Some("libx264"));
+        assert_eq!(calls[0].option_value("-pix_fmt"), Some("yuv420p"));
+        assert_eq!(calls[1].option_value("-ss"), Some("2.000000"));
+        assert_eq!(calls[1].option_value("-c:v"), Some("copy"));
+        assert!(calls[1].last_arg().unwrap().ends_with(".ts"));
+        assert_eq!(calls[2].option_value("-f"), Some("concat"));
+        assert_eq!(calls[2].option_value("-t"), Some("3.000000"));
+        assert_eq!(calls[2].option_values("-map"), vec!["0:v:0", "1:a?"]);
+        assert_eq!(calls[2].last_arg(), Some("out.mp4"));
+    }
+
+    #[test]
+    fn test_smart_trim_rebases_keyframes_on_start_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.ts");
+        fs::write(&input, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(
+            r#"{"format": {"filename": "in.ts", "start_time": "1.500000", "duration": "10.0"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                             "pix_fmt": "yuv420p", "r_frame_rate": "25/1"}]}"#,
+        );
+        executor.respond(
+            "packet=",
+            ExecutionOutput::success(
+                r#"{"packets": [{"pts_time": "1.5", "flags": "K_"},
+                                {"pts_time": "3.5", "flags": "K_"},
+                                {"pts_time": "5.5", "flags": "K_"}]}"#,
+            ),
+        );
+
+        // ファイル先頭から 2 秒のキーフレームでコピーが始まる
+        let plan = ffmpeg
+            .smart_trim(&input, "out.mp4", at(1.0), at(4.0))
+            .unwrap();
+        assert_eq!(plan.segments.len(), 2);
+        assert_eq!(plan.segments[1].kind, CutSegmentKind::Copy);
+        assert_eq!(plan.segments[1].start, at(2.0));
+        assert_eq!(plan.segments[1].end, at(4.0));
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls[1].option_value("-ss"), Some("2.000000"));
+    }
+}
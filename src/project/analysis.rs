@@ -0,0 +1,514 @@
+/// Media analysis of the clips on a timeline.
+///
+/// Detection passes run over the part of the source asset a clip uses, and
+/// the detected source times are mapped onto the timeline, so that clips can
+/// be split, marked or cut where something was detected.
+use crate::audio::silence::{self, SilenceInterval, SilenceOptions};
+use crate::ffmpeg::{AnalysisScope, CropRect, FFmpeg, QcOptions, QcReport, SceneOptions};
+use crate::project::image_sequence::ImageSequence;
+use crate::project::timeline::{Clip, Marker, TimelineError};
+use crate::project::{AssetReference, ClipId, Project, ProjectError, Result, TrackId};
+use crate::utility::time::{Duration, TimePosition};
+
+/// A scene change inside a clip of a track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackSceneCut {
+    /// The clip the change was detected in.
+    pub clip: ClipId,
+    /// Time of the change in the clip's source asset.
+    pub source_time: TimePosition,
+    /// Position of the change on the timeline.
+    pub position: TimePosition,
+    /// How different the frame is from the previous one, from 0.0 to 1.0.
+    pub score: f64,
+}
+
+/// Builds the scope of a pass over the part of an asset a clip uses.
+fn clip_scope(asset: &AssetReference, start: TimePosition, end: TimePosition) -> AnalysisScope {
+    let input_options = ImageSequence::from_asset(asset)
+        .map(|sequence| sequence.input_options())
+        .unwrap_or_default();
+    AnalysisScope::new()
+        .with_input_options(input_options)
+        .with_range(start, (end - start).as_seconds())
+}
+
+impl Project {
+    /// Finds a clip and the asset it uses.
+    fn clip_and_asset(
+        &self,
+        track_id: TrackId,
+        clip_id: ClipId,
+    ) -> Result<(&Clip, &AssetReference)> {
+        let clip = self
+            .timeline
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?
+            .get_clip(clip_id)
+            .ok_or(TimelineError::ClipNotFound {
+                track: track_id,
+                clip: clip_id,
+            })?;
+        let asset = self
+            .get_asset(clip.asset_id())
+            .ok_or(ProjectError::AssetNotFound(clip.asset_id()))?;
+        Ok((clip, asset))
+    }
+
+    /// Detects the scene changes of the clips on a track.
+    ///
+    /// The scope of `options` is replaced by the source range of each clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` instance to run
+    /// * `track_id` - The track to analyze
+    /// * `options` - Threshold and detector of the detection
+    ///
+    /// # Returns
+    ///
+    /// The scene changes in timeline order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or an asset is not found, or a
+    /// detection pass fails.
+    pub fn detect_scenes(
+        &self,
+        ffmpeg: &FFmpeg,
+        track_id: TrackId,
+        options: &SceneOptions,
+    ) -> Result<Vec<TrackSceneCut>> {
+        let track = self
+            .timeline
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?;
+
+        let mut cuts = Vec::new();
+        for clip in track.get_clips() {
+            let asset = self
+                .get_asset(clip.asset_id())
+                .ok_or(ProjectError::AssetNotFound(clip.asset_id()))?;
+            let options = options.clone().with_scope(clip_scope(
+                asset,
+                clip.source_start(),
+                clip.source_end(),
+            ));
+            let detected = ffmpeg
+                .detect_scenes(&asset.path, &options)
+                .map_err(|e| ProjectError::Analysis(e.to_string()))?;
+
+            cuts.extend(detected.into_iter().filter_map(|cut| {
+                Some(TrackSceneCut {
+                    clip: clip.id(),
+                    source_time: cut.time,
+                    position: clip.timeline_position(cut.time)?,
+                    score: cut.score,
+                })
+            }));
+        }
+        cuts.sort_by_key(|cut| cut.position);
+        Ok(cuts)
+    }
+
+    /// Splits the clips of a track at scene changes.
+    ///
+    /// All splits are recorded as one transaction, so a single undo restores
+    /// the clips. If a split fails, the timeline is left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The track containing the clips
+    /// * `cuts` - Scene changes returned by [`Project::detect_scenes`]
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the clips created by the splits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or a clip is not found, or a
+    /// transaction is already in progress.
+    pub fn split_at_scenes(
+        &mut self,
+        track_id: TrackId,
+        cuts: &[TrackSceneCut],
+    ) -> Result<Vec<ClipId>> {
+        // カットの順序を保ったままクリップごとにまとめる
+        let mut by_clip: Vec<(ClipId, Vec<TimePosition>)> = Vec::new();
+        for cut in cuts {
+            match by_clip.iter_mut().find(|(clip, _)| *clip == cut.clip) {
+                Some((_, positions)) => positions.push(cut.position),
+                None => by_clip.push((cut.clip, vec![cut.position])),
+            }
+        }
+
+        self.timeline
+            .begin_transaction(Some("Split at scenes".to_string()))
+            .map_err(|e| TimelineError::InvalidOperation(e.to_string()))?;
+        // 途中で失敗した場合は変更前の状態に戻す
+        let snapshot = self.timeline.clone();
+        let result = (|| {
+            let mut created = Vec::new();
+            for (clip, positions) in &by_clip {
+                created.extend(
+                    self.timeline
+                        .split_clip_at_with_history(track_id, *clip, positions)?,
+                );
+            }
+            Ok::<_, TimelineError>(created)
+        })();
+
+        match result {
+            Ok(created) => {
+                self.timeline
+                    .commit_transaction()
+                    .map_err(|e| TimelineError::InvalidOperation(e.to_string()))?;
+                self.project_metadata.update_modified();
+                Ok(created)
+            }
+            Err(e) => {
+                self.timeline = snapshot;
+                let _ = self.timeline.rollback_transaction();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Adds a timeline marker at each scene change.
+    ///
+    /// # Arguments
+    ///
+    /// * `cuts` - Scene changes returned by [`Project::detect_scenes`]
+    pub fn mark_scenes(&mut self, cuts: &[TrackSceneCut]) {
+        for cut in cuts {
+            self.timeline.add_marker(Marker::new(
+                cut.position,
+                format!("Scene (score {:.2})", cut.score),
+            ));
+        }
+        self.project_metadata.update_modified();
+    }
+
+    /// Detects the silences in the part of the source a clip uses.
+    ///
+    /// The scope of `options` is replaced by the source range of the clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` instance to run
+    /// * `track_id` - The track containing the clip
+    /// * `clip_id` - The clip to analyze
+    /// * `options` - Threshold and shortest silence of the detection
+    ///
+    /// # Returns
+    ///
+    /// The silences in source time, in time order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track, clip or asset is not found, or the
+    /// detection pass fails.
+    pub fn detect_clip_silence(
+        &self,
+        ffmpeg: &FFmpeg,
+        track_id: TrackId,
+        clip_id: ClipId,
+        options: &SilenceOptions,
+    ) -> Result<Vec<SilenceInterval>> {
+        let (clip, asset) = self.clip_and_asset(track_id, clip_id)?;
+        let options =
+            options
+                .clone()
+                .with_scope(clip_scope(asset, clip.source_start(), clip.source_end()));
+        silence::detect_silence(ffmpeg, &asset.path, &options)
+            .map_err(|e| ProjectError::Analysis(e.to_string()))
+    }
+
+    /// Cuts silences out of a clip and closes the gaps.
+    ///
+    /// The cut is recorded as one transaction, so a single undo restores
+    /// the clip and the positions of the later clips.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The track containing the clip
+    /// * `clip_id` - The clip to cut
+    /// * `silences` - Silences returned by [`Project::detect_clip_silence`]
+    /// * `padding` - Length of silence kept next to the speech
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the kept parts of the clip in timeline order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or clip is not found, or a transaction
+    /// is already in progress.
+    pub fn remove_silences(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        silences: &[SilenceInterval],
+        padding: Duration,
+    ) -> Result<Vec<ClipId>> {
+        let ranges: Vec<(TimePosition, TimePosition)> = silences
+            .iter()
+            .map(|silence| (silence.start, silence.end))
+            .collect();
+        let parts = self
+            .timeline
+            .remove_source_ranges_with_history(track_id, clip_id, &ranges, padding)?;
+        self.project_metadata.update_modified();
+        Ok(parts)
+    }
+
+    /// Runs a quality check on the part of the source a clip uses.
+    ///
+    /// The scope of `options` is replaced by the source range of the clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` instance to run
+    /// * `track_id` - The track containing the clip
+    /// * `clip_id` - The clip to check
+    /// * `options` - Detectors of the check
+    ///
+    /// # Returns
+    ///
+    /// The findings, with times in source time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track, clip or asset is not found, or the
+    /// check fails.
+    pub fn quality_check_clip(
+        &self,
+        ffmpeg: &FFmpeg,
+        track_id: TrackId,
+        clip_id: ClipId,
+        options: &QcOptions,
+    ) -> Result<QcReport> {
+        let (clip, asset) = self.clip_and_asset(track_id, clip_id)?;
+        let options =
+            options
+                .clone()
+                .with_scope(clip_scope(asset, clip.source_start(), clip.source_end()));
+        ffmpeg
+            .quality_check(&asset.path, &options)
+            .map_err(|e| ProjectError::Analysis(e.to_string()))
+    }
+
+    /// Crops a clip to a detected area without borders.
+    ///
+    /// Nothing changes if the area keeps whole frames of the asset. The crop
+    /// is recorded in the edit history, so it can be undone.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The track containing the clip
+    /// * `clip_id` - The clip to crop
+    /// * `crop` - The area found by [`Project::quality_check_clip`]
+    ///
+    /// # Returns
+    ///
+    /// `true` if the crop was applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track, clip or asset is not found, or a
+    /// transaction is already in progress.
+    pub fn apply_detected_crop(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        crop: CropRect,
+    ) -> Result<bool> {
+        let (_, asset) = self.clip_and_asset(track_id, clip_id)?;
+        if let Some((width, height)) = asset.metadata.dimensions
+            && crop.is_full_frame(width, height)
+        {
+            return Ok(false);
+        }
+
+        self.timeline
+            .set_clip_crop_with_history(track_id, clip_id, Some(crop))?;
+        self.project_metadata.update_modified();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::ffmpeg::ExecutionOutput;
+    use crate::project::{AssetMetadata, TrackKind};
+
+    /// Creates a project with a 10 second clip of the test executable.
+    fn project_with_clip(
+        kind: TrackKind,
+        position: f64,
+        source_start: f64,
+    ) -> (Project, TrackId, ClipId) {
+        let mut project = Project::new("analysis");
+        let asset = project.add_asset(
+            std::env::current_exe().unwrap(),
+            AssetMetadata {
+                duration: Some(Duration::from_seconds(60.0)),
+                dimensions: Some((1920, 1080)),
+                asset_type: "video".to_string(),
+                extra: HashMap::new(),
+            },
+        );
+        let track = project.timeline.add_track(kind);
+        let clip = ClipId::new();
+        project
+            .timeline
+            .add_clip(
+                track,
+                Clip::new(
+                    clip,
+                    asset,
+                    TimePosition::from_seconds(position),
+                    Duration::from_seconds(10.0),
+                    TimePosition::from_seconds(source_start),
+                    TimePosition::from_seconds(source_start + 10.0),
+                ),
+            )
+            .unwrap();
+        (project, track, clip)
+    }
+
+    #[test]
+    fn test_detect_and_split_scenes() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[Parsed_metadata_1 @ 0x55] frame:0 pts:48 pts_time:2.0\n\
+                 [Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.7\n",
+            ),
+        );
+        let (mut project, track, clip) = project_with_clip(TrackKind::Video, 5.0, 10.0);
+
+        let cuts = project
+            .detect_scenes(&ffmpeg, track, &SceneOptions::default())
+            .unwrap();
+        assert_eq!(cuts.len(), 1);
+        assert_eq!(cuts[0].clip, clip);
+        assert_eq!(cuts[0].source_time.as_seconds(), 12.0);
+        assert_eq!(cuts[0].position.as_seconds(), 7.0);
+
+        let call = &executor.ffmpeg_calls()[0];
+        assert_eq!(call.option_value("-ss"), Some("10"));
+        assert_eq!(call.option_value("-t"), Some("10"));
+
+        project.mark_scenes(&cuts);
+        assert_eq!(project.timeline.markers().len(), 1);
+        assert_eq!(project.timeline.markers()[0].label, "Scene (score 0.70)");
+
+        let created = project.split_at_scenes(track, &cuts).unwrap();
+        assert_eq!(created.len(), 1);
+        let clips = project.timeline.get_track(track).unwrap().get_clips();
+        assert_eq!(clips.len(), 2);
+
+        project.timeline.undo().unwrap();
+        let clips = project.timeline.get_track(track).unwrap().get_clips();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].duration().as_seconds(), 10.0);
+    }
+
+    #[test]
+    fn test_split_at_scenes_rolls_back_on_error() {
+        let (mut project, track, clip) = project_with_clip(TrackKind::Video, 0.0, 0.0);
+        let cut = |clip, position| TrackSceneCut {
+            clip,
+            source_time: TimePosition::from_seconds(position),
+            position: TimePosition::from_seconds(position),
+            score: 0.5,
+        };
+        let cuts = [cut(clip, 4.0), cut(ClipId::new(), 6.0)];
+
+        assert!(project.split_at_scenes(track, &cuts).is_err());
+        let clips = project.timeline.get_track(track).unwrap().get_clips();
+        assert_eq!(clips.len(), 1);
+        assert!(!project.timeline.can_undo());
+        assert!(project.split_at_scenes(track, &cuts[..1]).is_ok());
+    }
+
+    #[test]
+    fn test_detect_and_remove_silences() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "silencedetect",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[silencedetect @ 0x55] silence_start: 3\n\
+                 [silencedetect @ 0x55] silence_end: 5 | silence_duration: 2\n",
+            ),
+        );
+        let (mut project, track, clip) = project_with_clip(TrackKind::Audio, 0.0, 30.0);
+
+        let silences = project
+            .detect_clip_silence(&ffmpeg, track, clip, &SilenceOptions::default())
+            .unwrap();
+        assert_eq!(silences.len(), 1);
+        assert_eq!(silences[0].start.as_seconds(), 33.0);
+        assert_eq!(executor.ffmpeg_calls()[0].option_value("-ss"), Some("30"));
+
+        let parts = project
+            .remove_silences(track, clip, &silences, Duration::from_seconds(0.25))
+            .unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(project.timeline.duration(), Duration::from_seconds(8.5));
+
+        project.timeline.undo().unwrap();
+        assert_eq!(
+            project.timeline.get_track(track).unwrap().get_clips().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_quality_check_and_crop_clip() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "null",
+            ExecutionOutput::success(Vec::new()).with_stderr(
+                "[blackdetect @ 0x55] black_start:0 black_end:2 black_duration:2\n\
+                 [Parsed_cropdetect_2 @ 0x57] x:0 y:140 pts:2 t:1.0 crop=1920:800:0:140\n",
+            ),
+        );
+        let (mut project, track, clip) = project_with_clip(TrackKind::Video, 0.0, 20.0);
+
+        let report = project
+            .quality_check_clip(&ffmpeg, track, clip, &QcOptions::new())
+            .unwrap();
+        assert_eq!(report.black[0].end.as_seconds(), 22.0);
+        let crop = report.crop.unwrap();
+
+        let full = CropRect {
+            width: 1920,
+            height: 1080,
+            x: 0,
+            y: 0,
+        };
+        assert!(!project.apply_detected_crop(track, clip, full).unwrap());
+        assert!(project.apply_detected_crop(track, clip, crop).unwrap());
+
+        let crop_of = |project: &Project| {
+            project
+                .timeline
+                .get_track(track)
+                .unwrap()
+                .get_clip(clip)
+                .unwrap()
+                .crop()
+        };
+        assert_eq!(crop_of(&project), Some(crop));
+
+        project.timeline.undo().unwrap();
+        assert_eq!(crop_of(&project), None);
+    }
+}
@@ -0,0 +1,323 @@
+/// Numbered image sequences.
+///
+/// An image sequence is a directory of numbered frames such as
+/// `shot_00001.png`, `shot_00002.png`, ..., addressed by a printf-style
+/// pattern (`shot_%05d.png`) as understood by `FFmpeg`'s `image2` format.
+/// A sequence is imported into a project as a single asset whose path is the
+/// pattern; the frame rate, first frame number and frame count are kept in
+/// the asset's extra metadata.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::project::{AssetMetadata, AssetReference, ProjectError, Result};
+use crate::utility::time::Duration;
+
+/// Asset type of image sequence assets.
+pub const IMAGE_SEQUENCE_ASSET_TYPE: &str = "image_sequence";
+
+/// Extra metadata key of the frame rate.
+const FRAME_RATE_KEY: &str = "frame_rate";
+/// Extra metadata key of the first frame number.
+const START_NUMBER_KEY: &str = "start_number";
+/// Extra metadata key of the number of frames.
+const FRAME_COUNT_KEY: &str = "frame_count";
+
+/// A printf-style frame number pattern split around its placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramePattern {
+    /// Directory of the frames.
+    directory: PathBuf,
+    /// File name before the frame number.
+    prefix: String,
+    /// Zero-padded width of the frame number (0 for no padding).
+    width: usize,
+    /// File name after the frame number.
+    suffix: String,
+}
+
+impl FramePattern {
+    /// Parses a pattern such as `plates/shot_%05d.png` or `frame_%d.tif`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Path whose file name contains one `%d` or `%0Nd`
+    ///
+    /// # Returns
+    ///
+    /// The parsed pattern, or `None` if the file name has no placeholder.
+    #[must_use]
+    pub fn parse(pattern: &Path) -> Option<Self> {
+        let file_name = pattern.file_name()?.to_str()?;
+        let start = file_name.find('%')?;
+        let rest = &file_name[start + 1..];
+        let end = rest.find('d')?;
+        let width_spec = &rest[..end];
+
+        let width = if width_spec.is_empty() {
+            0
+        } else if width_spec.starts_with('0') && width_spec.chars().all(|c| c.is_ascii_digit()) {
+            width_spec.parse().ok()?
+        } else {
+            return None;
+        };
+
+        let suffix = &rest[end + 1..];
+        // 2つ目のプレースホルダーはサポートしない
+        if suffix.contains('%') {
+            return None;
+        }
+
+        Some(Self {
+            directory: pattern.parent().map(Path::to_path_buf).unwrap_or_default(),
+            prefix: file_name[..start].to_string(),
+            width,
+            suffix: suffix.to_string(),
+        })
+    }
+
+    /// Gets the path of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The frame number
+    #[must_use]
+    pub fn frame_path(&self, number: u32) -> PathBuf {
+        self.directory.join(format!(
+            "{}{number:0width$}{}",
+            self.prefix,
+            self.suffix,
+            width = self.width
+        ))
+    }
+
+    /// Gets the frame number of a file name matching the pattern.
+    fn frame_number(&self, file_name: &str) -> Option<u32> {
+        let digits = file_name
+            .strip_prefix(&self.prefix)?
+            .strip_suffix(&self.suffix)?;
+        if digits.is_empty()
+            || !digits.chars().all(|c| c.is_ascii_digit())
+            || (self.width > 0 && digits.len() < self.width)
+        {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+/// A numbered image sequence on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSequence {
+    /// Path pattern of the frames (e.g. `plates/shot_%05d.png`).
+    pub pattern: PathBuf,
+    /// Frame rate the sequence is played at.
+    pub frame_rate: f64,
+    /// Number of the first frame.
+    pub start_number: u32,
+    /// Number of frames in the sequence.
+    pub frame_count: u32,
+}
+
+impl ImageSequence {
+    /// Scans the frames of a sequence on disk.
+    ///
+    /// The sequence starts at the lowest frame number found and must be
+    /// contiguous, because `FFmpeg` stops reading at the first missing frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Path pattern of the frames (e.g. `plates/shot_%05d.png`)
+    /// * `frame_rate` - Frame rate the sequence is played at
+    ///
+    /// # Returns
+    ///
+    /// The scanned sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid, the directory cannot be
+    /// read, no frames are found, or a frame is missing.
+    pub fn scan(pattern: &Path, frame_rate: f64) -> Result<Self> {
+        if frame_rate <= 0.0 {
+            return Err(ProjectError::InvalidAsset(
+                "Image sequence frame rate must be positive".to_string(),
+            ));
+        }
+        let parsed = FramePattern::parse(pattern).ok_or_else(|| {
+            ProjectError::InvalidAsset(format!(
+                "Not an image sequence pattern (expected %d or %0Nd): {}",
+                pattern.display()
+            ))
+        })?;
+
+        let directory = if parsed.directory.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parsed.directory.as_path()
+        };
+        let mut numbers: Vec<u32> = fs::read_dir(directory)?
+            .flatten()
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| parsed.frame_number(name))
+            })
+            .collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        let (Some(&first), Some(&last)) = (numbers.first(), numbers.last()) else {
+            return Err(ProjectError::InvalidAsset(format!(
+                "No frames found for {}",
+                pattern.display()
+            )));
+        };
+
+        if let Some(missing) = numbers
+            .windows(2)
+            .find(|pair| pair[1] != pair[0] + 1)
+            .map(|pair| pair[0] + 1)
+        {
+            return Err(ProjectError::InvalidAsset(format!(
+                "Image sequence {} is missing frame {}",
+                pattern.display(),
+                parsed.frame_path(missing).display()
+            )));
+        }
+
+        Ok(Self {
+            pattern: pattern.to_path_buf(),
+            frame_rate,
+            start_number: first,
+            frame_count: last - first + 1,
+        })
+    }
+
+    /// Gets the sequence stored in an asset.
+    ///
+    /// # Arguments
+    ///
+    /// * `asset` - The asset to read
+    ///
+    /// # Returns
+    ///
+    /// The sequence, or `None` if the asset is not an image sequence.
+    #[must_use]
+    pub fn from_asset(asset: &AssetReference) -> Option<Self> {
+        if asset.metadata.asset_type != IMAGE_SEQUENCE_ASSET_TYPE {
+            return None;
+        }
+        let extra = &asset.metadata.extra;
+        Some(Self {
+            pattern: asset.path.clone(),
+            frame_rate: extra.get(FRAME_RATE_KEY)?.parse().ok()?,
+            start_number: extra.get(START_NUMBER_KEY)?.parse().ok()?,
+            frame_count: extra.get(FRAME_COUNT_KEY)?.parse().ok()?,
+        })
+    }
+
+    /// Creates the asset metadata of the sequence.
+    ///
+    /// The dimensions are read from the first frame when its format is
+    /// supported by the `image` crate.
+    #[must_use]
+    pub fn to_metadata(&self) -> AssetMetadata {
+        let mut extra = HashMap::new();
+        extra.insert(FRAME_RATE_KEY.to_string(), self.frame_rate.to_string());
+        extra.insert(START_NUMBER_KEY.to_string(), self.start_number.to_string());
+        extra.insert(FRAME_COUNT_KEY.to_string(), self.frame_count.to_string());
+
+        AssetMetadata {
+            duration: Some(self.duration()),
+            dimensions: self
+                .frame_paths()
+                .next()
+                .and_then(|first| image::image_dimensions(first).ok()),
+            asset_type: IMAGE_SEQUENCE_ASSET_TYPE.to_string(),
+            extra,
+        }
+    }
+
+    /// Gets the duration of the sequence at its frame rate.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_seconds(f64::from(self.frame_count) / self.frame_rate)
+    }
+
+    /// Gets the paths of all frames in order.
+    pub fn frame_paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        let pattern = FramePattern::parse(&self.pattern);
+        (0..self.frame_count).filter_map(move |offset| {
+            pattern
+                .as_ref()
+                .map(|pattern| pattern.frame_path(self.start_number + offset))
+        })
+    }
+
+    /// Gets the `FFmpeg` input options for reading the sequence.
+    ///
+    /// # Returns
+    ///
+    /// A flat list of arguments to place before `-i <pattern>`.
+    #[must_use]
+    pub fn input_options(&self) -> Vec<String> {
+        vec![
+            "-f".to_string(),
+            "image2".to_string(),
+            "-framerate".to_string(),
+            self.frame_rate.to_string(),
+            "-start_number".to_string(),
+            self.start_number.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern = FramePattern::parse(Path::new("plates/shot_%05d.png")).unwrap();
+        assert_eq!(
+            pattern.frame_path(42),
+            PathBuf::from("plates/shot_00042.png")
+        );
+        assert_eq!(pattern.frame_number("shot_00042.png"), Some(42));
+        assert_eq!(pattern.frame_number("shot_042.png"), None);
+        assert_eq!(pattern.frame_number("shot_00042.tif"), None);
+
+        let unpadded = FramePattern::parse(Path::new("frame_%d.tif")).unwrap();
+        assert_eq!(unpadded.frame_path(7), PathBuf::from("frame_7.tif"));
+
+        assert!(FramePattern::parse(Path::new("shot.png")).is_none());
+        assert!(FramePattern::parse(Path::new("shot_%5d.png")).is_none());
+    }
+
+    #[test]
+    fn test_scan_sequence() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for number in 1001..=1010 {
+            fs::write(dir.path().join(format!("shot_{number:05}.png")), b"").unwrap();
+        }
+        fs::write(dir.path().join("shot_notes.txt"), b"").unwrap();
+
+        let pattern = dir.path().join("shot_%05d.png");
+        let sequence = ImageSequence::scan(&pattern, 24.0).unwrap();
+        assert_eq!(sequence.start_number, 1001);
+        assert_eq!(sequence.frame_count, 10);
+        assert!((sequence.duration().as_seconds() - 10.0 / 24.0).abs() < 1e-9);
+
+        let asset = AssetReference {
+            id: crate::project::AssetId::new(),
+            path: pattern.clone(),
+            metadata: sequence.to_metadata(),
+        };
+        assert_eq!(ImageSequence::from_asset(&asset), Some(sequence));
+
+        fs::remove_file(dir.path().join("shot_01005.png")).unwrap();
+        assert!(ImageSequence::scan(&pattern, 24.0).is_err());
+    }
+}
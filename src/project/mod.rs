@@ -3,8 +3,10 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use uuid::Uuid;
 
-use crate::utility::time::Duration;
+use crate::utility::time::{Duration, TimePosition};
 
+pub mod analysis;
+pub mod image_sequence;
 pub mod rendering;
 pub mod serialization;
 pub mod timeline;
@@ -130,9 +132,17 @@ pub enum ProjectError {
     #[error("Asset not found: {0}")]
     AssetNotFound(AssetId),
 
+    /// Asset is invalid or cannot be imported.
+    #[error("Invalid asset: {0}")]
+    InvalidAsset(String),
+
     /// Rendering error.
     #[error("Rendering error: {0}")]
     Rendering(#[from] rendering::RenderError),
+
+    /// Media analysis failed.
+    #[error("Analysis error: {0}")]
+    Analysis(String),
 }
 
 /// Type alias for project operation results.
@@ -275,6 +285,26 @@ impl Project {
         id
     }
 
+    /// Adds a numbered image sequence to the project as a single asset.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Path pattern of the frames (e.g. `plates/shot_%05d.png`)
+    /// * `frame_rate` - Frame rate the sequence is played at
+    ///
+    /// # Returns
+    ///
+    /// The ID of the newly added asset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or the frames cannot be
+    /// found or are not contiguous.
+    pub fn add_image_sequence(&mut self, pattern: PathBuf, frame_rate: f64) -> Result<AssetId> {
+        let sequence = image_sequence::ImageSequence::scan(&pattern, frame_rate)?;
+        Ok(self.add_asset(pattern, sequence.to_metadata()))
+    }
+
     /// Gets an asset by its ID.
     ///
     /// # Arguments
@@ -399,6 +429,36 @@ impl Project {
         Ok(result)
     }
 
+    /// Renders a single frame of the project.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Position on the timeline to render
+    /// * `size` - Width and height of the frame (in pixels)
+    ///
+    /// # Returns
+    ///
+    /// The composited frame; areas without video are transparent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size is zero or composing the frame failed.
+    pub fn render_frame(&self, time: TimePosition, size: (u32, u32)) -> Result<image::RgbaImage> {
+        let (width, height) = size;
+        if width == 0 || height == 0 {
+            return Err(ProjectError::Rendering(
+                rendering::RenderError::ConfigError("Frame dimensions cannot be zero".to_string()),
+            ));
+        }
+
+        let mut compositor =
+            rendering::TrackCompositor::new(self.timeline.clone(), self.assets.clone());
+        let frame = compositor
+            .render_frame(time, width, height)
+            .map_err(rendering::RenderError::from)?;
+        Ok(frame)
+    }
+
     /// Loads and prepares assets for faster rendering.
     ///
     /// This pre-renders and caches assets for quicker access during editing and rendering.
@@ -429,8 +489,7 @@ impl Project {
 
         // キャッシュを初期化
         // システムの一時ディレクトリを使用
-        let cache_dir = std::env::temp_dir().join("edv_cache");
-        if let Err(e) = pipeline.init_cache(cache_dir, None) {
+        if let Err(e) = pipeline.init_cache(rendering::RenderCache::default_dir(), None) {
             return Err(ProjectError::Rendering(e));
         }
 
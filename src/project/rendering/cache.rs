@@ -2,37 +2,203 @@
 ///
 /// This module implements a caching system for rendered assets and intermediate files,
 /// improving performance by avoiding redundant rendering operations.
+///
+/// The cache keeps an index of its entries in `cache_index.json` inside the
+/// cache directory, so cached files survive process restarts. Entries are
+/// evicted in least-recently-used order when the cache grows beyond its
+/// maximum size, and optionally once they have not been used for longer than
+/// a maximum age. When the index is loaded, entries whose files are missing
+/// or have an unexpected size are discarded.
+///
+/// Rendered assets remember the identity of the source file they were
+/// rendered from. If the source file has changed since, the cached render is
+/// treated as stale and removed on lookup.
 use std::collections::HashMap;
 use std::fs;
+use std::hash::Hasher;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-use crate::project::AssetId;
+use serde::{Deserialize, Serialize};
+
+use crate::project::image_sequence::ImageSequence;
 use crate::project::rendering::{RenderError, Result};
+use crate::project::{AssetId, AssetReference};
 use crate::utility::time::Duration;
 
+/// File name of the persisted cache index.
+const INDEX_FILE_NAME: &str = "cache_index.json";
+
+/// Version of the cache index format.
+const INDEX_VERSION: u32 = 1;
+
+/// File name prefixes of the files managed by the cache.
+const CACHE_FILE_PREFIXES: [&str; 2] = ["asset_", "segment_"];
+
 /// Hash key for cache entries.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct CacheKey {
-    /// Asset ID for the source asset.
-    asset_id: AssetId,
-    /// Hash of the rendering parameters.
-    params_hash: u64,
+enum CacheKey {
+    /// A rendered asset.
+    Asset {
+        /// Asset ID for the source asset.
+        asset_id: AssetId,
+        /// Hash of the rendering parameters.
+        params_hash: u64,
+    },
+    /// An encoded timeline segment.
+    Segment {
+        /// Hash of everything that affects the segment's output.
+        segment_hash: u64,
+    },
+}
+
+/// Identity of a source file that cached output was rendered from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourceFingerprint {
+    /// Size of the source file in bytes.
+    pub size: u64,
+    /// Last modification time of the source file, if the platform reports it.
+    pub modified: Option<SystemTime>,
+    /// Hash of the file contents, if content hashing was requested.
+    pub content_hash: Option<u64>,
+}
+
+impl SourceFingerprint {
+    /// Reads the fingerprint of a source file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the source file
+    /// * `hash_contents` - Whether to also hash the file contents
+    ///
+    /// # Returns
+    ///
+    /// The fingerprint of the file, or an error if the file cannot be read.
+    pub fn from_path(path: &Path, hash_contents: bool) -> Result<Self> {
+        let metadata = fs::metadata(path).map_err(|e| {
+            RenderError::Io(format!("Failed to get metadata of {}: {e}", path.display()))
+        })?;
+
+        let content_hash = if hash_contents {
+            Some(Self::hash_contents(path)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            content_hash,
+        })
+    }
+
+    /// Reads the fingerprint of the source files of an asset.
+    ///
+    /// Image sequences are fingerprinted across all of their frames: the
+    /// sizes are summed, the latest modification time is kept, and the
+    /// content hashes are combined, so replacing any frame invalidates
+    /// cached output. Other assets are fingerprinted by their path.
+    ///
+    /// # Arguments
+    ///
+    /// * `asset` - The asset to fingerprint
+    /// * `hash_contents` - Whether to also hash the file contents
+    ///
+    /// # Returns
+    ///
+    /// The fingerprint of the asset, or an error if a file cannot be read.
+    pub fn from_asset(asset: &AssetReference, hash_contents: bool) -> Result<Self> {
+        let Some(sequence) = ImageSequence::from_asset(asset) else {
+            return Self::from_path(&asset.path, hash_contents);
+        };
+
+        let mut combined = Self {
+            size: 0,
+            modified: None,
+            content_hash: hash_contents.then_some(0),
+        };
+        for frame in sequence.frame_paths() {
+            let fingerprint = Self::from_path(&frame, hash_contents)?;
+            combined.size += fingerprint.size;
+            combined.modified = combined.modified.max(fingerprint.modified);
+            if let (Some(hash), Some(frame_hash)) =
+                (combined.content_hash.as_mut(), fingerprint.content_hash)
+            {
+                *hash = hash.rotate_left(5) ^ frame_hash;
+            }
+        }
+
+        Ok(combined)
+    }
+
+    /// Hashes the contents of a file with `FnvHasher`.
+    fn hash_contents(path: &Path) -> Result<u64> {
+        let mut file = fs::File::open(path)
+            .map_err(|e| RenderError::Io(format!("Failed to open {}: {e}", path.display())))?;
+
+        let mut hasher = FnvHasher::default();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| RenderError::Io(format!("Failed to read {}: {e}", path.display())))?;
+            if read == 0 {
+                break;
+            }
+            hasher.write(&buffer[..read]);
+        }
+
+        Ok(hasher.finish())
+    }
+}
+
+/// 64-bit FNV-1a hasher.
+///
+/// Unlike `DefaultHasher`, whose algorithm may change between Rust releases,
+/// FNV-1a gives the same hash in every build, so hashes persisted in the
+/// cache index stay comparable.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
 }
 
-/// Metadata for a cached asset.
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Metadata for a cached asset or timeline segment.
 #[derive(Debug, Clone)]
 pub struct CacheMetadata {
     /// When the cached file was created.
     pub created_at: SystemTime,
-    /// Source asset ID.
-    pub source_asset_id: AssetId,
+    /// When the cached file was last used.
+    pub last_accessed: SystemTime,
+    /// Source asset ID (`None` for timeline segments).
+    pub source_asset_id: Option<AssetId>,
     /// Duration of the cached content.
     pub duration: Duration,
-    /// Rendering parameters hash.
+    /// Rendering parameters hash (the segment hash for timeline segments).
     pub params_hash: u64,
     /// Size of the cached file in bytes.
     pub file_size: u64,
+    /// Identity of the source file (`None` for timeline segments, whose
+    /// sources are part of the segment hash).
+    pub source: Option<SourceFingerprint>,
 }
 
 /// Cache entry for a rendered asset.
@@ -44,6 +210,161 @@ pub struct CacheEntry {
     pub metadata: CacheMetadata,
 }
 
+/// Summary of the contents of a render cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    /// Number of cached rendered assets.
+    pub asset_entries: usize,
+    /// Number of cached timeline segments.
+    pub segment_entries: usize,
+    /// Total size of the cached files in bytes.
+    pub total_size: u64,
+    /// Maximum size of the cache in bytes, or `None` if unlimited.
+    pub max_size: Option<u64>,
+    /// When the least recently used entry was last used.
+    pub oldest_access: Option<SystemTime>,
+    /// When the most recently used entry was last used.
+    pub newest_access: Option<SystemTime>,
+}
+
+impl CacheStats {
+    /// Gets the total number of cache entries.
+    #[must_use]
+    pub fn entry_count(&self) -> usize {
+        self.asset_entries + self.segment_entries
+    }
+}
+
+/// Result of pruning a render cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Number of entries removed because their file was missing or damaged.
+    pub invalid_entries: usize,
+    /// Number of entries removed because they exceeded the maximum age.
+    pub expired_entries: usize,
+    /// Number of entries evicted to stay within the maximum size.
+    pub evicted_entries: usize,
+    /// Number of cache files on disk that no entry referred to.
+    pub orphaned_files: usize,
+    /// Number of bytes freed on disk.
+    pub freed_bytes: u64,
+}
+
+/// Persisted form of the cache index.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    /// Version of the index format.
+    version: u32,
+    /// Persisted cache entries.
+    entries: Vec<IndexEntry>,
+}
+
+/// Persisted key of a cache entry.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum IndexKey {
+    /// A rendered asset.
+    Asset {
+        /// Asset ID of the source asset.
+        asset_id: String,
+        /// Hash of the rendering parameters.
+        params_hash: u64,
+    },
+    /// An encoded timeline segment.
+    Segment {
+        /// Hash of the segment.
+        segment_hash: u64,
+    },
+}
+
+/// Persisted cache entry.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Key of the entry.
+    #[serde(flatten)]
+    key: IndexKey,
+    /// Name of the cached file, relative to the cache directory.
+    file_name: String,
+    /// When the cached file was created.
+    created_at: SystemTime,
+    /// When the cached file was last used.
+    last_accessed: SystemTime,
+    /// Duration of the cached content in seconds.
+    duration: f64,
+    /// Size of the cached file in bytes.
+    file_size: u64,
+    /// Identity of the source file.
+    #[serde(default)]
+    source: Option<SourceFingerprint>,
+}
+
+impl IndexEntry {
+    /// Creates a persisted entry from an in-memory cache entry.
+    fn from_entry(key: &CacheKey, entry: &CacheEntry) -> Option<Self> {
+        let file_name = entry.path.file_name()?.to_string_lossy().to_string();
+        let key = match key {
+            CacheKey::Asset {
+                asset_id,
+                params_hash,
+            } => IndexKey::Asset {
+                asset_id: asset_id.to_string(),
+                params_hash: *params_hash,
+            },
+            CacheKey::Segment { segment_hash } => IndexKey::Segment {
+                segment_hash: *segment_hash,
+            },
+        };
+
+        Some(Self {
+            key,
+            file_name,
+            created_at: entry.metadata.created_at,
+            last_accessed: entry.metadata.last_accessed,
+            duration: entry.metadata.duration.as_seconds(),
+            file_size: entry.metadata.file_size,
+            source: entry.metadata.source.clone(),
+        })
+    }
+
+    /// Restores the in-memory cache entry, or `None` if the key is malformed.
+    fn into_entry(self, cache_dir: &Path) -> Option<(CacheKey, CacheEntry)> {
+        let (key, source_asset_id, params_hash) = match self.key {
+            IndexKey::Asset {
+                asset_id,
+                params_hash,
+            } => {
+                let asset_id: AssetId = asset_id.parse().ok()?;
+                (
+                    CacheKey::Asset {
+                        asset_id,
+                        params_hash,
+                    },
+                    Some(asset_id),
+                    params_hash,
+                )
+            }
+            IndexKey::Segment { segment_hash } => {
+                (CacheKey::Segment { segment_hash }, None, segment_hash)
+            }
+        };
+
+        let entry = CacheEntry {
+            path: cache_dir.join(self.file_name),
+            metadata: CacheMetadata {
+                created_at: self.created_at,
+                last_accessed: self.last_accessed,
+                source_asset_id,
+                duration: Duration::from_seconds(self.duration),
+                params_hash,
+                file_size: self.file_size,
+                source: self.source,
+            },
+        };
+
+        Some((key, entry))
+    }
+}
+
 /// Manages the rendering cache.
 #[derive(Debug)]
 pub struct RenderCache {
@@ -53,15 +374,22 @@ pub struct RenderCache {
     entries: HashMap<CacheKey, CacheEntry>,
     /// Maximum size of the cache in bytes.
     max_size: Option<u64>,
+    /// Maximum time an entry may go unused before it is evicted.
+    max_age: Option<std::time::Duration>,
     /// Current size of the cache in bytes.
     current_size: u64,
     /// Whether the cache is enabled.
     enabled: bool,
+    /// Whether the in-memory index differs from the persisted one.
+    index_dirty: bool,
 }
 
 impl RenderCache {
     /// Creates a new render cache with the specified cache directory.
     ///
+    /// If the directory already contains a cache index, its entries are
+    /// loaded and checked against the files on disk.
+    ///
     /// # Arguments
     ///
     /// * `cache_dir` - The directory where cache files will be stored
@@ -82,67 +410,143 @@ impl RenderCache {
             cache_dir,
             entries: HashMap::new(),
             max_size,
+            max_age: None,
             current_size: 0,
             enabled: true,
+            index_dirty: false,
         };
 
-        // 既存のキャッシュファイルを読み込み
+        // 既存のキャッシュインデックスを読み込み
         cache.load_existing_entries()?;
 
         Ok(cache)
     }
 
-    /// Loads existing cache entries from the cache directory.
-    fn load_existing_entries(&mut self) -> Result<()> {
-        // キャッシュディレクトリ内のファイルを列挙
-        let entries = fs::read_dir(&self.cache_dir)
-            .map_err(|e| RenderError::Io(format!("Failed to read cache directory: {}", e)))?;
+    /// Gets the default directory of the render cache.
+    ///
+    /// # Returns
+    ///
+    /// The `edv_cache` directory inside the system temporary directory.
+    #[must_use]
+    pub fn default_dir() -> PathBuf {
+        std::env::temp_dir().join("edv_cache")
+    }
 
-        // トータルサイズを計算
-        let mut total_size = 0;
+    /// Gets the path of the cache index file.
+    fn index_path(&self) -> PathBuf {
+        self.cache_dir.join(INDEX_FILE_NAME)
+    }
 
-        // キャッシュのメタデータファイル名
-        let metadata_path = self.cache_dir.join("cache_index.json");
+    /// Loads existing cache entries from the cache index.
+    ///
+    /// Entries whose file is missing or does not have the recorded size are
+    /// dropped. An unreadable index is treated as empty, since the cache can
+    /// always be rebuilt.
+    fn load_existing_entries(&mut self) -> Result<()> {
+        let index_path = self.index_path();
+        if !index_path.exists() {
+            return Ok(());
+        }
 
-        // メタデータが存在する場合は読み込む
-        if metadata_path.exists() {
-            let metadata_json = fs::read_to_string(&metadata_path)
-                .map_err(|e| RenderError::Io(format!("Failed to read cache metadata: {}", e)))?;
+        let index_json = fs::read_to_string(&index_path)
+            .map_err(|e| RenderError::Io(format!("Failed to read cache metadata: {}", e)))?;
 
-            // JSONからキャッシュエントリを読み込む（簡略化のため実装を省略）
-            // 実際の実装では、ここでJSONデシリアライズを行い、self.entriesを設定
+        let index = match serde_json::from_str::<CacheIndex>(&index_json) {
+            Ok(index) if index.version == INDEX_VERSION => index,
+            Ok(index) => {
+                eprintln!(
+                    "Ignoring cache index with unsupported version {}",
+                    index.version
+                );
+                self.index_dirty = true;
+                CacheIndex::default()
+            }
+            Err(e) => {
+                eprintln!("Ignoring unreadable cache index: {e}");
+                self.index_dirty = true;
+                CacheIndex::default()
+            }
+        };
 
-            // 各ファイルのサイズをチェック
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    // メタデータファイルはスキップ
-                    if path == metadata_path {
-                        continue;
-                    }
+        for index_entry in index.entries {
+            let Some((key, entry)) = index_entry.into_entry(&self.cache_dir) else {
+                self.index_dirty = true;
+                continue;
+            };
 
-                    if let Ok(metadata) = fs::metadata(&path) {
-                        total_size += metadata.len();
-                    }
-                }
+            // 整合性チェック：ファイルが存在し、サイズが一致するものだけを復元
+            if !Self::is_intact(&entry) {
+                let _ = fs::remove_file(&entry.path);
+                self.index_dirty = true;
+                continue;
             }
+
+            self.current_size += entry.metadata.file_size;
+            self.entries.insert(key, entry);
         }
 
-        self.current_size = total_size;
+        self.flush()
+    }
 
-        Ok(())
+    /// Checks whether the file of a cache entry exists with the recorded size.
+    fn is_intact(entry: &CacheEntry) -> bool {
+        fs::metadata(&entry.path)
+            .map(|metadata| metadata.is_file() && metadata.len() == entry.metadata.file_size)
+            .unwrap_or(false)
     }
 
     /// Saves the cache metadata to disk.
+    ///
+    /// The index is written to a temporary file first and then renamed, so
+    /// an interrupted write never leaves a truncated index behind.
     fn save_metadata(&self) -> Result<()> {
-        let metadata_path = self.cache_dir.join("cache_index.json");
+        let mut entries: Vec<IndexEntry> = self
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| IndexEntry::from_entry(key, entry))
+            .collect();
+        entries.sort_by_key(|e| e.created_at);
+
+        let index = CacheIndex {
+            version: INDEX_VERSION,
+            entries,
+        };
+        let index_json = serde_json::to_string_pretty(&index)
+            .map_err(|e| RenderError::Cache(format!("Failed to serialize cache index: {e}")))?;
 
-        // 簡略化のため、実際のJSON作成を省略
-        // 実際の実装では、ここでself.entriesをJSONシリアライズして保存
+        let index_path = self.index_path();
+        let temp_path = index_path.with_extension("json.tmp");
+        fs::write(&temp_path, index_json)
+            .map_err(|e| RenderError::Io(format!("Failed to write cache metadata: {e}")))?;
+        fs::rename(&temp_path, &index_path)
+            .map_err(|e| RenderError::Io(format!("Failed to write cache metadata: {e}")))?;
 
         Ok(())
     }
 
+    /// Writes pending access time updates to the cache index.
+    ///
+    /// Lookups only update the in-memory index. The updates are persisted by
+    /// the next modification of the cache, by this method, or when the cache
+    /// is dropped.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the index is up to date, or an error if writing it failed.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.index_dirty {
+            self.save_metadata()?;
+            self.index_dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Persists the index after a modification of the cache.
+    fn commit(&mut self) -> Result<()> {
+        self.index_dirty = true;
+        self.flush()
+    }
+
     /// Generates a cache key for an asset and rendering parameters.
     ///
     /// # Arguments
@@ -154,7 +558,7 @@ impl RenderCache {
     ///
     /// A cache key for the given asset and parameters.
     fn make_key(&self, asset_id: AssetId, params: u64) -> CacheKey {
-        CacheKey {
+        CacheKey::Asset {
             asset_id,
             params_hash: params,
         }
@@ -178,23 +582,75 @@ impl RenderCache {
         hasher.finish()
     }
 
+    /// Looks up an entry and marks it as used.
+    ///
+    /// Entries whose file has been removed from disk are treated as missing.
+    fn touch(&mut self, key: &CacheKey) -> Option<&CacheEntry> {
+        if !self.enabled {
+            return None;
+        }
+
+        let entry = self.entries.get_mut(key)?;
+        if !entry.path.exists() {
+            return None;
+        }
+
+        entry.metadata.last_accessed = SystemTime::now();
+        self.index_dirty = true;
+        Some(entry)
+    }
+
     /// Gets a cached asset if available.
     ///
+    /// If the source file has changed since the asset was cached, the stale
+    /// entry is removed and `None` is returned.
+    ///
     /// # Arguments
     ///
     /// * `asset_id` - ID of the source asset
     /// * `params_hash` - Hash of the rendering parameters
+    /// * `source` - Current fingerprint of the source file
     ///
     /// # Returns
     ///
     /// The cache entry if found, or `None` if not found.
-    pub fn get(&self, asset_id: AssetId, params_hash: u64) -> Option<&CacheEntry> {
-        if !self.enabled {
+    pub fn get(
+        &mut self,
+        asset_id: AssetId,
+        params_hash: u64,
+        source: &SourceFingerprint,
+    ) -> Option<&CacheEntry> {
+        let key = self.make_key(asset_id, params_hash);
+
+        // ソースファイルが変更されていればキャッシュを無効化
+        let stale = self
+            .entries
+            .get(&key)
+            .is_some_and(|entry| !Self::matches_source(entry, source));
+        if stale {
+            self.remove_entry(&key);
+            self.index_dirty = true;
             return None;
         }
 
-        let key = self.make_key(asset_id, params_hash);
-        self.entries.get(&key)
+        self.touch(&key)
+    }
+
+    /// Checks whether a cache entry was rendered from the given source file.
+    ///
+    /// The content hash is only compared when both fingerprints have one, so
+    /// enabling content hashing does not invalidate existing entries.
+    fn matches_source(entry: &CacheEntry, source: &SourceFingerprint) -> bool {
+        let Some(cached) = &entry.metadata.source else {
+            return false;
+        };
+
+        let same_contents = match (cached.content_hash, source.content_hash) {
+            (Some(cached_hash), Some(current_hash)) => cached_hash == current_hash,
+            _ => true,
+        };
+
+        cached.size == source.size && cached.modified == source.modified && same_contents
     }
 
     /// Adds a rendered asset to the cache.
@@ -203,6 +659,7 @@ impl RenderCache {
     ///
     /// * `asset_id` - ID of the source asset
     /// * `params_hash` - Hash of the rendering parameters
+    /// * `source` - Fingerprint of the source file the asset was rendered from
     /// * `file_path` - Path to the rendered file
     /// * `duration` - Duration of the rendered content
     ///
@@ -213,97 +670,309 @@ impl RenderCache {
         &mut self,
         asset_id: AssetId,
         params_hash: u64,
+        source: SourceFingerprint,
         file_path: &Path,
         duration: Duration,
+    ) -> Result<PathBuf> {
+        // キャッシュファイルの名前を生成
+        let cache_file_name = format!("asset_{}_{}.cache", asset_id, params_hash);
+        let key = self.make_key(asset_id, params_hash);
+
+        self.insert(
+            key,
+            cache_file_name,
+            file_path,
+            duration,
+            Some(asset_id),
+            Some(source),
+        )
+    }
+
+    /// Gets a cached timeline segment if available.
+    ///
+    /// Entries whose file has been removed from disk are treated as missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_hash` - Hash of the clips, assets, effects and settings of the segment
+    ///
+    /// # Returns
+    ///
+    /// The cache entry if found, or `None` if not found.
+    pub fn get_segment(&mut self, segment_hash: u64) -> Option<&CacheEntry> {
+        self.touch(&CacheKey::Segment { segment_hash })
+    }
+
+    /// Adds an encoded timeline segment to the cache.
+    ///
+    /// The file extension of `file_path` is preserved so that the cached
+    /// segment can be concatenated directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_hash` - Hash of the clips, assets, effects and settings of the segment
+    /// * `file_path` - Path to the encoded segment
+    /// * `duration` - Duration of the segment
+    ///
+    /// # Returns
+    ///
+    /// The path to the cached file, or an error if caching failed.
+    pub fn add_segment(
+        &mut self,
+        segment_hash: u64,
+        file_path: &Path,
+        duration: Duration,
+    ) -> Result<PathBuf> {
+        let extension = file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("cache");
+        let cache_file_name = format!("segment_{segment_hash:016x}.{extension}");
+        let key = CacheKey::Segment { segment_hash };
+
+        self.insert(key, cache_file_name, file_path, duration, None, None)
+    }
+
+    /// Copies a file into the cache and records it under the given key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is larger than the maximum cache size,
+    /// since it would have to be evicted again right away, or if it cannot
+    /// be copied or the index cannot be saved.
+    fn insert(
+        &mut self,
+        key: CacheKey,
+        cache_file_name: String,
+        file_path: &Path,
+        duration: Duration,
+        source_asset_id: Option<AssetId>,
+        source: Option<SourceFingerprint>,
     ) -> Result<PathBuf> {
         if !self.enabled {
             return Ok(file_path.to_path_buf());
         }
 
         // ファイルのメタデータを取得
-        let file_metadata = fs::metadata(file_path)
-            .map_err(|e| RenderError::Io(format!("Failed to get file metadata: {}", e)))?;
-
-        let file_size = file_metadata.len();
-
-        // キャッシュキーを作成
-        let key = self.make_key(asset_id, params_hash);
-
-        // キャッシュファイルの名前を生成
-        let cache_file_name = format!("asset_{}_{}.cache", asset_id, params_hash);
-        let cache_path = self.cache_dir.join(cache_file_name);
+        let file_size = fs::metadata(file_path)
+            .map_err(|e| RenderError::Io(format!("Failed to get file metadata: {}", e)))?
+            .len();
+        if let Some(max_size) = self.max_size.filter(|&max_size| file_size > max_size) {
+            return Err(RenderError::Cache(format!(
+                "{} ({file_size} bytes) is larger than the cache ({max_size} bytes)",
+                file_path.display()
+            )));
+        }
 
         // ファイルをキャッシュにコピー
+        let cache_path = self.cache_dir.join(cache_file_name);
         fs::copy(file_path, &cache_path)
             .map_err(|e| RenderError::Io(format!("Failed to copy file to cache: {}", e)))?;
 
-        // キャッシュメタデータを作成
-        let metadata = CacheMetadata {
-            created_at: SystemTime::now(),
-            source_asset_id: asset_id,
-            duration,
-            params_hash,
-            file_size,
+        let params_hash = match key {
+            CacheKey::Asset { params_hash, .. } => params_hash,
+            CacheKey::Segment { segment_hash } => segment_hash,
         };
 
-        // キャッシュエントリを作成
+        let now = SystemTime::now();
         let entry = CacheEntry {
             path: cache_path.clone(),
-            metadata,
+            metadata: CacheMetadata {
+                created_at: now,
+                last_accessed: now,
+                source_asset_id,
+                duration,
+                params_hash,
+                file_size,
+                source,
+            },
         };
 
-        // キャッシュに追加
-        self.entries.insert(key, entry);
+        // 同じキーの古いエントリがあればサイズを差し引く
+        if let Some(previous) = self.entries.insert(key, entry) {
+            self.current_size = self
+                .current_size
+                .saturating_sub(previous.metadata.file_size);
+        }
         self.current_size += file_size;
 
-        // キャッシュサイズが最大サイズを超えた場合、古いエントリを削除
+        // キャッシュサイズが最大サイズを超えた場合、最も長く使われていないエントリを削除
         self.prune_if_needed();
 
         // メタデータ保存
-        self.save_metadata()?;
+        self.commit()?;
 
         Ok(cache_path)
     }
 
+    /// Removes an entry and its file from the cache.
+    ///
+    /// # Returns
+    ///
+    /// The size of the removed entry in bytes, or `None` if there was no entry.
+    fn remove_entry(&mut self, key: &CacheKey) -> Option<u64> {
+        let entry = self.entries.remove(key)?;
+
+        // ファイルを削除
+        if let Err(e) = fs::remove_file(&entry.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                // エラーをログに記録（実際の実装ではロガーを使用）
+                eprintln!("Failed to remove cache file: {}", e);
+            }
+        }
+
+        // キャッシュサイズを更新
+        self.current_size = self.current_size.saturating_sub(entry.metadata.file_size);
+        Some(entry.metadata.file_size)
+    }
+
+    /// Removes entries whose last use is older than the maximum age.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed entries and the number of freed bytes.
+    fn evict_expired(&mut self) -> (usize, u64) {
+        let Some(max_age) = self.max_age else {
+            return (0, 0);
+        };
+
+        let now = SystemTime::now();
+        let expired: Vec<CacheKey> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                now.duration_since(entry.metadata.last_accessed)
+                    .map(|age| age > max_age)
+                    .unwrap_or(false)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut freed = 0;
+        for key in &expired {
+            freed += self.remove_entry(key).unwrap_or(0);
+        }
+        (expired.len(), freed)
+    }
+
+    /// Evicts the least recently used entries until the cache fits its maximum size.
+    ///
+    /// # Returns
+    ///
+    /// The number of evicted entries and the number of freed bytes.
+    fn evict_least_recently_used(&mut self) -> (usize, u64) {
+        let Some(max_size) = self.max_size else {
+            return (0, 0);
+        };
+        if self.current_size <= max_size {
+            return (0, 0);
+        }
+
+        // 最終使用日時の古い順にエントリをソート
+        let mut candidates: Vec<(CacheKey, SystemTime)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.metadata.last_accessed))
+            .collect();
+        candidates.sort_by_key(|c| c.1);
+
+        let mut evicted = 0;
+        let mut freed = 0;
+        for (key, _) in candidates {
+            if self.current_size <= max_size {
+                break;
+            }
+            if let Some(size) = self.remove_entry(&key) {
+                evicted += 1;
+                freed += size;
+            }
+        }
+        (evicted, freed)
+    }
+
     /// Prunes the cache if it's larger than the maximum size.
     fn prune_if_needed(&mut self) {
-        if let Some(max_size) = self.max_size {
-            if self.current_size > max_size {
-                // 古い順にエントリをソート
-                let mut entries_to_remove: Vec<_> = self.entries.iter().collect();
-                entries_to_remove
-                    .sort_by(|a, b| a.1.metadata.created_at.cmp(&b.1.metadata.created_at));
-
-                // 削除するキーと容量を記録
-                let mut keys_to_remove = Vec::new();
-                let mut size_to_remove = 0u64;
-
-                // キャッシュサイズが最大サイズ以下になるまで古いエントリを削除リストに追加
-                for (key, entry) in &entries_to_remove {
-                    keys_to_remove.push((*key).clone());
-                    size_to_remove += entry.metadata.file_size;
-
-                    // キャッシュサイズが最大サイズ以下になったか確認
-                    if self.current_size - size_to_remove <= max_size {
-                        break;
-                    }
-                }
+        self.evict_expired();
+        self.evict_least_recently_used();
+    }
 
-                // 実際にエントリを削除
-                for key in keys_to_remove {
-                    if let Some(entry) = self.entries.remove(&key) {
-                        // ファイルを削除
-                        if let Err(e) = fs::remove_file(&entry.path) {
-                            // エラーをログに記録（実際の実装ではロガーを使用）
-                            eprintln!("Failed to remove cache file: {}", e);
-                        }
-
-                        // キャッシュサイズを更新
-                        self.current_size -= entry.metadata.file_size;
-                    }
+    /// Checks the cache and removes everything that is no longer usable.
+    ///
+    /// This drops entries whose file is missing or has been modified,
+    /// removes entries older than the maximum age, evicts least recently used
+    /// entries until the cache fits its maximum size, and deletes cache files
+    /// that are not referenced by the index.
+    ///
+    /// # Returns
+    ///
+    /// A report of the removed entries, or an error if the index could not be saved.
+    pub fn prune(&mut self) -> Result<PruneReport> {
+        let mut report = PruneReport::default();
+
+        // 整合性チェックに失敗したエントリを削除
+        let invalid: Vec<CacheKey> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !Self::is_intact(entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &invalid {
+            if let Some(path) = self.entries.get(key).map(|entry| entry.path.clone()) {
+                report.freed_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            }
+            self.remove_entry(key);
+        }
+        report.invalid_entries = invalid.len();
+
+        let (expired, expired_bytes) = self.evict_expired();
+        report.expired_entries = expired;
+        report.freed_bytes += expired_bytes;
+
+        let (evicted, evicted_bytes) = self.evict_least_recently_used();
+        report.evicted_entries = evicted;
+        report.freed_bytes += evicted_bytes;
+
+        for path in self.orphaned_files()? {
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.orphaned_files += 1;
+                    report.freed_bytes += size;
                 }
+                Err(e) => eprintln!("Failed to remove cache file: {}", e),
             }
         }
+
+        self.commit()?;
+
+        Ok(report)
+    }
+
+    /// Lists cache files in the cache directory that no entry refers to.
+    ///
+    /// Only files named like cache files are considered, so unrelated files in
+    /// a shared directory are left alone.
+    fn orphaned_files(&self) -> Result<Vec<PathBuf>> {
+        let dir_entries = fs::read_dir(&self.cache_dir)
+            .map_err(|e| RenderError::Io(format!("Failed to read cache directory: {}", e)))?;
+
+        let mut orphaned = Vec::new();
+        for dir_entry in dir_entries.flatten() {
+            let path = dir_entry.path();
+            let is_cache_file = path.is_file()
+                && path.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    CACHE_FILE_PREFIXES
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix))
+                });
+
+            if is_cache_file && !self.entries.values().any(|entry| entry.path == path) {
+                orphaned.push(path);
+            }
+        }
+
+        Ok(orphaned)
     }
 
     /// Invalidates a cached asset.
@@ -321,31 +990,23 @@ impl RenderCache {
         let keys_to_remove: Vec<CacheKey> = self
             .entries
             .keys()
-            .filter(|k| {
-                k.asset_id == asset_id
-                    && (params_hash.is_none() || params_hash == Some(k.params_hash))
+            .filter(|k| match k {
+                CacheKey::Asset {
+                    asset_id: id,
+                    params_hash: hash,
+                } => *id == asset_id && (params_hash.is_none() || params_hash == Some(*hash)),
+                CacheKey::Segment { .. } => false,
             })
             .cloned()
             .collect();
 
         // 該当するエントリを削除
         for key in keys_to_remove {
-            if let Some(entry) = self.entries.remove(&key) {
-                // ファイルを削除
-                if let Err(e) = fs::remove_file(&entry.path) {
-                    // エラーをログに記録（実際の実装ではロガーを使用）
-                    eprintln!("Failed to remove cache file: {}", e);
-                }
-
-                // キャッシュサイズを更新
-                self.current_size -= entry.metadata.file_size;
-            }
+            self.remove_entry(&key);
         }
 
         // メタデータ保存
-        self.save_metadata()?;
-
-        Ok(())
+        self.commit()
     }
 
     /// Clears all cached assets.
@@ -355,21 +1016,41 @@ impl RenderCache {
     /// `Ok(())` if the cache was cleared, or an error if clearing failed.
     pub fn clear(&mut self) -> Result<()> {
         // すべてのキャッシュファイルを削除
-        for entry in &self.entries {
-            if let Err(e) = fs::remove_file(&entry.1.path) {
-                // エラーをログに記録（実際の実装ではロガーを使用）
-                eprintln!("Failed to remove cache file: {}", e);
-            }
+        let keys: Vec<CacheKey> = self.entries.keys().cloned().collect();
+        for key in keys {
+            self.remove_entry(&key);
         }
-
-        // エントリをクリア
-        self.entries.clear();
         self.current_size = 0;
 
         // メタデータ保存
-        self.save_metadata()?;
+        self.commit()
+    }
 
-        Ok(())
+    /// Gets a summary of the cache contents.
+    ///
+    /// # Returns
+    ///
+    /// Statistics about the cached entries.
+    #[must_use]
+    pub fn stats(&self) -> CacheStats {
+        let segment_entries = self
+            .entries
+            .keys()
+            .filter(|key| matches!(key, CacheKey::Segment { .. }))
+            .count();
+        let accesses = self
+            .entries
+            .values()
+            .map(|entry| entry.metadata.last_accessed);
+
+        CacheStats {
+            asset_entries: self.entries.len() - segment_entries,
+            segment_entries,
+            total_size: self.current_size,
+            max_size: self.max_size,
+            oldest_access: accesses.clone().min(),
+            newest_access: accesses.max(),
+        }
     }
 
     /// Enables or disables the cache.
@@ -410,4 +1091,233 @@ impl RenderCache {
     pub fn max_size(&self) -> Option<u64> {
         self.max_size
     }
+
+    /// Sets the maximum size of the cache in bytes.
+    ///
+    /// The new limit is enforced the next time an entry is added or the
+    /// cache is pruned.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_size` - The maximum size, or `None` for no limit
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Gets the maximum time an entry may go unused before it is evicted.
+    ///
+    /// # Returns
+    ///
+    /// The maximum age, or `None` if entries never expire.
+    #[must_use]
+    pub fn max_age(&self) -> Option<std::time::Duration> {
+        self.max_age
+    }
+
+    /// Sets the maximum time an entry may go unused before it is evicted.
+    ///
+    /// The new limit is enforced the next time an entry is added or the
+    /// cache is pruned.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - The maximum age, or `None` if entries never expire
+    pub fn set_max_age(&mut self, max_age: Option<std::time::Duration>) {
+        self.max_age = max_age;
+    }
+}
+
+impl Drop for RenderCache {
+    fn drop(&mut self) {
+        // 未保存のアクセス日時を書き出す
+        if let Err(e) = self.flush() {
+            eprintln!("Failed to save cache index: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_source(dir: &Path, name: &str, size: usize) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_fnv_hasher_matches_reference_values() {
+        assert_eq!(FnvHasher::default().finish(), 0xcbf2_9ce4_8422_2325);
+        let mut hasher = FnvHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_index_survives_restart() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = write_source(source_dir.path(), "segment.mp4", 128);
+        let asset_id = AssetId::new();
+        let fingerprint = SourceFingerprint::from_path(&source, true).unwrap();
+
+        {
+            let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+            cache
+                .add_segment(42, &source, Duration::from_seconds(1.0))
+                .unwrap();
+            cache
+                .add(
+                    asset_id,
+                    7,
+                    fingerprint.clone(),
+                    &source,
+                    Duration::from_seconds(2.0),
+                )
+                .unwrap();
+        }
+
+        let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+        assert_eq!(cache.current_size(), 256);
+        assert!(cache.get_segment(42).is_some());
+        let entry = cache.get(asset_id, 7, &fingerprint).unwrap();
+        assert_eq!(entry.metadata.source_asset_id, Some(asset_id));
+        assert_eq!(entry.metadata.source.as_ref(), Some(&fingerprint));
+        assert_eq!(entry.metadata.duration.as_seconds(), 2.0);
+    }
+
+    #[test]
+    fn test_changed_source_invalidates_asset() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = write_source(source_dir.path(), "clip.mp4", 32);
+        let asset_id = AssetId::new();
+
+        let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+        let original = SourceFingerprint::from_path(&source, true).unwrap();
+        cache
+            .add(
+                asset_id,
+                7,
+                original.clone(),
+                &source,
+                Duration::from_seconds(1.0),
+            )
+            .unwrap();
+        assert!(cache.get(asset_id, 7, &original).is_some());
+
+        // Same size, different contents
+        fs::write(&source, vec![1u8; 32]).unwrap();
+        let mut replaced = SourceFingerprint::from_path(&source, true).unwrap();
+        replaced.modified = original.modified;
+        assert_ne!(replaced.content_hash, original.content_hash);
+
+        assert!(cache.get(asset_id, 7, &replaced).is_none());
+        assert_eq!(cache.stats().entry_count(), 0);
+        assert_eq!(cache.current_size(), 0);
+    }
+
+    #[test]
+    fn test_damaged_entries_are_dropped_on_load() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = write_source(source_dir.path(), "segment.mp4", 64);
+
+        let (missing, truncated) = {
+            let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+            let missing = cache
+                .add_segment(1, &source, Duration::from_seconds(1.0))
+                .unwrap();
+            let truncated = cache
+                .add_segment(2, &source, Duration::from_seconds(1.0))
+                .unwrap();
+            cache
+                .add_segment(3, &source, Duration::from_seconds(1.0))
+                .unwrap();
+            (missing, truncated)
+        };
+
+        fs::remove_file(&missing).unwrap();
+        fs::write(&truncated, b"short").unwrap();
+
+        let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+        assert!(cache.get_segment(1).is_none());
+        assert!(cache.get_segment(2).is_none());
+        assert!(cache.get_segment(3).is_some());
+        assert_eq!(cache.current_size(), 64);
+        assert!(!truncated.exists());
+    }
+
+    #[test]
+    fn test_unreadable_index_starts_empty() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        fs::write(cache_dir.path().join(INDEX_FILE_NAME), "not json").unwrap();
+
+        let cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+        assert_eq!(cache.stats().entry_count(), 0);
+    }
+
+    #[test]
+    fn test_eviction_is_least_recently_used() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = write_source(source_dir.path(), "segment.mp4", 100);
+
+        let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), Some(250)).unwrap();
+        cache
+            .add_segment(1, &source, Duration::from_seconds(1.0))
+            .unwrap();
+        cache
+            .add_segment(2, &source, Duration::from_seconds(1.0))
+            .unwrap();
+
+        // Using the older entry makes the other one the eviction candidate
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(cache.get_segment(1).is_some());
+
+        cache
+            .add_segment(3, &source, Duration::from_seconds(1.0))
+            .unwrap();
+
+        assert!(cache.get_segment(1).is_some());
+        assert!(cache.get_segment(2).is_none());
+        assert!(cache.get_segment(3).is_some());
+        assert_eq!(cache.current_size(), 200);
+
+        // 上限を超えるファイルはコピーせず、既存のエントリも残す
+        let large = write_source(source_dir.path(), "large.mp4", 300);
+        assert!(
+            cache
+                .add_segment(4, &large, Duration::from_seconds(1.0))
+                .is_err()
+        );
+        assert!(cache.get_segment(4).is_none());
+        assert!(cache.get_segment(1).is_some());
+        assert_eq!(cache.current_size(), 200);
+    }
+
+    #[test]
+    fn test_prune_removes_expired_and_orphaned_files() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = write_source(source_dir.path(), "segment.mp4", 10);
+
+        let mut cache = RenderCache::new(cache_dir.path().to_path_buf(), None).unwrap();
+        cache
+            .add_segment(1, &source, Duration::from_seconds(1.0))
+            .unwrap();
+        write_source(cache_dir.path(), "segment_orphan.mp4", 5);
+        write_source(cache_dir.path(), "notes.txt", 5);
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        cache.set_max_age(Some(std::time::Duration::from_millis(1)));
+        let report = cache.prune().unwrap();
+
+        assert_eq!(report.expired_entries, 1);
+        assert_eq!(report.orphaned_files, 1);
+        assert_eq!(report.freed_bytes, 15);
+        assert_eq!(cache.stats().entry_count(), 0);
+        assert!(cache_dir.path().join("notes.txt").exists());
+    }
 }
@@ -17,7 +17,7 @@
 ///         +set_progress_tracker(SharedProgressTracker): void
 ///         +compose(RenderConfig): Result
 ///         -prepare_tracks(RenderConfig): Result~HashMap~
-///         -composite_tracks(HashMap, RenderConfig, Path): Result
+///         -composite_tracks(&HashMap, RenderConfig, Path): Result
 ///         -calculate_timeline_duration(): Duration
 ///         -prepare_video_track(Track, RenderConfig): Result~PreparedTrack~
 ///         -prepare_audio_track(Track, RenderConfig): Result~PreparedTrack~
@@ -80,18 +80,25 @@
 ///     PrepareTracks -.-> Cancel
 ///     Composite -.-> Cancel
 /// ```
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-use crate::ffmpeg::FFmpeg;
+use crate::ffmpeg::analysis::value_after;
+use crate::ffmpeg::capabilities::filter_names;
+use crate::ffmpeg::{CropRect, FFmpeg};
 use crate::project::AssetId;
 use crate::project::AssetReference;
-use crate::project::rendering::config::{AudioCodec, RenderConfig, VideoCodec};
+use image::RgbaImage;
+
+use crate::project::rendering::config::{
+    AudioCodec, ImageSequenceOptions, OutputFormat, RateControl, RenderConfig,
+};
 use crate::project::rendering::gpu_accelerator::GpuAccelerator;
 use crate::project::rendering::progress::{RenderStage, SharedProgressTracker};
+use crate::project::rendering::stems::StemOptions;
 use crate::project::timeline::keyframes::{EasingFunction, KeyframeAnimation};
 use crate::project::timeline::multi_track;
 use crate::project::timeline::{Clip, Timeline, Track, TrackId, TrackKind};
@@ -291,6 +298,28 @@ pub enum CompositionError {
 /// Type alias for composition operation results.
 pub type Result<T> = std::result::Result<T, CompositionError>;
 
+/// Gets the rendering configuration of a single still frame.
+///
+/// The frame is written as `frame_0.png` in `directory`, with an alpha
+/// channel so that uncovered areas stay transparent.
+///
+/// # Arguments
+///
+/// * `directory` - Directory to write the frame to
+/// * `width` - Width of the frame (in pixels)
+/// * `height` - Height of the frame (in pixels)
+#[must_use]
+pub fn still_frame_config(directory: &Path, width: u32, height: u32) -> RenderConfig {
+    RenderConfig::new(directory.join("frame_%d.png"))
+        .with_resolution(width, height)
+        .with_format(OutputFormat::PngSequence)
+        .with_image_sequence(ImageSequenceOptions {
+            start_number: 0,
+            alpha: true,
+        })
+        .with_cache(false)
+}
+
 /// Intermediate file created during the composition process.
 #[derive(Debug)]
 struct IntermediateFile {
@@ -320,6 +349,32 @@ impl IntermediateFile {
     }
 }
 
+/// The streams of an output that a compositor encodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderedStreams {
+    /// Every stream the output format supports.
+    #[default]
+    All,
+    /// Only the video, e.g. for segments that are joined before the audio is added.
+    VideoOnly,
+    /// Only the audio.
+    AudioOnly,
+}
+
+impl RenderedStreams {
+    /// Checks whether the video is encoded.
+    #[must_use]
+    pub fn includes_video(self) -> bool {
+        self != Self::AudioOnly
+    }
+
+    /// Checks whether the audio is encoded.
+    #[must_use]
+    pub fn includes_audio(self) -> bool {
+        self != Self::VideoOnly
+    }
+}
+
 /// Represents a track prepared for composition.
 #[derive(Debug)]
 struct PreparedTrack {
@@ -354,11 +409,17 @@ pub struct TrackCompositor {
     /// Progress tracker for the composition.
     progress: Option<SharedProgressTracker>,
 
+    /// Frame progress trackers of the outputs, in output order.
+    output_progress: Vec<SharedProgressTracker>,
+
     /// Whether to optimize for complex timelines.
     optimize_complex: bool,
 
     /// GPU accelerator for hardware-accelerated rendering.
     gpu_accelerator: Option<GpuAccelerator>,
+
+    /// The streams of the output to encode.
+    streams: RenderedStreams,
 }
 
 impl TrackCompositor {
@@ -379,8 +440,10 @@ impl TrackCompositor {
             assets,
             intermediate_files: Vec::new(),
             progress: None,
+            output_progress: Vec::new(),
             optimize_complex: false,
             gpu_accelerator: None,
+            streams: RenderedStreams::All,
         }
     }
 
@@ -389,6 +452,19 @@ impl TrackCompositor {
         self.progress = Some(progress);
     }
 
+    /// Sets the trackers receiving the encoded frames of each output.
+    ///
+    /// The trackers follow the order of the outputs passed to
+    /// `compose_outputs`; a single tracker receives the frames of `compose`.
+    /// Their totals are expected to be set to the frame counts of the outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `trackers` - One progress tracker per output
+    pub fn set_output_progress_trackers(&mut self, trackers: Vec<SharedProgressTracker>) {
+        self.output_progress = trackers;
+    }
+
     /// Sets whether to optimize for complex timelines.
     pub fn set_optimize_complex(&mut self, optimize: bool) {
         self.optimize_complex = optimize;
@@ -403,11 +479,70 @@ impl TrackCompositor {
         self.gpu_accelerator = Some(accelerator);
     }
 
+    /// Sets the streams of the output to encode.
+    ///
+    /// # Arguments
+    ///
+    /// * `streams` - The streams to encode; the others are left out of the output
+    pub fn set_rendered_streams(&mut self, streams: RenderedStreams) {
+        self.streams = streams;
+    }
+
     /// Gets the asset with the given ID.
     fn get_asset(&self, asset_id: AssetId) -> Option<&AssetReference> {
         self.assets.iter().find(|asset| asset.id == asset_id)
     }
 
+    /// Runs the final encode and reports the frames of every output.
+    ///
+    /// `FFmpeg` reports how far it has written the outputs; the time is
+    /// converted to frames at the frame rate of each output and added to its
+    /// tracker set with `set_output_progress_trackers`.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The encode to run
+    /// * `outputs` - The configurations of the outputs written by `command`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encode fails.
+    fn execute_encode(
+        &self,
+        command: &crate::ffmpeg::command::FFmpegCommand<'_>,
+        outputs: &[&RenderConfig],
+    ) -> Result<()> {
+        if self.output_progress.is_empty() {
+            return command.execute().map_err(CompositionError::FFmpeg);
+        }
+
+        let mut reported = vec![0_u64; self.output_progress.len()];
+        command
+            .execute_with_progress(|line| {
+                // 最後の報告では端数を切り捨てずに全フレームを完了とする
+                let finished = line.trim() == "progress=end";
+                let seconds = match value_after(line, "out_time_us=") {
+                    Some(micros) => micros.max(0.0) / 1_000_000.0,
+                    None if finished => f64::INFINITY,
+                    None => return,
+                };
+                for ((tracker, config), done) in
+                    self.output_progress.iter().zip(outputs).zip(&mut reported)
+                {
+                    let frames = if seconds.is_finite() {
+                        ((seconds * config.frame_rate) as u64).min(tracker.get_total())
+                    } else {
+                        tracker.get_total()
+                    };
+                    if frames > *done {
+                        tracker.increment_progress(frames - *done);
+                        *done = frames;
+                    }
+                }
+            })
+            .map_err(CompositionError::FFmpeg)
+    }
+
     /// Updates the composition progress.
     fn update_progress(&self, stage: RenderStage) {
         if let Some(progress) = &self.progress {
@@ -464,6 +599,7 @@ impl TrackCompositor {
             // 結果を保存するためのスレッドセーフなコンテナ
             let prepared_results = Mutex::new(Vec::with_capacity(tracks_to_process.len()));
             let temp_files = Mutex::new(Vec::new());
+            let default_ffmpeg = FFmpeg::scoped();
 
             // スレッドプールを構成して並列処理を実行
             rayon::ThreadPoolBuilder::new()
@@ -478,6 +614,7 @@ impl TrackCompositor {
                             if self.is_cancelled() {
                                 return;
                             }
+                            let _default = default_ffmpeg.clone().map(FFmpeg::scoped_default);
 
                             let result = match kind {
                                 TrackKind::Video => {
@@ -756,8 +893,20 @@ impl TrackCompositor {
             ));
         }
 
-        // Composite prepared tracks to produce the final output
-        self.composite_tracks(prepared_tracks, config, &config.output_path)?;
+        // Composite prepared tracks to produce the final output, once more in
+        // software if the hardware encoder or decoder cannot be initialized
+        match self.composite_tracks(&prepared_tracks, config, &config.output_path) {
+            Err(CompositionError::FFmpeg(error))
+                if self
+                    .gpu_accelerator
+                    .as_mut()
+                    .is_some_and(|gpu_acc| gpu_acc.fall_back_to_software(&error)) =>
+            {
+                eprintln!("{error}; retrying without hardware acceleration");
+                self.composite_tracks(&prepared_tracks, config, &config.output_path)?;
+            }
+            result => result?,
+        }
 
         // Update progress
         self.update_progress(RenderStage::Completed);
@@ -765,6 +914,363 @@ impl TrackCompositor {
         Ok(())
     }
 
+    /// Composes the timeline into several output files in one pass.
+    ///
+    /// The tracks are decoded and composited once, at the largest output
+    /// resolution, and the composited video and audio are split into one
+    /// branch per output. Every branch is scaled to the size and frame rate
+    /// of its output and encoded with its own settings by the same `FFmpeg`
+    /// process.
+    ///
+    /// Outputs that need their own pass (two-pass rate control and
+    /// HLS/DASH packaging) cannot be part of a shared pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - The rendering configurations of the outputs
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if all outputs were written, or an error if composition failed.
+    pub fn compose_outputs(&mut self, outputs: &[RenderConfig]) -> Result<()> {
+        if let [config] = outputs {
+            return self.compose(config);
+        }
+        let Some(master) = outputs
+            .iter()
+            .max_by_key(|config| u64::from(config.width) * u64::from(config.height))
+        else {
+            return Err(CompositionError::IncompatibleTracks(
+                "No outputs to render".to_string(),
+            ));
+        };
+
+        for config in outputs {
+            if config.format.is_streaming() || config.rate_control.is_two_pass() {
+                return Err(CompositionError::IncompatibleTracks(format!(
+                    "{} needs its own rendering pass",
+                    config.output_path.display()
+                )));
+            }
+            if config.output_path.as_os_str().is_empty() {
+                return Err(CompositionError::IncompatibleTracks(
+                    "Output path is empty".to_string(),
+                ));
+            }
+            if let Some(parent) = config.output_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        // アルファを残す出力があれば、合成もアルファ付きで行う
+        let mut composite_config = master.clone();
+        if outputs.iter().any(RenderConfig::preserves_alpha) {
+            composite_config.format = OutputFormat::PngSequence;
+            composite_config.image_sequence.alpha = true;
+        }
+
+        self.update_progress(RenderStage::Preparing);
+        let prepared_tracks = self.prepare_tracks(&composite_config)?;
+
+        if self.is_cancelled() {
+            return Err(CompositionError::IncompatibleTracks(
+                "Rendering cancelled".to_string(),
+            ));
+        }
+
+        self.composite_outputs(prepared_tracks, &composite_config, outputs)?;
+        self.update_progress(RenderStage::Completed);
+
+        Ok(())
+    }
+
+    /// Renders the composited timeline at a single point in time.
+    ///
+    /// The frame goes through the same track preparation and filtergraph as
+    /// a full render, restricted to one frame and written as a PNG with an
+    /// alpha channel. Where no video clip covers the time, the frame is fully
+    /// transparent.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Position on the timeline to render
+    /// * `width` - Width of the frame (in pixels)
+    /// * `height` - Height of the frame (in pixels)
+    ///
+    /// # Returns
+    ///
+    /// The rendered frame, or an error if composition failed.
+    pub fn render_frame(
+        &mut self,
+        time: TimePosition,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage> {
+        let covered = self.timeline.get_tracks().iter().any(|track| {
+            track.kind() == TrackKind::Video
+                && track
+                    .get_clips()
+                    .iter()
+                    .any(|clip| clip.position() <= time && time < clip.position() + clip.duration())
+        });
+        if !covered {
+            return Ok(RgbaImage::new(width, height));
+        }
+
+        let work_dir = tempfile::TempDir::new()?;
+        let config = still_frame_config(work_dir.path(), width, height);
+        let frame_duration = Duration::from_frames(1.0, config.frame_rate);
+        let config = config.with_range(Some(time), Some(time + frame_duration));
+
+        self.compose(&config)?;
+
+        let frame_path = work_dir.path().join("frame_0.png");
+        let frame = image::open(&frame_path).map_err(|e| {
+            CompositionError::AssetFileError(format!(
+                "Failed to read rendered frame {}: {e}",
+                frame_path.display()
+            ))
+        })?;
+        Ok(frame.to_rgba8())
+    }
+
+    /// Gets the `FFmpeg` filters the composition of the timeline uses.
+    ///
+    /// The filtergraphs are generated for the tracks of the timeline as they
+    /// would be for rendering, so the filters can be checked against the
+    /// capabilities of `FFmpeg` before any track is prepared.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration
+    ///
+    /// # Returns
+    ///
+    /// The names of the required filters.
+    #[must_use]
+    pub fn required_filters(&self, config: &RenderConfig) -> BTreeSet<String> {
+        let tracks: Vec<PreparedTrack> = self
+            .timeline
+            .get_tracks()
+            .iter()
+            .filter(|track| !track.get_clips().is_empty())
+            .map(|track| PreparedTrack {
+                id: track.id(),
+                kind: track.kind(),
+                file: None,
+                clips: track.get_clips().to_vec(),
+                duration: track
+                    .get_clips()
+                    .iter()
+                    .map(|clip| clip.position() + clip.duration())
+                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                    .unwrap_or_else(TimePosition::zero)
+                    .to_duration(),
+            })
+            .collect();
+        let of_kind = |kind: TrackKind| -> Vec<&PreparedTrack> {
+            tracks.iter().filter(|track| track.kind == kind).collect()
+        };
+        let video_tracks = of_kind(TrackKind::Video);
+        let audio_tracks = of_kind(TrackKind::Audio);
+
+        let mut filters = BTreeSet::new();
+        if config.stems.renders_mix() {
+            if !config.format.is_audio_only() {
+                filters.extend(filter_names(
+                    &self.generate_video_filtergraph(&video_tracks, config),
+                ));
+            }
+            if config.format.supports_audio() && !audio_tracks.is_empty() {
+                filters.extend(filter_names(&self.generate_audio_filtergraph(
+                    &audio_tracks,
+                    video_tracks.len(),
+                    config,
+                )));
+                if let Some(trim) = Self::audio_range_filter(config) {
+                    filters.extend(filter_names(&trim));
+                }
+            }
+        }
+        if config.stems.is_enabled() {
+            filters.extend(filter_names(&self.generate_audio_filtergraph(
+                &audio_tracks,
+                0,
+                config,
+            )));
+            filters.extend(["apad", "atrim", "asetpts", "anullsrc"].map(String::from));
+        }
+        filters
+    }
+
+    /// Renders the audio tracks of the timeline to separate stem files.
+    ///
+    /// Each stem is mixed with the same volume and effect settings as the
+    /// final mix, padded with silence and trimmed to the rendered range, so
+    /// that all stems start together and have the same length. A stem whose
+    /// tracks have no clips in the timeline is written as silence.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration with the stem options
+    ///
+    /// # Returns
+    ///
+    /// The paths of the written stems, in timeline or group order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a stem group refers to a track that is not an
+    /// audio track, the timeline has no audio tracks, or `FFmpeg` fails.
+    pub fn compose_stems(&mut self, config: &RenderConfig) -> Result<Vec<PathBuf>> {
+        let stems = &config.stems;
+        let groups = self.stem_groups(stems)?;
+        if groups.is_empty() {
+            return Err(CompositionError::IncompatibleTracks(
+                "The timeline has no audio tracks to export as stems".to_string(),
+            ));
+        }
+
+        let directory = stems.directory_for(&config.output_path);
+        std::fs::create_dir_all(&directory)?;
+
+        let prepared_tracks = self.prepare_tracks(config)?;
+        self.update_progress(RenderStage::Rendering);
+
+        let ffmpeg = FFmpeg::detect().map_err(CompositionError::FFmpeg)?;
+        let start = config
+            .start_position
+            .unwrap_or_else(TimePosition::zero)
+            .as_seconds();
+        let end = start + self.output_duration(config).as_seconds();
+        let sample_rate = stems.sample_rate.to_string();
+
+        let mut paths = Vec::with_capacity(groups.len());
+        for (name, track_ids) in groups {
+            if self.is_cancelled() {
+                return Err(CompositionError::IncompatibleTracks(
+                    "Composition cancelled".to_string(),
+                ));
+            }
+
+            let tracks: Vec<&PreparedTrack> = track_ids
+                .iter()
+                .filter_map(|id| prepared_tracks.get(id))
+                .filter(|track| track.file.is_some())
+                .collect();
+
+            let mut command = ffmpeg.command();
+            let filtergraph = if tracks.is_empty() {
+                command.input_options(["-f", "lavfi"]);
+                command.add_input(format!("anullsrc=r={sample_rate}:cl=stereo"));
+                format!(
+                    "[0:a] atrim=duration={duration} [stem]",
+                    duration = end - start
+                )
+            } else {
+                for track in &tracks {
+                    if let Some(file) = &track.file {
+                        command.add_input(file.path());
+                    }
+                }
+                // トラックの長さに関わらず、レンダリング範囲ちょうどに揃える
+                format!(
+                    "{mix};[aout] apad,atrim=start={start}:end={end},asetpts=PTS-STARTPTS [stem]",
+                    mix = self.generate_audio_filtergraph(&tracks, 0, config)
+                )
+            };
+
+            let path = stems.stem_path(&directory, &name);
+            command.add_output_option("-filter_complex", filtergraph);
+            command.output_options([
+                "-map",
+                "[stem]",
+                "-c:a",
+                stems.format.encoder(),
+                "-ar",
+                &sample_rate,
+            ]);
+            command.set_output(&path);
+            command.overwrite(true);
+            command.execute().map_err(CompositionError::FFmpeg)?;
+
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+
+    /// Resolves the stems to render into their names and tracks.
+    ///
+    /// Without groups, every audio track becomes a stem named after the
+    /// track; unnamed tracks and repeated names are numbered by track index.
+    ///
+    /// # Arguments
+    ///
+    /// * `stems` - The stem options
+    ///
+    /// # Returns
+    ///
+    /// The name and tracks of every stem.
+    fn stem_groups(&self, stems: &StemOptions) -> Result<Vec<(String, Vec<TrackId>)>> {
+        let tracks = self.timeline.get_tracks();
+
+        if stems.groups.is_empty() {
+            let mut groups: Vec<(String, Vec<TrackId>)> = Vec::new();
+            for (index, track) in tracks.iter().enumerate() {
+                if track.kind() != TrackKind::Audio {
+                    continue;
+                }
+                let name = track.name().trim();
+                let name = if name.is_empty() || groups.iter().any(|(other, _)| other == name) {
+                    format!("track_{index}")
+                } else {
+                    name.to_string()
+                };
+                groups.push((name, vec![track.id()]));
+            }
+            return Ok(groups);
+        }
+
+        stems
+            .groups
+            .iter()
+            .map(|group| {
+                let ids = group
+                    .tracks
+                    .iter()
+                    .map(|&index| match tracks.get(index) {
+                        Some(track) if track.kind() == TrackKind::Audio => Ok(track.id()),
+                        _ => Err(CompositionError::IncompatibleTracks(format!(
+                            "Stem {} refers to track {index}, which is not an audio track",
+                            group.name
+                        ))),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((group.name.clone(), ids))
+            })
+            .collect()
+    }
+
+    /// Gets the crop applied to a prepared video track.
+    ///
+    /// A track is composited as a single input, so its clips are cropped
+    /// only when they all use the same crop.
+    ///
+    /// # Arguments
+    ///
+    /// * `track` - The prepared track
+    ///
+    /// # Returns
+    ///
+    /// The crop shared by the clips, or `None` if the clips are not cropped
+    /// or use different crops.
+    fn track_crop(track: &PreparedTrack) -> Option<CropRect> {
+        let mut crops = track.clips.iter().map(Clip::crop);
+        let first = crops.next()??;
+        crops.all(|crop| crop == Some(first)).then_some(first)
+    }
+
     /// Generates an FFmpeg filter graph for multi-track video composition.
     ///
     /// This function creates a complex filtergraph to layer multiple video tracks
@@ -822,15 +1328,24 @@ impl TrackCompositor {
             ordered_tracks = remaining_tracks;
         }
 
+        // Outputs with an alpha channel keep full chroma so the alpha survives compositing
+        let (track_format, overlay_format) = if config.preserves_alpha() {
+            ("yuva444p", "yuv444")
+        } else {
+            ("yuva420p", "yuv420")
+        };
+
         // Process each track to build filter graph
         for (i, track) in ordered_tracks.iter().enumerate() {
             let input_index = i; // Input index matches FFmpeg input order
 
             // Ensure alpha channel support (for transparent compositing)
-            let format_filter = format!(
-                "[{input_index}:v] format=yuva420p",
-                input_index = input_index
-            );
+            let mut format_filter = format!("[{input_index}:v] format={track_format}");
+
+            // Crop before scaling so the kept area fills the output
+            if let Some(crop) = Self::track_crop(track) {
+                format_filter = format!("{format_filter},{}", crop.filter());
+            }
 
             // Scale to output size
             let scale_filter = format!(
@@ -907,7 +1422,7 @@ impl TrackCompositor {
                 // Use appropriate overlay filter based on selected blend mode
                 let overlay_filter = match blend_mode {
                     BlendMode::Normal => format!(
-                        "{prev}[v{current}] overlay=shortest=1:format=yuv420",
+                        "{prev}[v{current}] overlay=shortest=1:format={overlay_format}",
                         prev = overlay_chain,
                         current = i
                     ),
@@ -1038,6 +1553,8 @@ impl TrackCompositor {
     /// # Arguments
     ///
     /// * `audio_tracks` - The prepared audio tracks to compose
+    /// * `first_input` - FFmpeg input index of the first audio track; the
+    ///   audio inputs follow the video inputs
     /// * `config` - The render configuration
     ///
     /// # Returns
@@ -1046,6 +1563,7 @@ impl TrackCompositor {
     fn generate_audio_filtergraph(
         &self,
         audio_tracks: &[&PreparedTrack],
+        first_input: usize,
         _config: &RenderConfig,
     ) -> String {
         if audio_tracks.is_empty() {
@@ -1061,7 +1579,7 @@ impl TrackCompositor {
 
         // Process each track (order is not important - all mixed)
         for (i, track) in audio_tracks.iter().enumerate() {
-            let input_index = i; // Input index matches FFmpeg input order
+            let input_index = first_input + i; // Input index matches FFmpeg input order
 
             // Apply volume adjustment
             let volume_value = self.get_track_volume(track.id);
@@ -1224,6 +1742,365 @@ impl TrackCompositor {
         1.0
     }
 
+    /// Gets the duration of the rendered range of an output.
+    fn output_duration(&self, config: &RenderConfig) -> Duration {
+        match (config.start_position, config.end_position) {
+            (start, Some(end)) => end - start.unwrap_or_else(TimePosition::zero),
+            (Some(start), None) => self.calculate_timeline_duration() - start.to_duration(),
+            (None, None) => self.calculate_timeline_duration(),
+        }
+    }
+
+    /// Gets the GPU accelerator that encodes the video of an output, if any.
+    fn hardware_encoder(&self, config: &RenderConfig) -> Option<&GpuAccelerator> {
+        let encodes_video = !config.format.is_audio_only();
+        self.gpu_accelerator.as_ref().filter(|gpu_acc| {
+            encodes_video && config.format.image_encoder().is_none() && gpu_acc.is_enabled()
+        })
+    }
+
+    /// Builds the encoder options of an output.
+    ///
+    /// The options cover the video and audio encoders, the encoder threads
+    /// and the pixel format, but not the rendered range, the muxer or the
+    /// two-pass options. Streams left out by `set_rendered_streams` are
+    /// disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    /// * `rate_control` - The resolved rate control of the output
+    ///
+    /// # Returns
+    ///
+    /// A flat list of output arguments.
+    fn encoding_options(&self, config: &RenderConfig, rate_control: RateControl) -> Vec<String> {
+        let mut options = if self.streams.includes_video() {
+            Self::video_encoder_options(config, rate_control, self.hardware_encoder(config))
+        } else {
+            vec!["-vn".to_string()]
+        };
+        if self.streams.includes_audio() {
+            options.extend(Self::audio_encoder_options(config));
+        } else {
+            options.push("-an".to_string());
+        }
+
+        // Limit the encoder threads when requested (e.g. for parallel segments)
+        if let Some(threads) = config.threads {
+            options.push("-threads".to_string());
+            options.push(threads.to_string());
+        }
+
+        options
+    }
+
+    /// Builds the `-ss` and `-t` options restricting media to the rendered
+    /// range of an output.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    ///
+    /// # Returns
+    ///
+    /// A flat list of arguments, empty if the whole timeline is rendered.
+    fn range_options(config: &RenderConfig) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(start) = config.start_position {
+            options.push("-ss".to_string());
+            options.push(start.as_seconds().to_string());
+        }
+        if let Some(end) = config.end_position {
+            let start = config.start_position.unwrap_or_else(TimePosition::zero);
+            options.push("-t".to_string());
+            options.push((end - start).as_seconds().to_string());
+        }
+        options
+    }
+
+    /// Builds the filter cutting the rendered range out of the mixed audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    ///
+    /// # Returns
+    ///
+    /// The filter, or `None` if the whole timeline is rendered.
+    fn audio_range_filter(config: &RenderConfig) -> Option<String> {
+        let start = config.start_position.map(|start| start.as_seconds());
+        let end = config.end_position.map(|end| end.as_seconds());
+        let trim = match (start, end) {
+            (None, None) => return None,
+            (Some(start), None) => format!("atrim=start={start}"),
+            (None, Some(end)) => format!("atrim=end={end}"),
+            (Some(start), Some(end)) => format!("atrim=start={start}:end={end}"),
+        };
+        Some(format!("{trim},asetpts=PTS-STARTPTS"))
+    }
+
+    /// Builds the video encoder options of an output, including the pixel
+    /// format, or `-vn` for audio-only formats.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    /// * `rate_control` - The resolved rate control of the output
+    /// * `hardware_encoder` - The GPU accelerator encoding the video, if any
+    ///
+    /// # Returns
+    ///
+    /// A flat list of output arguments.
+    pub(crate) fn video_encoder_options(
+        config: &RenderConfig,
+        rate_control: RateControl,
+        hardware_encoder: Option<&GpuAccelerator>,
+    ) -> Vec<String> {
+        let mut options: Vec<String> = Vec::new();
+        let push = |options: &mut Vec<String>, option: &str, value: &str| {
+            options.push(option.to_string());
+            options.push(value.to_string());
+        };
+
+        let video_codec = config.video_codec;
+        let image_encoder = config.format.image_encoder();
+
+        // Apply hardware acceleration for encoding if available
+        if config.format.is_audio_only() {
+            options.push("-vn".to_string());
+            return options;
+        } else if let Some(image_encoder) = image_encoder {
+            // 連番画像はフォーマット固有のエンコーダーで 1 フレームずつ書き出す
+            push(&mut options, "-c:v", image_encoder);
+            let pixel_format = if config.image_sequence.alpha {
+                "rgba"
+            } else {
+                "rgb24"
+            };
+            push(&mut options, "-pix_fmt", pixel_format);
+            push(
+                &mut options,
+                "-start_number",
+                &config.image_sequence.start_number.to_string(),
+            );
+            return options;
+        } else if let Some(gpu_acc) = hardware_encoder {
+            // Set hardware encoder and encoder-specific options
+            push(&mut options, "-c:v", gpu_acc.get_encoder_name(video_codec));
+            options.extend(gpu_acc.get_encoder_options(
+                video_codec,
+                config.video_quality,
+                rate_control,
+            ));
+        } else {
+            // Use software encoder with the quality/bitrate options of the codec
+            push(&mut options, "-c:v", video_codec.to_ffmpeg_codec());
+            options.extend(video_codec.encoder_options(rate_control, config.video_quality));
+        }
+
+        // Set pixel format (needed for some encoders)
+        if let Some(pixel_format) = video_codec.pixel_format() {
+            push(&mut options, "-pix_fmt", pixel_format);
+        }
+
+        options
+    }
+
+    /// Builds the audio encoder options of an output, or `-an` for formats
+    /// without audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    ///
+    /// # Returns
+    ///
+    /// A flat list of output arguments.
+    pub(crate) fn audio_encoder_options(config: &RenderConfig) -> Vec<String> {
+        let mut options: Vec<String> = Vec::new();
+        let push = |options: &mut Vec<String>, option: &str, value: &str| {
+            options.push(option.to_string());
+            options.push(value.to_string());
+        };
+
+        if !config.format.supports_audio() {
+            options.push("-an".to_string());
+            return options;
+        }
+
+        let audio_codec = config.audio_codec;
+        push(&mut options, "-c:a", audio_codec.to_ffmpeg_codec());
+
+        // Set audio quality based on codec and quality setting
+        match audio_codec {
+            AudioCodec::AAC | AudioCodec::Opus | AudioCodec::MP3 => {
+                if let Some(bitrate) = audio_codec.bitrate(config.audio_quality) {
+                    push(&mut options, "-b:a", &format!("{bitrate}k"));
+                }
+            }
+            AudioCodec::Vorbis => {
+                let quality = ((config.audio_quality as f32 / 100.0) * 10.0).round() as i32;
+                push(&mut options, "-q:a", &quality.to_string());
+            }
+            AudioCodec::Flac => {
+                // Lossless; quality only trades encoding speed for size
+                let level = ((config.audio_quality as f32 / 100.0) * 12.0).round() as u32;
+                push(&mut options, "-compression_level", &level.to_string());
+            }
+            AudioCodec::Pcm | AudioCodec::Copy => { /* No options needed */ }
+        }
+
+        options
+    }
+
+    /// Composites the prepared tracks once and encodes every output from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `prepared_tracks` - The prepared tracks to composite
+    /// * `composite_config` - The configuration the composite is built with
+    /// * `outputs` - The rendering configurations of the outputs
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if composition was successful, or an error if composition failed.
+    fn composite_outputs(
+        &mut self,
+        prepared_tracks: HashMap<TrackId, PreparedTrack>,
+        composite_config: &RenderConfig,
+        outputs: &[RenderConfig],
+    ) -> Result<()> {
+        self.update_progress(RenderStage::Rendering);
+
+        let ffmpeg = FFmpeg::detect().map_err(CompositionError::FFmpeg)?;
+        let mut command = ffmpeg.command();
+
+        if let Some(gpu_acc) = self.gpu_accelerator.as_ref().filter(|g| g.is_enabled()) {
+            for option in gpu_acc.get_decoder_options() {
+                command.add_input_option(option, "");
+            }
+        }
+
+        // 映像・音声を使う出力が一つでもあれば合成する
+        let video_outputs: Vec<usize> = (0..outputs.len())
+            .filter(|&i| !outputs[i].format.is_audio_only())
+            .collect();
+        let audio_outputs: Vec<usize> = (0..outputs.len())
+            .filter(|&i| outputs[i].format.supports_audio())
+            .collect();
+
+        let video_tracks: Vec<_> = prepared_tracks
+            .values()
+            .filter(|track| {
+                !video_outputs.is_empty() && track.kind == TrackKind::Video && track.file.is_some()
+            })
+            .collect();
+        let audio_tracks: Vec<_> = prepared_tracks
+            .values()
+            .filter(|track| {
+                !audio_outputs.is_empty() && track.kind == TrackKind::Audio && track.file.is_some()
+            })
+            .collect();
+
+        for track in video_tracks.iter().chain(&audio_tracks) {
+            if let Some(file) = &track.file {
+                command.add_input(file.path());
+            }
+        }
+
+        let mut filter_parts = Vec::new();
+        let video_filtergraph = self.generate_video_filtergraph(&video_tracks, composite_config);
+        let audio_filtergraph =
+            self.generate_audio_filtergraph(&audio_tracks, video_tracks.len(), composite_config);
+
+        // 合成結果を出力ごとに分岐させ、それぞれのサイズとフレームレートに揃える
+        let composited_video = Self::composited_video_label(video_tracks.len());
+        if let Some(composited_video) = composited_video
+            .as_ref()
+            .filter(|_| !video_outputs.is_empty())
+        {
+            filter_parts.push(video_filtergraph);
+            let branches: String = video_outputs.iter().map(|i| format!("[s{i}]")).collect();
+            filter_parts.push(format!(
+                "{composited_video} split={count} {branches}",
+                count = video_outputs.len()
+            ));
+            for &i in &video_outputs {
+                let config = &outputs[i];
+                filter_parts.push(format!(
+                    "[s{i}] scale={width}:{height},setsar=1,fps={fps} [ov{i}]",
+                    width = config.width,
+                    height = config.height,
+                    fps = config.frame_rate
+                ));
+            }
+        }
+        let has_audio = !audio_tracks.is_empty() && !audio_outputs.is_empty();
+        if has_audio {
+            filter_parts.push(audio_filtergraph);
+            let branches: String = audio_outputs.iter().map(|i| format!("[oa{i}]")).collect();
+            filter_parts.push(format!(
+                "[aout] asplit={count} {branches}",
+                count = audio_outputs.len()
+            ));
+        }
+
+        if !filter_parts.is_empty() {
+            command.add_output_option("-filter_complex", filter_parts.join(";"));
+        }
+
+        // 最後の出力以外は、出力パスまでをオプションとして並べる
+        let (last, others) = outputs.split_last().ok_or_else(|| {
+            CompositionError::IncompatibleTracks("No outputs to render".to_string())
+        })?;
+        for (i, config) in outputs.iter().enumerate() {
+            if composited_video.is_some() && video_outputs.contains(&i) {
+                command.output_options(["-map".to_string(), format!("[ov{i}]")]);
+            }
+            if has_audio && audio_outputs.contains(&i) {
+                command.output_options(["-map".to_string(), format!("[oa{i}]")]);
+            }
+
+            // 出力ごとに範囲が異なりうるため、ここでは出力側で切り出す
+            let rate_control = config.resolved_rate_control(self.output_duration(config));
+            command.output_options(self.encoding_options(config, rate_control));
+            command.output_options(Self::range_options(config));
+            command.add_output_option("-f", config.format.muxer());
+
+            if i < others.len() {
+                command.output_options([config.output_path.to_string_lossy()]);
+            }
+        }
+
+        command.set_output(&last.output_path);
+        command.overwrite(true);
+        let output_configs: Vec<&RenderConfig> = outputs.iter().collect();
+        self.execute_encode(&command, &output_configs)?;
+
+        Ok(())
+    }
+
+    /// Gets the filtergraph label of the composited video.
+    ///
+    /// The first track is the base layer `[v0]`, and every further track is
+    /// overlaid into `[v<n>]`, see `generate_video_filtergraph`.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_count` - Number of composited video tracks
+    ///
+    /// # Returns
+    ///
+    /// The label, or `None` if there are no video tracks.
+    fn composited_video_label(track_count: usize) -> Option<String> {
+        match track_count {
+            0 => None,
+            1 => Some("[v0]".to_string()),
+            count => Some(format!("[v{count}]")),
+        }
+    }
+
     /// Composite the prepared tracks together.
     ///
     /// # Arguments
@@ -1237,7 +2114,7 @@ impl TrackCompositor {
     /// `Ok(())` if composition was successful, or an error if composition failed.
     fn composite_tracks(
         &mut self,
-        prepared_tracks: HashMap<TrackId, PreparedTrack>,
+        prepared_tracks: &HashMap<TrackId, PreparedTrack>,
         config: &RenderConfig,
         output_path: &Path,
     ) -> Result<()> {
@@ -1258,21 +2135,27 @@ impl TrackCompositor {
             }
         }
 
+        // Audio-only formats drop the video and GIF output drops the audio
+        let encodes_video = !config.format.is_audio_only() && self.streams.includes_video();
+        let encodes_audio = config.format.supports_audio() && self.streams.includes_audio();
+
         // Separate tracks by kind
         let video_tracks: Vec<_> = prepared_tracks
             .values()
-            .filter(|track| track.kind == TrackKind::Video && track.file.is_some())
+            .filter(|track| encodes_video && track.kind == TrackKind::Video && track.file.is_some())
             .collect();
 
         let audio_tracks: Vec<_> = prepared_tracks
             .values()
-            .filter(|track| track.kind == TrackKind::Audio && track.file.is_some())
+            .filter(|track| encodes_audio && track.kind == TrackKind::Audio && track.file.is_some())
             .collect();
 
-        // Add input files
+        // Add input files, seeking the video to the rendered range so that
+        // a segment only decodes its own part of the timeline
+        let range_options = Self::range_options(config);
         for track in &video_tracks {
             if let Some(file) = &track.file {
-                command.add_input(file.path());
+                command.input_with_options(&range_options, file.path());
             }
         }
 
@@ -1284,7 +2167,8 @@ impl TrackCompositor {
 
         // Generate filtergraphs
         let video_filtergraph = self.generate_video_filtergraph(&video_tracks, config);
-        let audio_filtergraph = self.generate_audio_filtergraph(&audio_tracks, config);
+        let audio_filtergraph =
+            self.generate_audio_filtergraph(&audio_tracks, video_tracks.len(), config);
 
         // Add filtergraphs if they're not empty
         let mut filtergraph = String::new();
@@ -1298,6 +2182,11 @@ impl TrackCompositor {
                 filtergraph.push(';');
             }
             filtergraph.push_str(&audio_filtergraph);
+
+            // 音声は先頭から処理し、フェードの位置を保ったまま範囲を切り出す
+            if let Some(trim) = Self::audio_range_filter(config) {
+                filtergraph.push_str(&format!(";[aout] {trim} [arange]"));
+            }
         }
 
         if !filtergraph.is_empty() {
@@ -1305,113 +2194,47 @@ impl TrackCompositor {
         }
 
         // Set output options
-        // Select video and audio codec
         let video_codec = config.video_codec;
-        let audio_codec = config.audio_codec;
-
-        // Apply hardware acceleration for encoding if available
-        if let Some(gpu_acc) = &self.gpu_accelerator {
-            if gpu_acc.is_enabled() {
-                // Set hardware encoder
-                let hw_encoder = gpu_acc.get_encoder_name(video_codec);
-                command.add_output_option("-c:v", &hw_encoder);
-
-                // Add encoder-specific options
-                for option in gpu_acc.get_encoder_options(video_codec, config.video_quality) {
-                    let parts: Vec<&str> = option.splitn(2, ' ').collect();
-                    if parts.len() == 2 {
-                        command.add_output_option(parts[0], parts[1]);
-                    } else if !option.is_empty() {
-                        command.add_output_option(option, "");
-                    }
-                }
-            } else {
-                // Use software encoder
-                command.add_output_option("-c:v", video_codec.to_ffmpeg_codec());
-
-                // Set quality/bitrate based on codec and quality setting
-                match video_codec {
-                    VideoCodec::H264 | VideoCodec::H265 => {
-                        let crf =
-                            30 - ((config.video_quality as f32 / 100.0) * 28.0).round() as i32;
-                        command.add_output_option("-crf", &crf.to_string());
-                        command.add_output_option("-preset", "medium");
-                    }
-                    VideoCodec::VP9 => {
-                        let bitrate = ((config.video_quality as f32 / 100.0) * 8000.0 + 2000.0)
-                            .round() as u32;
-                        command.add_output_option("-b:v", &format!("{}k", bitrate));
-                    }
-                    VideoCodec::AV1 => {
-                        let crf =
-                            30 - ((config.video_quality as f32 / 100.0) * 25.0).round() as i32;
-                        command.add_output_option("-crf", &crf.to_string());
-                    }
-                    VideoCodec::Copy => { /* No options needed for copy */ }
-                }
-            }
+        let image_encoder = config.format.image_encoder();
+        let rate_control = config.resolved_rate_control(self.output_duration(config));
+        let hw_encoder = self.hardware_encoder(config);
+        command.output_options(self.encoding_options(config, rate_control));
+
+        // Two-pass encoding first analyses the video into a statistics file,
+        // which the second pass uses to distribute the bitrate. Hardware
+        // encoders handle two-pass modes through their own options.
+        let pass_log_dir = if encodes_video
+            && image_encoder.is_none()
+            && rate_control.is_two_pass()
+            && hw_encoder.is_none()
+        {
+            Some(tempfile::TempDir::new().map_err(CompositionError::IntermediateFileError)?)
         } else {
-            // Use software encoder
-            command.add_output_option("-c:v", video_codec.to_ffmpeg_codec());
-
-            // Set quality/bitrate based on codec and quality setting
-            match video_codec {
-                VideoCodec::H264 | VideoCodec::H265 => {
-                    let crf = 30 - ((config.video_quality as f32 / 100.0) * 28.0).round() as i32;
-                    command.add_output_option("-crf", &crf.to_string());
-                    command.add_output_option("-preset", "medium");
-                }
-                VideoCodec::VP9 => {
-                    let bitrate =
-                        ((config.video_quality as f32 / 100.0) * 8000.0 + 2000.0).round() as u32;
-                    command.add_output_option("-b:v", &format!("{}k", bitrate));
-                }
-                VideoCodec::AV1 => {
-                    let crf = 30 - ((config.video_quality as f32 / 100.0) * 25.0).round() as i32;
-                    command.add_output_option("-crf", &crf.to_string());
-                }
-                VideoCodec::Copy => { /* No options needed for copy */ }
-            }
-        }
-
-        // Audio codec options
-        command.add_output_option("-c:a", audio_codec.to_ffmpeg_codec());
-
-        // Set audio quality based on codec and quality setting
-        match audio_codec {
-            AudioCodec::AAC => {
-                let bitrate = ((config.audio_quality as f32 / 100.0) * 320.0 + 64.0).round() as u32;
-                command.add_output_option("-b:a", &format!("{}k", bitrate));
-            }
-            AudioCodec::Opus => {
-                let bitrate = ((config.audio_quality as f32 / 100.0) * 256.0 + 32.0).round() as u32;
-                command.add_output_option("-b:a", &format!("{}k", bitrate));
-            }
-            AudioCodec::MP3 => {
-                let bitrate = ((config.audio_quality as f32 / 100.0) * 320.0 + 64.0).round() as u32;
-                command.add_output_option("-b:a", &format!("{}k", bitrate));
-            }
-            AudioCodec::Vorbis => {
-                let quality = ((config.audio_quality as f32 / 100.0) * 10.0).round() as i32;
-                command.add_output_option("-q:a", &quality.to_string());
-            }
-            AudioCodec::Copy => { /* No options needed for copy */ }
+            None
+        };
+        if let Some(pass_log_dir) = &pass_log_dir {
+            let log_prefix = pass_log_dir.path().join("pass");
+            let null_device = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+            let mut first_pass = command.clone();
+            first_pass.output_options(video_codec.pass_options(1, &log_prefix));
+            first_pass.output_options(["-an", "-f", "null"]);
+            first_pass.set_output(null_device);
+            first_pass.overwrite(true);
+            first_pass.execute().map_err(CompositionError::FFmpeg)?;
+
+            command.output_options(video_codec.pass_options(2, &log_prefix));
         }
 
         // Set output format based on container
-        command.add_output_option("-f", config.format.extension());
-
-        // Set pixel format (needed for some encoders)
-        if !matches!(video_codec, VideoCodec::Copy) {
-            command.add_output_option("-pix_fmt", "yuv420p");
-        }
+        command.add_output_option("-f", config.format.muxer());
 
         // Set output path and overwrite flag
         command.set_output(output_path);
         command.overwrite(true);
 
         // Execute the command
-        command.execute().map_err(CompositionError::FFmpeg)?;
+        self.execute_encode(&command, &[config])?;
 
         Ok(())
     }
@@ -1765,4 +2588,216 @@ mod tests {
         let volume = compositor.get_track_volume(track_id);
         assert_eq!(volume, 1.0, "Should return 1.0 volume as default");
     }
+
+    #[test]
+    fn test_render_frame_without_video_is_transparent() {
+        let (timeline, _) = create_test_timeline_with_track("Empty Track", false);
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+
+        let frame = compositor
+            .render_frame(TimePosition::from_seconds(1.0), 64, 36)
+            .unwrap();
+        assert_eq!(frame.dimensions(), (64, 36));
+        assert!(frame.pixels().all(|pixel| pixel.0[3] == 0));
+    }
+
+    #[test]
+    fn test_compose_range_seeks_inputs_instead_of_output() {
+        let mut timeline = Timeline::new();
+        for kind in [TrackKind::Video, TrackKind::Audio] {
+            let track = timeline.add_track(kind);
+            let clip = Clip::new(
+                crate::project::ClipId::new(),
+                crate::project::AssetId::new(),
+                TimePosition::from_seconds(0.0),
+                Duration::from_seconds(60.0),
+                TimePosition::from_seconds(0.0),
+                TimePosition::from_seconds(60.0),
+            );
+            timeline.add_clip(track, clip).unwrap();
+        }
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let config = RenderConfig::new(output_dir.path().join("out.mp4")).with_range(
+            Some(TimePosition::from_seconds(30.0)),
+            Some(TimePosition::from_seconds(40.0)),
+        );
+
+        let mut compositor = TrackCompositor::new(timeline.clone(), Vec::new());
+        compositor.compose(&config).unwrap();
+        let call = &executor.ffmpeg_calls()[0];
+        // 映像入力だけをシークし、出力側では切り出さない
+        let first_input = call.args.iter().position(|arg| arg == "-i").unwrap();
+        assert_eq!(
+            call.args[first_input - 4..first_input],
+            ["-ss", "30", "-t", "10"]
+        );
+        assert_eq!(call.option_values("-ss").len(), 1);
+        assert!(
+            call.option_value("-filter_complex")
+                .unwrap()
+                .contains("[aout] atrim=start=30:end=40,asetpts=PTS-STARTPTS [arange]")
+        );
+
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+        compositor.set_rendered_streams(RenderedStreams::VideoOnly);
+        compositor.compose(&config).unwrap();
+        let call = &executor.ffmpeg_calls()[1];
+        assert!(call.has_arg("-an"));
+        assert!(!call.has_arg("-c:a"));
+        assert_eq!(call.option_values("-i").len(), 1);
+    }
+
+    #[test]
+    fn test_compose_labels_audio_inputs_after_video_inputs() {
+        let mut timeline = Timeline::new();
+        for kind in [TrackKind::Video, TrackKind::Audio] {
+            let track = timeline.add_track(kind);
+            let clip = Clip::new(
+                crate::project::ClipId::new(),
+                crate::project::AssetId::new(),
+                TimePosition::from_seconds(0.0),
+                Duration::from_seconds(10.0),
+                TimePosition::from_seconds(0.0),
+                TimePosition::from_seconds(10.0),
+            );
+            timeline.add_clip(track, clip).unwrap();
+        }
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let config = RenderConfig::new(output_dir.path().join("out.mp4"));
+
+        let mut compositor = TrackCompositor::new(timeline.clone(), Vec::new());
+        compositor.compose(&config).unwrap();
+        // 複数出力の共有パスも同じ入力順になる
+        let outputs = [
+            config.clone(),
+            RenderConfig::new(output_dir.path().join("small.mp4")).with_resolution(640, 360),
+        ];
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+        compositor.compose_outputs(&outputs).unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 2);
+        for call in &calls {
+            // 入力 0 は映像トラック、音声トラックは入力 1 になる
+            assert_eq!(call.option_values("-i").len(), 2);
+            let filter = call.option_value("-filter_complex").unwrap();
+            assert!(filter.contains("[1:a] aformat"), "{filter}");
+            assert!(!filter.contains("[0:a]"), "{filter}");
+        }
+    }
+
+    #[test]
+    fn test_compose_crops_clips_before_scaling() {
+        let mut timeline = Timeline::new();
+        let track = timeline.add_track(TrackKind::Video);
+        let crop = CropRect {
+            width: 1920,
+            height: 800,
+            x: 0,
+            y: 140,
+        };
+        let clip = Clip::new(
+            crate::project::ClipId::new(),
+            crate::project::AssetId::new(),
+            TimePosition::from_seconds(0.0),
+            Duration::from_seconds(10.0),
+            TimePosition::from_seconds(0.0),
+            TimePosition::from_seconds(10.0),
+        )
+        .with_crop(Some(crop));
+        timeline.add_clip(track, clip).unwrap();
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        let mut compositor = TrackCompositor::new(timeline.clone(), Vec::new());
+        compositor
+            .compose(&RenderConfig::new(output_dir.path().join("out.mp4")))
+            .unwrap();
+        let outputs = [
+            RenderConfig::new(output_dir.path().join("large.mp4")),
+            RenderConfig::new(output_dir.path().join("small.mp4")).with_resolution(640, 360),
+        ];
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+        compositor.compose_outputs(&outputs).unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 2);
+        for call in &calls {
+            let filtergraph = call.option_value("-filter_complex").unwrap();
+            let crop_at = filtergraph.find("crop=1920:800:0:140").unwrap();
+            assert!(crop_at < filtergraph.find("scale=").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_compose_outputs_reports_progress_per_output() {
+        use crate::ffmpeg::ExecutionOutput;
+
+        let mut timeline = Timeline::new();
+        let track = timeline.add_track(TrackKind::Video);
+        let clip = Clip::new(
+            crate::project::ClipId::new(),
+            crate::project::AssetId::new(),
+            TimePosition::from_seconds(0.0),
+            Duration::from_seconds(10.0),
+            TimePosition::from_seconds(0.0),
+            TimePosition::from_seconds(10.0),
+        );
+        timeline.add_clip(track, clip).unwrap();
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        executor.respond(
+            "-progress",
+            ExecutionOutput::success(Vec::new())
+                .with_stderr("out_time_us=4000000\nprogress=continue\n"),
+        );
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let outputs = [
+            RenderConfig::new(output_dir.path().join("large.mp4")).with_frame_rate(25.0),
+            RenderConfig::new(output_dir.path().join("small.mp4"))
+                .with_resolution(640, 360)
+                .with_frame_rate(50.0),
+        ];
+        let trackers = [SharedProgressTracker::new(), SharedProgressTracker::new()];
+        trackers[0].set_total(250);
+        trackers[1].set_total(500);
+
+        let mut compositor = TrackCompositor::new(timeline.clone(), Vec::new());
+        compositor.set_output_progress_trackers(trackers.to_vec());
+        compositor.compose_outputs(&outputs).unwrap();
+        // 4 秒分を出力ごとのフレームレートで換算する
+        assert_eq!(trackers[0].get_progress(), 100);
+        assert_eq!(trackers[1].get_progress(), 200);
+
+        executor.respond(
+            "-progress",
+            ExecutionOutput::success(Vec::new()).with_stderr("out_time_us=9990000\nprogress=end\n"),
+        );
+        let trackers = [SharedProgressTracker::new(), SharedProgressTracker::new()];
+        trackers[0].set_total(250);
+        trackers[1].set_total(500);
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+        compositor.set_output_progress_trackers(trackers.to_vec());
+        compositor.compose_outputs(&outputs).unwrap();
+        assert_eq!(trackers[0].get_progress(), 250);
+        assert_eq!(trackers[1].get_progress(), 500);
+    }
+
+    #[test]
+    fn test_compose_outputs_rejects_two_pass_output() {
+        let (timeline, _) = create_test_timeline_with_track("Video Track", true);
+        let mut compositor = TrackCompositor::new(timeline, Vec::new());
+
+        let master = RenderConfig::new(std::path::PathBuf::from("master.mp4"));
+        let web = RenderConfig::new(std::path::PathBuf::from("web.mp4"))
+            .with_rate_control(RateControl::TwoPassBitrate { bitrate: 2000 });
+
+        let error = compositor.compose_outputs(&[master, web]).unwrap_err();
+        assert!(error.to_string().contains("own rendering pass"));
+    }
 }
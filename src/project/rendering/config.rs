@@ -2,27 +2,50 @@
 ///
 /// This module defines the configuration options for rendering a timeline
 /// to a video file, including format selection, codec options, and quality settings.
-use crate::utility::time::TimePosition;
+use crate::ffmpeg::{Capabilities, ComponentKind, FFmpeg};
+use crate::project::image_sequence::FramePattern;
+use crate::project::rendering::stems::StemOptions;
+use crate::project::rendering::streaming::{self, StreamingOptions};
+use crate::project::rendering::verification::VerificationOptions;
+use crate::utility::time::{Duration, TimePosition};
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 /// Video codec types supported by the renderer.
 ///
 /// This enum represents the different video codecs that can be used
 /// for rendering video tracks. Each variant corresponds to a specific
 /// codec implementation in FFmpeg.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+// Variant names are accepted as aliases so queue files written before the
+// lowercase names still load.
+#[serde(rename_all = "lowercase")]
 pub enum VideoCodec {
     /// Copy video stream without re-encoding
+    #[serde(alias = "Copy")]
     Copy,
     /// H.264/AVC codec
+    #[serde(alias = "H264")]
     H264,
     /// H.265/HEVC codec
+    #[serde(alias = "H265")]
     H265,
     /// VP9 codec
+    #[serde(alias = "VP9")]
     VP9,
     /// AV1 codec
+    #[serde(alias = "AV1")]
     AV1,
+    /// Apple ProRes 422 HQ codec
+    ProRes,
+    /// Apple ProRes 4444 codec with alpha channel
+    ProRes4444,
+    /// Avid DNxHR HQ codec
+    DnxHr,
+    /// FFV1 lossless codec
+    Ffv1,
 }
 
 impl Default for VideoCodec {
@@ -44,6 +67,184 @@ impl VideoCodec {
             Self::H265 => "libx265",
             Self::VP9 => "libvpx-vp9",
             Self::AV1 => "libaom-av1",
+            Self::ProRes | Self::ProRes4444 => "prores_ks",
+            Self::DnxHr => "dnxhd",
+            Self::Ffv1 => "ffv1",
+        }
+    }
+
+    /// Gets the pixel format to encode with.
+    ///
+    /// # Returns
+    ///
+    /// The FFmpeg pixel format name, or `None` when the stream is copied or
+    /// the encoder keeps the composited pixel format (lossless FFV1).
+    #[must_use]
+    pub fn pixel_format(&self) -> Option<&'static str> {
+        match self {
+            Self::Copy | Self::Ffv1 => None,
+            Self::ProRes => Some("yuv422p10le"),
+            Self::ProRes4444 => Some("yuva444p10le"),
+            Self::DnxHr => Some("yuv422p"),
+            Self::H264 | Self::H265 | Self::VP9 | Self::AV1 => Some("yuv420p"),
+        }
+    }
+
+    /// Determines whether the codec supports explicit rate-control modes.
+    ///
+    /// Intermediate and lossless codecs encode at a fixed rate given by their
+    /// profile and only accept `RateControl::Quality`.
+    #[must_use]
+    pub fn supports_rate_control(&self) -> bool {
+        matches!(self, Self::H264 | Self::H265 | Self::VP9 | Self::AV1)
+    }
+
+    /// Determines whether the codec encodes an alpha channel.
+    #[must_use]
+    pub fn has_alpha(&self) -> bool {
+        *self == Self::ProRes4444
+    }
+
+    /// Gets the software encoder options for a rate-control mode.
+    ///
+    /// A target file size must be resolved into a bitrate with
+    /// `RateControl::resolve` first; an unresolved size falls back to the
+    /// quality setting. The pass-specific options of two-pass encoding are
+    /// added separately with `pass_options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_control` - The rate-control mode
+    /// * `quality` - Video quality (1-100), used by `RateControl::Quality`
+    ///
+    /// # Returns
+    ///
+    /// A flat list of FFmpeg output arguments.
+    #[must_use]
+    pub fn encoder_options(&self, rate_control: RateControl, quality: u32) -> Vec<String> {
+        let mut options: Vec<String> = Vec::new();
+        let mut push = |option: &str, value: String| {
+            options.push(option.to_string());
+            options.push(value);
+        };
+
+        match self {
+            Self::Copy => return options,
+            // Intermediate and lossless codecs have fixed-rate profiles and no rate control
+            Self::ProRes => {
+                push("-profile:v", "3".to_string());
+                return options;
+            }
+            Self::ProRes4444 => {
+                push("-profile:v", "4".to_string());
+                push("-alpha_bits", "16".to_string());
+                return options;
+            }
+            Self::DnxHr => {
+                push("-profile:v", "dnxhr_hq".to_string());
+                return options;
+            }
+            Self::Ffv1 => {
+                // 全フレームをキーフレームにし、スライスごとにCRCを付ける
+                push("-level", "3".to_string());
+                push("-g", "1".to_string());
+                push("-slicecrc", "1".to_string());
+                return options;
+            }
+            Self::H264 | Self::H265 | Self::VP9 | Self::AV1 => {}
+        }
+
+        match rate_control {
+            RateControl::Quality | RateControl::TwoPassSize { .. } => match self {
+                Self::H264 | Self::H265 => {
+                    let crf = 30 - ((quality as f32 / 100.0) * 28.0).round() as i32;
+                    push("-crf", crf.to_string());
+                }
+                Self::VP9 => {
+                    let bitrate = ((quality as f32 / 100.0) * 8000.0 + 2000.0).round() as u32;
+                    push("-b:v", format!("{bitrate}k"));
+                }
+                _ => {
+                    let crf = 30 - ((quality as f32 / 100.0) * 25.0).round() as i32;
+                    push("-crf", crf.to_string());
+                }
+            },
+            RateControl::Crf { value } => {
+                push("-crf", value.to_string());
+                // libvpx/libaom は -b:v 0 で固定品質モードになる
+                if matches!(self, Self::VP9 | Self::AV1) {
+                    push("-b:v", "0".to_string());
+                }
+            }
+            RateControl::Cbr { bitrate } => {
+                push("-b:v", format!("{bitrate}k"));
+                push("-minrate", format!("{bitrate}k"));
+                push("-maxrate", format!("{bitrate}k"));
+                push("-bufsize", format!("{}k", bitrate.saturating_mul(2)));
+            }
+            RateControl::CappedVbr {
+                bitrate,
+                max_bitrate,
+            } => {
+                push("-b:v", format!("{bitrate}k"));
+                push("-maxrate", format!("{max_bitrate}k"));
+                push("-bufsize", format!("{}k", max_bitrate.saturating_mul(2)));
+            }
+            RateControl::TwoPassBitrate { bitrate } => {
+                push("-b:v", format!("{bitrate}k"));
+            }
+        }
+
+        if matches!(self, Self::H264 | Self::H265) {
+            push("-preset", "medium".to_string());
+        }
+
+        options
+    }
+
+    /// Gets the options for one pass of a two-pass encode.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - The pass number (1 or 2)
+    /// * `log_prefix` - Path prefix of the pass statistics files
+    ///
+    /// # Returns
+    ///
+    /// A flat list of FFmpeg output arguments.
+    #[must_use]
+    pub fn pass_options(&self, pass: u8, log_prefix: &Path) -> Vec<String> {
+        match self {
+            // libx265 は -pass を受け付けないため x265-params で指定する
+            Self::H265 => vec![
+                "-x265-params".to_string(),
+                format!("pass={pass}:stats={}.log", log_prefix.display()),
+            ],
+            _ => vec![
+                "-pass".to_string(),
+                pass.to_string(),
+                "-passlogfile".to_string(),
+                log_prefix.display().to_string(),
+            ],
+        }
+    }
+}
+
+impl FromStr for VideoCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "copy" => Ok(Self::Copy),
+            "h264" => Ok(Self::H264),
+            "h265" | "hevc" => Ok(Self::H265),
+            "vp9" => Ok(Self::VP9),
+            "av1" => Ok(Self::AV1),
+            "prores" => Ok(Self::ProRes),
+            "prores4444" => Ok(Self::ProRes4444),
+            "dnxhr" => Ok(Self::DnxHr),
+            "ffv1" => Ok(Self::Ffv1),
+            _ => Err(format!("Unknown video codec: {s}")),
         }
     }
 }
@@ -53,18 +254,28 @@ impl VideoCodec {
 /// This enum represents the different audio codecs that can be used
 /// for rendering audio tracks. Each variant corresponds to a specific
 /// codec implementation in FFmpeg.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AudioCodec {
     /// Copy audio stream without re-encoding
+    #[serde(alias = "Copy")]
     Copy,
     /// AAC codec
+    #[serde(alias = "AAC")]
     AAC,
     /// Opus codec
+    #[serde(alias = "Opus")]
     Opus,
     /// MP3 codec
+    #[serde(alias = "MP3")]
     MP3,
     /// Vorbis codec
+    #[serde(alias = "Vorbis")]
     Vorbis,
+    /// Uncompressed 16-bit PCM
+    Pcm,
+    /// FLAC lossless codec
+    Flac,
 }
 
 impl Default for AudioCodec {
@@ -86,6 +297,46 @@ impl AudioCodec {
             Self::Opus => "libopus",
             Self::MP3 => "libmp3lame",
             Self::Vorbis => "libvorbis",
+            Self::Pcm => "pcm_s16le",
+            Self::Flac => "flac",
+        }
+    }
+
+    /// Gets the bitrate the encoder targets for a quality level.
+    ///
+    /// # Arguments
+    ///
+    /// * `quality` - Audio quality (1-100)
+    ///
+    /// # Returns
+    ///
+    /// The bitrate in kbit/s, or `None` if the codec is not bitrate-driven.
+    #[must_use]
+    pub fn bitrate(&self, quality: u32) -> Option<u32> {
+        let quality = quality as f32 / 100.0;
+        match self {
+            Self::AAC | Self::MP3 => Some((quality * 320.0 + 64.0).round() as u32),
+            Self::Opus => Some((quality * 256.0 + 32.0).round() as u32),
+            // 48 kHz ステレオ 16 ビット
+            Self::Pcm => Some(1536),
+            Self::Vorbis | Self::Flac | Self::Copy => None,
+        }
+    }
+}
+
+impl FromStr for AudioCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "copy" => Ok(Self::Copy),
+            "aac" => Ok(Self::AAC),
+            "opus" => Ok(Self::Opus),
+            "mp3" => Ok(Self::MP3),
+            "vorbis" => Ok(Self::Vorbis),
+            "pcm" => Ok(Self::Pcm),
+            "flac" => Ok(Self::Flac),
+            _ => Err(format!("Unknown audio codec: {s}")),
         }
     }
 }
@@ -94,23 +345,32 @@ impl AudioCodec {
 ///
 /// This enum represents different hardware acceleration methods that can
 /// be used to accelerate video processing with hardware support.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HardwareAccelType {
     /// No hardware acceleration, use CPU only
+    #[serde(alias = "None")]
     None,
     /// NVIDIA GPU acceleration (NVENC/NVDEC)
+    #[serde(alias = "Nvidia")]
     Nvidia,
     /// AMD GPU acceleration (AMF)
+    #[serde(alias = "Amd")]
     Amd,
     /// Intel Quick Sync acceleration
+    #[serde(alias = "Intel")]
     Intel,
     /// Video Acceleration API (for Linux)
+    #[serde(alias = "Vaapi")]
     Vaapi,
     /// DirectX Video Acceleration (for Windows)
+    #[serde(alias = "Dxva2")]
     Dxva2,
     /// Video Toolbox (for macOS)
+    #[serde(alias = "VideoToolbox")]
     VideoToolbox,
     /// Auto-detect the best available hardware acceleration
+    #[serde(alias = "Auto")]
     Auto,
 }
 
@@ -169,10 +429,65 @@ impl HardwareAccelType {
 
     /// Detects available hardware acceleration methods on the current system.
     ///
+    /// The methods are taken from the capabilities of the installed `FFmpeg`.
+    /// If `FFmpeg` cannot be queried, they are guessed from the devices and
+    /// environment of the system.
+    ///
     /// # Returns
     ///
     /// A vector of available hardware acceleration types.
     pub fn detect_available() -> Vec<Self> {
+        FFmpeg::detect()
+            .and_then(|ffmpeg| ffmpeg.capabilities())
+            .map_or_else(|_| Self::guess_available(), |c| Self::supported_by(&c))
+    }
+
+    /// Gets the hardware acceleration methods an `FFmpeg` build supports.
+    ///
+    /// A method is supported if `FFmpeg` lists it as a hardware decoder, or
+    /// if it has the method's H.264 hardware encoder (AMF only encodes).
+    ///
+    /// # Arguments
+    ///
+    /// * `capabilities` - The capabilities of the `FFmpeg` build
+    ///
+    /// # Returns
+    ///
+    /// The supported types, including `None` and, if any method is
+    /// supported, `Auto`.
+    #[must_use]
+    pub fn supported_by(capabilities: &Capabilities) -> Vec<Self> {
+        let mut available = vec![Self::None];
+        available.extend(
+            [
+                Self::Nvidia,
+                Self::Amd,
+                Self::Intel,
+                Self::Vaapi,
+                Self::Dxva2,
+                Self::VideoToolbox,
+            ]
+            .into_iter()
+            .filter(|accel| {
+                accel
+                    .to_ffmpeg_hwaccel()
+                    .is_some_and(|hwaccel| capabilities.has(ComponentKind::HwAccel, hwaccel))
+                    || accel
+                        .get_hw_encoder_name(VideoCodec::H264)
+                        .is_some_and(|encoder| capabilities.has(ComponentKind::Encoder, encoder))
+            }),
+        );
+
+        if available.len() > 1 {
+            available.push(Self::Auto);
+        }
+
+        available
+    }
+
+    /// Guesses the available hardware acceleration methods from the devices
+    /// and environment of the system.
+    fn guess_available() -> Vec<Self> {
         let mut available = vec![Self::None]; // None is always available
 
         // Check for NVIDIA GPUs (simplified - in real impl would check for CUDA-capable devices)
@@ -212,18 +527,44 @@ impl HardwareAccelType {
 }
 
 /// Output format options for rendering.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     /// MP4 container format (default).
+    #[serde(alias = "MP4")]
     MP4,
     /// WebM container format.
+    #[serde(alias = "WebM")]
     WebM,
     /// MOV container format.
+    #[serde(alias = "MOV")]
     MOV,
     /// MKV container format.
+    #[serde(alias = "MKV")]
     MKV,
     /// GIF format (video only, no audio).
+    #[serde(alias = "GIF")]
     GIF,
+    /// MXF container format for broadcast delivery.
+    MXF,
+    /// WAV audio-only format.
+    WAV,
+    /// FLAC audio-only format.
+    FLAC,
+    /// MP3 audio-only format.
+    MP3,
+    /// M4A (AAC in MP4) audio-only format.
+    M4A,
+    /// HLS adaptive streaming (master playlist with one rendition per ladder rung).
+    HLS,
+    /// MPEG-DASH adaptive streaming (MPD with one representation per ladder rung).
+    DASH,
+    /// Numbered PNG frames (the output path is a pattern such as `frame_%05d.png`).
+    #[serde(rename = "png_sequence")]
+    PngSequence,
+    /// Numbered TIFF frames (the output path is a pattern such as `frame_%05d.tif`).
+    #[serde(rename = "tiff_sequence")]
+    TiffSequence,
 }
 
 impl Default for OutputFormat {
@@ -242,12 +583,341 @@ impl OutputFormat {
             Self::MOV => "mov",
             Self::MKV => "mkv",
             Self::GIF => "gif",
+            Self::MXF => "mxf",
+            Self::WAV => "wav",
+            Self::FLAC => "flac",
+            Self::MP3 => "mp3",
+            Self::M4A => "m4a",
+            Self::HLS => "m3u8",
+            Self::DASH => "mpd",
+            Self::PngSequence => "png",
+            Self::TiffSequence => "tif",
+        }
+    }
+
+    /// Gets the name of the FFmpeg muxer for this format.
+    #[must_use]
+    pub fn muxer(&self) -> &'static str {
+        match self {
+            Self::MKV => "matroska",
+            // M4A は MP4 の音声用プロファイル
+            Self::M4A => "ipod",
+            Self::HLS => "hls",
+            Self::DASH => "dash",
+            Self::PngSequence | Self::TiffSequence => "image2",
+            _ => self.extension(),
+        }
+    }
+
+    /// Determines whether the format stores only audio.
+    #[must_use]
+    pub fn is_audio_only(&self) -> bool {
+        matches!(self, Self::WAV | Self::FLAC | Self::MP3 | Self::M4A)
+    }
+
+    /// Determines whether the format is an adaptive streaming format.
+    #[must_use]
+    pub fn is_streaming(&self) -> bool {
+        matches!(self, Self::HLS | Self::DASH)
+    }
+
+    /// Determines whether the format writes numbered image files.
+    #[must_use]
+    pub fn is_image_sequence(&self) -> bool {
+        matches!(self, Self::PngSequence | Self::TiffSequence)
+    }
+
+    /// Gets the FFmpeg image encoder of an image sequence format.
+    ///
+    /// # Returns
+    ///
+    /// The encoder name, or `None` if the format is not an image sequence.
+    #[must_use]
+    pub fn image_encoder(&self) -> Option<&'static str> {
+        match self {
+            Self::PngSequence => Some("png"),
+            Self::TiffSequence => Some("tiff"),
+            _ => None,
+        }
+    }
+
+    /// Determines whether the format can store an audio stream.
+    #[must_use]
+    pub fn supports_audio(&self) -> bool {
+        *self != Self::GIF && !self.is_image_sequence()
+    }
+
+    /// Determines whether the format can store a video codec.
+    ///
+    /// Audio-only formats store no video, so every codec is accepted there.
+    /// Image sequences always use the image encoder of the format and ignore
+    /// the video codec.
+    #[must_use]
+    pub fn supports_video_codec(&self, codec: VideoCodec) -> bool {
+        use VideoCodec::{AV1, Copy, DnxHr, H264, H265, ProRes, ProRes4444, VP9};
+
+        match self {
+            Self::MP4 => matches!(codec, Copy | H264 | H265 | VP9 | AV1),
+            Self::WebM => matches!(codec, Copy | VP9 | AV1),
+            Self::MOV => matches!(codec, Copy | H264 | H265 | ProRes | ProRes4444 | DnxHr),
+            Self::MXF => matches!(codec, Copy | H264 | ProRes | DnxHr),
+            // ラダーは再エンコードするため、ストリームコピーはできない
+            Self::HLS => matches!(codec, H264 | H265),
+            Self::DASH => matches!(codec, H264 | H265 | VP9 | AV1),
+            Self::MKV | Self::GIF => true,
+            Self::WAV | Self::FLAC | Self::MP3 | Self::M4A => true,
+            Self::PngSequence | Self::TiffSequence => true,
+        }
+    }
+
+    /// Determines whether the format can store an audio codec.
+    ///
+    /// Formats without audio accept every codec because no audio is written.
+    #[must_use]
+    pub fn supports_audio_codec(&self, codec: AudioCodec) -> bool {
+        use AudioCodec::{AAC, Copy, Flac, MP3, Opus, Pcm, Vorbis};
+
+        match self {
+            Self::MP4 => matches!(codec, Copy | AAC | MP3 | Opus | Flac),
+            Self::WebM => matches!(codec, Copy | Opus | Vorbis),
+            Self::MOV => matches!(codec, Copy | AAC | MP3 | Pcm),
+            Self::MXF => matches!(codec, Copy | Pcm),
+            Self::HLS => matches!(codec, AAC),
+            Self::DASH => matches!(codec, AAC | Opus),
+            Self::MKV | Self::GIF | Self::PngSequence | Self::TiffSequence => true,
+            Self::WAV => matches!(codec, Copy | Pcm),
+            Self::FLAC => matches!(codec, Copy | Flac),
+            Self::MP3 => matches!(codec, Copy | MP3),
+            Self::M4A => matches!(codec, Copy | AAC),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mp4" => Ok(Self::MP4),
+            "webm" => Ok(Self::WebM),
+            "mov" => Ok(Self::MOV),
+            "mkv" => Ok(Self::MKV),
+            "gif" => Ok(Self::GIF),
+            "mxf" => Ok(Self::MXF),
+            "wav" => Ok(Self::WAV),
+            "flac" => Ok(Self::FLAC),
+            "mp3" => Ok(Self::MP3),
+            "m4a" => Ok(Self::M4A),
+            "hls" | "m3u8" => Ok(Self::HLS),
+            "dash" | "mpd" => Ok(Self::DASH),
+            "png-sequence" | "png_sequence" => Ok(Self::PngSequence),
+            "tiff-sequence" | "tiff_sequence" => Ok(Self::TiffSequence),
+            _ => Err(format!("Unknown output format: {s}")),
+        }
+    }
+}
+
+/// Rate-control mode used by the video encoder.
+///
+/// Bitrates are given in kbit/s. CRF values use the native scale of the
+/// encoder (0-51 for H.264/H.265 and the hardware encoders, 0-63 for VP9 and
+/// AV1), where lower values give higher quality.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RateControl {
+    /// Derive the rate control from `video_quality` (default).
+    #[default]
+    Quality,
+    /// Constant quality with the given rate factor.
+    Crf {
+        /// Constant rate factor.
+        value: u32,
+    },
+    /// Constant bitrate.
+    Cbr {
+        /// Target bitrate (kbit/s).
+        bitrate: u32,
+    },
+    /// Variable bitrate with an upper limit.
+    CappedVbr {
+        /// Average bitrate (kbit/s).
+        bitrate: u32,
+        /// Maximum bitrate (kbit/s).
+        max_bitrate: u32,
+    },
+    /// Two-pass encoding targeting an average bitrate.
+    TwoPassBitrate {
+        /// Average bitrate (kbit/s).
+        bitrate: u32,
+    },
+    /// Two-pass encoding targeting an output file size.
+    TwoPassSize {
+        /// Target size of the output file (in bytes).
+        size_bytes: u64,
+    },
+}
+
+impl RateControl {
+    /// Determines whether the mode requires two encoding passes.
+    #[must_use]
+    pub fn is_two_pass(&self) -> bool {
+        matches!(self, Self::TwoPassBitrate { .. } | Self::TwoPassSize { .. })
+    }
+
+    /// Resolves a target file size into a target video bitrate.
+    ///
+    /// The audio bitrate is subtracted from the total bitrate available for
+    /// the given duration. Other modes are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Duration of the rendered output
+    /// * `audio_bitrate` - Bitrate of the audio stream (kbit/s)
+    ///
+    /// # Returns
+    ///
+    /// The rate control with the target size replaced by a bitrate.
+    #[must_use]
+    pub fn resolve(self, duration: Duration, audio_bitrate: u32) -> Self {
+        match self {
+            Self::TwoPassSize { size_bytes } => {
+                let seconds = duration.as_seconds().max(0.001);
+                let total = (size_bytes as f64 * 8.0 / 1000.0 / seconds).floor() as u32;
+                // 音声分を差し引き、映像に最低限のビットレートを残す
+                let bitrate = total.saturating_sub(audio_bitrate).max(1);
+                Self::TwoPassBitrate { bitrate }
+            }
+            other => other,
+        }
+    }
+
+    /// Validates the rate control for the given codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value is out of range or the codec has no rate
+    /// control.
+    pub fn validate(&self, codec: VideoCodec) -> Result<(), String> {
+        if *self == Self::Quality {
+            return Ok(());
+        }
+
+        if !codec.supports_rate_control() {
+            return Err(format!(
+                "Video codec {codec:?} does not support rate control mode {self:?}"
+            ));
+        }
+
+        match *self {
+            Self::Quality => {}
+            Self::Crf { value } => {
+                let max = match codec {
+                    VideoCodec::VP9 | VideoCodec::AV1 => 63,
+                    _ => 51,
+                };
+                if value > max {
+                    return Err(format!("CRF must be between 0 and {max} for {codec:?}"));
+                }
+            }
+            Self::Cbr { bitrate } | Self::TwoPassBitrate { bitrate } => {
+                if bitrate == 0 {
+                    return Err("Bitrate must be positive".to_string());
+                }
+            }
+            Self::CappedVbr {
+                bitrate,
+                max_bitrate,
+            } => {
+                if bitrate == 0 {
+                    return Err("Bitrate must be positive".to_string());
+                }
+                if max_bitrate < bitrate {
+                    return Err("Maximum bitrate cannot be lower than the bitrate".to_string());
+                }
+            }
+            Self::TwoPassSize { size_bytes } => {
+                if size_bytes == 0 {
+                    return Err("Target file size must be positive".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for RateControl {
+    type Err = String;
+
+    /// Parses a rate control mode such as `crf=23`, `cbr=5000`,
+    /// `vbr=4000:6000`, `2pass=5000` or `size=100M`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate control: {s}");
+        if s.eq_ignore_ascii_case("quality") {
+            return Ok(Self::Quality);
+        }
+
+        let (mode, value) = s.split_once('=').ok_or_else(invalid)?;
+        let parse = |v: &str| v.parse::<u32>().map_err(|_| invalid());
+
+        match mode.to_ascii_lowercase().as_str() {
+            "crf" => Ok(Self::Crf {
+                value: parse(value)?,
+            }),
+            "cbr" => Ok(Self::Cbr {
+                bitrate: parse(value)?,
+            }),
+            "vbr" => {
+                let (bitrate, max_bitrate) = value.split_once(':').ok_or_else(invalid)?;
+                Ok(Self::CappedVbr {
+                    bitrate: parse(bitrate)?,
+                    max_bitrate: parse(max_bitrate)?,
+                })
+            }
+            "2pass" => Ok(Self::TwoPassBitrate {
+                bitrate: parse(value)?,
+            }),
+            "size" => {
+                let (number, multiplier) = match value.chars().last() {
+                    Some('k' | 'K') => (&value[..value.len() - 1], 1024),
+                    Some('m' | 'M') => (&value[..value.len() - 1], 1024 * 1024),
+                    Some('g' | 'G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+                    _ => (value, 1),
+                };
+                let size: u64 = number.parse().map_err(|_| invalid())?;
+                let size_bytes = size.checked_mul(multiplier).ok_or_else(invalid)?;
+                Ok(Self::TwoPassSize { size_bytes })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Numbering and alpha handling of image sequence outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSequenceOptions {
+    /// Number of the first written frame.
+    pub start_number: u32,
+    /// Whether to keep the alpha channel of the composited frames.
+    pub alpha: bool,
+}
+
+impl Default for ImageSequenceOptions {
+    fn default() -> Self {
+        Self {
+            start_number: 1,
+            alpha: false,
         }
     }
 }
 
 /// Configuration for timeline rendering.
-#[derive(Debug, Clone)]
+///
+/// Missing fields take their default values when deserializing, so stored
+/// configurations stay loadable when new options are added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RenderConfig {
     /// Output file path.
     pub output_path: PathBuf,
@@ -267,6 +937,9 @@ pub struct RenderConfig {
     /// Video quality (1-100, higher is better).
     pub video_quality: u32,
 
+    /// Rate-control mode of the video encoder.
+    pub rate_control: RateControl,
+
     /// Audio codec to use.
     pub audio_codec: AudioCodec,
 
@@ -288,7 +961,7 @@ pub struct RenderConfig {
     /// Whether to include subtitles in the output.
     pub include_subtitles: bool,
 
-    /// Whether to use cached assets when available.
+    /// Whether to use cached assets and timeline segments when available.
     pub use_cache: bool,
 
     /// Whether to auto-load assets on project load.
@@ -303,6 +976,10 @@ pub struct RenderConfig {
     /// Maximum cache size in bytes (if None, no limit).
     pub max_cache_size: Option<u64>,
 
+    /// Whether cached renders are keyed on a hash of the source file contents
+    /// in addition to its size and modification time.
+    pub hash_cache_sources: bool,
+
     /// Hardware acceleration type to use.
     pub hardware_accel_type: HardwareAccelType,
 
@@ -311,6 +988,24 @@ pub struct RenderConfig {
 
     /// Maximum GPU memory usage in bytes (if None, no limit).
     pub max_gpu_memory: Option<u64>,
+
+    /// Whether to split the timeline into segments that are encoded in parallel.
+    pub parallel_segments: bool,
+
+    /// Target duration of each segment when rendering in parallel.
+    pub segment_duration: Duration,
+
+    /// Encoding ladder and segmenting of HLS and DASH outputs.
+    pub streaming: StreamingOptions,
+
+    /// Frame numbering and alpha handling of image sequence outputs.
+    pub image_sequence: ImageSequenceOptions,
+
+    /// Per-track audio stems rendered with or instead of the output.
+    pub stems: StemOptions,
+
+    /// Post-render verification of the output against this configuration.
+    pub verification: VerificationOptions,
 }
 
 impl Default for RenderConfig {
@@ -322,6 +1017,7 @@ impl Default for RenderConfig {
             frame_rate: 30.0,
             video_codec: VideoCodec::default(),
             video_quality: 80,
+            rate_control: RateControl::default(),
             audio_codec: AudioCodec::default(),
             audio_quality: 80,
             format: OutputFormat::default(),
@@ -334,9 +1030,16 @@ impl Default for RenderConfig {
             optimize_complex_timelines: true,
             cache_dir: None,
             max_cache_size: Some(10 * 1024 * 1024 * 1024), // 10 GB default
+            hash_cache_sources: false,
             hardware_accel_type: HardwareAccelType::default(),
             use_hw_decoding: true,
             max_gpu_memory: None,
+            parallel_segments: false,
+            segment_duration: Duration::from_seconds(10.0),
+            streaming: StreamingOptions::default(),
+            image_sequence: ImageSequenceOptions::default(),
+            stems: StemOptions::default(),
+            verification: VerificationOptions::default(),
         }
     }
 }
@@ -374,6 +1077,13 @@ impl RenderConfig {
         self
     }
 
+    /// Sets the rate-control mode of the video encoder.
+    #[must_use]
+    pub fn with_rate_control(mut self, rate_control: RateControl) -> Self {
+        self.rate_control = rate_control;
+        self
+    }
+
     /// Sets the audio codec and quality.
     #[must_use]
     pub fn with_audio_settings(mut self, codec: AudioCodec, quality: u32) -> Self {
@@ -446,6 +1156,17 @@ impl RenderConfig {
         self
     }
 
+    /// Sets whether cached renders are keyed on a hash of the source file contents.
+    ///
+    /// Size and modification time detect most changes to a source file.
+    /// Hashing the contents also detects files that were replaced without
+    /// changing either, at the cost of reading every source file.
+    #[must_use]
+    pub fn with_source_content_hashing(mut self, enabled: bool) -> Self {
+        self.hash_cache_sources = enabled;
+        self
+    }
+
     /// Sets the hardware acceleration type to use.
     #[must_use]
     pub fn with_hardware_acceleration(mut self, accel_type: HardwareAccelType) -> Self {
@@ -467,6 +1188,178 @@ impl RenderConfig {
         self
     }
 
+    /// Enables parallel rendering of timeline segments.
+    ///
+    /// The timeline is split into segments of roughly `segment_duration`,
+    /// which are encoded concurrently and concatenated afterwards. With caching
+    /// enabled, segments that are unchanged since a previous render are reused.
+    #[must_use]
+    pub fn with_parallel_segments(mut self, segment_duration: Duration) -> Self {
+        self.parallel_segments = true;
+        self.segment_duration = segment_duration;
+        self
+    }
+
+    /// Sets the encoding ladder and segmenting of HLS and DASH outputs.
+    #[must_use]
+    pub fn with_streaming(mut self, streaming: StreamingOptions) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Sets the frame numbering and alpha handling of image sequence outputs.
+    #[must_use]
+    pub fn with_image_sequence(mut self, image_sequence: ImageSequenceOptions) -> Self {
+        self.image_sequence = image_sequence;
+        self
+    }
+
+    /// Sets the per-track audio stems rendered with or instead of the output.
+    #[must_use]
+    pub fn with_stems(mut self, stems: StemOptions) -> Self {
+        self.stems = stems;
+        self
+    }
+
+    /// Sets the post-render verification of the output.
+    #[must_use]
+    pub fn with_verification(mut self, verification: VerificationOptions) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Determines whether the composited frames keep their alpha channel.
+    ///
+    /// # Returns
+    ///
+    /// True for codecs with alpha and for image sequences with alpha enabled.
+    #[must_use]
+    pub fn preserves_alpha(&self) -> bool {
+        if self.format.is_image_sequence() {
+            self.image_sequence.alpha
+        } else {
+            self.video_codec.has_alpha()
+        }
+    }
+
+    /// Determines whether the output can be produced by concatenating
+    /// independently encoded segments.
+    ///
+    /// Stream copy cannot start segments on arbitrary frames, and GIF output
+    /// cannot be losslessly concatenated, so both are rendered in a single pass.
+    /// Image sequences are numbered across the whole output and are rendered
+    /// in a single pass as well. Segments only carry video, so audio-only
+    /// outputs have nothing to split.
+    ///
+    /// # Returns
+    ///
+    /// True if segmented rendering is enabled and supported, false otherwise.
+    #[must_use]
+    pub fn supports_segmented_rendering(&self) -> bool {
+        self.parallel_segments
+            && self.segment_duration.as_seconds() > 0.0
+            && self.video_codec != VideoCodec::Copy
+            && self.format != OutputFormat::GIF
+            && !self.format.is_image_sequence()
+            && !self.format.is_audio_only()
+    }
+
+    /// Gets the rate control to encode an output of the given duration with.
+    ///
+    /// A target file size is converted into a video bitrate, leaving room
+    /// for the audio stream. Audio codecs without a fixed bitrate are assumed
+    /// to use 192 kbit/s.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Duration of the rendered output
+    ///
+    /// # Returns
+    ///
+    /// The resolved rate control.
+    #[must_use]
+    pub fn resolved_rate_control(&self, duration: Duration) -> RateControl {
+        let audio_bitrate = if !self.format.supports_audio() {
+            0
+        } else {
+            self.audio_codec.bitrate(self.audio_quality).unwrap_or(192)
+        };
+        self.rate_control.resolve(duration, audio_bitrate)
+    }
+
+    /// Gets the `FFmpeg` encoders, muxers and hardware acceleration methods
+    /// needed to render this output.
+    ///
+    /// The filters of the composition depend on the timeline and are
+    /// reported by `TrackCompositor::required_filters`.
+    ///
+    /// # Returns
+    ///
+    /// The kinds and names of the required components.
+    #[must_use]
+    pub fn required_components(&self) -> Vec<(ComponentKind, &'static str)> {
+        let mut components = Vec::new();
+        let hardware = self.should_use_hardware_acceleration();
+
+        if self.stems.renders_mix() {
+            components.push((ComponentKind::Muxer, self.format.muxer()));
+
+            if !self.format.is_audio_only() {
+                let encoder = if let Some(encoder) = self.format.image_encoder() {
+                    Some(encoder)
+                } else if hardware {
+                    self.hardware_accel_type
+                        .get_hw_encoder_name(self.video_codec)
+                } else {
+                    Some(self.video_codec.to_ffmpeg_codec())
+                        .filter(|_| self.video_codec != VideoCodec::Copy)
+                };
+                components.extend(encoder.map(|encoder| (ComponentKind::Encoder, encoder)));
+            }
+            if self.format.supports_audio() && self.audio_codec != AudioCodec::Copy {
+                components.push((ComponentKind::Encoder, self.audio_codec.to_ffmpeg_codec()));
+            }
+            if hardware && self.use_hw_decoding {
+                components.extend(
+                    self.hardware_accel_type
+                        .to_ffmpeg_hwaccel()
+                        .map(|hwaccel| (ComponentKind::HwAccel, hwaccel)),
+                );
+            }
+
+            // 配信用出力は中間ファイルを経由し、ラダーの各段に分岐・縮小する
+            if self.format.is_streaming() {
+                let intermediate = streaming::intermediate_config(self, Path::new(""));
+                components.extend(intermediate.required_components());
+                components.push((ComponentKind::Filter, "split"));
+                components.push((ComponentKind::Filter, "scale"));
+            }
+        }
+
+        if self.stems.is_enabled() {
+            components.push((ComponentKind::Encoder, self.stems.format.encoder()));
+            components.push((ComponentKind::Muxer, self.stems.format.extension()));
+        }
+
+        components
+    }
+
+    /// Checks that an `FFmpeg` build can render this output.
+    ///
+    /// # Arguments
+    ///
+    /// * `capabilities` - The capabilities of the `FFmpeg` build
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first missing encoder, muxer or hardware
+    /// acceleration method.
+    pub fn check_capabilities(&self, capabilities: &Capabilities) -> Result<(), String> {
+        capabilities
+            .require_all(self.required_components())
+            .map_err(|e| format!("{e} (required by {})", self.output_path.display()))
+    }
+
     /// Validates the configuration and returns an error if invalid.
     pub fn validate(&self) -> Result<(), String> {
         if self.output_path.as_os_str().is_empty() {
@@ -481,8 +1374,50 @@ impl RenderConfig {
             return Err("Frame rate must be positive".to_string());
         }
 
+        if !self.format.supports_video_codec(self.video_codec) {
+            return Err(format!(
+                "Video codec {:?} cannot be stored in {} output",
+                self.video_codec,
+                self.format.extension()
+            ));
+        }
+
+        if !self.format.supports_audio_codec(self.audio_codec) {
+            return Err(format!(
+                "Audio codec {:?} cannot be stored in {} output",
+                self.audio_codec,
+                self.format.extension()
+            ));
+        }
+
+        // 音声のみの出力と連番画像では映像エンコードの設定は使われない
+        let encodes_video = !self.format.is_audio_only() && !self.format.is_image_sequence();
+
+        if self.format.is_image_sequence() && FramePattern::parse(&self.output_path).is_none() {
+            return Err(format!(
+                "Image sequence output path must contain a frame number pattern such as %05d: {}",
+                self.output_path.display()
+            ));
+        }
+
+        if encodes_video {
+            self.rate_control.validate(self.video_codec)?;
+        }
+
+        if self.format.is_streaming() {
+            self.streaming.validate()?;
+        }
+
+        self.stems.validate()?;
+        self.verification.validate()?;
+
+        if self.parallel_segments && self.segment_duration.as_seconds() <= 0.0 {
+            return Err("Segment duration must be positive".to_string());
+        }
+
         // Validate hardware acceleration type for the selected codec
-        if self.hardware_accel_type != HardwareAccelType::None
+        if encodes_video
+            && self.hardware_accel_type != HardwareAccelType::None
             && self.hardware_accel_type != HardwareAccelType::Auto
         {
             // Check if the selected codec is compatible with the hardware acceleration
@@ -534,6 +1469,7 @@ impl PartialEq for RenderConfig {
             && self.frame_rate == other.frame_rate
             && self.video_codec == other.video_codec
             && self.video_quality == other.video_quality
+            && self.rate_control == other.rate_control
             && self.audio_codec == other.audio_codec
             && self.audio_quality == other.audio_quality
             && self.format == other.format
@@ -543,6 +1479,12 @@ impl PartialEq for RenderConfig {
             && self.include_subtitles == other.include_subtitles
             && self.hardware_accel_type == other.hardware_accel_type
             && self.use_hw_decoding == other.use_hw_decoding
+            && self.parallel_segments == other.parallel_segments
+            && self.segment_duration == other.segment_duration
+            && self.streaming == other.streaming
+            && self.image_sequence == other.image_sequence
+            && self.stems == other.stems
+            && self.verification == other.verification
     }
 }
 
@@ -556,6 +1498,7 @@ impl Hash for RenderConfig {
         // We'll ignore frame_rate in the hash since it's an f64
         self.video_codec.hash(state);
         self.video_quality.hash(state);
+        self.rate_control.hash(state);
         self.audio_codec.hash(state);
         self.audio_quality.hash(state);
         self.format.hash(state);
@@ -565,5 +1508,294 @@ impl Hash for RenderConfig {
         self.include_subtitles.hash(state);
         self.hardware_accel_type.hash(state);
         self.use_hw_decoding.hash(state);
+        self.streaming.hash(state);
+        self.image_sequence.hash(state);
+        // Segmenting does not change the rendered output, so the parallel
+        // rendering settings are not part of the hash. Stems are separate
+        // files and verification only inspects the output, so neither
+        // changes the output either.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_control() {
+        assert_eq!(
+            "crf=23".parse::<RateControl>(),
+            Ok(RateControl::Crf { value: 23 })
+        );
+        assert_eq!(
+            "vbr=4000:6000".parse::<RateControl>(),
+            Ok(RateControl::CappedVbr {
+                bitrate: 4000,
+                max_bitrate: 6000
+            })
+        );
+        assert_eq!(
+            "size=10M".parse::<RateControl>(),
+            Ok(RateControl::TwoPassSize {
+                size_bytes: 10 * 1024 * 1024
+            })
+        );
+        assert!("vbr=4000".parse::<RateControl>().is_err());
+        assert!("size=18446744073709551615G".parse::<RateControl>().is_err());
+        assert_eq!(RateControl::default(), RateControl::Quality);
+        assert!("fast".parse::<RateControl>().is_err());
+    }
+
+    #[test]
+    fn test_target_size_resolves_to_bitrate() {
+        // 10 MB over 80 seconds is 1000 kbit/s in total
+        let rate_control = RateControl::TwoPassSize {
+            size_bytes: 10_000_000,
+        };
+        assert_eq!(
+            rate_control.resolve(Duration::from_seconds(80.0), 128),
+            RateControl::TwoPassBitrate { bitrate: 872 }
+        );
+
+        let crf = RateControl::Crf { value: 20 };
+        assert_eq!(crf.resolve(Duration::from_seconds(80.0), 128), crf);
+    }
+
+    #[test]
+    fn test_encoder_options_per_codec() {
+        let crf = RateControl::Crf { value: 30 };
+        assert_eq!(
+            VideoCodec::VP9.encoder_options(crf, 80),
+            ["-crf", "30", "-b:v", "0"]
+        );
+        assert_eq!(
+            VideoCodec::H264.encoder_options(crf, 80),
+            ["-crf", "30", "-preset", "medium"]
+        );
+
+        let cbr = VideoCodec::H265.encoder_options(RateControl::Cbr { bitrate: 5000 }, 80);
+        assert!(cbr.windows(2).any(|pair| pair == ["-maxrate", "5000k"]));
+        assert!(cbr.windows(2).any(|pair| pair == ["-minrate", "5000k"]));
+
+        assert_eq!(
+            VideoCodec::H265.pass_options(1, Path::new("/tmp/log")),
+            ["-x265-params", "pass=1:stats=/tmp/log.log"]
+        );
+        assert_eq!(
+            VideoCodec::H264.pass_options(2, Path::new("/tmp/log")),
+            ["-pass", "2", "-passlogfile", "/tmp/log"]
+        );
+    }
+
+    #[test]
+    fn test_validate_codec_container_pairs() {
+        let config = |format, video_codec, audio_codec| RenderConfig {
+            format,
+            video_codec,
+            audio_codec,
+            ..RenderConfig::new(PathBuf::from("out"))
+        };
+
+        assert!(
+            config(OutputFormat::MOV, VideoCodec::ProRes4444, AudioCodec::Pcm)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(OutputFormat::MXF, VideoCodec::DnxHr, AudioCodec::Pcm)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(OutputFormat::MKV, VideoCodec::Ffv1, AudioCodec::Flac)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(OutputFormat::MP4, VideoCodec::ProRes, AudioCodec::AAC)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config(OutputFormat::WebM, VideoCodec::VP9, AudioCodec::AAC)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config(OutputFormat::MXF, VideoCodec::DnxHr, AudioCodec::AAC)
+                .validate()
+                .is_err()
+        );
+
+        // Audio-only outputs ignore the video codec but check the audio codec
+        assert!(
+            config(OutputFormat::WAV, VideoCodec::H264, AudioCodec::Pcm)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(OutputFormat::M4A, VideoCodec::H264, AudioCodec::AAC)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(OutputFormat::FLAC, VideoCodec::H264, AudioCodec::MP3)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_muxer_names() {
+        assert_eq!(OutputFormat::MKV.muxer(), "matroska");
+        assert_eq!(OutputFormat::M4A.muxer(), "ipod");
+        assert_eq!(OutputFormat::MXF.muxer(), "mxf");
+        assert!(OutputFormat::WAV.is_audio_only());
+        assert!(!OutputFormat::GIF.supports_audio());
+    }
+
+    #[test]
+    fn test_deserialize_legacy_variant_names() {
+        let codecs: (VideoCodec, AudioCodec, OutputFormat, HardwareAccelType) =
+            serde_json::from_str(r#"["H265", "AAC", "WebM", "VideoToolbox"]"#).unwrap();
+        assert_eq!(
+            codecs,
+            (
+                VideoCodec::H265,
+                AudioCodec::AAC,
+                OutputFormat::WebM,
+                HardwareAccelType::VideoToolbox
+            )
+        );
+
+        assert_eq!(
+            serde_json::to_string(&VideoCodec::H265).unwrap(),
+            "\"h265\""
+        );
+        assert_eq!(
+            serde_json::from_str::<VideoCodec>("\"h265\"").unwrap(),
+            VideoCodec::H265
+        );
+    }
+
+    #[test]
+    fn test_validate_rate_control() {
+        let config = RenderConfig::new(PathBuf::from("out.mp4"));
+        assert!(
+            config
+                .clone()
+                .with_rate_control(RateControl::Crf { value: 60 })
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config
+                .clone()
+                .with_video_settings(VideoCodec::AV1, 80)
+                .with_rate_control(RateControl::Crf { value: 60 })
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config
+                .clone()
+                .with_rate_control(RateControl::CappedVbr {
+                    bitrate: 6000,
+                    max_bitrate: 4000
+                })
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config
+                .with_video_settings(VideoCodec::ProRes, 80)
+                .with_rate_control(RateControl::Cbr { bitrate: 5000 })
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_image_sequence_output() {
+        let config = RenderConfig::new(PathBuf::from("frames/out_%05d.png"))
+            .with_format(OutputFormat::PngSequence)
+            .with_parallel_segments(Duration::from_seconds(10.0));
+        assert!(config.validate().is_ok());
+        assert!(!config.supports_segmented_rendering());
+        assert!(!config.preserves_alpha());
+        assert_eq!(OutputFormat::PngSequence.muxer(), "image2");
+        assert!(!OutputFormat::TiffSequence.supports_audio());
+        assert_eq!(
+            "tiff-sequence".parse::<OutputFormat>(),
+            Ok(OutputFormat::TiffSequence)
+        );
+
+        let alpha = config.clone().with_image_sequence(ImageSequenceOptions {
+            start_number: 1001,
+            alpha: true,
+        });
+        assert!(alpha.preserves_alpha());
+        assert_ne!(alpha, config);
+
+        let without_pattern = RenderConfig::new(PathBuf::from("frames/out.png"))
+            .with_format(OutputFormat::PngSequence);
+        assert!(without_pattern.validate().is_err());
+    }
+
+    #[test]
+    fn test_check_capabilities() {
+        let capabilities = Capabilities {
+            encoders: ["libx264", "aac", "h264_nvenc"].map(String::from).into(),
+            muxers: ["mp4"].map(String::from).into(),
+            hwaccels: ["cuda"].map(String::from).into(),
+            ..Capabilities::default()
+        };
+
+        let config = RenderConfig::new(PathBuf::from("out.mp4"));
+        assert!(config.check_capabilities(&capabilities).is_ok());
+
+        let h265 = config.clone().with_video_settings(VideoCodec::H265, 80);
+        let error = h265.check_capabilities(&capabilities).unwrap_err();
+        assert_eq!(
+            error,
+            "FFmpeg is missing the encoder libx265 (required by out.mp4)"
+        );
+
+        assert_eq!(
+            HardwareAccelType::supported_by(&capabilities),
+            vec![
+                HardwareAccelType::None,
+                HardwareAccelType::Nvidia,
+                HardwareAccelType::Auto
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_available_queries_ffmpeg() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "-hwaccels",
+            crate::ffmpeg::ExecutionOutput::success("Hardware acceleration methods:\nvaapi\n"),
+        );
+        let _default = FFmpeg::scoped_default(ffmpeg);
+
+        assert_eq!(
+            HardwareAccelType::detect_available(),
+            vec![
+                HardwareAccelType::None,
+                HardwareAccelType::Vaapi,
+                HardwareAccelType::Auto
+            ]
+        );
+        let queried: Vec<String> = executor
+            .calls()
+            .iter()
+            .filter_map(|call| call.last_arg().map(str::to_string))
+            .collect();
+        assert_eq!(
+            queried,
+            ["-encoders", "-decoders", "-filters", "-muxers", "-hwaccels"]
+        );
     }
 }
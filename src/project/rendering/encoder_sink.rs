@@ -0,0 +1,721 @@
+/// Encoding frames and audio samples produced in Rust.
+///
+/// `EncoderSink` starts an `FFmpeg` encoder that reads `rawvideo` from its
+/// standard input and encodes it with the codecs and format of a
+/// `RenderConfig`, so that frames composited in Rust (or produced by an
+/// exporter) can be written without going through image files. Frames are
+/// written one at a time, as raw buffers, `RgbaImage`s or decoded `Frame`s.
+///
+/// Audio samples are interleaved 32-bit floats. They are spooled to a
+/// temporary file while the video is encoded and muxed with it when the
+/// sink is finished, since a process has only one standard input.
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+use image::RgbaImage;
+use tempfile::TempDir;
+
+use crate::ffmpeg::command::FFmpegCommand;
+use crate::ffmpeg::{
+    Error as FFmpegError, ExecutionOutput, FFmpeg, Frame, PixelFormat, SpawnedProcess,
+};
+use crate::project::rendering::compositor::TrackCompositor;
+use crate::project::rendering::config::{AudioCodec, RenderConfig, VideoCodec};
+use crate::project::rendering::{RenderError, Result};
+use crate::utility::time::Duration;
+
+/// Layout of the video frames written to a sink.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoInput {
+    /// Width of the frames in pixels.
+    pub width: u32,
+    /// Height of the frames in pixels.
+    pub height: u32,
+    /// Frames per second.
+    pub frame_rate: f64,
+    /// Pixel format of the frames.
+    pub pixel_format: PixelFormat,
+}
+
+impl VideoInput {
+    /// Creates a video input layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frames in pixels
+    /// * `height` - Height of the frames in pixels
+    /// * `frame_rate` - Frames per second
+    /// * `pixel_format` - Pixel format of the frames
+    #[must_use]
+    pub fn new(width: u32, height: u32, frame_rate: f64, pixel_format: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            frame_rate,
+            pixel_format,
+        }
+    }
+
+    /// Creates a video input layout with the size and frame rate of an
+    /// output.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    /// * `pixel_format` - Pixel format of the frames
+    #[must_use]
+    pub fn from_config(config: &RenderConfig, pixel_format: PixelFormat) -> Self {
+        Self::new(config.width, config.height, config.frame_rate, pixel_format)
+    }
+
+    /// Gets the size of one frame in bytes.
+    #[must_use]
+    pub fn frame_size(&self) -> usize {
+        self.pixel_format.frame_size(self.width, self.height)
+    }
+}
+
+/// Layout of the audio samples written to a sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInput {
+    /// Samples per second of each channel.
+    pub sample_rate: u32,
+    /// Number of interleaved channels.
+    pub channels: u16,
+}
+
+impl AudioInput {
+    /// Creates an audio input layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Samples per second of each channel
+    /// * `channels` - Number of interleaved channels
+    #[must_use]
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+        }
+    }
+}
+
+/// An `FFmpeg` encoder fed with frames and samples from Rust.
+///
+/// The sink must be finished with `finish`; dropping it stops the encoder
+/// and leaves the output incomplete.
+#[derive(Debug)]
+pub struct EncoderSink {
+    /// The `FFmpeg` instance running the encoder.
+    ffmpeg: FFmpeg,
+    /// The rendering configuration of the output.
+    config: RenderConfig,
+    /// Layout of the video frames, if the sink takes video.
+    video: Option<VideoInput>,
+    /// Layout of the audio samples, if the sink takes audio.
+    audio: Option<AudioInput>,
+    /// The video encoder, until it is finished.
+    process: Option<SpawnedProcess>,
+    /// The thread collecting the log of the video encoder.
+    log: Option<JoinHandle<String>>,
+    /// Directory of the intermediate video and the spooled samples.
+    workspace: Option<TempDir>,
+    /// The spooled audio samples, until the sink is finished.
+    samples: Option<BufWriter<File>>,
+    /// Number of frames written.
+    frames_written: u64,
+    /// Number of samples written per channel.
+    samples_written: u64,
+}
+
+impl EncoderSink {
+    /// Starts an encoder for an output.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The `FFmpeg` instance to run
+    /// * `config` - The rendering configuration of the output
+    /// * `video` - Layout of the video frames, or `None` for audio only
+    /// * `audio` - Layout of the audio samples, or `None` for video only
+    ///
+    /// # Returns
+    ///
+    /// The sink, ready for frames and samples.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be encoded from raw
+    /// input (streaming formats, two-pass rate control, stream copy), if
+    /// the inputs do not match the format, or if the encoder cannot start.
+    pub fn new(
+        ffmpeg: &FFmpeg,
+        config: &RenderConfig,
+        video: Option<VideoInput>,
+        audio: Option<AudioInput>,
+    ) -> Result<Self> {
+        validate(config, video.as_ref(), audio.as_ref())?;
+
+        let workspace = match audio {
+            Some(_) => Some(TempDir::new()?),
+            None => None,
+        };
+        let samples = match &workspace {
+            Some(workspace) => Some(BufWriter::new(File::create(
+                workspace.path().join(SAMPLES_FILE),
+            )?)),
+            None => None,
+        };
+
+        let mut sink = Self {
+            ffmpeg: ffmpeg.clone(),
+            config: config.clone(),
+            video,
+            audio,
+            process: None,
+            log: None,
+            workspace,
+            samples,
+            frames_written: 0,
+            samples_written: 0,
+        };
+        if let Some(video) = video {
+            sink.start_video(&video)?;
+        }
+        Ok(sink)
+    }
+
+    /// Gets the number of frames written so far.
+    #[must_use]
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// Gets the number of samples per channel written so far.
+    #[must_use]
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written
+    }
+
+    /// Writes a raw frame in the layout of the video input.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The pixels, plane after plane
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sink takes no video, if the frame has the
+    /// wrong size, or if the encoder has failed.
+    pub fn write_frame(&mut self, data: &[u8]) -> Result<()> {
+        let video = self
+            .video
+            .ok_or_else(|| RenderError::ConfigError("The encoder takes no video".to_string()))?;
+        if data.len() != video.frame_size() {
+            return Err(RenderError::ProcessingFailed(format!(
+                "Frame has {} bytes, expected {} for {}x{} {}",
+                data.len(),
+                video.frame_size(),
+                video.width,
+                video.height,
+                video.pixel_format.as_ffmpeg_str()
+            )));
+        }
+
+        let written = match self.process.as_mut().and_then(|p| p.stdin.as_mut()) {
+            Some(stdin) => stdin.write_all(data),
+            None => {
+                return Err(RenderError::ProcessingFailed(
+                    "The video encoder is not running".to_string(),
+                ));
+            }
+        };
+        match written {
+            Ok(()) => {
+                self.frames_written += 1;
+                Ok(())
+            }
+            // パイプが閉じられた場合はエンコーダーが終了しているので、その原因を返す
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Err(match self.finish_video() {
+                Ok(()) => RenderError::FFmpegError("The video encoder exited early".to_string()),
+                Err(error) => error,
+            }),
+            Err(e) => Err(RenderError::Io(format!("Failed to write frame: {e}"))),
+        }
+    }
+
+    /// Writes an RGBA image as a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - The frame, in the size of the video input
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the video input is not RGBA or has another size,
+    /// or if the frame cannot be written.
+    pub fn write_image(&mut self, image: &RgbaImage) -> Result<()> {
+        self.check_layout(PixelFormat::Rgba, image.width(), image.height())?;
+        self.write_frame(image.as_raw())
+    }
+
+    /// Writes a decoded frame, e.g. one read by a `FrameDecoder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame, in the layout of the video input
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame does not match the video input, or if
+    /// it cannot be written.
+    pub fn write_decoded(&mut self, frame: &Frame) -> Result<()> {
+        self.check_layout(frame.pixel_format, frame.width, frame.height)?;
+        self.write_frame(&frame.data)
+    }
+
+    /// Writes interleaved audio samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Samples in the range -1.0 to 1.0, channel after
+    ///   channel for each instant
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sink takes no audio, if the samples do not
+    /// fill whole instants, or if they cannot be spooled.
+    pub fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        let audio = self
+            .audio
+            .ok_or_else(|| RenderError::ConfigError("The encoder takes no audio".to_string()))?;
+        let channels = usize::from(audio.channels);
+        if !samples.len().is_multiple_of(channels) {
+            return Err(RenderError::ProcessingFailed(format!(
+                "{} samples do not fill {channels} channels",
+                samples.len()
+            )));
+        }
+
+        let spool = self.samples.as_mut().ok_or_else(|| {
+            RenderError::ProcessingFailed("The audio spool is closed".to_string())
+        })?;
+        for sample in samples {
+            spool
+                .write_all(&sample.to_le_bytes())
+                .map_err(|e| RenderError::Io(format!("Failed to write samples: {e}")))?;
+        }
+        self.samples_written += (samples.len() / channels) as u64;
+        Ok(())
+    }
+
+    /// Finishes the output: closes the video input, waits for the encoder
+    /// and muxes the audio in.
+    ///
+    /// # Returns
+    ///
+    /// The path of the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoder or the audio mux fails.
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.finish_video()?;
+        if let Some(audio) = self.audio {
+            self.mux_audio(&audio)?;
+        }
+        Ok(self.config.output_path.clone())
+    }
+
+    /// Starts the video encoder.
+    fn start_video(&mut self, video: &VideoInput) -> Result<()> {
+        let output = self.video_output_path();
+        let rate_control = self.config.resolved_rate_control(Duration::zero());
+
+        let mut command = FFmpegCommand::new(&self.ffmpeg);
+        command
+            .input_options([
+                "-f".to_string(),
+                "rawvideo".to_string(),
+                "-pix_fmt".to_string(),
+                video.pixel_format.as_ffmpeg_str().to_string(),
+                "-s".to_string(),
+                format!("{}x{}", video.width, video.height),
+                "-framerate".to_string(),
+                video.frame_rate.to_string(),
+            ])
+            .input("pipe:0");
+        if (video.width, video.height) != (self.config.width, self.config.height) {
+            command.add_output_option(
+                "-vf",
+                format!("scale={}:{}", self.config.width, self.config.height),
+            );
+        }
+        command.output_options(TrackCompositor::video_encoder_options(
+            &self.config,
+            rate_control,
+            None,
+        ));
+        if self.audio.is_some() {
+            command.output_options(["-an"]);
+        }
+        if let Some(threads) = self.config.threads {
+            command.add_output_option("-threads", threads.to_string());
+        }
+        command
+            .add_output_option("-f", self.config.format.muxer())
+            .set_output(&output)
+            .overwrite(true);
+
+        let args = command.args().map_err(ffmpeg_error)?;
+        let mut process = self
+            .ffmpeg
+            .executor()
+            .spawn(self.ffmpeg.path(), &args)
+            .map_err(|e| RenderError::FFmpegError(format!("Failed to start the encoder: {e}")))?;
+
+        // 標準出力は使わないので閉じ、標準エラーはログとして集める
+        process.stdout = None;
+        let stderr = process.stderr.take();
+        self.log = stderr.map(|mut stderr| {
+            thread::spawn(move || {
+                let mut log = String::new();
+                let _ = std::io::Read::read_to_string(&mut stderr, &mut log);
+                log
+            })
+        });
+        self.process = Some(process);
+        Ok(())
+    }
+
+    /// Closes the video input and waits for the encoder.
+    fn finish_video(&mut self) -> Result<()> {
+        let Some(mut process) = self.process.take() else {
+            return Ok(());
+        };
+        process.stdin = None;
+        let status = process.wait().map_err(|e| {
+            RenderError::FFmpegError(format!("Failed to wait for the encoder: {e}"))
+        })?;
+        let stderr = self
+            .log
+            .take()
+            .and_then(|log| log.join().ok())
+            .unwrap_or_default();
+
+        if status.success {
+            return Ok(());
+        }
+        let output = ExecutionOutput {
+            exit_code: status.exit_code,
+            success: false,
+            stdout: Vec::new(),
+            stderr,
+        };
+        Err(ffmpeg_error(FFmpegError::from_failed_run(&output, || {
+            FFmpegError::ProcessTerminated {
+                exit_code: output.exit_code,
+                message: format!("FFmpeg encoder failed: {}", output.stderr.trim()),
+            }
+        })))
+    }
+
+    /// Encodes the spooled samples into the output, together with the
+    /// intermediate video if there is one.
+    fn mux_audio(&mut self, audio: &AudioInput) -> Result<()> {
+        if let Some(spool) = self.samples.take() {
+            spool
+                .into_inner()
+                .map_err(|e| RenderError::Io(format!("Failed to write samples: {e}")))?;
+        }
+        let Some(workspace) = &self.workspace else {
+            return Ok(());
+        };
+
+        let mut command = FFmpegCommand::new(&self.ffmpeg);
+        command
+            .input_options([
+                "-f".to_string(),
+                "f32le".to_string(),
+                "-ar".to_string(),
+                audio.sample_rate.to_string(),
+                "-ac".to_string(),
+                audio.channels.to_string(),
+            ])
+            .input(workspace.path().join(SAMPLES_FILE));
+        if self.video.is_some() {
+            // 映像はエンコード済みなので、そのままコピーする
+            command
+                .input(self.video_output_path())
+                .output_options(["-map", "1:v:0", "-map", "0:a:0", "-c:v", "copy"]);
+        } else {
+            command.output_options(["-vn"]);
+        }
+        command
+            .output_options(TrackCompositor::audio_encoder_options(&self.config))
+            .add_output_option("-f", self.config.format.muxer())
+            .set_output(&self.config.output_path)
+            .overwrite(true);
+        command.execute().map_err(ffmpeg_error)
+    }
+
+    /// Gets where the video encoder writes: the output itself, or an
+    /// intermediate file when audio is muxed in afterwards.
+    fn video_output_path(&self) -> PathBuf {
+        match &self.workspace {
+            Some(workspace) => workspace
+                .path()
+                .join(format!("video.{}", self.config.format.extension())),
+            None => self.config.output_path.clone(),
+        }
+    }
+
+    /// Checks that a frame has the layout of the video input.
+    fn check_layout(&self, pixel_format: PixelFormat, width: u32, height: u32) -> Result<()> {
+        let Some(video) = self.video else {
+            return Err(RenderError::ConfigError(
+                "The encoder takes no video".to_string(),
+            ));
+        };
+        if video.pixel_format != pixel_format || (video.width, video.height) != (width, height) {
+            return Err(RenderError::ProcessingFailed(format!(
+                "Frame is {width}x{height} {}, expected {}x{} {}",
+                pixel_format.as_ffmpeg_str(),
+                video.width,
+                video.height,
+                video.pixel_format.as_ffmpeg_str()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EncoderSink {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Name of the spooled samples in the workspace of a sink.
+const SAMPLES_FILE: &str = "audio.f32le";
+
+/// Checks that an output can be encoded from the given raw inputs.
+fn validate(
+    config: &RenderConfig,
+    video: Option<&VideoInput>,
+    audio: Option<&AudioInput>,
+) -> Result<()> {
+    let format = config.format;
+    if format.is_streaming() {
+        return Err(RenderError::ConfigError(format!(
+            "{format:?} output cannot be encoded from raw frames"
+        )));
+    }
+    if config.rate_control.is_two_pass() {
+        return Err(RenderError::ConfigError(
+            "Two-pass rate control cannot be used with raw frames".to_string(),
+        ));
+    }
+
+    match (video, audio) {
+        (None, None) => {
+            return Err(RenderError::ConfigError(
+                "The encoder needs a video or an audio input".to_string(),
+            ));
+        }
+        (Some(_), _) if format.is_audio_only() => {
+            return Err(RenderError::ConfigError(format!(
+                "{format:?} output cannot store video"
+            )));
+        }
+        (None, Some(_)) if !format.is_audio_only() => {
+            return Err(RenderError::ConfigError(format!(
+                "{format:?} output needs a video input"
+            )));
+        }
+        (_, Some(_)) if !format.supports_audio() => {
+            return Err(RenderError::ConfigError(format!(
+                "{format:?} output cannot store audio"
+            )));
+        }
+        _ => {}
+    }
+
+    if let Some(video) = video {
+        if video.width == 0 || video.height == 0 || video.frame_rate <= 0.0 {
+            return Err(RenderError::ConfigError(format!(
+                "Invalid video input {}x{} at {} fps",
+                video.width, video.height, video.frame_rate
+            )));
+        }
+        if config.video_codec == VideoCodec::Copy && !format.is_image_sequence() {
+            return Err(RenderError::ConfigError(
+                "Raw frames cannot be stream copied".to_string(),
+            ));
+        }
+    }
+    if let Some(audio) = audio {
+        if audio.sample_rate == 0 || audio.channels == 0 {
+            return Err(RenderError::ConfigError(format!(
+                "Invalid audio input of {} channels at {} Hz",
+                audio.channels, audio.sample_rate
+            )));
+        }
+        if config.audio_codec == AudioCodec::Copy {
+            return Err(RenderError::ConfigError(
+                "Raw samples cannot be stream copied".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Converts an `FFmpeg` error into a render error.
+fn ffmpeg_error(error: FFmpegError) -> RenderError {
+    RenderError::FFmpegError(error.to_string())
+}
+
+impl FFmpeg {
+    /// Starts an encoder fed with frames and samples from Rust.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    /// * `video` - Layout of the video frames, or `None` for audio only
+    /// * `audio` - Layout of the audio samples, or `None` for video only
+    ///
+    /// # Returns
+    ///
+    /// The sink to write the frames and samples to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output cannot be encoded from raw input or
+    /// the encoder cannot start.
+    pub fn encoder_sink(
+        &self,
+        config: &RenderConfig,
+        video: Option<VideoInput>,
+        audio: Option<AudioInput>,
+    ) -> Result<EncoderSink> {
+        EncoderSink::new(self, config, video, audio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::rendering::config::OutputFormat;
+    use std::path::Path;
+
+    /// Checks whether an argument is the given path.
+    fn same_path(arg: Option<&str>, path: &Path) -> bool {
+        arg.is_some_and(|arg| Path::new(arg) == path)
+    }
+
+    #[test]
+    fn test_encoder_sink_streams_frames() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let config = RenderConfig::new(PathBuf::from("/srv/out.mp4")).with_resolution(4, 2);
+        let video = VideoInput::from_config(&config, PixelFormat::Rgba);
+
+        let mut sink = ffmpeg.encoder_sink(&config, Some(video), None).unwrap();
+        sink.write_frame(&[7; 32]).unwrap();
+        sink.write_image(&RgbaImage::from_pixel(4, 2, image::Rgba([9, 9, 9, 9])))
+            .unwrap();
+        assert!(sink.write_frame(&[0; 8]).is_err());
+        assert!(sink.write_image(&RgbaImage::new(2, 2)).is_err());
+        assert_eq!(sink.frames_written(), 2);
+        assert_eq!(sink.finish().unwrap(), PathBuf::from("/srv/out.mp4"));
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 1);
+        let call = &calls[0];
+        assert_eq!(call.option_value("-f"), Some("rawvideo"));
+        assert_eq!(call.option_value("-pix_fmt"), Some("rgba"));
+        assert_eq!(call.option_value("-s"), Some("4x2"));
+        assert_eq!(call.option_value("-i"), Some("pipe:0"));
+        assert_eq!(call.option_value("-c:v"), Some("libx264"));
+        assert!(!call.has_arg("-vf"));
+        assert!(same_path(call.last_arg(), Path::new("/srv/out.mp4")));
+
+        let stdin = executor.stdin(executor.calls().len() - 1);
+        assert_eq!(stdin.len(), 64);
+        assert_eq!(&stdin[..32], &[7; 32]);
+        assert_eq!(&stdin[32..], &[9; 32]);
+    }
+
+    #[test]
+    fn test_encoder_sink_muxes_audio() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let config = RenderConfig::new(PathBuf::from("/srv/out.mp4")).with_resolution(8, 4);
+        let video = VideoInput::new(4, 2, 25.0, PixelFormat::Yuv420p);
+
+        let mut sink = ffmpeg
+            .encoder_sink(&config, Some(video), Some(AudioInput::new(48_000, 2)))
+            .unwrap();
+        sink.write_frame(&[0; 12]).unwrap();
+        sink.write_samples(&[0.0, 0.5, -0.5, 1.0]).unwrap();
+        assert!(sink.write_samples(&[0.0]).is_err());
+        assert_eq!(sink.samples_written(), 2);
+        sink.finish().unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls.len(), 2);
+        let (encode, mux) = (&calls[0], &calls[1]);
+        assert_eq!(encode.option_value("-vf"), Some("scale=8:4"));
+        assert!(encode.has_arg("-an"));
+        assert!(!same_path(encode.last_arg(), Path::new("/srv/out.mp4")));
+
+        assert_eq!(mux.option_value("-f"), Some("f32le"));
+        assert_eq!(mux.option_value("-ar"), Some("48000"));
+        assert_eq!(mux.option_values("-map"), vec!["1:v:0", "0:a:0"]);
+        assert_eq!(mux.option_value("-c:v"), Some("copy"));
+        assert_eq!(mux.option_value("-c:a"), Some("aac"));
+        assert!(same_path(mux.last_arg(), Path::new("/srv/out.mp4")));
+    }
+
+    #[test]
+    fn test_encoder_sink_rejects_unsupported_outputs() {
+        let (ffmpeg, _executor) = FFmpeg::recording();
+        let video = Some(VideoInput::new(4, 2, 25.0, PixelFormat::Rgba));
+        let audio = Some(AudioInput::new(48_000, 2));
+
+        let hls = RenderConfig::new(PathBuf::from("/srv/out.m3u8")).with_format(OutputFormat::HLS);
+        assert!(ffmpeg.encoder_sink(&hls, video, None).is_err());
+
+        let gif = RenderConfig::new(PathBuf::from("/srv/out.gif")).with_format(OutputFormat::GIF);
+        assert!(ffmpeg.encoder_sink(&gif, video, audio).is_err());
+
+        let copy = RenderConfig::new(PathBuf::from("/srv/out.mp4"))
+            .with_video_settings(VideoCodec::Copy, 80);
+        assert!(ffmpeg.encoder_sink(&copy, video, None).is_err());
+
+        let wav = RenderConfig::new(PathBuf::from("/srv/out.wav")).with_format(OutputFormat::WAV);
+        assert!(ffmpeg.encoder_sink(&wav, video, audio).is_err());
+        assert!(ffmpeg.encoder_sink(&wav, None, None).is_err());
+        assert!(ffmpeg.encoder_sink(&wav, None, audio).is_ok());
+    }
+
+    #[test]
+    fn test_encoder_sink_reports_encoder_failure() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "rawvideo",
+            ExecutionOutput::failure(
+                1,
+                "/srv/out.mp4: Permission denied\nError opening output files: Permission denied",
+            ),
+        );
+        let config = RenderConfig::new(PathBuf::from("/srv/out.mp4")).with_resolution(4, 2);
+        let video = VideoInput::from_config(&config, PixelFormat::Rgba);
+
+        let sink = ffmpeg.encoder_sink(&config, Some(video), None).unwrap();
+        let error = sink.finish().unwrap_err();
+        assert!(error.to_string().contains("permission denied"), "{error}");
+    }
+}
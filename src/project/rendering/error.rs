@@ -37,6 +37,10 @@ pub enum RenderError {
     #[error("Timeline error: {0}")]
     Timeline(String),
 
+    /// The rendered output does not match the configuration
+    #[error("Output verification failed: {0}")]
+    VerificationFailed(String),
+
     /// Processing failed
     #[error("Processing failed: {0}")]
     ProcessingFailed(String),
@@ -3,7 +3,7 @@
 /// This module provides functionality for GPU-accelerated rendering operations,
 /// optimizing performance by utilizing available hardware acceleration.
 use crate::ffmpeg::{Error as FFmpegError, FFmpeg, Result as FFmpegResult};
-use crate::project::rendering::config::{HardwareAccelType, RenderConfig, VideoCodec};
+use crate::project::rendering::config::{HardwareAccelType, RateControl, RenderConfig, VideoCodec};
 use crate::utility::gpu_test::{self, GpuTestResult};
 use std::collections::HashMap;
 use std::path::Path;
@@ -65,7 +65,10 @@ impl GpuAccelerator {
     ///
     /// A new `GpuAccelerator` instance.
     pub fn new(ffmpeg: Arc<FFmpeg>) -> Self {
-        let available_types = HardwareAccelType::detect_available();
+        let available_types = ffmpeg.capabilities().map_or_else(
+            |_| HardwareAccelType::detect_available(),
+            |capabilities| HardwareAccelType::supported_by(&capabilities),
+        );
         let selected_type = HardwareAccelType::None;
 
         Self {
@@ -162,6 +165,35 @@ impl GpuAccelerator {
         Ok(())
     }
 
+    /// Disables hardware acceleration after a failure caused by it.
+    ///
+    /// The selected method is removed from the available types, so that it
+    /// is not selected again, and the caller can repeat the command with
+    /// software encoding and decoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error of the failed `FFmpeg` command
+    ///
+    /// # Returns
+    ///
+    /// `true` if acceleration was enabled and caused the failure, `false`
+    /// if the command would fail in software as well.
+    pub fn fall_back_to_software(&mut self, error: &FFmpegError) -> bool {
+        let hardware_failure = error
+            .failure_cause()
+            .is_some_and(|cause| cause.is_hardware_related());
+        if !self.is_enabled() || !hardware_failure {
+            return false;
+        }
+
+        let failed_type = self.selected_type;
+        self.available_types.retain(|accel| *accel != failed_type);
+        self.selected_type = HardwareAccelType::None;
+        self.enabled = false;
+        true
+    }
+
     /// Runs benchmarks on all available hardware acceleration types.
     ///
     /// # Returns
@@ -309,18 +341,36 @@ impl GpuAccelerator {
 
     /// Gets appropriate FFmpeg encoder options for hardware-accelerated encoding.
     ///
+    /// Explicit rate-control modes are mapped to the rate-control options of
+    /// the selected encoder. Hardware encoders cannot run FFmpeg's two-pass
+    /// mode: NVENC uses its internal multipass mode instead, and the other
+    /// encoders use a capped VBR at the target bitrate.
+    ///
     /// # Arguments
     ///
     /// * `codec` - The video codec to get options for
     /// * `quality` - Video quality (1-100)
+    /// * `rate_control` - The rate-control mode (resolved to a bitrate when
+    ///   targeting a file size)
     ///
     /// # Returns
     ///
     /// A vector of FFmpeg arguments for hardware-accelerated encoding.
-    pub fn get_encoder_options(&self, codec: VideoCodec, quality: u32) -> Vec<String> {
+    pub fn get_encoder_options(
+        &self,
+        codec: VideoCodec,
+        quality: u32,
+        rate_control: RateControl,
+    ) -> Vec<String> {
         let mut options = Vec::new();
 
-        if !self.is_enabled() {
+        if rate_control != RateControl::Quality {
+            if self.is_enabled() {
+                options = self.get_rate_control_options(rate_control);
+            } else {
+                options = codec.encoder_options(rate_control, quality);
+            }
+        } else if !self.is_enabled() {
             // Software encoding options based on codec and quality
             match codec {
                 VideoCodec::H264 => {
@@ -389,6 +439,13 @@ impl GpuAccelerator {
                     let cpu_used = 8 - ((quality as f32 / 100.0) * 8.0).round() as u32;
                     options.push(cpu_used.to_string());
                 }
+                VideoCodec::ProRes
+                | VideoCodec::ProRes4444
+                | VideoCodec::DnxHr
+                | VideoCodec::Ffv1 => {
+                    // Fixed-rate profiles; these codecs have no quality-based rate control
+                    options = codec.encoder_options(RateControl::Quality, quality);
+                }
                 VideoCodec::Copy => {
                     // No encoding options for copy
                 }
@@ -525,6 +582,156 @@ impl GpuAccelerator {
         options
     }
 
+    /// Gets the rate-control options of the selected hardware encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_control` - The rate-control mode
+    ///
+    /// # Returns
+    ///
+    /// A vector of FFmpeg arguments selecting the rate control.
+    fn get_rate_control_options(&self, rate_control: RateControl) -> Vec<String> {
+        // 2パスはハードウェアでは1回の実行で近似する
+        let rate_control = match rate_control {
+            RateControl::TwoPassBitrate { bitrate }
+                if self.selected_type != HardwareAccelType::Nvidia =>
+            {
+                RateControl::CappedVbr {
+                    bitrate,
+                    max_bitrate: bitrate.saturating_mul(3) / 2,
+                }
+            }
+            other => other,
+        };
+
+        match (self.selected_type, rate_control) {
+            (HardwareAccelType::Nvidia, RateControl::Crf { value }) => vec![
+                "-rc".into(),
+                "vbr".into(),
+                "-cq".into(),
+                value.to_string(),
+                "-b:v".into(),
+                "0".into(),
+            ],
+            (HardwareAccelType::Nvidia, RateControl::Cbr { bitrate }) => {
+                vec![
+                    "-rc".into(),
+                    "cbr".into(),
+                    "-b:v".into(),
+                    format!("{bitrate}k"),
+                ]
+            }
+            (HardwareAccelType::Nvidia, RateControl::TwoPassBitrate { bitrate }) => vec![
+                "-rc".into(),
+                "vbr".into(),
+                "-multipass".into(),
+                "fullres".into(),
+                "-b:v".into(),
+                format!("{bitrate}k"),
+            ],
+            (HardwareAccelType::Amd, RateControl::Crf { value }) => vec![
+                "-rc".into(),
+                "cqp".into(),
+                "-qp_i".into(),
+                value.to_string(),
+                "-qp_p".into(),
+                value.to_string(),
+            ],
+            (HardwareAccelType::Amd, RateControl::Cbr { bitrate }) => {
+                vec![
+                    "-rc".into(),
+                    "cbr".into(),
+                    "-b:v".into(),
+                    format!("{bitrate}k"),
+                ]
+            }
+            (
+                HardwareAccelType::Amd,
+                RateControl::CappedVbr {
+                    bitrate,
+                    max_bitrate,
+                },
+            ) => vec![
+                "-rc".into(),
+                "vbr_peak".into(),
+                "-b:v".into(),
+                format!("{bitrate}k"),
+                "-maxrate".into(),
+                format!("{max_bitrate}k"),
+            ],
+            (HardwareAccelType::Intel, RateControl::Crf { value }) => {
+                vec!["-global_quality".into(), value.to_string()]
+            }
+            (HardwareAccelType::Vaapi, RateControl::Crf { value }) => {
+                vec![
+                    "-rc_mode".into(),
+                    "CQP".into(),
+                    "-qp".into(),
+                    value.to_string(),
+                ]
+            }
+            (HardwareAccelType::Vaapi, RateControl::Cbr { bitrate }) => vec![
+                "-rc_mode".into(),
+                "CBR".into(),
+                "-b:v".into(),
+                format!("{bitrate}k"),
+                "-maxrate".into(),
+                format!("{bitrate}k"),
+            ],
+            (
+                HardwareAccelType::Vaapi,
+                RateControl::CappedVbr {
+                    bitrate,
+                    max_bitrate,
+                },
+            ) => vec![
+                "-rc_mode".into(),
+                "VBR".into(),
+                "-b:v".into(),
+                format!("{bitrate}k"),
+                "-maxrate".into(),
+                format!("{max_bitrate}k"),
+            ],
+            (HardwareAccelType::VideoToolbox, RateControl::Crf { value }) => {
+                // VideoToolbox の品質は 1-100（高いほど高画質）
+                let q = 100 - (value.min(51) * 100 / 51).min(99);
+                vec!["-q:v".into(), q.to_string()]
+            }
+            // NVENC, QSV, VideoToolbox and the other encoders share the
+            // generic bitrate options
+            (_, RateControl::Cbr { bitrate }) => vec![
+                "-b:v".into(),
+                format!("{bitrate}k"),
+                "-minrate".into(),
+                format!("{bitrate}k"),
+                "-maxrate".into(),
+                format!("{bitrate}k"),
+                "-bufsize".into(),
+                format!("{}k", bitrate.saturating_mul(2)),
+            ],
+            (
+                _,
+                RateControl::CappedVbr {
+                    bitrate,
+                    max_bitrate,
+                },
+            ) => vec![
+                "-b:v".into(),
+                format!("{bitrate}k"),
+                "-maxrate".into(),
+                format!("{max_bitrate}k"),
+                "-bufsize".into(),
+                format!("{}k", max_bitrate.saturating_mul(2)),
+            ],
+            (_, RateControl::Crf { value }) => vec!["-qp".into(), value.to_string()],
+            (_, RateControl::TwoPassBitrate { bitrate }) => {
+                vec!["-b:v".into(), format!("{bitrate}k")]
+            }
+            (_, RateControl::Quality | RateControl::TwoPassSize { .. }) => Vec::new(),
+        }
+    }
+
     /// Applies GPU acceleration settings to an FFmpeg command.
     ///
     /// # Arguments
@@ -566,7 +773,7 @@ impl GpuAccelerator {
             command.add_output_option("-c:v", &hw_encoder);
 
             // Add any encoder-specific options
-            let encoder_options = self.get_encoder_options(codec, quality);
+            let encoder_options = self.get_encoder_options(codec, quality, RateControl::Quality);
             for option in encoder_options {
                 let parts: Vec<&str> = option.splitn(2, ' ').collect();
                 if parts.len() == 2 {
@@ -616,10 +823,50 @@ pub fn create_gpu_accelerator(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ffmpeg::FailureCause;
 
     // Tests will be skipped by default as they require FFmpeg
     // To run them, use: cargo test -- --ignored
 
+    #[test]
+    fn test_fall_back_to_software_after_hwaccel_failure() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond(
+            "-encoders",
+            crate::ffmpeg::ExecutionOutput::success(
+                "Encoders:\n ------\n V....D h264_nvenc  NVIDIA NVENC H.264 encoder\n",
+            ),
+        );
+        let mut accelerator = GpuAccelerator::new(Arc::new(ffmpeg));
+        accelerator
+            .select_acceleration_type(HardwareAccelType::Nvidia)
+            .unwrap();
+
+        let unrelated = FFmpegError::Failed {
+            cause: FailureCause::DiskFull,
+            exit_code: Some(1),
+            stderr: String::new(),
+        };
+        assert!(!accelerator.fall_back_to_software(&unrelated));
+        assert!(accelerator.is_enabled());
+
+        let hwaccel = FFmpegError::Failed {
+            cause: FailureCause::HwAccelInit {
+                message: "Cannot load libcuda.so.1".to_string(),
+            },
+            exit_code: Some(1),
+            stderr: String::new(),
+        };
+        assert!(accelerator.fall_back_to_software(&hwaccel));
+        assert!(!accelerator.is_enabled());
+        assert_eq!(accelerator.get_encoder_name(VideoCodec::H264), "libx264");
+        assert!(
+            accelerator
+                .select_acceleration_type(HardwareAccelType::Nvidia)
+                .is_err()
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_gpu_accelerator_initialization() {
@@ -649,12 +896,14 @@ mod tests {
             let mut accelerator = GpuAccelerator::new(ffmpeg);
 
             // Test software encoding options
-            let cpu_options = accelerator.get_encoder_options(VideoCodec::H264, 75);
+            let cpu_options =
+                accelerator.get_encoder_options(VideoCodec::H264, 75, RateControl::Quality);
             println!("CPU encoding options: {:?}", cpu_options);
 
             // Test with a GPU if available
             if let Ok(()) = accelerator.select_acceleration_type(HardwareAccelType::Nvidia) {
-                let gpu_options = accelerator.get_encoder_options(VideoCodec::H264, 75);
+                let gpu_options =
+                    accelerator.get_encoder_options(VideoCodec::H264, 75, RateControl::Quality);
                 println!("GPU encoding options: {:?}", gpu_options);
             }
         }
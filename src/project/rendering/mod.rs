@@ -11,15 +11,40 @@
 pub mod cache;
 pub mod compositor;
 pub mod config;
+pub mod encoder_sink;
 pub mod error;
 pub mod gpu_accelerator;
 pub mod pipeline;
+pub mod presets;
 pub mod progress;
+pub mod queue;
+pub mod segments;
+pub mod stems;
+pub mod streaming;
+pub mod thumbnails;
+pub mod verification;
 
-pub use cache::{CacheEntry, CacheMetadata, RenderCache};
-pub use compositor::{CompositionError, TrackCompositor};
-pub use config::{AudioCodec, OutputFormat, RenderConfig, VideoCodec};
+pub use cache::{
+    CacheEntry, CacheMetadata, CacheStats, PruneReport, RenderCache, SourceFingerprint,
+};
+pub use compositor::{CompositionError, RenderedStreams, TrackCompositor};
+pub use config::{
+    AudioCodec, ImageSequenceOptions, OutputFormat, RateControl, RenderConfig, VideoCodec,
+};
+pub use encoder_sink::{AudioInput, EncoderSink, VideoInput};
 pub use error::{RenderError, Result};
 pub use gpu_accelerator::{GpuAccelerator, create_gpu_accelerator, has_gpu_acceleration};
-pub use pipeline::{RenderPipeline, RenderResult, render_project, render_project_simple};
+pub use pipeline::{
+    RenderPipeline, RenderResult, render_project, render_project_outputs, render_project_simple,
+    render_project_with_cache,
+};
+pub use presets::{PresetRegistry, RenderPreset, RenderSettings};
 pub use progress::{ProgressCallback, RenderProgress, RenderStage, SharedProgressTracker};
+pub use queue::{JobStatus, RenderJob, RenderQueue};
+pub use segments::{TimelineSegment, plan_segments};
+pub use stems::{StemFormat, StemGroup, StemMode, StemOptions};
+pub use streaming::{LadderRung, StreamingOptions};
+pub use thumbnails::{Thumbnail, ThumbnailOptions, ThumbnailSpacing};
+pub use verification::{
+    VerificationCheck, VerificationItem, VerificationOptions, VerificationReport,
+};
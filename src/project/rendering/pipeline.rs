@@ -3,21 +3,27 @@
 /// This module provides the main pipeline for rendering timeline projects
 /// to video files, coordinating the various stages of the rendering process.
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 
+use crate::ffmpeg::{ComponentKind, FFmpeg};
 use crate::project::Project;
-use crate::project::rendering::compositor::TrackCompositor;
+use crate::project::rendering::cache::SourceFingerprint;
+use crate::project::rendering::compositor::{RenderedStreams, TrackCompositor};
 use crate::project::rendering::config::RenderConfig;
 use crate::project::rendering::gpu_accelerator::{self, GpuAccelerator};
 use crate::project::rendering::progress::{
     ProgressCallback, RenderProgress, RenderStage, SharedProgressTracker,
 };
+use crate::project::rendering::segments::{self, TimelineSegment};
+use crate::project::rendering::streaming;
+use crate::project::rendering::verification::{self, ExpectedOutput, VerificationReport};
 use crate::project::rendering::{RenderCache, RenderError};
-use crate::utility::time::Duration;
+use crate::project::timeline::TrackKind;
+use crate::utility::time::{Duration, TimePosition};
 
 /// Result of a rendering operation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RenderResult {
     /// Path to the rendered output file.
     pub output_path: std::path::PathBuf,
@@ -36,6 +42,14 @@ pub struct RenderResult {
 
     /// Whether the result was loaded from cache.
     pub from_cache: bool,
+
+    /// Paths to the rendered audio stems, if stems were enabled.
+    #[serde(default)]
+    pub stems: Vec<std::path::PathBuf>,
+
+    /// Verification of the output against the configuration, if enabled.
+    #[serde(default)]
+    pub verification: Option<VerificationReport>,
 }
 
 /// Manages the rendering pipeline for timeline projects.
@@ -53,14 +67,18 @@ pub struct RenderPipeline {
     /// Start time of the rendering process.
     start_time: Option<std::time::Instant>,
 
-    /// Cache for rendered assets.
-    cache: Option<Arc<RenderCache>>,
+    /// Cache for rendered assets and timeline segments.
+    cache: Option<Arc<Mutex<RenderCache>>>,
 
     /// Whether the pipeline is currently in auto-loading mode.
     auto_loading: bool,
 
     /// GPU accelerator for hardware-accelerated rendering.
     gpu_accelerator: Option<GpuAccelerator>,
+
+    /// Further outputs rendered from the same composite as the main output,
+    /// each with its own progress tracker.
+    additional_outputs: Vec<(RenderConfig, SharedProgressTracker)>,
 }
 
 impl RenderPipeline {
@@ -88,6 +106,41 @@ impl RenderPipeline {
             cache: None,
             auto_loading: false,
             gpu_accelerator: None,
+            additional_outputs: Vec::new(),
+        }
+    }
+
+    /// Adds an output that is rendered together with the main output.
+    ///
+    /// All outputs are produced by `render_outputs` from a single decode and
+    /// composite of the timeline, e.g. a master, a web file and a preview.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    pub fn add_output(&mut self, config: RenderConfig) {
+        self.additional_outputs
+            .push((config, SharedProgressTracker::new()));
+    }
+
+    /// Gets the progress tracker of an output.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the output; 0 is the main output, followed by
+    ///   the outputs in the order they were added
+    ///
+    /// # Returns
+    ///
+    /// The progress tracker, or `None` if there is no such output.
+    #[must_use]
+    pub fn output_progress(&self, index: usize) -> Option<&SharedProgressTracker> {
+        match index {
+            0 => Some(&self.progress),
+            _ => self
+                .additional_outputs
+                .get(index - 1)
+                .map(|(_, progress)| progress),
         }
     }
 
@@ -143,11 +196,24 @@ impl RenderPipeline {
         let render_cache = RenderCache::new(cache_directory, max_cache_size)
             .map_err(|e| RenderError::Cache(format!("Failed to initialize cache: {}", e)))?;
 
-        self.cache = Some(Arc::new(render_cache));
+        self.cache = Some(Arc::new(Mutex::new(render_cache)));
 
         Ok(())
     }
 
+    /// Uses a render cache that is shared with other pipelines.
+    ///
+    /// Pipelines that render at the same time must share one cache instance
+    /// per cache directory. Separate instances on the same directory would
+    /// overwrite each other's index and evict each other's files.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The shared cache
+    pub fn set_cache(&mut self, cache: Arc<Mutex<RenderCache>>) {
+        self.cache = Some(cache);
+    }
+
     /// Auto-loads assets when the project is loaded.
     ///
     /// This renders all assets in the project at load time,
@@ -175,8 +241,12 @@ impl RenderPipeline {
         self.auto_loading = true;
         self.progress.set_stage(RenderStage::Preparing);
 
+        let mut cache_guard = cache
+            .lock()
+            .map_err(|_| RenderError::Cache("Render cache lock is poisoned".to_string()))?;
+
         // レンダリング設定のパラメータハッシュを計算（一度だけ）
-        let params_hash = cache.hash_params(&self.config);
+        let params_hash = cache_guard.hash_params(&self.config);
 
         // 処理が必要なアセットをフィルタリング
         let assets_to_process: Vec<_> = self
@@ -188,11 +258,14 @@ impl RenderPipeline {
                 let is_media = matches!(asset.metadata.asset_type.as_str(), "video" | "audio");
 
                 // すでにキャッシュされているものはスキップ
-                let cached = cache.get(asset.id, params_hash).is_some();
+                // ソースファイルが変更されていればキャッシュは無効
+                let cached = SourceFingerprint::from_asset(asset, self.config.hash_cache_sources)
+                    .is_ok_and(|source| cache_guard.get(asset.id, params_hash, &source).is_some());
 
                 is_media && !cached
             })
             .collect();
+        drop(cache_guard);
 
         // 処理するものがなければ早期リターン
         if assets_to_process.is_empty() {
@@ -204,7 +277,6 @@ impl RenderPipeline {
 
         // rayonを使用して並列処理
         use rayon::prelude::*;
-        use std::sync::{Arc, Mutex};
 
         let errors = Mutex::new(Vec::new());
         let progress = Arc::new(self.progress.clone());
@@ -324,28 +396,35 @@ impl RenderPipeline {
             return Err(RenderError::Cancelled);
         }
 
-        // Create a track compositor
-        let mut compositor =
-            TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+        self.check_capabilities(std::slice::from_ref(&self.config))?;
 
-        // Set progress tracker and optimization flag
-        compositor.set_progress_tracker(self.progress.clone());
-        compositor.set_optimize_complex(self.config.optimize_complex_timelines);
+        // Calculate timeline duration
+        let timeline_duration = Self::calculate_timeline_duration(&self.project);
 
-        // Pass GPU accelerator to compositor if available
-        if let Some(gpu_acc) = &self.gpu_accelerator {
-            compositor.set_gpu_accelerator(gpu_acc.clone());
+        // ステムのみの書き出しでは最終出力をレンダリングしない
+        if self.config.stems.renders_mix() {
+            if self.config.format.is_streaming() {
+                self.render_streaming(timeline_duration)?;
+            } else {
+                self.render_timeline(timeline_duration)?;
+            }
         }
 
-        // Compose and render the timeline
-        compositor.compose(&self.config)?;
+        let stems = if self.config.stems.is_enabled() {
+            self.render_stems()?
+        } else {
+            Vec::new()
+        };
+
+        let verification = if self.config.stems.renders_mix() {
+            self.verify_output(&self.config, timeline_duration)?
+        } else {
+            None
+        };
 
         // Get render time
         let render_time = self.start_time.unwrap().elapsed();
 
-        // Calculate timeline duration
-        let timeline_duration = Self::calculate_timeline_duration(&self.project);
-
         // Calculate total frames
         let total_frames = (timeline_duration.as_seconds() * self.config.frame_rate) as u64;
 
@@ -360,18 +439,539 @@ impl RenderPipeline {
         self.progress.set_stage(RenderStage::Completed);
 
         // Create and return the result
+        let output_path = if self.config.stems.renders_mix() {
+            self.config.output_path.clone()
+        } else {
+            self.config.stems.directory_for(&self.config.output_path)
+        };
         let result = RenderResult {
-            output_path: self.config.output_path.clone(),
+            output_path,
             duration: timeline_duration,
             total_frames,
             render_time,
             average_render_fps: avg_fps,
             from_cache: false,
+            stems,
+            verification,
         };
 
         Ok(result)
     }
 
+    /// Renders the main output and all added outputs.
+    ///
+    /// Outputs that can share a pass are produced from one decode and
+    /// composite of the timeline. Outputs that need their own pass (two-pass
+    /// rate control, HLS/DASH and outputs with stems) are rendered one after
+    /// another afterwards.
+    ///
+    /// # Returns
+    ///
+    /// One result per output, the main output first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an output configuration is invalid or rendering
+    /// any output fails.
+    pub fn render_outputs(&mut self) -> Result<Vec<RenderResult>, RenderError> {
+        if self.additional_outputs.is_empty() {
+            return Ok(vec![self.render()?]);
+        }
+
+        let outputs: Vec<(RenderConfig, SharedProgressTracker)> =
+            std::iter::once((self.config.clone(), self.progress.clone()))
+                .chain(self.additional_outputs.iter().cloned())
+                .collect();
+        for (config, _) in &outputs {
+            config.validate().map_err(|e| {
+                RenderError::ConfigError(format!("{}: {e}", config.output_path.display()))
+            })?;
+        }
+
+        let (shared, separate): (Vec<usize>, Vec<usize>) = (0..outputs.len()).partition(|&i| {
+            let config = &outputs[i].0;
+            !config.format.is_streaming()
+                && !config.rate_control.is_two_pass()
+                && !config.stems.is_enabled()
+        });
+
+        let timeline_duration = Self::calculate_timeline_duration(&self.project);
+        let mut results: Vec<Option<RenderResult>> = vec![None; outputs.len()];
+
+        if !shared.is_empty() {
+            let start_time = std::time::Instant::now();
+            for &i in &shared {
+                let (config, progress) = &outputs[i];
+                let duration = Self::range_duration(config, timeline_duration);
+                progress.set_total((duration.as_seconds() * config.frame_rate) as u64);
+                progress.set_stage(RenderStage::Rendering);
+            }
+
+            let shared_configs: Vec<RenderConfig> =
+                shared.iter().map(|&i| outputs[i].0.clone()).collect();
+            self.check_capabilities(&shared_configs)?;
+            let mut compositor =
+                TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+            compositor.set_progress_tracker(self.progress.clone());
+            // 共有パスの進捗を各出力のフレーム数として報告する
+            compositor.set_output_progress_trackers(
+                shared.iter().map(|&i| outputs[i].1.clone()).collect(),
+            );
+            compositor.set_optimize_complex(self.config.optimize_complex_timelines);
+            if let Some(gpu_acc) = &self.gpu_accelerator {
+                compositor.set_gpu_accelerator(gpu_acc.clone());
+            }
+
+            compositor.compose_outputs(&shared_configs)?;
+
+            let render_time = start_time.elapsed();
+            for &i in &shared {
+                let (config, progress) = &outputs[i];
+                let mut result = Self::output_result(config, timeline_duration, render_time);
+                result.verification = self.verify_output(config, timeline_duration)?;
+                progress.set_stage(RenderStage::Completed);
+                results[i] = Some(result);
+            }
+        }
+
+        // 専用のパスが必要な出力は、設定と進捗を差し替えて個別にレンダリングする
+        for &i in &separate {
+            let (config, progress) = outputs[i].clone();
+            let main_config = std::mem::replace(&mut self.config, config);
+            let main_progress = std::mem::replace(&mut self.progress, progress);
+            let rendered = self.render();
+            self.config = main_config;
+            self.progress = main_progress;
+            results[i] = Some(rendered?);
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Checks that `FFmpeg` has every encoder, muxer, filter and hardware
+    /// acceleration method needed to render the outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `configs` - The rendering configurations of the outputs; several
+    ///   outputs are rendered from one composite
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error naming the first missing component, or
+    /// an `FFmpeg` error if `FFmpeg` cannot be found or queried.
+    fn check_capabilities(&self, configs: &[RenderConfig]) -> Result<(), RenderError> {
+        let ffmpeg = FFmpeg::detect().map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+        let capabilities = ffmpeg
+            .capabilities()
+            .map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+
+        let compositor =
+            TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+        for config in configs {
+            config
+                .check_capabilities(&capabilities)
+                .map_err(RenderError::ConfigError)?;
+
+            let mut filters = compositor.required_filters(config);
+            if configs.len() > 1 {
+                // 合成結果を出力ごとに分岐させるフィルター
+                filters.extend(["split", "asplit", "scale", "setsar", "fps"].map(String::from));
+            }
+            capabilities
+                .require_all(
+                    filters
+                        .iter()
+                        .map(|name| (ComponentKind::Filter, name.as_str())),
+                )
+                .map_err(|e| {
+                    RenderError::ConfigError(format!(
+                        "{e} (required by {})",
+                        config.output_path.display()
+                    ))
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds the result of an output rendered in a shared pass.
+    fn output_result(
+        config: &RenderConfig,
+        timeline_duration: Duration,
+        render_time: std::time::Duration,
+    ) -> RenderResult {
+        let duration = Self::range_duration(config, timeline_duration);
+        let total_frames = (duration.as_seconds() * config.frame_rate) as u64;
+        let average_render_fps = if render_time.as_secs_f64() > 0.0 {
+            total_frames as f64 / render_time.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        RenderResult {
+            output_path: config.output_path.clone(),
+            duration,
+            total_frames,
+            render_time,
+            average_render_fps,
+            from_cache: false,
+            stems: Vec::new(),
+            verification: None,
+        }
+    }
+
+    /// Gets the duration of the rendered range of an output.
+    fn range_duration(config: &RenderConfig, timeline_duration: Duration) -> Duration {
+        let start = config.start_position.unwrap_or_else(TimePosition::zero);
+        let end = config
+            .end_position
+            .unwrap_or_else(|| TimePosition::from_seconds(timeline_duration.as_seconds()));
+        end - start
+    }
+
+    /// Verifies a rendered output against its configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the output
+    /// * `timeline_duration` - Duration of the timeline
+    ///
+    /// # Returns
+    ///
+    /// The verification report, or `None` if verification is disabled or the
+    /// output is not a single media file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output cannot be probed, or if it does not
+    /// match and the configuration fails the render on a mismatch.
+    fn verify_output(
+        &self,
+        config: &RenderConfig,
+        timeline_duration: Duration,
+    ) -> Result<Option<VerificationReport>, RenderError> {
+        if !config.verification.enabled || !verification::is_verifiable(config) {
+            return Ok(None);
+        }
+        self.progress.set_stage(RenderStage::PostProcessing);
+
+        let has_clips = |kind: TrackKind| {
+            self.project
+                .timeline
+                .get_tracks()
+                .iter()
+                .any(|track| track.kind() == kind && !track.get_clips().is_empty())
+        };
+        let expected = ExpectedOutput {
+            duration: Self::range_duration(config, timeline_duration),
+            has_video: has_clips(TrackKind::Video),
+            has_audio: has_clips(TrackKind::Audio),
+        };
+
+        let ffmpeg = FFmpeg::detect().map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+        verification::verify_output(&ffmpeg, config, &expected).map(Some)
+    }
+
+    /// Composes the timeline into the configured output file.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeline_duration` - Duration of the timeline
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if composing or encoding the timeline fails.
+    fn render_timeline(&self, timeline_duration: Duration) -> Result<(), RenderError> {
+        // Split long timelines into segments that are encoded in parallel
+        let segments = self.plan_render_segments(timeline_duration);
+
+        if segments.len() > 1 {
+            self.render_segmented(&segments)?;
+        } else {
+            // Create a track compositor
+            let mut compositor =
+                TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+
+            // Set progress tracker and optimization flag
+            compositor.set_progress_tracker(self.progress.clone());
+            compositor.set_optimize_complex(self.config.optimize_complex_timelines);
+
+            // Pass GPU accelerator to compositor if available
+            if let Some(gpu_acc) = &self.gpu_accelerator {
+                compositor.set_gpu_accelerator(gpu_acc.clone());
+            }
+
+            // Compose and render the timeline
+            compositor.compose(&self.config)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders the audio stems of the timeline.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the rendered stems.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stems cannot be rendered.
+    fn render_stems(&self) -> Result<Vec<std::path::PathBuf>, RenderError> {
+        if self.progress.is_cancelled() {
+            return Err(RenderError::Cancelled);
+        }
+
+        let mut compositor =
+            TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+        compositor.set_progress_tracker(self.progress.clone());
+        compositor.set_optimize_complex(self.config.optimize_complex_timelines);
+
+        Ok(compositor.compose_stems(&self.config)?)
+    }
+
+    /// Renders an HLS or DASH output.
+    ///
+    /// The timeline is rendered into an intermediate file first, which is
+    /// then encoded into every rung of the encoding ladder and segmented.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeline_duration` - Duration of the timeline
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the intermediate render or the packaging fails.
+    fn render_streaming(&mut self, timeline_duration: Duration) -> Result<(), RenderError> {
+        let work_dir = tempfile::TempDir::new()?;
+
+        // 中間ファイルの設定で通常のレンダリング経路（分割・キャッシュ含む）を使う
+        let intermediate = streaming::intermediate_config(&self.config, work_dir.path());
+        let streaming_config = std::mem::replace(&mut self.config, intermediate);
+        let rendered = self.render_timeline(timeline_duration);
+        let intermediate = std::mem::replace(&mut self.config, streaming_config);
+        rendered?;
+
+        if self.progress.is_cancelled() {
+            return Err(RenderError::Cancelled);
+        }
+        self.progress.set_stage(RenderStage::PostProcessing);
+
+        let ffmpeg = FFmpeg::detect().map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+        streaming::package(
+            &ffmpeg,
+            &intermediate.output_path,
+            &self.config,
+            self.has_audio(),
+        )
+    }
+
+    /// Checks whether the timeline has any audio to render.
+    fn has_audio(&self) -> bool {
+        self.project
+            .timeline
+            .get_tracks()
+            .iter()
+            .any(|track| track.kind() == TrackKind::Audio && !track.get_clips().is_empty())
+    }
+
+    /// Plans the segments for parallel rendering.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeline_duration` - Duration of the timeline
+    ///
+    /// # Returns
+    ///
+    /// The planned segments, or an empty vector if the output should be
+    /// rendered in a single pass.
+    fn plan_render_segments(&self, timeline_duration: Duration) -> Vec<TimelineSegment> {
+        if !self.config.supports_segmented_rendering() {
+            return Vec::new();
+        }
+
+        let start = self
+            .config
+            .start_position
+            .unwrap_or_else(TimePosition::zero);
+        let end = self
+            .config
+            .end_position
+            .unwrap_or_else(|| TimePosition::from_seconds(timeline_duration.as_seconds()));
+
+        segments::plan_segments(
+            start,
+            end,
+            self.config.segment_duration,
+            self.config.frame_rate,
+        )
+    }
+
+    /// Renders the given segments in parallel and concatenates them.
+    ///
+    /// When caching is enabled, segments whose clips, assets, effects and
+    /// output settings are unchanged since a previous render are taken from
+    /// the cache, and only the remaining segments are encoded. Newly encoded
+    /// segments are added to the cache once the output has been written.
+    /// The segments only contain video; the audio is encoded once over the
+    /// whole range and added while the segments are joined.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - The segments to render
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the output was written, or an error if rendering failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a segment fails to render, rendering is cancelled,
+    /// or the segments cannot be concatenated.
+    fn render_segmented(&self, segments: &[TimelineSegment]) -> Result<(), RenderError> {
+        let ffmpeg = FFmpeg::detect().map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+
+        // Create output directory if it doesn't exist
+        if let Some(parent) = self.config.output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // セグメントファイル用の作業ディレクトリ（終了時に自動削除）
+        let work_dir = tempfile::TempDir::new()?;
+
+        // 目標ファイルサイズは各セグメントではなく出力全体の長さで換算する
+        let total_duration = segments.iter().fold(Duration::zero(), |total, segment| {
+            total + segment.duration()
+        });
+        let mut config = self.config.clone();
+        config.rate_control = config.resolved_rate_control(total_duration);
+
+        // Progress is aggregated over all segments in frames
+        let total_frames: u64 = segments
+            .iter()
+            .map(|segment| segment.frame_count(self.config.frame_rate))
+            .sum();
+        self.progress.set_total(total_frames);
+        self.progress.set_stage(RenderStage::Rendering);
+
+        let cache = self.cache.as_ref().filter(|_| self.config.use_cache);
+        // ソースファイルの読み込みはキャッシュを使う場合だけ行う
+        let hashes: Vec<u64> = if cache.is_some() {
+            let sources =
+                segments::source_fingerprints(&self.project, self.config.hash_cache_sources);
+            segments
+                .iter()
+                .map(|segment| segments::segment_hash(&self.project, &config, segment, &sources))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // キャッシュ済みのセグメントを探し、変更されたものだけをレンダリングする
+        let mut segment_paths: Vec<Option<PathBuf>> = vec![None; segments.len()];
+        if let Some(cache) = cache {
+            let mut cache = cache
+                .lock()
+                .map_err(|_| RenderError::Cache("Render cache lock is poisoned".to_string()))?;
+            for (position, (path, hash)) in segment_paths.iter_mut().zip(&hashes).enumerate() {
+                // 他のレンダリングの追加で削除されても使えるよう、ロック中に作業ディレクトリへリンクする
+                if let Some(entry) = cache.get_segment(*hash) {
+                    let extension = entry.path.extension().unwrap_or_default();
+                    let linked = work_dir
+                        .path()
+                        .join(format!("cached_{position:05}"))
+                        .with_extension(extension);
+                    if std::fs::hard_link(&entry.path, &linked)
+                        .or_else(|_| std::fs::copy(&entry.path, &linked).map(|_| ()))
+                        .is_ok()
+                    {
+                        *path = Some(linked);
+                    }
+                }
+            }
+        }
+
+        let mut pending_positions = Vec::new();
+        for (position, (segment, path)) in segments.iter().zip(&segment_paths).enumerate() {
+            if path.is_some() {
+                self.progress
+                    .increment_progress(segment.frame_count(self.config.frame_rate));
+            } else {
+                pending_positions.push(position);
+            }
+        }
+
+        let pending: Vec<TimelineSegment> = pending_positions
+            .iter()
+            .map(|&position| segments[position])
+            .collect();
+        let rendered = if pending.is_empty() {
+            Vec::new()
+        } else {
+            segments::render_segments(
+                &self.project,
+                &config,
+                &pending,
+                work_dir.path(),
+                &self.progress,
+                self.gpu_accelerator.as_ref(),
+            )?
+        };
+
+        for (&position, path) in pending_positions.iter().zip(&rendered) {
+            segment_paths[position] = Some(path.clone());
+        }
+        let segment_paths: Vec<PathBuf> = segment_paths.into_iter().flatten().collect();
+
+        // 音声はセグメントごとのプライミングで継ぎ目に隙間ができないよう、範囲全体で一度だけエンコードする
+        let audio_path = if config.format.supports_audio() && self.has_audio() {
+            if self.progress.is_cancelled() {
+                return Err(RenderError::Cancelled);
+            }
+            let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+                return Err(RenderError::Other("No segments to render".to_string()));
+            };
+            let mut audio_config = config.clone().with_range(Some(first.start), Some(last.end));
+            audio_config.output_path = work_dir
+                .path()
+                .join(format!("audio.{}", config.format.extension()));
+
+            let mut compositor =
+                TrackCompositor::new(self.project.timeline.clone(), self.project.assets.clone());
+            compositor.set_optimize_complex(self.config.optimize_complex_timelines);
+            compositor.set_rendered_streams(RenderedStreams::AudioOnly);
+            compositor.compose(&audio_config)?;
+            Some(audio_config.output_path)
+        } else {
+            None
+        };
+
+        self.progress.set_stage(RenderStage::PostProcessing);
+        segments::concat_segments(
+            &ffmpeg,
+            &segment_paths,
+            audio_path.as_deref(),
+            work_dir.path(),
+            &self.config.output_path,
+        )?;
+
+        // 出力の書き込み後にキャッシュへ追加する（追加時の削除で使用中のファイルが消えないように）
+        if let Some(cache) = cache {
+            let mut cache = cache
+                .lock()
+                .map_err(|_| RenderError::Cache("Render cache lock is poisoned".to_string()))?;
+            for (&position, path) in pending_positions.iter().zip(&rendered) {
+                let segment = &segments[position];
+                if let Err(e) = cache.add_segment(hashes[position], path, segment.duration()) {
+                    eprintln!("Failed to cache segment {}: {e}", segment.index);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Renders the project asynchronously.
     ///
     /// # Arguments
@@ -388,7 +988,9 @@ impl RenderPipeline {
     where
         F: FnOnce(Result<RenderResult, RenderError>) + Send + 'static,
     {
+        let default_ffmpeg = FFmpeg::scoped();
         thread::spawn(move || {
+            let _default = default_ffmpeg.map(FFmpeg::scoped_default);
             let result = self.render();
             if let Some(callback) = callback {
                 callback(result.clone());
@@ -452,13 +1054,37 @@ impl RenderPipeline {
 ///
 /// A `Result` containing rendering results on success, or an error if rendering failed.
 pub fn render_project(project: Project, config: RenderConfig) -> Result<RenderResult, RenderError> {
+    render_project_with_cache(project, config, None)
+}
+
+/// Renders a project with a render cache shared with other renders.
+///
+/// # Arguments
+///
+/// * `project` - The project to render
+/// * `config` - The rendering configuration
+/// * `cache` - The shared cache, or `None` to open the cache of `config`
+///
+/// # Returns
+///
+/// The render result, or an error if rendering failed.
+///
+/// # Errors
+///
+/// Returns an error if rendering fails.
+pub fn render_project_with_cache(
+    project: Project,
+    config: RenderConfig,
+    cache: Option<Arc<Mutex<RenderCache>>>,
+) -> Result<RenderResult, RenderError> {
     let mut pipeline = RenderPipeline::new(project, config);
 
     // コアキャッシュディレクトリを使用してキャッシュを初期化
-    if pipeline.config.use_cache {
+    if let Some(cache) = cache.filter(|_| pipeline.config.use_cache) {
+        pipeline.set_cache(cache);
+    } else if pipeline.config.use_cache {
         // コアモジュールからキャッシュディレクトリを取得できない場合は一時ディレクトリを使用
-        let default_cache_dir = std::env::temp_dir().join("edv_cache");
-        let _ = pipeline.init_cache(default_cache_dir, None);
+        let _ = pipeline.init_cache(RenderCache::default_dir(), None);
     }
 
     // 設定に応じてアセットを自動読み込み
@@ -469,6 +1095,47 @@ pub fn render_project(project: Project, config: RenderConfig) -> Result<RenderRe
     pipeline.render()
 }
 
+/// Renders a project to several outputs from a single composite.
+///
+/// The first configuration is the main output; its cache and asset loading
+/// settings apply to the whole render.
+///
+/// # Arguments
+///
+/// * `project` - The project to render
+/// * `configs` - The rendering configurations of the outputs
+///
+/// # Returns
+///
+/// One result per output, in the order of `configs`.
+///
+/// # Errors
+///
+/// Returns an error if no configuration is given or rendering fails.
+pub fn render_project_outputs(
+    project: Project,
+    configs: Vec<RenderConfig>,
+) -> Result<Vec<RenderResult>, RenderError> {
+    let mut configs = configs.into_iter();
+    let main = configs
+        .next()
+        .ok_or_else(|| RenderError::ConfigError("No outputs to render".to_string()))?;
+
+    let mut pipeline = RenderPipeline::new(project, main);
+    for config in configs {
+        pipeline.add_output(config);
+    }
+
+    if pipeline.config.use_cache {
+        let _ = pipeline.init_cache(RenderCache::default_dir(), None);
+    }
+    if pipeline.config.auto_load_assets {
+        let _ = pipeline.auto_load_assets();
+    }
+
+    pipeline.render_outputs()
+}
+
 /// Renders a project to the specified output path with default settings.
 ///
 /// This is a convenience function for quick rendering with default settings.
@@ -0,0 +1,677 @@
+/// Named render presets.
+///
+/// A preset is a named set of render settings that populates a
+/// `RenderConfig`. Built-in presets cover common delivery targets, and users
+/// can add their own presets as TOML or JSON files in the `presets`
+/// directory of the configuration directory. A user preset with the same
+/// name as a built-in preset replaces it.
+///
+/// A preset file contains an optional description, an optional preset to
+/// inherit from, and the settings to apply:
+///
+/// ```toml
+/// description = "720p web upload"
+/// inherits = "web-1080p-h264"
+///
+/// [settings]
+/// width = 1280
+/// height = 720
+/// ```
+///
+/// Settings are layered: the inherited preset first, then the preset's own
+/// settings, then any overrides given on the command line. The resulting
+/// configuration is checked with `RenderConfig::validate`.
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::rendering::config::{
+    AudioCodec, HardwareAccelType, OutputFormat, RateControl, RenderConfig, VideoCodec,
+};
+use crate::project::rendering::stems::{StemFormat, StemGroup, StemMode};
+use crate::project::rendering::streaming::LadderRung;
+use crate::project::rendering::{RenderError, Result};
+use crate::utility::time::Duration;
+
+/// Name of the preset directory inside the configuration directory.
+const PRESET_DIR_NAME: &str = "presets";
+
+/// Render settings that a preset or override can set.
+///
+/// Every field is optional; unset fields leave the configuration unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    /// Video resolution width (in pixels).
+    pub width: Option<u32>,
+    /// Video resolution height (in pixels).
+    pub height: Option<u32>,
+    /// Video frame rate (frames per second).
+    pub frame_rate: Option<f64>,
+    /// Video codec to use.
+    pub video_codec: Option<VideoCodec>,
+    /// Video quality (1-100, higher is better).
+    pub video_quality: Option<u32>,
+    /// Rate-control mode of the video encoder.
+    pub rate_control: Option<RateControl>,
+    /// Audio codec to use.
+    pub audio_codec: Option<AudioCodec>,
+    /// Audio quality (1-100, higher is better).
+    pub audio_quality: Option<u32>,
+    /// Output container format.
+    pub format: Option<OutputFormat>,
+    /// Number of render threads to use.
+    pub threads: Option<usize>,
+    /// Whether to include subtitles in the output.
+    pub include_subtitles: Option<bool>,
+    /// Hardware acceleration type to use.
+    pub hardware_accel_type: Option<HardwareAccelType>,
+    /// Segment duration for parallel rendering; enables segmented rendering,
+    /// or disables it when zero.
+    pub segment_duration: Option<Duration>,
+    /// Encoding ladder of HLS and DASH outputs.
+    pub ladder: Option<Vec<LadderRung>>,
+    /// Media segment duration of HLS and DASH outputs.
+    pub stream_segment_duration: Option<Duration>,
+    /// Number of the first frame of image sequence outputs.
+    pub start_number: Option<u32>,
+    /// Whether image sequence outputs keep the alpha channel.
+    pub alpha: Option<bool>,
+    /// Whether per-track audio stems are rendered.
+    pub stems: Option<StemMode>,
+    /// File format of the audio stems.
+    pub stem_format: Option<StemFormat>,
+    /// Directory of the audio stems.
+    pub stem_directory: Option<PathBuf>,
+    /// Groups of tracks mixed into one stem.
+    pub stem_groups: Option<Vec<StemGroup>>,
+    /// Whether the output is verified after rendering.
+    pub verify: Option<bool>,
+    /// Whether a failed verification fails the render.
+    pub fail_on_mismatch: Option<bool>,
+}
+
+impl RenderSettings {
+    /// Layers other settings on top of these settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - Settings that take precedence where they are set
+    ///
+    /// # Returns
+    ///
+    /// The combined settings.
+    #[must_use]
+    pub fn layered(&self, overrides: &RenderSettings) -> RenderSettings {
+        RenderSettings {
+            width: overrides.width.or(self.width),
+            height: overrides.height.or(self.height),
+            frame_rate: overrides.frame_rate.or(self.frame_rate),
+            video_codec: overrides.video_codec.or(self.video_codec),
+            video_quality: overrides.video_quality.or(self.video_quality),
+            rate_control: overrides.rate_control.or(self.rate_control),
+            audio_codec: overrides.audio_codec.or(self.audio_codec),
+            audio_quality: overrides.audio_quality.or(self.audio_quality),
+            format: overrides.format.or(self.format),
+            threads: overrides.threads.or(self.threads),
+            include_subtitles: overrides.include_subtitles.or(self.include_subtitles),
+            hardware_accel_type: overrides.hardware_accel_type.or(self.hardware_accel_type),
+            segment_duration: overrides.segment_duration.or(self.segment_duration),
+            ladder: overrides.ladder.clone().or_else(|| self.ladder.clone()),
+            stream_segment_duration: overrides
+                .stream_segment_duration
+                .or(self.stream_segment_duration),
+            start_number: overrides.start_number.or(self.start_number),
+            alpha: overrides.alpha.or(self.alpha),
+            stems: overrides.stems.or(self.stems),
+            stem_format: overrides.stem_format.or(self.stem_format),
+            stem_directory: overrides
+                .stem_directory
+                .clone()
+                .or_else(|| self.stem_directory.clone()),
+            stem_groups: overrides
+                .stem_groups
+                .clone()
+                .or_else(|| self.stem_groups.clone()),
+            verify: overrides.verify.or(self.verify),
+            fail_on_mismatch: overrides.fail_on_mismatch.or(self.fail_on_mismatch),
+        }
+    }
+
+    /// Applies the settings to a rendering configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration to update
+    pub fn apply(&self, config: &mut RenderConfig) {
+        if let Some(width) = self.width {
+            config.width = width;
+        }
+        if let Some(height) = self.height {
+            config.height = height;
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            config.frame_rate = frame_rate;
+        }
+        if let Some(video_codec) = self.video_codec {
+            config.video_codec = video_codec;
+        }
+        if let Some(video_quality) = self.video_quality {
+            config.video_quality = video_quality.min(100);
+        }
+        if let Some(rate_control) = self.rate_control {
+            config.rate_control = rate_control;
+        }
+        if let Some(audio_codec) = self.audio_codec {
+            config.audio_codec = audio_codec;
+        }
+        if let Some(audio_quality) = self.audio_quality {
+            config.audio_quality = audio_quality.min(100);
+        }
+        if let Some(format) = self.format {
+            config.format = format;
+        }
+        if let Some(threads) = self.threads {
+            config.threads = Some(threads);
+        }
+        if let Some(include_subtitles) = self.include_subtitles {
+            config.include_subtitles = include_subtitles;
+        }
+        if let Some(hardware_accel_type) = self.hardware_accel_type {
+            config.hardware_accel_type = hardware_accel_type;
+        }
+        // 0 秒は分割レンダリングを無効にする
+        if let Some(segment_duration) = self.segment_duration {
+            config.parallel_segments = segment_duration.as_seconds() > 0.0;
+            if config.parallel_segments {
+                config.segment_duration = segment_duration;
+            }
+        }
+        if let Some(ladder) = &self.ladder {
+            config.streaming.ladder = ladder.clone();
+        }
+        if let Some(stream_segment_duration) = self.stream_segment_duration {
+            config.streaming.segment_duration = stream_segment_duration;
+        }
+        if let Some(start_number) = self.start_number {
+            config.image_sequence.start_number = start_number;
+        }
+        if let Some(alpha) = self.alpha {
+            config.image_sequence.alpha = alpha;
+        }
+        if let Some(stems) = self.stems {
+            config.stems.mode = stems;
+        }
+        if let Some(stem_format) = self.stem_format {
+            config.stems.format = stem_format;
+        }
+        if let Some(stem_directory) = &self.stem_directory {
+            config.stems.directory = Some(stem_directory.clone());
+        }
+        if let Some(stem_groups) = &self.stem_groups {
+            config.stems.groups = stem_groups.clone();
+        }
+        if let Some(verify) = self.verify {
+            config.verification.enabled = verify;
+        }
+        if let Some(fail_on_mismatch) = self.fail_on_mismatch {
+            config.verification.fail_on_mismatch = fail_on_mismatch;
+        }
+    }
+}
+
+/// A named set of render settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderPreset {
+    /// Name of the preset.
+    pub name: String,
+    /// Human-readable description of the preset.
+    pub description: String,
+    /// Settings of the preset, including inherited settings.
+    pub settings: RenderSettings,
+    /// File the preset was loaded from (`None` for built-in presets).
+    pub source: Option<PathBuf>,
+}
+
+impl RenderPreset {
+    /// Builds a rendering configuration from the preset.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_path` - Path of the output file
+    /// * `overrides` - Settings that take precedence over the preset
+    ///
+    /// # Returns
+    ///
+    /// The validated rendering configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resulting configuration is invalid.
+    pub fn to_config(
+        &self,
+        output_path: PathBuf,
+        overrides: &RenderSettings,
+    ) -> Result<RenderConfig> {
+        let mut config = RenderConfig::new(output_path);
+        self.settings.layered(overrides).apply(&mut config);
+
+        config.validate().map_err(|e| {
+            RenderError::ConfigError(format!("Preset \"{}\" is invalid: {e}", self.name))
+        })?;
+
+        Ok(config)
+    }
+}
+
+/// Contents of a user preset file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+    /// Human-readable description of the preset.
+    #[serde(default)]
+    description: Option<String>,
+    /// Name of the preset to inherit settings from.
+    #[serde(default)]
+    inherits: Option<String>,
+    /// Settings of the preset.
+    #[serde(default)]
+    settings: RenderSettings,
+}
+
+/// Collection of the available render presets.
+#[derive(Debug, Clone)]
+pub struct PresetRegistry {
+    /// Presets indexed by name.
+    presets: BTreeMap<String, RenderPreset>,
+}
+
+impl PresetRegistry {
+    /// Creates a registry containing the built-in presets.
+    #[must_use]
+    pub fn builtin() -> Self {
+        let builtin = [
+            (
+                "web-1080p-h264",
+                "1080p H.264/AAC MP4 for web delivery",
+                RenderSettings {
+                    width: Some(1920),
+                    height: Some(1080),
+                    frame_rate: Some(30.0),
+                    video_codec: Some(VideoCodec::H264),
+                    video_quality: Some(75),
+                    audio_codec: Some(AudioCodec::AAC),
+                    audio_quality: Some(60),
+                    format: Some(OutputFormat::MP4),
+                    ..RenderSettings::default()
+                },
+            ),
+            (
+                "archive-prores",
+                "ProRes 422 HQ with uncompressed audio in MOV for archiving",
+                RenderSettings {
+                    video_codec: Some(VideoCodec::ProRes),
+                    video_quality: Some(100),
+                    audio_codec: Some(AudioCodec::Pcm),
+                    audio_quality: Some(100),
+                    format: Some(OutputFormat::MOV),
+                    ..RenderSettings::default()
+                },
+            ),
+            (
+                "social-vertical-9x16",
+                "1080x1920 vertical H.264/AAC MP4 for social media",
+                RenderSettings {
+                    width: Some(1080),
+                    height: Some(1920),
+                    frame_rate: Some(30.0),
+                    video_codec: Some(VideoCodec::H264),
+                    video_quality: Some(70),
+                    audio_codec: Some(AudioCodec::AAC),
+                    audio_quality: Some(60),
+                    format: Some(OutputFormat::MP4),
+                    ..RenderSettings::default()
+                },
+            ),
+        ];
+
+        let presets = builtin
+            .into_iter()
+            .map(|(name, description, settings)| {
+                (
+                    name.to_string(),
+                    RenderPreset {
+                        name: name.to_string(),
+                        description: description.to_string(),
+                        settings,
+                        source: None,
+                    },
+                )
+            })
+            .collect();
+
+        Self { presets }
+    }
+
+    /// Creates a registry with the built-in presets and the user presets.
+    ///
+    /// User presets are read from the `presets` directory inside
+    /// `config_dir`. Files with a `.toml` or `.json` extension are loaded,
+    /// and the file name without extension is the preset name. A missing
+    /// directory simply yields the built-in presets.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_dir` - The user configuration directory
+    ///
+    /// # Returns
+    ///
+    /// The registry with all presets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a preset file cannot be read or parsed, inherits
+    /// from an unknown preset, or produces an invalid configuration.
+    pub fn load(config_dir: &Path) -> Result<Self> {
+        let mut registry = Self::builtin();
+
+        let preset_dir = config_dir.join(PRESET_DIR_NAME);
+        if !preset_dir.is_dir() {
+            return Ok(registry);
+        }
+
+        let entries = fs::read_dir(&preset_dir)
+            .map_err(|e| RenderError::Io(format!("Failed to read preset directory: {e}")))?;
+
+        let mut files = BTreeMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            if let Some(file) = Self::read_file(&path)? {
+                files.insert(name, (path, file));
+            }
+        }
+
+        // 継承元を先に解決するため、名前ごとに再帰的に登録する
+        let names: Vec<String> = files.keys().cloned().collect();
+        for name in names {
+            registry.resolve(&name, &mut files, &mut HashSet::new())?;
+        }
+
+        // 設定として成立するかを読み込み時に検証
+        for preset in registry.presets.values() {
+            preset.to_config(
+                PathBuf::from(preset_file_name(preset)),
+                &RenderSettings::default(),
+            )?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Reads a preset file, or returns `None` if it is not a preset file.
+    fn read_file(path: &Path) -> Result<Option<PresetFile>> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        let parse_error =
+            |e: String| RenderError::ConfigError(format!("Invalid preset {}: {e}", path.display()));
+
+        let file = match extension.as_deref() {
+            Some("toml") => {
+                let contents = Self::read_to_string(path)?;
+                toml::from_str(&contents).map_err(|e| parse_error(e.to_string()))?
+            }
+            Some("json") => {
+                let contents = Self::read_to_string(path)?;
+                serde_json::from_str(&contents).map_err(|e| parse_error(e.to_string()))?
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(file))
+    }
+
+    /// Reads a preset file into a string.
+    fn read_to_string(path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+            .map_err(|e| RenderError::Io(format!("Failed to read preset {}: {e}", path.display())))
+    }
+
+    /// Registers a user preset after registering the preset it inherits from.
+    fn resolve(
+        &mut self,
+        name: &str,
+        files: &mut BTreeMap<String, (PathBuf, PresetFile)>,
+        resolving: &mut HashSet<String>,
+    ) -> Result<()> {
+        let Some((path, file)) = files.remove(name) else {
+            // 既に登録済み（または組み込みプリセット）
+            return Ok(());
+        };
+
+        if !resolving.insert(name.to_string()) {
+            return Err(RenderError::ConfigError(format!(
+                "Preset \"{name}\" inherits from itself"
+            )));
+        }
+
+        let base = match &file.inherits {
+            Some(parent) => {
+                if resolving.contains(parent) {
+                    return Err(RenderError::ConfigError(format!(
+                        "Preset \"{name}\" inherits from itself"
+                    )));
+                }
+                self.resolve(parent, files, resolving)?;
+                self.get(parent)
+                    .map_err(|_| {
+                        RenderError::ConfigError(format!(
+                            "Preset \"{name}\" inherits from unknown preset \"{parent}\""
+                        ))
+                    })?
+                    .settings
+                    .clone()
+            }
+            None => RenderSettings::default(),
+        };
+
+        let preset = RenderPreset {
+            name: name.to_string(),
+            description: file.description.unwrap_or_default(),
+            settings: base.layered(&file.settings),
+            source: Some(path),
+        };
+        self.presets.insert(name.to_string(), preset);
+
+        Ok(())
+    }
+
+    /// Gets a preset by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the preset
+    ///
+    /// # Returns
+    ///
+    /// The preset with the given name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the available presets if there is no such preset.
+    pub fn get(&self, name: &str) -> Result<&RenderPreset> {
+        self.presets.get(name).ok_or_else(|| {
+            let available: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            RenderError::ConfigError(format!(
+                "Unknown preset \"{name}\" (available: {})",
+                available.join(", ")
+            ))
+        })
+    }
+
+    /// Gets all presets ordered by name.
+    pub fn presets(&self) -> impl Iterator<Item = &RenderPreset> {
+        self.presets.values()
+    }
+}
+
+/// Gets an output file name for validating a preset.
+fn preset_file_name(preset: &RenderPreset) -> String {
+    let format = preset.settings.format.unwrap_or_default();
+    if format.is_image_sequence() {
+        format!("preset.%05d.{}", format.extension())
+    } else {
+        format!("preset.{}", format.extension())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_presets_are_valid() {
+        let registry = PresetRegistry::builtin();
+        for name in ["web-1080p-h264", "archive-prores", "social-vertical-9x16"] {
+            let preset = registry.get(name).unwrap();
+            preset
+                .to_config(PathBuf::from("out.mp4"), &RenderSettings::default())
+                .unwrap();
+        }
+
+        let vertical = registry
+            .get("social-vertical-9x16")
+            .unwrap()
+            .to_config(PathBuf::from("out.mp4"), &RenderSettings::default())
+            .unwrap();
+        assert_eq!((vertical.width, vertical.height), (1080, 1920));
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let registry = PresetRegistry::builtin();
+        let overrides = RenderSettings {
+            width: Some(1280),
+            height: Some(720),
+            ..RenderSettings::default()
+        };
+
+        let config = registry
+            .get("web-1080p-h264")
+            .unwrap()
+            .to_config(PathBuf::from("out.mp4"), &overrides)
+            .unwrap();
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert_eq!(config.video_codec, VideoCodec::H264);
+
+        let invalid = RenderSettings {
+            frame_rate: Some(0.0),
+            ..RenderSettings::default()
+        };
+        assert!(
+            registry
+                .get("web-1080p-h264")
+                .unwrap()
+                .to_config(PathBuf::from("out.mp4"), &invalid)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_quality_overrides_are_clamped() {
+        let overrides = RenderSettings {
+            video_quality: Some(250),
+            audio_quality: Some(101),
+            ..RenderSettings::default()
+        };
+
+        let config = PresetRegistry::builtin()
+            .get("web-1080p-h264")
+            .unwrap()
+            .to_config(PathBuf::from("out.mp4"), &overrides)
+            .unwrap();
+        assert_eq!((config.video_quality, config.audio_quality), (100, 100));
+    }
+
+    #[test]
+    fn test_zero_segment_duration_disables_segmenting() {
+        let preset = PresetRegistry::builtin();
+        let preset = preset.get("web-1080p-h264").unwrap();
+        let segmented = RenderSettings {
+            segment_duration: Some(Duration::from_seconds(20.0)),
+            ..RenderSettings::default()
+        };
+        let config = preset
+            .to_config(PathBuf::from("out.mp4"), &segmented)
+            .unwrap();
+        assert!(config.parallel_segments);
+        assert_eq!(config.segment_duration, Duration::from_seconds(20.0));
+
+        let single_pass = RenderSettings {
+            segment_duration: Some(Duration::zero()),
+            ..RenderSettings::default()
+        };
+        let config = preset
+            .to_config(PathBuf::from("out.mp4"), &single_pass)
+            .unwrap();
+        assert!(!config.parallel_segments);
+    }
+
+    #[test]
+    fn test_load_user_presets_with_inheritance() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let preset_dir = config_dir.path().join(PRESET_DIR_NAME);
+        fs::create_dir_all(&preset_dir).unwrap();
+        fs::write(
+            preset_dir.join("web-720p.toml"),
+            "description = \"720p web\"\ninherits = \"web-1080p-h264\"\n\n[settings]\nwidth = 1280\nheight = 720\n",
+        )
+        .unwrap();
+        fs::write(
+            preset_dir.join("web-720p-60.json"),
+            r#"{"inherits": "web-720p", "settings": {"frame_rate": 60.0, "video_codec": "h265", "rate_control": {"mode": "capped_vbr", "bitrate": 4000, "max_bitrate": 6000}}}"#,
+        )
+        .unwrap();
+        fs::write(preset_dir.join("notes.txt"), "ignored").unwrap();
+
+        let registry = PresetRegistry::load(config_dir.path()).unwrap();
+        let preset = registry.get("web-720p-60").unwrap();
+        assert_eq!(preset.settings.width, Some(1280));
+        assert_eq!(preset.settings.frame_rate, Some(60.0));
+        assert_eq!(preset.settings.video_codec, Some(VideoCodec::H265));
+        assert_eq!(
+            preset.settings.rate_control,
+            Some(RateControl::CappedVbr {
+                bitrate: 4000,
+                max_bitrate: 6000
+            })
+        );
+        assert_eq!(preset.settings.audio_codec, Some(AudioCodec::AAC));
+        assert_eq!(registry.get("web-720p").unwrap().description, "720p web");
+        assert!(registry.get("notes").is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_presets() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let preset_dir = config_dir.path().join(PRESET_DIR_NAME);
+        fs::create_dir_all(&preset_dir).unwrap();
+
+        fs::write(preset_dir.join("typo.toml"), "[settings]\nwidht = 1280\n").unwrap();
+        assert!(PresetRegistry::load(config_dir.path()).is_err());
+
+        fs::write(preset_dir.join("typo.toml"), "[settings]\nwidth = 0\n").unwrap();
+        assert!(PresetRegistry::load(config_dir.path()).is_err());
+
+        fs::write(preset_dir.join("typo.toml"), "inherits = \"typo\"\n").unwrap();
+        assert!(PresetRegistry::load(config_dir.path()).is_err());
+    }
+}
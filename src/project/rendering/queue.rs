@@ -0,0 +1,713 @@
+/// Render queue with persisted batch jobs.
+///
+/// This module manages a queue of render jobs stored in a JSON file. Each job
+/// pairs a project file with a `RenderConfig`. The queue is saved after every
+/// state change, so a batch can be resumed after a crash: jobs that were
+/// running when the process stopped are put back into the pending state the
+/// next time the queue is opened.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ffmpeg::FFmpeg;
+use crate::project::Project;
+use crate::project::rendering::cache::RenderCache;
+use crate::project::rendering::config::RenderConfig;
+use crate::project::rendering::pipeline::{RenderResult, render_project_with_cache};
+use crate::project::rendering::{RenderError, Result};
+
+/// Version of the queue file format.
+const QUEUE_VERSION: u32 = 1;
+
+/// Status of a render job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting to be rendered.
+    Pending,
+    /// Currently being rendered.
+    Running,
+    /// Rendered successfully.
+    Completed,
+    /// Rendering failed.
+    Failed,
+}
+
+impl JobStatus {
+    /// Gets a string representation of the status.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A single entry in a job's log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobLogEntry {
+    /// When the entry was written.
+    pub time: DateTime<Utc>,
+    /// The log message.
+    pub message: String,
+}
+
+/// A render job in the queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderJob {
+    /// Unique identifier of the job.
+    pub id: String,
+    /// Path to the project file to render.
+    pub project_path: PathBuf,
+    /// Rendering configuration, including the output path.
+    pub config: RenderConfig,
+    /// Current status of the job.
+    pub status: JobStatus,
+    /// Number of times rendering has been started.
+    pub attempts: u32,
+    /// When the job was added to the queue.
+    pub created_at: DateTime<Utc>,
+    /// When the last attempt finished.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Error message of the last failed attempt.
+    pub error: Option<String>,
+    /// Result of the successful render.
+    pub result: Option<RenderResult>,
+    /// Log of the job's state changes.
+    pub log: Vec<JobLogEntry>,
+}
+
+impl RenderJob {
+    /// Creates a new pending job.
+    fn new(project_path: PathBuf, config: RenderConfig) -> Self {
+        let mut job = Self {
+            id: Uuid::new_v4().to_string(),
+            project_path,
+            config,
+            status: JobStatus::Pending,
+            attempts: 0,
+            created_at: Utc::now(),
+            finished_at: None,
+            error: None,
+            result: None,
+            log: Vec::new(),
+        };
+        job.log("Added to queue");
+        job
+    }
+
+    /// Gets the short form of the job ID used in listings.
+    #[must_use]
+    pub fn short_id(&self) -> &str {
+        &self.id[..8.min(self.id.len())]
+    }
+
+    /// Appends a message to the job log.
+    fn log(&mut self, message: impl Into<String>) {
+        self.log.push(JobLogEntry {
+            time: Utc::now(),
+            message: message.into(),
+        });
+    }
+
+    /// Puts the job back into the pending state.
+    fn reset(&mut self, reason: &str) {
+        self.status = JobStatus::Pending;
+        self.error = None;
+        self.finished_at = None;
+        self.log(reason);
+    }
+}
+
+/// Summary of a queue run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueRunSummary {
+    /// Number of jobs rendered successfully.
+    pub completed: usize,
+    /// Number of jobs that failed.
+    pub failed: usize,
+}
+
+/// Persisted form of the queue.
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueFile {
+    /// Version of the queue file format.
+    version: u32,
+    /// Jobs in the order they were added.
+    jobs: Vec<RenderJob>,
+}
+
+/// A queue of render jobs persisted in a JSON file.
+#[derive(Debug)]
+pub struct RenderQueue {
+    /// Path of the queue file.
+    path: PathBuf,
+    /// Jobs in the order they were added.
+    jobs: Vec<RenderJob>,
+}
+
+impl RenderQueue {
+    /// Opens a queue file, or creates an empty queue if it does not exist.
+    ///
+    /// Jobs that were left running by an interrupted process are reset to
+    /// pending so that they are rendered again by the next run.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the queue file
+    ///
+    /// # Returns
+    ///
+    /// The opened queue.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue file exists but cannot be read or parsed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(Self {
+                path,
+                jobs: Vec::new(),
+            });
+        }
+
+        let json = fs::read_to_string(&path)
+            .map_err(|e| RenderError::Io(format!("Failed to read render queue: {e}")))?;
+        let file: QueueFile = serde_json::from_str(&json)
+            .map_err(|e| RenderError::Other(format!("Invalid render queue file: {e}")))?;
+        if file.version != QUEUE_VERSION {
+            return Err(RenderError::Other(format!(
+                "Unsupported render queue version: {}",
+                file.version
+            )));
+        }
+
+        let mut queue = Self {
+            path,
+            jobs: file.jobs,
+        };
+
+        // クラッシュ等で中断されたジョブを再実行できるように戻す
+        let mut recovered = false;
+        for job in &mut queue.jobs {
+            if job.status == JobStatus::Running {
+                job.reset("Interrupted before finishing; queued again");
+                recovered = true;
+            }
+        }
+        if recovered {
+            queue.save()?;
+        }
+
+        Ok(queue)
+    }
+
+    /// Gets the path of the queue file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the jobs in the queue.
+    #[must_use]
+    pub fn jobs(&self) -> &[RenderJob] {
+        &self.jobs
+    }
+
+    /// Saves the queue to its file.
+    ///
+    /// The queue is written to a temporary file first and then renamed, so
+    /// an interrupted write never leaves a truncated queue behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let file = QueueFile {
+            version: QUEUE_VERSION,
+            jobs: self.jobs.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| RenderError::Other(format!("Failed to serialize render queue: {e}")))?;
+
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| RenderError::Io(format!("Failed to write render queue: {e}")))?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| RenderError::Io(format!("Failed to write render queue: {e}")))?;
+
+        Ok(())
+    }
+
+    /// Adds a job to the queue.
+    ///
+    /// Relative paths are resolved against the current directory before the
+    /// job is saved, so the queue can be run from any directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_path` - Path to the project file to render
+    /// * `config` - Rendering configuration, including the output path
+    ///
+    /// # Returns
+    ///
+    /// The added job.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid, the project file
+    /// does not exist, or the queue cannot be saved.
+    pub fn add(&mut self, project_path: PathBuf, mut config: RenderConfig) -> Result<&RenderJob> {
+        config.validate().map_err(RenderError::ConfigError)?;
+
+        let project_path = fs::canonicalize(&project_path).map_err(|e| {
+            RenderError::Io(format!(
+                "Failed to resolve project path {}: {e}",
+                project_path.display()
+            ))
+        })?;
+        config.output_path = absolute_path(&config.output_path)?;
+        if let Some(cache_dir) = &config.cache_dir {
+            config.cache_dir = Some(absolute_path(cache_dir)?);
+        }
+
+        self.jobs.push(RenderJob::new(project_path, config));
+        self.save()?;
+
+        Ok(&self.jobs[self.jobs.len() - 1])
+    }
+
+    /// Finds the position of a job by its ID or a unique prefix of it.
+    fn find(&self, id: &str) -> Result<usize> {
+        let matches: Vec<usize> = self
+            .jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| job.id.starts_with(id))
+            .map(|(index, _)| index)
+            .collect();
+
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(RenderError::Other(format!("No render job with ID {id}"))),
+            _ => Err(RenderError::Other(format!(
+                "Render job ID {id} is ambiguous"
+            ))),
+        }
+    }
+
+    /// Queues failed jobs to be rendered again.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID or ID prefix of the job to retry, or `None` to retry all failed jobs
+    ///
+    /// # Returns
+    ///
+    /// The number of jobs queued again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job does not exist, has not failed, or the
+    /// queue cannot be saved.
+    pub fn retry(&mut self, id: Option<&str>) -> Result<usize> {
+        let positions: Vec<usize> = match id {
+            Some(id) => {
+                let index = self.find(id)?;
+                if self.jobs[index].status != JobStatus::Failed {
+                    return Err(RenderError::Other(format!(
+                        "Render job {} is {}, only failed jobs can be retried",
+                        self.jobs[index].short_id(),
+                        self.jobs[index].status.as_str()
+                    )));
+                }
+                vec![index]
+            }
+            None => self
+                .jobs
+                .iter()
+                .enumerate()
+                .filter(|(_, job)| job.status == JobStatus::Failed)
+                .map(|(index, _)| index)
+                .collect(),
+        };
+
+        for &index in &positions {
+            self.jobs[index].reset("Queued for retry");
+        }
+        self.save()?;
+
+        Ok(positions.len())
+    }
+
+    /// Renders all pending jobs.
+    ///
+    /// Each job loads its project file and renders it with its configuration.
+    /// Jobs that use the same cache directory share one `RenderCache`, so
+    /// concurrent jobs do not overwrite each other's cache index or evict
+    /// each other's segments.
+    ///
+    /// # Arguments
+    ///
+    /// * `concurrency` - Maximum number of jobs rendered at the same time
+    ///
+    /// # Returns
+    ///
+    /// A summary of the completed and failed jobs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be saved. Failing jobs are
+    /// recorded in the queue and do not stop the run.
+    pub fn run(&mut self, concurrency: usize) -> Result<QueueRunSummary> {
+        let caches = SharedCaches::default();
+        self.run_with(concurrency, |job| {
+            let project = Project::load(&job.project_path)
+                .map_err(|e| RenderError::Other(format!("Failed to load project: {e}")))?;
+            render_project_with_cache(project, job.config.clone(), caches.get(&job.config)?)
+        })
+    }
+
+    /// Renders all pending jobs with a custom render function.
+    ///
+    /// Up to `concurrency` jobs are rendered at the same time, in the order
+    /// they were added. The queue file is saved whenever a job starts or
+    /// finishes.
+    ///
+    /// # Arguments
+    ///
+    /// * `concurrency` - Maximum number of jobs rendered at the same time
+    /// * `render` - Function that renders a single job
+    ///
+    /// # Returns
+    ///
+    /// A summary of the completed and failed jobs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be saved.
+    pub fn run_with<F>(&mut self, concurrency: usize, render: F) -> Result<QueueRunSummary>
+    where
+        F: Fn(&RenderJob) -> Result<RenderResult> + Sync,
+    {
+        let state = Mutex::new((&mut *self, QueueRunSummary::default()));
+        let save_error = Mutex::new(None);
+        let default_ffmpeg = FFmpeg::scoped();
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency.max(1) {
+                scope.spawn(|| {
+                    let _default = default_ffmpeg.clone().map(FFmpeg::scoped_default);
+                    loop {
+                        // 次の保留中ジョブを取り出して実行中にする
+                        let job = {
+                            let Ok(mut guard) = state.lock() else {
+                                return;
+                            };
+                            let queue = &mut guard.0;
+                            let Some(job) = queue
+                                .jobs
+                                .iter_mut()
+                                .find(|job| job.status == JobStatus::Pending)
+                            else {
+                                return;
+                            };
+
+                            job.status = JobStatus::Running;
+                            job.attempts += 1;
+                            job.log(format!("Started attempt {}", job.attempts));
+                            let job = job.clone();
+
+                            if let Err(e) = queue.save() {
+                                if let Ok(mut error) = save_error.lock() {
+                                    *error = Some(e);
+                                }
+                                return;
+                            }
+                            job
+                        };
+
+                        let outcome = render(&job);
+
+                        let Ok(mut guard) = state.lock() else {
+                            return;
+                        };
+                        let (queue, summary) = &mut *guard;
+                        let Some(entry) = queue.jobs.iter_mut().find(|entry| entry.id == job.id)
+                        else {
+                            continue;
+                        };
+
+                        entry.finished_at = Some(Utc::now());
+                        match outcome {
+                            Ok(result) => {
+                                entry.status = JobStatus::Completed;
+                                entry.log(format!(
+                                    "Completed {} in {:.1}s",
+                                    result.output_path.display(),
+                                    result.render_time.as_secs_f64()
+                                ));
+                                entry.result = Some(result);
+                                summary.completed += 1;
+                            }
+                            Err(e) => {
+                                entry.status = JobStatus::Failed;
+                                entry.log(format!("Failed: {e}"));
+                                entry.error = Some(e.to_string());
+                                summary.failed += 1;
+                            }
+                        }
+
+                        if let Err(e) = queue.save() {
+                            if let Ok(mut error) = save_error.lock() {
+                                *error = Some(e);
+                            }
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        if let Some(e) = save_error.into_inner().unwrap_or_default() {
+            return Err(e);
+        }
+
+        let (_, summary) = state
+            .into_inner()
+            .map_err(|_| RenderError::Other("Render queue state is poisoned".to_string()))?;
+        Ok(summary)
+    }
+}
+
+/// Render caches shared by the jobs of a queue run, one per cache directory.
+#[derive(Debug, Default)]
+struct SharedCaches(Mutex<HashMap<PathBuf, Arc<Mutex<RenderCache>>>>);
+
+impl SharedCaches {
+    /// Gets the cache a job renders with, opening it on first use.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration of the job
+    ///
+    /// # Returns
+    ///
+    /// The shared cache, or `None` if the job does not use the cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache cannot be opened.
+    fn get(&self, config: &RenderConfig) -> Result<Option<Arc<Mutex<RenderCache>>>> {
+        if !config.use_cache {
+            return Ok(None);
+        }
+
+        let cache_dir = config
+            .cache_dir
+            .clone()
+            .unwrap_or_else(RenderCache::default_dir);
+        let mut caches = self
+            .0
+            .lock()
+            .map_err(|_| RenderError::Cache("Render cache lock is poisoned".to_string()))?;
+        if let Some(cache) = caches.get(&cache_dir) {
+            return Ok(Some(Arc::clone(cache)));
+        }
+
+        let cache = RenderCache::new(cache_dir.clone(), config.max_cache_size)
+            .map_err(|e| RenderError::Cache(format!("Failed to initialize cache: {e}")))?;
+        let cache = Arc::new(Mutex::new(cache));
+        caches.insert(cache_dir, Arc::clone(&cache));
+        Ok(Some(cache))
+    }
+}
+
+/// Makes a path absolute without requiring it to exist.
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path)
+        .map_err(|e| RenderError::Io(format!("Failed to resolve {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::time::Duration;
+
+    fn config(output: &str) -> RenderConfig {
+        RenderConfig {
+            output_path: PathBuf::from(output),
+            ..RenderConfig::default()
+        }
+    }
+
+    fn project(dir: &tempfile::TempDir, name: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, "{}").unwrap();
+        path
+    }
+
+    fn success(job: &RenderJob) -> Result<RenderResult> {
+        Ok(RenderResult {
+            output_path: job.config.output_path.clone(),
+            duration: Duration::from_seconds(1.0),
+            total_frames: 30,
+            render_time: std::time::Duration::from_millis(10),
+            average_render_fps: 3000.0,
+            from_cache: false,
+            stems: Vec::new(),
+            verification: None,
+        })
+    }
+
+    #[test]
+    fn test_queue_persists_jobs_and_results() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("queue.json");
+
+        let mut queue = RenderQueue::open(&path).unwrap();
+        queue.add(project(&dir, "a.edv"), config("a.mp4")).unwrap();
+        queue.add(project(&dir, "b.edv"), config("b.mp4")).unwrap();
+
+        let summary = queue.run_with(2, success).unwrap();
+        assert_eq!(summary.completed, 2);
+
+        let reopened = RenderQueue::open(&path).unwrap();
+        assert_eq!(reopened.jobs().len(), 2);
+        for job in reopened.jobs() {
+            assert_eq!(job.status, JobStatus::Completed);
+            assert_eq!(job.attempts, 1);
+            assert_eq!(job.result.as_ref().unwrap().total_frames, 30);
+        }
+        assert_eq!(
+            reopened.jobs()[1].config.output_path,
+            std::env::current_dir().unwrap().join("b.mp4")
+        );
+    }
+
+    #[test]
+    fn test_jobs_keep_resolved_paths_when_run_from_another_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("queue.json");
+        let project_path = project(&dir, "a.edv");
+        // カレントディレクトリから見た相対パスで追加する
+        let cwd = std::env::current_dir().unwrap();
+        let mut relative = PathBuf::new();
+        for _ in cwd.components().skip(1) {
+            relative.push("..");
+        }
+        relative.extend(project_path.components().skip(1));
+        assert!(relative.is_relative());
+
+        let mut queue = RenderQueue::open(&path).unwrap();
+        let mut job_config = config("out/a.mp4");
+        job_config.cache_dir = Some(PathBuf::from("cache"));
+        queue.add(relative, job_config).unwrap();
+        assert!(
+            queue
+                .add(PathBuf::from("missing.edv"), config("b.mp4"))
+                .is_err()
+        );
+
+        // 保存されたパスは絶対パスなので、カレントディレクトリに依存しない
+        let mut reopened = RenderQueue::open(&path).unwrap();
+        let summary = reopened
+            .run_with(1, |job| {
+                assert_eq!(job.project_path, fs::canonicalize(&project_path).unwrap());
+                assert!(job.project_path.exists());
+                assert_eq!(job.config.output_path, cwd.join("out/a.mp4"));
+                assert_eq!(job.config.cache_dir, Some(cwd.join("cache")));
+                success(job)
+            })
+            .unwrap();
+        assert_eq!(summary.completed, 1);
+        assert_eq!(summary.failed, 0);
+    }
+
+    #[test]
+    fn test_interrupted_jobs_are_resumed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("queue.json");
+
+        let mut queue = RenderQueue::open(&path).unwrap();
+        queue.add(project(&dir, "a.edv"), config("a.mp4")).unwrap();
+        queue.jobs[0].status = JobStatus::Running;
+        queue.save().unwrap();
+
+        let reopened = RenderQueue::open(&path).unwrap();
+        assert_eq!(reopened.jobs()[0].status, JobStatus::Pending);
+    }
+
+    #[test]
+    fn test_failed_jobs_can_be_retried() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut queue = RenderQueue::open(dir.path().join("queue.json")).unwrap();
+        queue.add(project(&dir, "a.edv"), config("a.mp4")).unwrap();
+
+        let summary = queue
+            .run_with(1, |_| Err(RenderError::Other("boom".to_string())))
+            .unwrap();
+        assert_eq!(summary.failed, 1);
+        assert_eq!(
+            queue.jobs()[0].error.as_deref(),
+            Some("Rendering error: boom")
+        );
+
+        let id = queue.jobs()[0].short_id().to_string();
+        assert_eq!(queue.retry(Some(&id)).unwrap(), 1);
+        assert_eq!(queue.jobs()[0].status, JobStatus::Pending);
+
+        let summary = queue.run_with(1, success).unwrap();
+        assert_eq!(summary.completed, 1);
+        assert_eq!(queue.jobs()[0].attempts, 2);
+        assert!(queue.retry(Some(&id)).is_err());
+    }
+
+    #[test]
+    fn test_add_rejects_invalid_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut queue = RenderQueue::open(dir.path().join("queue.json")).unwrap();
+
+        let mut invalid = config("a.mp4");
+        invalid.width = 0;
+        assert!(queue.add(PathBuf::from("a.edv"), invalid).is_err());
+        assert!(queue.jobs().is_empty());
+    }
+
+    #[test]
+    fn test_jobs_share_one_cache_per_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let caches = SharedCaches::default();
+        let with_cache = |output: &str, cache_dir: &str| {
+            let mut config = config(output);
+            config.use_cache = true;
+            config.cache_dir = Some(dir.path().join(cache_dir));
+            config
+        };
+
+        let first = caches.get(&with_cache("a.mp4", "cache")).unwrap().unwrap();
+        let second = caches.get(&with_cache("b.mp4", "cache")).unwrap().unwrap();
+        let other = caches.get(&with_cache("c.mp4", "other")).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &other));
+
+        let mut uncached = config("d.mp4");
+        uncached.use_cache = false;
+        assert!(caches.get(&uncached).unwrap().is_none());
+    }
+}
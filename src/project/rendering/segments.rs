@@ -0,0 +1,726 @@
+/// Parallel segmented rendering.
+///
+/// This module splits a timeline into segments that can be encoded
+/// independently, renders them concurrently across the available CPU cores
+/// and losslessly concatenates the encoded segments into the final output.
+///
+/// Segment boundaries are always placed on exact frame boundaries. Because
+/// every segment is a separate encode, its first frame is a keyframe, which
+/// allows the segments to be joined with stream copy and no re-encoding.
+/// Segments only contain video: audio encoders such as AAC add priming
+/// samples to every encode, which would leave gaps at the seams, so the
+/// audio is encoded once over the whole range and added when joining.
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::ffmpeg::FFmpeg;
+use crate::project::rendering::cache::{FnvHasher, SourceFingerprint};
+use crate::project::rendering::compositor::{RenderedStreams, TrackCompositor};
+use crate::project::rendering::config::RenderConfig;
+use crate::project::rendering::gpu_accelerator::GpuAccelerator;
+use crate::project::rendering::progress::SharedProgressTracker;
+use crate::project::rendering::{RenderError, Result};
+use crate::project::timeline::TrackId;
+use crate::project::timeline::keyframes::KeyframeAnimation;
+use crate::project::{AssetId, Project};
+use crate::utility::time::{Duration, TimePosition};
+
+/// A contiguous range of the timeline that is encoded on its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineSegment {
+    /// Position of the segment in the rendering order.
+    pub index: usize,
+    /// Start position of the segment on the timeline.
+    pub start: TimePosition,
+    /// End position of the segment on the timeline (exclusive).
+    pub end: TimePosition,
+}
+
+impl TimelineSegment {
+    /// Gets the duration of the segment.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Gets the number of frames in the segment at the given frame rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - The frame rate in frames per second
+    #[must_use]
+    pub fn frame_count(&self, frame_rate: f64) -> u64 {
+        self.end
+            .frames(frame_rate)
+            .saturating_sub(self.start.frames(frame_rate))
+    }
+}
+
+/// Splits a timeline range into frame-aligned segments.
+///
+/// Every segment is roughly `segment_duration` long, snapped to whole frames
+/// so that no frame is duplicated or dropped at the boundaries. The last
+/// segment absorbs any remainder.
+///
+/// # Arguments
+///
+/// * `start` - Start position of the range to render
+/// * `end` - End position of the range to render
+/// * `segment_duration` - Target duration of each segment
+/// * `frame_rate` - Output frame rate
+///
+/// # Returns
+///
+/// The planned segments in timeline order. An empty range yields no segments.
+#[must_use]
+pub fn plan_segments(
+    start: TimePosition,
+    end: TimePosition,
+    segment_duration: Duration,
+    frame_rate: f64,
+) -> Vec<TimelineSegment> {
+    if frame_rate <= 0.0 || end <= start {
+        return Vec::new();
+    }
+
+    let first_frame = start.frames(frame_rate);
+    let last_frame = end.frames(frame_rate);
+    if last_frame <= first_frame {
+        return Vec::new();
+    }
+
+    // At least one frame per segment, even for very short segment durations
+    let frames_per_segment = segment_duration.frames(frame_rate).max(1);
+
+    let mut segments = Vec::new();
+    let mut segment_start = first_frame;
+    while segment_start < last_frame {
+        let mut segment_end = (segment_start + frames_per_segment).min(last_frame);
+
+        // Avoid a tiny trailing segment by merging it into the current one
+        if last_frame - segment_end < frames_per_segment / 2 {
+            segment_end = last_frame;
+        }
+
+        segments.push(TimelineSegment {
+            index: segments.len(),
+            start: TimePosition::from_frames(segment_start as f64, frame_rate),
+            end: TimePosition::from_frames(segment_end as f64, frame_rate),
+        });
+        segment_start = segment_end;
+    }
+
+    segments
+}
+
+/// Computes the cache key of a segment.
+///
+/// The hash covers everything that affects the encoded frames of the segment:
+/// the clips overlapping its time range with their crop and their assets
+/// including the identity of the source files, the tracks that contain those
+/// clips together with their name (which selects the default blend mode and
+/// volume), keyframe animations and relationships to other tracks, and the
+/// output settings of the render. Edits outside the segment's time range do
+/// not change its hash, so unchanged segments can be reused from the cache.
+///
+/// The hash is persisted in the cache index, so it is computed with
+/// `FnvHasher`, which gives the same result in every build.
+///
+/// # Arguments
+///
+/// * `project` - The project being rendered
+/// * `config` - The rendering configuration for the whole output
+/// * `segment` - The segment to hash
+/// * `sources` - Fingerprints of the asset source files, see `source_fingerprints`
+///
+/// # Returns
+///
+/// A hash identifying the encoded contents of the segment.
+#[must_use]
+pub fn segment_hash(
+    project: &Project,
+    config: &RenderConfig,
+    segment: &TimelineSegment,
+    sources: &HashMap<AssetId, SourceFingerprint>,
+) -> u64 {
+    let mut hasher = FnvHasher::default();
+
+    segment.start.frames(config.frame_rate).hash(&mut hasher);
+    segment.end.frames(config.frame_rate).hash(&mut hasher);
+
+    // 出力先やスレッド数はエンコード結果に影響しないため除外する
+    let mut output_settings = config.clone();
+    output_settings.output_path = PathBuf::new();
+    output_settings.threads = None;
+    output_settings.hash(&mut hasher);
+    config.frame_rate.to_bits().hash(&mut hasher);
+
+    // トラックの順序は合成順に影響するため、そのままの順序でハッシュする
+    for track in project.timeline.get_tracks() {
+        let clips: Vec<_> = track
+            .get_clips()
+            .iter()
+            .filter(|clip| clip.position() < segment.end && clip.end_position() > segment.start)
+            .collect();
+        if clips.is_empty() {
+            continue;
+        }
+
+        track.id().hash(&mut hasher);
+        track.kind().as_str().hash(&mut hasher);
+        track.name().hash(&mut hasher);
+        track.is_muted().hash(&mut hasher);
+        if let Some(keyframes) = track.keyframes() {
+            hash_keyframes(keyframes, &mut hasher);
+        }
+        hash_relationships(project, track.id(), &mut hasher);
+
+        for clip in clips {
+            clip.asset_id().hash(&mut hasher);
+            clip.position().as_seconds().to_bits().hash(&mut hasher);
+            clip.duration().as_seconds().to_bits().hash(&mut hasher);
+            clip.source_start().as_seconds().to_bits().hash(&mut hasher);
+            clip.source_end().as_seconds().to_bits().hash(&mut hasher);
+            clip.crop().hash(&mut hasher);
+
+            if let Some(asset) = project.get_asset(clip.asset_id()) {
+                asset.path.hash(&mut hasher);
+                asset.metadata.asset_type.hash(&mut hasher);
+                asset.metadata.dimensions.hash(&mut hasher);
+            }
+            // 同じパスでファイルが差し替えられた場合もハッシュが変わるようにする
+            sources.get(&clip.asset_id()).hash(&mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Reads the fingerprints of the source files of all assets in a project.
+///
+/// The fingerprints are read once per render and shared between the
+/// segments. Assets whose file cannot be read are left out.
+///
+/// # Arguments
+///
+/// * `project` - The project being rendered
+/// * `hash_contents` - Whether to also hash the contents of the source files
+///
+/// # Returns
+///
+/// The fingerprints indexed by asset ID.
+#[must_use]
+pub fn source_fingerprints(
+    project: &Project,
+    hash_contents: bool,
+) -> HashMap<AssetId, SourceFingerprint> {
+    project
+        .assets
+        .iter()
+        .filter_map(|asset| {
+            SourceFingerprint::from_asset(asset, hash_contents)
+                .ok()
+                .map(|fingerprint| (asset.id, fingerprint))
+        })
+        .collect()
+}
+
+/// Feeds the relationships of a track to other tracks into a segment hash,
+/// in both directions and in a stable order.
+fn hash_relationships(project: &Project, track_id: TrackId, hasher: &mut FnvHasher) {
+    let mut relationships: Vec<(String, String, String)> = project
+        .timeline
+        .multi_track_manager()
+        .get_all_relationships()
+        .iter()
+        .flat_map(|(source, targets)| {
+            targets
+                .iter()
+                .map(move |(target, relationship)| (*source, *target, *relationship))
+        })
+        .filter(|(source, target, _)| *source == track_id || *target == track_id)
+        .map(|(source, target, relationship)| {
+            (
+                source.to_string(),
+                target.to_string(),
+                format!("{relationship:?}"),
+            )
+        })
+        .collect();
+    relationships.sort();
+    relationships.hash(hasher);
+}
+
+/// Feeds the keyframes of a track into a segment hash.
+fn hash_keyframes(animation: &KeyframeAnimation, hasher: &mut FnvHasher) {
+    let mut properties = animation.properties();
+    properties.sort();
+
+    for property in properties {
+        let Some(track) = animation.get_track(&property) else {
+            continue;
+        };
+        property.hash(hasher);
+        for keyframe in track.keyframes() {
+            keyframe.time().as_seconds().to_bits().hash(hasher);
+            keyframe.value().to_bits().hash(hasher);
+            keyframe.easing().as_str().hash(hasher);
+        }
+    }
+}
+
+/// Determines how many segments are encoded at the same time.
+///
+/// # Arguments
+///
+/// * `config` - The rendering configuration
+/// * `segment_count` - The number of planned segments
+///
+/// # Returns
+///
+/// The number of concurrent segment encodes, at least one.
+#[must_use]
+pub fn segment_concurrency(config: &RenderConfig, segment_count: usize) -> usize {
+    config
+        .threads
+        .unwrap_or_else(num_cpus::get)
+        .min(segment_count)
+        .max(1)
+}
+
+/// Renders timeline segments in parallel.
+///
+/// Each segment is composed by its own `TrackCompositor` and written to
+/// `work_dir` without audio. At most `segment_concurrency` segments are encoded at once and
+/// the CPU cores are shared between the concurrent `FFmpeg` processes.
+/// Progress is reported in frames through `progress` as segments complete.
+///
+/// # Arguments
+///
+/// * `project` - The project being rendered
+/// * `config` - The rendering configuration for the whole output
+/// * `segments` - The segments to render
+/// * `work_dir` - Directory for the encoded segment files
+/// * `progress` - Progress tracker shared with the pipeline
+/// * `gpu_accelerator` - Optional GPU accelerator for the encodes
+///
+/// # Returns
+///
+/// The paths of the encoded segments in timeline order.
+///
+/// # Errors
+///
+/// Returns an error if any segment fails to render or if rendering is cancelled.
+pub fn render_segments(
+    project: &Project,
+    config: &RenderConfig,
+    segments: &[TimelineSegment],
+    work_dir: &Path,
+    progress: &SharedProgressTracker,
+    gpu_accelerator: Option<&GpuAccelerator>,
+) -> Result<Vec<PathBuf>> {
+    use rayon::prelude::*;
+
+    let concurrency = segment_concurrency(config, segments.len());
+    let threads_per_encode = (num_cpus::get() / concurrency).max(1);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()
+        .map_err(|e| RenderError::Other(format!("Failed to create render thread pool: {e}")))?;
+
+    let errors = Mutex::new(Vec::new());
+    // スレッドごとの既定のFFmpegをワーカースレッドに引き継ぐ
+    let default_ffmpeg = FFmpeg::scoped();
+
+    let outputs: Vec<Option<PathBuf>> = pool.install(|| {
+        segments
+            .par_iter()
+            .map(|segment| {
+                // キャンセルされた場合は残りのセグメントをスキップ
+                if progress.is_cancelled() {
+                    return None;
+                }
+                let _default = default_ffmpeg.clone().map(FFmpeg::scoped_default);
+
+                let output_path = work_dir.join(format!(
+                    "segment_{:05}.{}",
+                    segment.index,
+                    config.format.extension()
+                ));
+
+                let mut segment_config = config
+                    .clone()
+                    .with_range(Some(segment.start), Some(segment.end))
+                    .with_threads(threads_per_encode);
+                segment_config.output_path = output_path.clone();
+
+                let mut compositor =
+                    TrackCompositor::new(project.timeline.clone(), project.assets.clone());
+                compositor.set_optimize_complex(config.optimize_complex_timelines);
+                compositor.set_rendered_streams(RenderedStreams::VideoOnly);
+                if let Some(gpu_acc) = gpu_accelerator {
+                    compositor.set_gpu_accelerator(gpu_acc.clone());
+                }
+
+                match compositor.compose(&segment_config) {
+                    Ok(()) => {
+                        progress.increment_progress(segment.frame_count(config.frame_rate));
+                        Some(output_path)
+                    }
+                    Err(e) => {
+                        if let Ok(mut error_list) = errors.lock() {
+                            error_list.push(format!("Segment {} failed: {e}", segment.index));
+                        }
+                        None
+                    }
+                }
+            })
+            .collect()
+    });
+
+    let error_list = errors.into_inner().unwrap_or_default();
+    if !error_list.is_empty() {
+        return Err(RenderError::ProcessingFailed(error_list.join("; ")));
+    }
+
+    if progress.is_cancelled() {
+        return Err(RenderError::Cancelled);
+    }
+
+    outputs
+        .into_iter()
+        .map(|path| path.ok_or_else(|| RenderError::Other("Segment output is missing".to_string())))
+        .collect()
+}
+
+/// Losslessly concatenates encoded segments into a single output file.
+///
+/// This uses the `FFmpeg` concat demuxer with stream copy, so the segments
+/// must share the same codecs and encoding parameters. The audio, encoded
+/// once for the whole range, is copied alongside the joined video.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` instance to use
+/// * `segment_paths` - Paths of the encoded video segments in timeline order
+/// * `audio_path` - Path of the encoded audio, if the output has audio
+/// * `work_dir` - Directory for the concat list file
+/// * `output_path` - Path of the final output file
+///
+/// # Errors
+///
+/// Returns an error if the concat list cannot be written or `FFmpeg` fails.
+pub fn concat_segments(
+    ffmpeg: &FFmpeg,
+    segment_paths: &[PathBuf],
+    audio_path: Option<&Path>,
+    work_dir: &Path,
+    output_path: &Path,
+) -> Result<()> {
+    if segment_paths.is_empty() {
+        return Err(RenderError::Other("No segments to concatenate".to_string()));
+    }
+
+    let list_path = work_dir.join("segments.txt");
+    fs::write(&list_path, concat_list(segment_paths))?;
+
+    let mut command = ffmpeg.command();
+    command
+        .input_options(["-f", "concat", "-safe", "0"])
+        .input(&list_path);
+    if let Some(audio_path) = audio_path {
+        command
+            .input(audio_path)
+            .output_options(["-map", "0:v:0", "-map", "1:a:0"]);
+    }
+    command
+        .output_options(["-c", "copy"])
+        .set_output(output_path)
+        .overwrite(true);
+
+    command
+        .execute()
+        .map_err(|e| RenderError::FFmpegError(format!("Failed to concatenate segments: {e}")))
+}
+
+/// Builds the contents of a concat demuxer list file.
+fn concat_list(segment_paths: &[PathBuf]) -> String {
+    segment_paths
+        .iter()
+        .map(|path| {
+            // 単一引用符はconcatリストの書式に合わせてエスケープする
+            let escaped = path.to_string_lossy().replace('\'', "'\\''");
+            format!("file '{escaped}'\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::timeline::{Clip, TrackKind};
+    use crate::project::{AssetId, ClipId};
+
+    fn add_clip(project: &mut Project, kind: TrackKind, start: f64, length: f64) {
+        let track_id = project.timeline.add_track(kind);
+        let clip = Clip::new(
+            ClipId::new(),
+            AssetId::new(),
+            TimePosition::from_seconds(start),
+            Duration::from_seconds(length),
+            TimePosition::zero(),
+            TimePosition::from_seconds(length),
+        );
+        project.timeline.add_clip(track_id, clip).unwrap();
+    }
+
+    #[test]
+    fn test_plan_segments_frame_aligned() {
+        let segments = plan_segments(
+            TimePosition::zero(),
+            TimePosition::from_seconds(25.0),
+            Duration::from_seconds(10.0),
+            30.0,
+        );
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].frame_count(30.0), 300);
+        assert_eq!(segments[1].frame_count(30.0), 300);
+        assert_eq!(segments[2].frame_count(30.0), 150);
+
+        // Segments must be contiguous
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+
+        let total: u64 = segments.iter().map(|s| s.frame_count(30.0)).sum();
+        assert_eq!(total, 750);
+    }
+
+    #[test]
+    fn test_plan_segments_merges_short_tail() {
+        let segments = plan_segments(
+            TimePosition::zero(),
+            TimePosition::from_seconds(21.0),
+            Duration::from_seconds(10.0),
+            25.0,
+        );
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].frame_count(25.0), 275);
+    }
+
+    #[test]
+    fn test_plan_segments_empty_range() {
+        let segments = plan_segments(
+            TimePosition::from_seconds(5.0),
+            TimePosition::from_seconds(5.0),
+            Duration::from_seconds(10.0),
+            30.0,
+        );
+        assert!(segments.is_empty());
+    }
+
+    #[test]
+    fn test_concat_list_escapes_quotes() {
+        let list = concat_list(&[
+            PathBuf::from("/tmp/segment_00000.mp4"),
+            PathBuf::from("/tmp/it's.mp4"),
+        ]);
+        assert_eq!(
+            list,
+            "file '/tmp/segment_00000.mp4'\nfile '/tmp/it'\\''s.mp4'\n"
+        );
+    }
+
+    #[test]
+    fn test_concat_segments_muxes_audio() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let segments = [PathBuf::from("segment_00000.mp4")];
+        let audio = PathBuf::from("audio.mp4");
+
+        concat_segments(
+            &ffmpeg,
+            &segments,
+            Some(&audio),
+            work_dir.path(),
+            Path::new("out.mp4"),
+        )
+        .unwrap();
+        concat_segments(
+            &ffmpeg,
+            &segments,
+            None,
+            work_dir.path(),
+            Path::new("out.mp4"),
+        )
+        .unwrap();
+
+        let calls = executor.ffmpeg_calls();
+        assert_eq!(calls[0].option_values("-i")[1], "audio.mp4");
+        assert_eq!(calls[0].option_values("-map"), ["0:v:0", "1:a:0"]);
+        assert_eq!(calls[0].option_value("-c"), Some("copy"));
+        assert!(calls[1].option_values("-map").is_empty());
+    }
+
+    #[test]
+    fn test_render_segments_uses_scoped_ffmpeg_on_pool_threads() {
+        let (ffmpeg, executor) = FFmpeg::recording();
+        let _default = FFmpeg::scoped_default(ffmpeg);
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let config = RenderConfig::new(work_dir.path().join("out.mp4")).with_threads(4);
+        let segments = plan_segments(
+            TimePosition::zero(),
+            TimePosition::from_seconds(40.0),
+            Duration::from_seconds(10.0),
+            config.frame_rate,
+        );
+
+        let mut project = Project::new("segments");
+        add_clip(&mut project, TrackKind::Video, 0.0, 40.0);
+        let paths = render_segments(
+            &project,
+            &config,
+            &segments,
+            work_dir.path(),
+            &SharedProgressTracker::new(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(paths.len(), 4);
+        assert_eq!(executor.ffmpeg_calls().len(), 4);
+    }
+
+    #[test]
+    fn test_segment_hash_only_changes_for_edited_range() {
+        let config = RenderConfig::default();
+        let segments = plan_segments(
+            TimePosition::zero(),
+            TimePosition::from_seconds(30.0),
+            Duration::from_seconds(10.0),
+            config.frame_rate,
+        );
+
+        let mut project = Project::new("hash");
+        add_clip(&mut project, TrackKind::Video, 0.0, 30.0);
+        let before: Vec<u64> = segments
+            .iter()
+            .map(|segment| segment_hash(&project, &config, segment, &HashMap::new()))
+            .collect();
+
+        // Overlay a clip that only covers the last segment
+        add_clip(&mut project, TrackKind::Video, 22.0, 5.0);
+        let after: Vec<u64> = segments
+            .iter()
+            .map(|segment| segment_hash(&project, &config, segment, &HashMap::new()))
+            .collect();
+
+        assert_eq!(before[0], after[0]);
+        assert_eq!(before[1], after[1]);
+        assert_ne!(before[2], after[2]);
+
+        // Segments at different positions never share a hash
+        assert_ne!(before[0], before[1]);
+    }
+
+    #[test]
+    fn test_segment_hash_ignores_output_path() {
+        let mut project = Project::new("hash");
+        add_clip(&mut project, TrackKind::Video, 0.0, 10.0);
+        let segment = TimelineSegment {
+            index: 0,
+            start: TimePosition::zero(),
+            end: TimePosition::from_seconds(10.0),
+        };
+
+        let config = RenderConfig::default();
+        let mut moved = config.clone();
+        moved.output_path = PathBuf::from("/tmp/other.mp4");
+        assert_eq!(
+            segment_hash(&project, &config, &segment, &HashMap::new()),
+            segment_hash(&project, &moved, &segment, &HashMap::new())
+        );
+
+        let resized = config.clone().with_resolution(1280, 720);
+        assert_ne!(
+            segment_hash(&project, &config, &segment, &HashMap::new()),
+            segment_hash(&project, &resized, &segment, &HashMap::new())
+        );
+    }
+
+    #[test]
+    fn test_segment_hash_includes_track_properties() {
+        let mut project = Project::new("hash");
+        add_clip(&mut project, TrackKind::Video, 0.0, 10.0);
+        add_clip(&mut project, TrackKind::Video, 0.0, 10.0);
+        let segment = TimelineSegment {
+            index: 0,
+            start: TimePosition::zero(),
+            end: TimePosition::from_seconds(10.0),
+        };
+        let config = RenderConfig::default();
+        let hash = |project: &Project| segment_hash(project, &config, &segment, &HashMap::new());
+        let original = hash(&project);
+
+        // トラック名は既定のブレンドモードを決める
+        let track_ids: Vec<TrackId> = project
+            .timeline
+            .get_tracks()
+            .iter()
+            .map(|track| track.id())
+            .collect();
+        project
+            .timeline
+            .get_track_mut(track_ids[1])
+            .unwrap()
+            .set_name("Overlay");
+        let renamed = hash(&project);
+        assert_ne!(original, renamed);
+
+        let timeline = project.timeline.clone();
+        project
+            .timeline
+            .multi_track_manager_mut()
+            .add_relationship(
+                track_ids[0],
+                track_ids[1],
+                crate::project::timeline::multi_track::TrackRelationship::VisibilityDependent,
+                &timeline,
+            )
+            .unwrap();
+        assert_ne!(renamed, hash(&project));
+        assert_eq!(hash(&project), hash(&project.clone()));
+    }
+
+    #[test]
+    fn test_segment_hash_includes_source_identity() {
+        let mut project = Project::new("hash");
+        add_clip(&mut project, TrackKind::Video, 0.0, 10.0);
+        let asset_id = project.timeline.get_tracks()[0].get_clips()[0].asset_id();
+        let segment = TimelineSegment {
+            index: 0,
+            start: TimePosition::zero(),
+            end: TimePosition::from_seconds(10.0),
+        };
+        let config = RenderConfig::default();
+
+        let fingerprint = |size| SourceFingerprint {
+            size,
+            modified: None,
+            content_hash: None,
+        };
+        let original = HashMap::from([(asset_id, fingerprint(100))]);
+        let replaced = HashMap::from([(asset_id, fingerprint(200))]);
+
+        assert_ne!(
+            segment_hash(&project, &config, &segment, &original),
+            segment_hash(&project, &config, &segment, &replaced)
+        );
+    }
+}
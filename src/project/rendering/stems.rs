@@ -0,0 +1,295 @@
+/// Per-track audio stem export.
+///
+/// Stems are the audio tracks of a timeline rendered to separate files, so
+/// that dialogue, music and effects can be mixed again elsewhere. Each stem
+/// is a WAV or FLAC file that starts at the start of the rendered range and
+/// lasts exactly as long as the render, so the stems line up with each other
+/// and with the final mix when they are imported into a DAW.
+///
+/// By default every audio track becomes one stem. Groups can be defined to
+/// mix several tracks into one stem, e.g. all dialogue tracks.
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Sample rate stems are written at unless configured otherwise.
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// File format of the stems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StemFormat {
+    /// 24-bit PCM in a WAV file.
+    #[default]
+    Wav,
+    /// Lossless FLAC.
+    Flac,
+}
+
+impl StemFormat {
+    /// Gets the file extension of the format.
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+        }
+    }
+
+    /// Gets the `FFmpeg` audio encoder of the format.
+    #[must_use]
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::Wav => "pcm_s24le",
+            Self::Flac => "flac",
+        }
+    }
+}
+
+impl FromStr for StemFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wav" => Ok(Self::Wav),
+            "flac" => Ok(Self::Flac),
+            _ => Err(format!("Unknown stem format: {s}")),
+        }
+    }
+}
+
+/// Whether stems are rendered, and whether the final mix is rendered too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StemMode {
+    /// Only the final output is rendered (default).
+    #[default]
+    Off,
+    /// The stems are rendered alongside the final output.
+    WithMix,
+    /// Only the stems are rendered.
+    Only,
+}
+
+impl FromStr for StemMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "off" => Ok(Self::Off),
+            "with_mix" | "mix" => Ok(Self::WithMix),
+            "only" => Ok(Self::Only),
+            _ => Err(format!("Unknown stem mode: {s}")),
+        }
+    }
+}
+
+/// A stem mixed from several audio tracks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StemGroup {
+    /// Name of the stem, used as its file name (e.g. `dialogue`).
+    pub name: String,
+    /// Indices of the tracks in the timeline that are mixed into the stem.
+    pub tracks: Vec<usize>,
+}
+
+impl StemGroup {
+    /// Creates a new stem group.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the stem
+    /// * `tracks` - Indices of the tracks in the timeline
+    #[must_use]
+    pub fn new(name: impl Into<String>, tracks: Vec<usize>) -> Self {
+        Self {
+            name: name.into(),
+            tracks,
+        }
+    }
+}
+
+impl FromStr for StemGroup {
+    type Err = String;
+
+    /// Parses a group such as `dialogue=0,2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid stem group (expected name=track,track): {s}");
+        let (name, tracks) = s.split_once('=').ok_or_else(invalid)?;
+        let tracks = tracks
+            .split(',')
+            .map(|track| track.trim().parse::<usize>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(name.trim(), tracks))
+    }
+}
+
+/// Settings of the stem export.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StemOptions {
+    /// Whether stems are rendered.
+    pub mode: StemMode,
+    /// File format of the stems.
+    pub format: StemFormat,
+    /// Sample rate of the stems (Hz).
+    pub sample_rate: u32,
+    /// Directory of the stems (default: `<output name>_stems` next to the output).
+    pub directory: Option<PathBuf>,
+    /// Groups of tracks mixed into one stem; if empty, every audio track is
+    /// rendered to its own stem.
+    pub groups: Vec<StemGroup>,
+}
+
+impl Default for StemOptions {
+    fn default() -> Self {
+        Self {
+            mode: StemMode::Off,
+            format: StemFormat::Wav,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            directory: None,
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl StemOptions {
+    /// Creates stem options that render the stems in the given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Whether to render the final output too
+    /// * `format` - File format of the stems
+    #[must_use]
+    pub fn new(mode: StemMode, format: StemFormat) -> Self {
+        Self {
+            mode,
+            format,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the directory of the stems.
+    #[must_use]
+    pub fn with_directory(mut self, directory: PathBuf) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+
+    /// Adds a group of tracks mixed into one stem.
+    #[must_use]
+    pub fn with_group(mut self, group: StemGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// Determines whether stems are rendered.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.mode != StemMode::Off
+    }
+
+    /// Determines whether the final output is rendered.
+    #[must_use]
+    pub fn renders_mix(&self) -> bool {
+        self.mode != StemMode::Only
+    }
+
+    /// Gets the directory the stems of an output are written to.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_path` - Path of the final output
+    #[must_use]
+    pub fn directory_for(&self, output_path: &Path) -> PathBuf {
+        if let Some(directory) = &self.directory {
+            return directory.clone();
+        }
+        let name = output_path
+            .file_stem()
+            .map_or_else(|| "output".into(), |stem| stem.to_string_lossy());
+        output_path.with_file_name(format!("{name}_stems"))
+    }
+
+    /// Gets the path of a stem.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory of the stems
+    /// * `name` - Name of the stem
+    #[must_use]
+    pub fn stem_path(&self, directory: &Path, name: &str) -> PathBuf {
+        // ファイル名に使えない文字は置き換える
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        directory.join(format!("{file_name}.{}", self.format.extension()))
+    }
+
+    /// Validates the stem options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample rate is zero, or a group has no name,
+    /// no tracks, or the same name as another group.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if self.sample_rate == 0 {
+            return Err("Stem sample rate must be positive".to_string());
+        }
+        for (i, group) in self.groups.iter().enumerate() {
+            if group.name.trim().is_empty() {
+                return Err("Stem group name cannot be empty".to_string());
+            }
+            if group.tracks.is_empty() {
+                return Err(format!("Stem group {} has no tracks", group.name));
+            }
+            if self.groups[..i]
+                .iter()
+                .any(|other| other.name == group.name)
+            {
+                return Err(format!("Duplicate stem group: {}", group.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stem_group() {
+        let group: StemGroup = "dialogue=0, 2".parse().unwrap();
+        assert_eq!(group, StemGroup::new("dialogue", vec![0, 2]));
+        assert!("dialogue".parse::<StemGroup>().is_err());
+        assert!("music=a".parse::<StemGroup>().is_err());
+    }
+
+    #[test]
+    fn test_stem_paths() {
+        let options = StemOptions::new(StemMode::WithMix, StemFormat::Flac);
+        let directory = options.directory_for(Path::new("out/final.mp4"));
+        assert_eq!(directory, PathBuf::from("out/final_stems"));
+        assert_eq!(
+            options.stem_path(&directory, "Music / Score"),
+            PathBuf::from("out/final_stems/Music___Score.flac")
+        );
+
+        let duplicate = options
+            .with_group(StemGroup::new("fx", vec![1]))
+            .with_group(StemGroup::new("fx", vec![2]));
+        assert!(duplicate.validate().is_err());
+    }
+}
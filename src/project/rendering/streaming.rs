@@ -0,0 +1,554 @@
+/// Adaptive streaming output (HLS and DASH).
+///
+/// Streaming outputs are rendered in two steps. The timeline is first
+/// composed into a high-quality intermediate file at the largest resolution
+/// of the encoding ladder. The intermediate is then encoded once per ladder
+/// rung in a single `FFmpeg` run, with keyframes forced at every segment
+/// boundary so that all renditions can be switched between, and the HLS or
+/// DASH muxer writes the segments and the master playlist or MPD.
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ffmpeg::FFmpeg;
+use crate::project::rendering::config::{
+    AudioCodec, OutputFormat, RateControl, RenderConfig, VideoCodec,
+};
+use crate::project::rendering::{RenderError, Result};
+use crate::utility::time::Duration;
+
+/// CRF of the intermediate render; visually lossless for H.264.
+const INTERMEDIATE_CRF: u32 = 12;
+
+/// One rendition of an encoding ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LadderRung {
+    /// Video width (in pixels).
+    pub width: u32,
+    /// Video height (in pixels).
+    pub height: u32,
+    /// Average video bitrate (kbit/s).
+    pub bitrate: u32,
+    /// Maximum video bitrate (kbit/s); defaults to 107% of the bitrate.
+    #[serde(default)]
+    pub max_bitrate: Option<u32>,
+}
+
+impl LadderRung {
+    /// Creates a new ladder rung.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Video width (in pixels)
+    /// * `height` - Video height (in pixels)
+    /// * `bitrate` - Average video bitrate (kbit/s)
+    #[must_use]
+    pub fn new(width: u32, height: u32, bitrate: u32) -> Self {
+        Self {
+            width,
+            height,
+            bitrate,
+            max_bitrate: None,
+        }
+    }
+
+    /// Gets the maximum bitrate of the rung (kbit/s).
+    #[must_use]
+    pub fn max_bitrate(&self) -> u32 {
+        self.max_bitrate
+            .unwrap_or_else(|| self.bitrate.saturating_mul(107) / 100)
+    }
+
+    /// Validates the rung.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size is zero or odd, or the bitrates are invalid.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("Ladder rung dimensions cannot be zero".to_string());
+        }
+        // 4:2:0 のクロマサブサンプリングには偶数のサイズが必要
+        if !self.width.is_multiple_of(2) || !self.height.is_multiple_of(2) {
+            return Err(format!(
+                "Ladder rung dimensions must be even: {}x{}",
+                self.width, self.height
+            ));
+        }
+        if self.bitrate == 0 {
+            return Err("Ladder rung bitrate must be positive".to_string());
+        }
+        if self.max_bitrate() < self.bitrate {
+            return Err("Ladder rung maximum bitrate cannot be lower than its bitrate".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LadderRung {
+    type Err = String;
+
+    /// Parses a rung such as `1280x720@2800` or `1280x720@2800:3000`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("Invalid ladder rung (expected WxH@KBPS[:MAX_KBPS]): {s}");
+        let (size, rates) = s.trim().split_once('@').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let (bitrate, max_bitrate) = match rates.split_once(':') {
+            Some((bitrate, max_bitrate)) => (bitrate, Some(max_bitrate)),
+            None => (rates, None),
+        };
+
+        Ok(Self {
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+            bitrate: bitrate.parse().map_err(|_| invalid())?,
+            max_bitrate: max_bitrate
+                .map(|max| max.parse().map_err(|_| invalid()))
+                .transpose()?,
+        })
+    }
+}
+
+/// Settings of HLS and DASH outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamingOptions {
+    /// Renditions to encode, from highest to lowest quality. When empty, a
+    /// single rendition at the configured resolution is encoded.
+    pub ladder: Vec<LadderRung>,
+    /// Target duration of each media segment; keyframes are aligned to it.
+    pub segment_duration: Duration,
+}
+
+impl Default for StreamingOptions {
+    fn default() -> Self {
+        Self {
+            ladder: Vec::new(),
+            segment_duration: Duration::from_seconds(6.0),
+        }
+    }
+}
+
+impl Hash for StreamingOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ladder.hash(state);
+        self.segment_duration.as_seconds().to_bits().hash(state);
+    }
+}
+
+impl StreamingOptions {
+    /// Parses an encoding ladder such as `1920x1080@5000,1280x720@2800`.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - Comma-separated ladder rungs
+    ///
+    /// # Returns
+    ///
+    /// The parsed rungs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rung cannot be parsed.
+    pub fn parse_ladder(spec: &str) -> std::result::Result<Vec<LadderRung>, String> {
+        spec.split(',')
+            .filter(|rung| !rung.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Gets the rungs to encode for a configuration.
+    ///
+    /// Without an explicit ladder, a single rung at the configured resolution
+    /// is used. Its bitrate comes from the rate control when it sets one and
+    /// is otherwise derived from the video quality.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The rendering configuration
+    ///
+    /// # Returns
+    ///
+    /// The rungs to encode.
+    #[must_use]
+    pub fn rungs(&self, config: &RenderConfig) -> Vec<LadderRung> {
+        if !self.ladder.is_empty() {
+            return self.ladder.clone();
+        }
+
+        let (bitrate, max_bitrate) = match config.rate_control {
+            RateControl::Cbr { bitrate } | RateControl::TwoPassBitrate { bitrate } => {
+                (bitrate, None)
+            }
+            RateControl::CappedVbr {
+                bitrate,
+                max_bitrate,
+            } => (bitrate, Some(max_bitrate)),
+            _ => (
+                ((config.video_quality as f32 / 100.0) * 8000.0 + 2000.0).round() as u32,
+                None,
+            ),
+        };
+
+        vec![LadderRung {
+            width: config.width,
+            height: config.height,
+            bitrate,
+            max_bitrate,
+        }]
+    }
+
+    /// Validates the streaming settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment duration or a ladder rung is invalid.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.segment_duration.as_seconds() <= 0.0 {
+            return Err("Streaming segment duration must be positive".to_string());
+        }
+        self.ladder.iter().try_for_each(LadderRung::validate)
+    }
+}
+
+/// Creates the configuration of the intermediate render of a streaming output.
+///
+/// The intermediate is a near-lossless H.264/FLAC Matroska file at the
+/// largest resolution of the ladder.
+///
+/// # Arguments
+///
+/// * `config` - The streaming output configuration
+/// * `work_dir` - Directory to write the intermediate file to
+///
+/// # Returns
+///
+/// The configuration of the intermediate render.
+#[must_use]
+pub fn intermediate_config(config: &RenderConfig, work_dir: &Path) -> RenderConfig {
+    let rungs = config.streaming.rungs(config);
+    let largest = rungs
+        .iter()
+        .max_by_key(|rung| u64::from(rung.width) * u64::from(rung.height))
+        .copied()
+        .unwrap_or_else(|| LadderRung::new(config.width, config.height, 0));
+
+    let mut intermediate = config.clone();
+    intermediate.output_path = work_dir.join("intermediate.mkv");
+    intermediate.format = OutputFormat::MKV;
+    intermediate.width = largest.width;
+    intermediate.height = largest.height;
+    intermediate.video_codec = VideoCodec::H264;
+    intermediate.rate_control = RateControl::Crf {
+        value: INTERMEDIATE_CRF,
+    };
+    intermediate.audio_codec = AudioCodec::Flac;
+    intermediate
+}
+
+/// Builds the filter graph that scales the intermediate to every rung.
+///
+/// # Arguments
+///
+/// * `rungs` - The rungs to encode
+///
+/// # Returns
+///
+/// The filter graph with one output labelled `[vN]` per rung.
+#[must_use]
+pub fn ladder_filtergraph(rungs: &[LadderRung]) -> String {
+    let scale = |index: usize, rung: &LadderRung| {
+        format!("scale={}:{},setsar=1[v{index}]", rung.width, rung.height)
+    };
+
+    if rungs.len() == 1 {
+        return format!("[0:v]{}", scale(0, &rungs[0]));
+    }
+
+    let split_outputs: String = (0..rungs.len()).map(|i| format!("[s{i}]")).collect();
+    let mut parts = vec![format!("[0:v]split={}{split_outputs}", rungs.len())];
+    parts.extend(
+        rungs
+            .iter()
+            .enumerate()
+            .map(|(i, rung)| format!("[s{i}]{}", scale(i, rung))),
+    );
+    parts.join(";")
+}
+
+/// Builds the output options that encode the ladder and write the stream.
+///
+/// # Arguments
+///
+/// * `config` - The streaming output configuration
+/// * `rungs` - The rungs to encode
+/// * `has_audio` - Whether the intermediate has an audio stream
+///
+/// # Returns
+///
+/// A flat list of `FFmpeg` output arguments.
+#[must_use]
+pub fn ladder_options(config: &RenderConfig, rungs: &[LadderRung], has_audio: bool) -> Vec<String> {
+    let mut options: Vec<String> = Vec::new();
+    let mut push = |option: &str, value: String| {
+        options.push(option.to_string());
+        options.push(value);
+    };
+
+    let segment_seconds = config.streaming.segment_duration.as_seconds();
+    let gop = (segment_seconds * config.frame_rate).round().max(1.0) as u32;
+    let codec = config.video_codec;
+
+    for (i, rung) in rungs.iter().enumerate() {
+        push("-map", format!("[v{i}]"));
+        push(&format!("-c:v:{i}"), codec.to_ffmpeg_codec().to_string());
+        push(&format!("-b:v:{i}"), format!("{}k", rung.bitrate));
+        push(
+            &format!("-maxrate:v:{i}"),
+            format!("{}k", rung.max_bitrate()),
+        );
+        push(
+            &format!("-bufsize:v:{i}"),
+            format!("{}k", rung.max_bitrate().saturating_mul(2)),
+        );
+    }
+
+    // HLS の各バリアントは音声を個別に持つ。DASH では1つの音声を共有する
+    let audio_streams = match (has_audio, config.format) {
+        (false, _) => 0,
+        (true, OutputFormat::HLS) => rungs.len(),
+        (true, _) => 1,
+    };
+    for _ in 0..audio_streams {
+        push("-map", "0:a:0".to_string());
+    }
+    if audio_streams > 0 {
+        push("-c:a", config.audio_codec.to_ffmpeg_codec().to_string());
+        if let Some(bitrate) = config.audio_codec.bitrate(config.audio_quality) {
+            push("-b:a", format!("{bitrate}k"));
+        }
+    }
+
+    // Keyframes at every segment boundary keep the renditions switchable
+    push("-r", config.frame_rate.to_string());
+    push("-g", gop.to_string());
+    push("-keyint_min", gop.to_string());
+    push(
+        "-force_key_frames",
+        format!("expr:gte(t,n_forced*{segment_seconds})"),
+    );
+    match codec {
+        VideoCodec::H264 => push("-sc_threshold", "0".to_string()),
+        VideoCodec::H265 => push(
+            "-x265-params",
+            format!("keyint={gop}:min-keyint={gop}:scenecut=0"),
+        ),
+        _ => {}
+    }
+    if let Some(pixel_format) = codec.pixel_format() {
+        push("-pix_fmt", pixel_format.to_string());
+    }
+    if let Some(threads) = config.threads {
+        push("-threads", threads.to_string());
+    }
+
+    match config.format {
+        OutputFormat::HLS => {
+            let master = config.output_path.file_name().map_or_else(
+                || "master.m3u8".to_string(),
+                |name| name.to_string_lossy().to_string(),
+            );
+            let segment_pattern = output_dir(config).join(if codec == VideoCodec::H265 {
+                "stream_%v_%05d.m4s"
+            } else {
+                "stream_%v_%05d.ts"
+            });
+            let stream_map = (0..rungs.len())
+                .map(|i| {
+                    if audio_streams > 0 {
+                        format!("v:{i},a:{i}")
+                    } else {
+                        format!("v:{i}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            push("-f", "hls".to_string());
+            push("-hls_time", segment_seconds.to_string());
+            push("-hls_playlist_type", "vod".to_string());
+            // HEVC は HLS では fMP4 セグメントが必要
+            if codec == VideoCodec::H265 {
+                push("-hls_segment_type", "fmp4".to_string());
+                push("-tag:v", "hvc1".to_string());
+            }
+            push(
+                "-hls_segment_filename",
+                segment_pattern.display().to_string(),
+            );
+            push("-master_pl_name", master);
+            push("-var_stream_map", stream_map);
+        }
+        _ => {
+            let adaptation_sets = if audio_streams > 0 {
+                "id=0,streams=v id=1,streams=a"
+            } else {
+                "id=0,streams=v"
+            };
+
+            push("-f", "dash".to_string());
+            push("-seg_duration", segment_seconds.to_string());
+            push("-use_template", "1".to_string());
+            push("-use_timeline", "1".to_string());
+            push("-adaptation_sets", adaptation_sets.to_string());
+        }
+    }
+
+    options
+}
+
+/// Gets the path `FFmpeg` writes the stream to.
+///
+/// HLS writes one media playlist per rendition next to the master playlist,
+/// while DASH writes the MPD itself.
+#[must_use]
+pub fn stream_output_path(config: &RenderConfig) -> PathBuf {
+    match config.format {
+        OutputFormat::HLS => output_dir(config).join("stream_%v.m3u8"),
+        _ => config.output_path.clone(),
+    }
+}
+
+/// Gets the directory the stream files are written to.
+fn output_dir(config: &RenderConfig) -> PathBuf {
+    config
+        .output_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Encodes the ladder from an intermediate render and writes the stream.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` instance to use
+/// * `intermediate` - Path of the intermediate render
+/// * `config` - The streaming output configuration
+/// * `has_audio` - Whether the intermediate has an audio stream
+///
+/// # Errors
+///
+/// Returns an error if the output directory cannot be created or `FFmpeg` fails.
+pub fn package(
+    ffmpeg: &FFmpeg,
+    intermediate: &Path,
+    config: &RenderConfig,
+    has_audio: bool,
+) -> Result<()> {
+    let rungs = config.streaming.rungs(config);
+    if let Some(dir) = config.output_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut command = ffmpeg.command();
+    command
+        .input(intermediate)
+        .filter_complex(ladder_filtergraph(&rungs))
+        .output_options(ladder_options(config, &rungs, has_audio))
+        .set_output(stream_output_path(config))
+        .overwrite(true);
+
+    command.execute().map_err(|e| {
+        RenderError::FFmpegError(format!(
+            "Failed to write the {:?} stream: {e}",
+            config.format
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streaming_config(format: OutputFormat, ladder: &str) -> RenderConfig {
+        let mut config = RenderConfig::new(PathBuf::from("dist/master.m3u8"))
+            .with_format(format)
+            .with_frame_rate(25.0);
+        config.streaming.ladder = StreamingOptions::parse_ladder(ladder).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_parse_ladder() {
+        let ladder = StreamingOptions::parse_ladder("1920x1080@5000, 640x360@800:1000").unwrap();
+        assert_eq!(ladder[0], LadderRung::new(1920, 1080, 5000));
+        assert_eq!(ladder[1].max_bitrate(), 1000);
+        assert_eq!(ladder[0].max_bitrate(), 5350);
+
+        assert!(StreamingOptions::parse_ladder("1920x1080").is_err());
+        assert!(LadderRung::new(1279, 720, 2800).validate().is_err());
+    }
+
+    #[test]
+    fn test_ladder_filtergraph() {
+        let rungs = [
+            LadderRung::new(1280, 720, 2800),
+            LadderRung::new(640, 360, 800),
+        ];
+        assert_eq!(
+            ladder_filtergraph(&rungs),
+            "[0:v]split=2[s0][s1];[s0]scale=1280:720,setsar=1[v0];[s1]scale=640:360,setsar=1[v1]"
+        );
+        assert_eq!(
+            ladder_filtergraph(&rungs[..1]),
+            "[0:v]scale=1280:720,setsar=1[v0]"
+        );
+    }
+
+    #[test]
+    fn test_hls_options_align_keyframes() {
+        let config = streaming_config(OutputFormat::HLS, "1280x720@2800,640x360@800");
+        let rungs = config.streaming.rungs(&config);
+        let options = ladder_options(&config, &rungs, true);
+        let value = |option: &str| {
+            options
+                .windows(2)
+                .find(|pair| pair[0] == option)
+                .map(|pair| pair[1].clone())
+        };
+
+        // 6 second segments at 25 fps
+        assert_eq!(value("-g").as_deref(), Some("150"));
+        assert_eq!(value("-sc_threshold").as_deref(), Some("0"));
+        assert_eq!(value("-var_stream_map").as_deref(), Some("v:0,a:0 v:1,a:1"));
+        assert_eq!(value("-master_pl_name").as_deref(), Some("master.m3u8"));
+        assert_eq!(value("-b:v:1").as_deref(), Some("800k"));
+        assert_eq!(options.iter().filter(|o| *o == "0:a:0").count(), 2);
+        assert_eq!(
+            stream_output_path(&config),
+            PathBuf::from("dist/stream_%v.m3u8")
+        );
+    }
+
+    #[test]
+    fn test_dash_options_share_audio() {
+        let config = streaming_config(OutputFormat::DASH, "1280x720@2800,640x360@800");
+        let rungs = config.streaming.rungs(&config);
+        let options = ladder_options(&config, &rungs, true);
+
+        assert_eq!(options.iter().filter(|o| *o == "0:a:0").count(), 1);
+        assert!(
+            options
+                .windows(2)
+                .any(|pair| pair == ["-adaptation_sets", "id=0,streams=v id=1,streams=a"])
+        );
+
+        let intermediate = intermediate_config(&config, Path::new("/tmp/work"));
+        assert_eq!((intermediate.width, intermediate.height), (1280, 720));
+        assert_eq!(intermediate.format, OutputFormat::MKV);
+        assert!(intermediate.validate().is_ok());
+    }
+}
@@ -0,0 +1,619 @@
+/// Thumbnails, timeline strips and contact sheets.
+///
+/// Asset thumbnails are extracted with `FFmpeg`, either evenly spaced over
+/// the asset or at detected scene changes. Timeline thumbnails go through
+/// the compositor, so a strip of a track shows the track as it is rendered.
+/// Both are cached in the `RenderCache` when one is given: asset thumbnails
+/// as asset entries keyed on the time and size, and timeline frames as
+/// one-frame timeline segments, so edits outside a frame keep it cached.
+///
+/// Contact sheets lay thumbnails out in a grid with a timecode label under
+/// each one, for review.
+use std::collections::HashMap;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage, imageops};
+
+use crate::ffmpeg::FFmpeg;
+use crate::project::image_sequence::ImageSequence;
+use crate::project::rendering::cache::{RenderCache, SourceFingerprint};
+use crate::project::rendering::compositor::still_frame_config;
+use crate::project::rendering::segments::{self, TimelineSegment};
+use crate::project::rendering::{RenderError, Result};
+use crate::project::timeline::TrackId;
+use crate::project::{AssetId, AssetReference, Project, ProjectError};
+use crate::utility::time::{Duration, TimePosition};
+
+/// Background color of strips and contact sheets.
+const SHEET_BACKGROUND: Rgba<u8> = Rgba([24, 24, 24, 255]);
+/// Color of the timecode labels.
+const LABEL_COLOR: Rgba<u8> = Rgba([230, 230, 230, 255]);
+/// Space between and around the cells of a contact sheet (in pixels).
+const SHEET_PADDING: u32 = 8;
+/// Size of a label font pixel (in pixels).
+const LABEL_SCALE: u32 = 2;
+
+/// How thumbnails of an asset are placed in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThumbnailSpacing {
+    /// The given number of thumbnails spread evenly over the asset.
+    Even(u32),
+    /// One thumbnail at the start and one at each scene change.
+    Scenes {
+        /// Scene change score (0.0-1.0) above which a frame starts a new scene.
+        threshold: f64,
+        /// Maximum number of thumbnails.
+        max_count: u32,
+    },
+}
+
+/// Size and placement of thumbnails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThumbnailOptions {
+    /// Width of each thumbnail (in pixels).
+    pub width: u32,
+    /// Height of each thumbnail (in pixels).
+    pub height: u32,
+    /// How thumbnails are placed in time.
+    pub spacing: ThumbnailSpacing,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            width: 160,
+            height: 90,
+            spacing: ThumbnailSpacing::Even(10),
+        }
+    }
+}
+
+impl ThumbnailOptions {
+    /// Sets the size of each thumbnail.
+    #[must_use]
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Sets how thumbnails are placed in time.
+    #[must_use]
+    pub fn with_spacing(mut self, spacing: ThumbnailSpacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Validates the options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size or the number of thumbnails is zero, or
+    /// the scene threshold is outside 0.0-1.0.
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(RenderError::ConfigError(
+                "Thumbnail dimensions cannot be zero".to_string(),
+            ));
+        }
+        match self.spacing {
+            ThumbnailSpacing::Even(0) | ThumbnailSpacing::Scenes { max_count: 0, .. } => Err(
+                RenderError::ConfigError("Thumbnail count must be positive".to_string()),
+            ),
+            ThumbnailSpacing::Scenes { threshold, .. } if !(0.0..=1.0).contains(&threshold) => Err(
+                RenderError::ConfigError("Scene threshold must be between 0.0 and 1.0".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A thumbnail and the time it shows.
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// Time of the thumbnail in the asset or on the timeline.
+    pub time: TimePosition,
+    /// The thumbnail image.
+    pub image: RgbaImage,
+}
+
+/// Generates thumbnails of an asset.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` instance to extract frames with
+/// * `asset` - The asset to generate thumbnails of
+/// * `options` - Size and placement of the thumbnails
+/// * `cache` - Cache to reuse and store thumbnails in, if any
+///
+/// # Returns
+///
+/// The thumbnails in time order.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, the duration of the asset
+/// is unknown, or extracting a frame fails.
+pub fn asset_thumbnails(
+    ffmpeg: &FFmpeg,
+    asset: &AssetReference,
+    options: &ThumbnailOptions,
+    mut cache: Option<&mut RenderCache>,
+) -> Result<Vec<Thumbnail>> {
+    options.validate()?;
+
+    let times = match options.spacing {
+        ThumbnailSpacing::Even(count) => {
+            let duration = match asset.metadata.duration {
+                Some(duration) => duration,
+                None => media_duration(ffmpeg, &asset.path)?,
+            };
+            even_times(duration, count)
+        }
+        ThumbnailSpacing::Scenes {
+            threshold,
+            max_count,
+        } => scene_times(ffmpeg, asset, threshold, max_count)?,
+    };
+
+    let source = SourceFingerprint::from_asset(asset, false)?;
+    let work_dir = tempfile::TempDir::new()?;
+    let mut thumbnails = Vec::with_capacity(times.len());
+    for time in times {
+        let params_hash = thumbnail_hash(time, options);
+        let cached = cache
+            .as_deref_mut()
+            .and_then(|cache| cache.get(asset.id, params_hash, &source))
+            .map(|entry| entry.path.clone());
+        if let Some(image) = cached.and_then(|path| load_image(&path).ok()) {
+            thumbnails.push(Thumbnail { time, image });
+            continue;
+        }
+
+        let frame_path = work_dir.path().join("thumbnail.png");
+        extract_frame(ffmpeg, asset, time, options, &frame_path)?;
+        if let Some(cache) = cache.as_deref_mut() {
+            cache.add(
+                asset.id,
+                params_hash,
+                source.clone(),
+                &frame_path,
+                Duration::zero(),
+            )?;
+        }
+        thumbnails.push(Thumbnail {
+            time,
+            image: load_image(&frame_path)?,
+        });
+    }
+
+    Ok(thumbnails)
+}
+
+/// Renders evenly spaced thumbnails of the whole timeline.
+///
+/// # Arguments
+///
+/// * `project` - The project to render
+/// * `count` - Number of thumbnails
+/// * `size` - Width and height of each thumbnail (in pixels)
+/// * `cache` - Cache to reuse and store frames in, if any
+///
+/// # Returns
+///
+/// The thumbnails in time order.
+///
+/// # Errors
+///
+/// Returns an error if rendering a frame fails.
+pub fn timeline_thumbnails(
+    project: &Project,
+    count: u32,
+    size: (u32, u32),
+    mut cache: Option<&mut RenderCache>,
+) -> Result<Vec<Thumbnail>> {
+    let sources = segments::source_fingerprints(project, false);
+    even_times(project.timeline.duration(), count)
+        .into_iter()
+        .map(|time| {
+            let image = timeline_frame(project, time, size, &sources, cache.as_deref_mut())?;
+            Ok(Thumbnail { time, image })
+        })
+        .collect()
+}
+
+/// Renders a thumbnail strip of one track.
+///
+/// The track is rendered on its own at evenly spaced times over the
+/// timeline, and the frames are placed side by side.
+///
+/// # Arguments
+///
+/// * `project` - The project containing the track
+/// * `track_id` - The track to render
+/// * `count` - Number of frames in the strip
+/// * `size` - Width and height of each frame (in pixels)
+/// * `cache` - Cache to reuse and store frames in, if any
+///
+/// # Returns
+///
+/// The strip image.
+///
+/// # Errors
+///
+/// Returns an error if the track does not exist or rendering a frame fails.
+pub fn track_strip(
+    project: &Project,
+    track_id: TrackId,
+    count: u32,
+    size: (u32, u32),
+    cache: Option<&mut RenderCache>,
+) -> Result<RgbaImage> {
+    if !project.timeline.has_track(track_id) {
+        return Err(RenderError::Timeline(format!(
+            "Track not found: {track_id}"
+        )));
+    }
+
+    // 対象トラックだけを残したプロジェクトで合成する
+    let mut single_track = project.clone();
+    let other_tracks: Vec<_> = single_track
+        .timeline
+        .get_tracks()
+        .iter()
+        .map(|track| track.id())
+        .filter(|id| *id != track_id)
+        .collect();
+    for id in other_tracks {
+        single_track
+            .timeline
+            .remove_track(id)
+            .map_err(|e| RenderError::Timeline(e.to_string()))?;
+    }
+    // 他のトラックを外しても、時間軸はタイムライン全体に揃える
+    let sources = segments::source_fingerprints(&single_track, false);
+    let times = even_times(project.timeline.duration(), count);
+
+    let (width, height) = size;
+    let mut strip = RgbaImage::from_pixel(width * times.len() as u32, height, SHEET_BACKGROUND);
+    let mut cache = cache;
+    for (i, time) in times.into_iter().enumerate() {
+        let frame = timeline_frame(&single_track, time, size, &sources, cache.as_deref_mut())?;
+        imageops::overlay(&mut strip, &frame, i64::from(width) * i as i64, 0);
+    }
+
+    Ok(strip)
+}
+
+/// Lays thumbnails out in a grid with a timecode label under each one.
+///
+/// # Arguments
+///
+/// * `thumbnails` - The thumbnails in display order
+/// * `columns` - Number of thumbnails per row
+/// * `fps` - Frame rate used for the frame field of the timecodes
+///
+/// # Returns
+///
+/// The contact sheet image, or an empty image if there are no thumbnails.
+#[must_use]
+pub fn contact_sheet(thumbnails: &[Thumbnail], columns: u32, fps: f64) -> RgbaImage {
+    let Some(first) = thumbnails.first() else {
+        return RgbaImage::new(0, 0);
+    };
+    let columns = columns.clamp(1, thumbnails.len() as u32);
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+
+    let (cell_width, cell_height) = first.image.dimensions();
+    let label_height = GLYPH_HEIGHT * LABEL_SCALE + SHEET_PADDING / 2;
+    let row_height = cell_height + label_height + SHEET_PADDING;
+    let width = columns * (cell_width + SHEET_PADDING) + SHEET_PADDING;
+    let height = rows * row_height + SHEET_PADDING;
+
+    let mut sheet = RgbaImage::from_pixel(width, height, SHEET_BACKGROUND);
+    for (i, thumbnail) in thumbnails.iter().enumerate() {
+        let column = i as u32 % columns;
+        let row = i as u32 / columns;
+        let x = SHEET_PADDING + column * (cell_width + SHEET_PADDING);
+        let y = SHEET_PADDING + row * row_height;
+
+        imageops::overlay(&mut sheet, &thumbnail.image, i64::from(x), i64::from(y));
+        draw_label(
+            &mut sheet,
+            &thumbnail.time.to_timecode(fps),
+            x,
+            y + cell_height + SHEET_PADDING / 2,
+        );
+    }
+
+    sheet
+}
+
+/// Gets evenly spaced times over a duration.
+///
+/// Each time is in the middle of its share of the duration, which avoids
+/// the often black first and last frames.
+fn even_times(duration: Duration, count: u32) -> Vec<TimePosition> {
+    let step = duration.as_seconds() / f64::from(count.max(1));
+    (0..count)
+        .map(|i| TimePosition::from_seconds(step * (f64::from(i) + 0.5)))
+        .collect()
+}
+
+/// Gets the start time and the scene change times of an asset.
+fn scene_times(
+    ffmpeg: &FFmpeg,
+    asset: &AssetReference,
+    threshold: f64,
+    max_count: u32,
+) -> Result<Vec<TimePosition>> {
+    let mut command = ffmpeg.command();
+    if let Some(sequence) = ImageSequence::from_asset(asset) {
+        command.input_options(sequence.input_options());
+    }
+    command
+        .input(&asset.path)
+        .output_options([
+            "-vf".to_string(),
+            format!("select='gt(scene,{threshold})',showinfo"),
+            "-an".to_string(),
+            "-f".to_string(),
+            "null".to_string(),
+        ])
+        .set_output("-");
+
+    let mut times = vec![TimePosition::zero()];
+    command
+        .execute_with_progress(|line| {
+            if let Some(seconds) = showinfo_time(line) {
+                times.push(TimePosition::from_seconds(seconds));
+            }
+        })
+        .map_err(|e| RenderError::FFmpegError(e.to_string()))?;
+
+    times.truncate(max_count as usize);
+    Ok(times)
+}
+
+/// Reads the `pts_time` of a `showinfo` filter log line.
+fn showinfo_time(line: &str) -> Option<f64> {
+    if !line.contains("Parsed_showinfo") {
+        return None;
+    }
+    let value = line.split("pts_time:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Gets the duration of a media file.
+fn media_duration(ffmpeg: &FFmpeg, path: &Path) -> Result<Duration> {
+    ffmpeg
+        .get_media_info(path)
+        .map_err(|e| RenderError::FFmpegError(e.to_string()))?
+        .duration_seconds()
+        .map(Duration::from_seconds)
+        .ok_or_else(|| {
+            RenderError::ProcessingFailed(format!("Duration of {} is unknown", path.display()))
+        })
+}
+
+/// Extracts one scaled frame of an asset into a PNG file.
+fn extract_frame(
+    ffmpeg: &FFmpeg,
+    asset: &AssetReference,
+    time: TimePosition,
+    options: &ThumbnailOptions,
+    output: &Path,
+) -> Result<()> {
+    let (width, height) = (options.width, options.height);
+    let mut command = ffmpeg.command();
+    if let Some(sequence) = ImageSequence::from_asset(asset) {
+        command.input_options(sequence.input_options());
+    }
+    // 入力側でシークし、縦横比を保ったままレターボックスで収める
+    command
+        .input_options(["-ss".to_string(), time.as_seconds().to_string()])
+        .input(&asset.path)
+        .output_options([
+            "-frames:v".to_string(),
+            "1".to_string(),
+            "-vf".to_string(),
+            format!(
+                "scale={width}:{height}:force_original_aspect_ratio=decrease,\
+                 pad={width}:{height}:(ow-iw)/2:(oh-ih)/2"
+            ),
+        ])
+        .set_output(output)
+        .overwrite(true);
+
+    command
+        .execute()
+        .map_err(|e| RenderError::FFmpegError(e.to_string()))
+}
+
+/// Renders one frame of the timeline, going through the cache if given.
+fn timeline_frame(
+    project: &Project,
+    time: TimePosition,
+    size: (u32, u32),
+    sources: &HashMap<AssetId, SourceFingerprint>,
+    cache: Option<&mut RenderCache>,
+) -> Result<RgbaImage> {
+    let Some(cache) = cache else {
+        return render_frame(project, time, size);
+    };
+
+    let config = still_frame_config(Path::new(""), size.0, size.1);
+    let segment = TimelineSegment {
+        index: 0,
+        start: time,
+        end: time + Duration::from_frames(1.0, config.frame_rate),
+    };
+    let hash = segments::segment_hash(project, &config, &segment, sources);
+
+    let cached = cache.get_segment(hash).map(|entry| entry.path.clone());
+    if let Some(image) = cached.and_then(|path| load_image(&path).ok()) {
+        return Ok(image);
+    }
+
+    let image = render_frame(project, time, size)?;
+    let work_dir = tempfile::TempDir::new()?;
+    let frame_path = work_dir.path().join("frame.png");
+    image
+        .save(&frame_path)
+        .map_err(|e| RenderError::Io(format!("Failed to write thumbnail: {e}")))?;
+    cache.add_segment(hash, &frame_path, segment.duration())?;
+
+    Ok(image)
+}
+
+/// Renders one frame of the timeline.
+fn render_frame(project: &Project, time: TimePosition, size: (u32, u32)) -> Result<RgbaImage> {
+    project.render_frame(time, size).map_err(|e| match e {
+        ProjectError::Rendering(e) => e,
+        other => RenderError::Other(other.to_string()),
+    })
+}
+
+/// Hashes the parameters of an asset thumbnail for the cache.
+fn thumbnail_hash(time: TimePosition, options: &ThumbnailOptions) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    "thumbnail".hash(&mut hasher);
+    time.as_seconds().to_bits().hash(&mut hasher);
+    options.width.hash(&mut hasher);
+    options.height.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Loads an image whose format is detected from its contents.
+///
+/// Cached files have a generic extension, so the format cannot be taken
+/// from the file name.
+fn load_image(path: &Path) -> Result<RgbaImage> {
+    let read_error =
+        |e: String| RenderError::Io(format!("Failed to read image {}: {e}", path.display()));
+    let image = image::io::Reader::open(path)
+        .map_err(|e| read_error(e.to_string()))?
+        .with_guessed_format()
+        .map_err(|e| read_error(e.to_string()))?
+        .decode()
+        .map_err(|e| read_error(e.to_string()))?;
+    Ok(image.to_rgba8())
+}
+
+/// Width of a label glyph (in font pixels).
+const GLYPH_WIDTH: u32 = 3;
+/// Height of a label glyph (in font pixels).
+const GLYPH_HEIGHT: u32 = 5;
+
+/// Gets the rows of a label glyph, three bits per row with the most
+/// significant bit on the left.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// Draws a timecode label with the built-in bitmap font.
+fn draw_label(image: &mut RgbaImage, text: &str, x: u32, y: u32) {
+    let advance = (GLYPH_WIDTH + 1) * LABEL_SCALE;
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i as u32 * advance;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                for dy in 0..LABEL_SCALE {
+                    for dx in 0..LABEL_SCALE {
+                        let px = glyph_x + column * LABEL_SCALE + dx;
+                        let py = y + row as u32 * LABEL_SCALE + dy;
+                        if px < image.width() && py < image.height() {
+                            image.put_pixel(px, py, LABEL_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_even_times() {
+        let times = even_times(Duration::from_seconds(10.0), 5);
+        let seconds: Vec<f64> = times.iter().map(TimePosition::as_seconds).collect();
+        assert_eq!(seconds, vec![1.0, 3.0, 5.0, 7.0, 9.0]);
+    }
+
+    #[test]
+    fn test_showinfo_time() {
+        let line = "[Parsed_showinfo_1 @ 0x55d0] n:   3 pts:  90090 pts_time:3.003   duration:3003";
+        assert_eq!(showinfo_time(line), Some(3.003));
+        assert_eq!(showinfo_time("frame=  120 fps=0.0 time=00:00:04.00"), None);
+    }
+
+    #[test]
+    fn test_contact_sheet_layout() {
+        let thumbnails: Vec<_> = (0..5)
+            .map(|i| Thumbnail {
+                time: TimePosition::from_seconds(f64::from(i)),
+                image: RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255])),
+            })
+            .collect();
+
+        let sheet = contact_sheet(&thumbnails, 3, 24.0);
+        let label_height = GLYPH_HEIGHT * LABEL_SCALE + SHEET_PADDING / 2;
+        assert_eq!(sheet.width(), 3 * (40 + SHEET_PADDING) + SHEET_PADDING);
+        assert_eq!(
+            sheet.height(),
+            2 * (20 + label_height + SHEET_PADDING) + SHEET_PADDING
+        );
+        assert_eq!(
+            *sheet.get_pixel(SHEET_PADDING, SHEET_PADDING),
+            Rgba([255, 0, 0, 255])
+        );
+        // ラベルの文字が描かれている
+        let label_y = SHEET_PADDING + 20 + SHEET_PADDING / 2;
+        assert!(
+            (0..label_height)
+                .any(|dy| *sheet.get_pixel(SHEET_PADDING, label_y + dy) == LABEL_COLOR)
+        );
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(ThumbnailOptions::default().validate().is_ok());
+        assert!(
+            ThumbnailOptions::default()
+                .with_size(0, 90)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ThumbnailOptions::default()
+                .with_spacing(ThumbnailSpacing::Scenes {
+                    threshold: 1.5,
+                    max_count: 10
+                })
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,492 @@
+/// Post-render verification of outputs.
+///
+/// After an output has been encoded, it is probed with `ffprobe` and
+/// compared against the rendering configuration it was produced with: its
+/// duration against the rendered range, the resolution and frame rate of the
+/// video stream, the codecs of the video and audio streams, and the number of
+/// streams. The comparison is returned as a `VerificationReport`, and the
+/// render can be failed when the output does not match.
+///
+/// HLS/DASH outputs and image sequences consist of many files and are not
+/// verified.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ffmpeg::{FFmpeg, MediaInfo};
+use crate::project::rendering::config::{AudioCodec, OutputFormat, RenderConfig, VideoCodec};
+use crate::project::rendering::{RenderError, Result};
+use crate::utility::time::Duration;
+
+/// Settings of the post-render verification.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerificationOptions {
+    /// Whether outputs are probed and verified after rendering.
+    pub enabled: bool,
+    /// Whether a mismatch fails the render instead of only being reported.
+    pub fail_on_mismatch: bool,
+    /// Allowed difference between the expected and actual duration (seconds).
+    pub duration_tolerance: f64,
+    /// Allowed relative difference between the expected and actual frame rate.
+    pub frame_rate_tolerance: f64,
+}
+
+impl Default for VerificationOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fail_on_mismatch: false,
+            duration_tolerance: 0.1,
+            frame_rate_tolerance: 0.01,
+        }
+    }
+}
+
+impl VerificationOptions {
+    /// Creates options that verify outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `fail_on_mismatch` - Whether a mismatch fails the render
+    #[must_use]
+    pub fn enabled(fail_on_mismatch: bool) -> Self {
+        Self {
+            enabled: true,
+            fail_on_mismatch,
+            ..Self::default()
+        }
+    }
+
+    /// Validates the verification options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tolerance is negative.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.duration_tolerance < 0.0 || self.frame_rate_tolerance < 0.0 {
+            return Err("Verification tolerances cannot be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Eq for VerificationOptions {}
+
+impl std::hash::Hash for VerificationOptions {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.enabled.hash(state);
+        self.fail_on_mismatch.hash(state);
+        // 許容誤差は f64 のためハッシュに含めない
+    }
+}
+
+/// A property of the output that is verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationItem {
+    /// Duration of the output.
+    Duration,
+    /// Width and height of the video stream.
+    Resolution,
+    /// Frame rate of the video stream.
+    FrameRate,
+    /// Codec of the video stream.
+    VideoCodec,
+    /// Codec of the audio stream.
+    AudioCodec,
+    /// Number of video and audio streams.
+    StreamCount,
+}
+
+impl fmt::Display for VerificationItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Duration => "duration",
+            Self::Resolution => "resolution",
+            Self::FrameRate => "frame rate",
+            Self::VideoCodec => "video codec",
+            Self::AudioCodec => "audio codec",
+            Self::StreamCount => "stream count",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The result of verifying one property of the output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationCheck {
+    /// The verified property.
+    pub item: VerificationItem,
+    /// The value requested by the configuration.
+    pub expected: String,
+    /// The value found in the output.
+    pub actual: String,
+    /// Whether the output matches the configuration.
+    pub passed: bool,
+}
+
+/// The result of verifying an output against its configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// Path to the verified output.
+    pub output_path: std::path::PathBuf,
+    /// The individual checks.
+    pub checks: Vec<VerificationCheck>,
+}
+
+impl VerificationReport {
+    /// Determines whether all checks passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Gets the checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &VerificationCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            return write!(
+                f,
+                "{}: all {} checks passed",
+                self.output_path.display(),
+                self.checks.len()
+            );
+        }
+        let failures: Vec<String> = self
+            .failures()
+            .map(|check| {
+                format!(
+                    "{} expected {}, found {}",
+                    check.item, check.expected, check.actual
+                )
+            })
+            .collect();
+        write!(f, "{}: {}", self.output_path.display(), failures.join("; "))
+    }
+}
+
+/// What the output of a render is expected to contain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpectedOutput {
+    /// Duration of the rendered range.
+    pub duration: Duration,
+    /// Whether the timeline has video to encode.
+    pub has_video: bool,
+    /// Whether the timeline has audio to encode.
+    pub has_audio: bool,
+}
+
+/// Determines whether an output can be verified.
+///
+/// # Arguments
+///
+/// * `config` - The rendering configuration of the output
+///
+/// # Returns
+///
+/// False for HLS/DASH outputs and image sequences, which are not single
+/// media files.
+#[must_use]
+pub fn is_verifiable(config: &RenderConfig) -> bool {
+    !config.format.is_streaming() && !config.format.is_image_sequence()
+}
+
+/// Probes an output and verifies it against its configuration.
+///
+/// # Arguments
+///
+/// * `ffmpeg` - The `FFmpeg` installation whose `ffprobe` is used
+/// * `config` - The rendering configuration of the output
+/// * `expected` - What the output is expected to contain
+///
+/// # Returns
+///
+/// The verification report.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be probed, or if it does not match
+/// and `fail_on_mismatch` is set.
+pub fn verify_output(
+    ffmpeg: &FFmpeg,
+    config: &RenderConfig,
+    expected: &ExpectedOutput,
+) -> Result<VerificationReport> {
+    let info = ffmpeg.get_media_info(&config.output_path).map_err(|e| {
+        RenderError::VerificationFailed(format!(
+            "Failed to probe {}: {e}",
+            config.output_path.display()
+        ))
+    })?;
+
+    let report = verify_media_info(&info, config, expected);
+    if config.verification.fail_on_mismatch && !report.passed() {
+        return Err(RenderError::VerificationFailed(report.to_string()));
+    }
+    Ok(report)
+}
+
+/// Verifies probed media information against a rendering configuration.
+///
+/// # Arguments
+///
+/// * `info` - The probed output
+/// * `config` - The rendering configuration of the output
+/// * `expected` - What the output is expected to contain
+///
+/// # Returns
+///
+/// The verification report.
+#[must_use]
+pub fn verify_media_info(
+    info: &MediaInfo,
+    config: &RenderConfig,
+    expected: &ExpectedOutput,
+) -> VerificationReport {
+    let options = &config.verification;
+    let mut checks = Vec::new();
+    let mut check = |item, expected: String, actual: String, passed| {
+        checks.push(VerificationCheck {
+            item,
+            expected,
+            actual,
+            passed,
+        });
+    };
+
+    let expected_duration = expected.duration.as_seconds();
+    match info.duration_seconds() {
+        Some(actual) => check(
+            VerificationItem::Duration,
+            format!("{expected_duration:.3}s"),
+            format!("{actual:.3}s"),
+            (actual - expected_duration).abs() <= options.duration_tolerance,
+        ),
+        None => check(
+            VerificationItem::Duration,
+            format!("{expected_duration:.3}s"),
+            "unknown".to_string(),
+            false,
+        ),
+    }
+
+    let video_streams = info.video_streams();
+    let audio_streams = info.audio_streams();
+    let expects_video = expected.has_video && !config.format.is_audio_only();
+    let expects_audio = expected.has_audio && config.format.supports_audio();
+    check(
+        VerificationItem::StreamCount,
+        stream_count(usize::from(expects_video), usize::from(expects_audio)),
+        stream_count(video_streams.len(), audio_streams.len()),
+        video_streams.len() == usize::from(expects_video)
+            && audio_streams.len() == usize::from(expects_audio),
+    );
+
+    if let (true, Some(video)) = (expects_video, video_streams.first()) {
+        let resolution = format!("{}x{}", config.width, config.height);
+        let actual = format!(
+            "{}x{}",
+            video.width.unwrap_or_default(),
+            video.height.unwrap_or_default()
+        );
+        let passed = actual == resolution;
+        check(VerificationItem::Resolution, resolution, actual, passed);
+
+        // GIF のフレーム間隔は 1/100 秒単位のため、フレームレートは一致しない
+        if config.format != OutputFormat::GIF {
+            let actual = video.frame_rate.as_deref().and_then(parse_frame_rate);
+            check(
+                VerificationItem::FrameRate,
+                format!("{:.3}", config.frame_rate),
+                actual.map_or_else(|| "unknown".to_string(), |fps| format!("{fps:.3}")),
+                actual.is_some_and(|fps| {
+                    (fps - config.frame_rate).abs()
+                        <= config.frame_rate * options.frame_rate_tolerance
+                }),
+            );
+        }
+
+        if let Some(codec) = expected_video_codec(config) {
+            check(
+                VerificationItem::VideoCodec,
+                codec.to_string(),
+                video.codec_name.clone(),
+                video.codec_name == codec,
+            );
+        }
+    }
+
+    if let (true, Some(audio), Some(codec)) = (
+        expects_audio,
+        audio_streams.first(),
+        expected_audio_codec(config.audio_codec),
+    ) {
+        check(
+            VerificationItem::AudioCodec,
+            codec.to_string(),
+            audio.codec_name.clone(),
+            audio.codec_name == codec,
+        );
+    }
+
+    VerificationReport {
+        output_path: config.output_path.clone(),
+        checks,
+    }
+}
+
+/// Formats a number of video and audio streams.
+fn stream_count(video: usize, audio: usize) -> String {
+    format!("{video} video, {audio} audio")
+}
+
+/// Parses an `ffprobe` frame rate such as `30000/1001` or `25`.
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let rate = match rate.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.parse().ok()?;
+            if denominator == 0.0 {
+                return None;
+            }
+            numerator.parse::<f64>().ok()? / denominator
+        }
+        None => rate.parse().ok()?,
+    };
+    (rate > 0.0).then_some(rate)
+}
+
+/// Gets the `ffprobe` codec name the video stream of an output should have.
+///
+/// # Returns
+///
+/// The codec name, or `None` when the stream is copied and can be anything.
+fn expected_video_codec(config: &RenderConfig) -> Option<&'static str> {
+    if config.format == OutputFormat::GIF {
+        return Some("gif");
+    }
+    match config.video_codec {
+        VideoCodec::Copy => None,
+        VideoCodec::H264 => Some("h264"),
+        VideoCodec::H265 => Some("hevc"),
+        VideoCodec::VP9 => Some("vp9"),
+        VideoCodec::AV1 => Some("av1"),
+        VideoCodec::ProRes | VideoCodec::ProRes4444 => Some("prores"),
+        VideoCodec::DnxHr => Some("dnxhd"),
+        VideoCodec::Ffv1 => Some("ffv1"),
+    }
+}
+
+/// Gets the `ffprobe` codec name of an audio codec.
+///
+/// # Returns
+///
+/// The codec name, or `None` when the stream is copied and can be anything.
+fn expected_audio_codec(codec: AudioCodec) -> Option<&'static str> {
+    match codec {
+        AudioCodec::Copy => None,
+        AudioCodec::AAC => Some("aac"),
+        AudioCodec::Opus => Some("opus"),
+        AudioCodec::MP3 => Some("mp3"),
+        AudioCodec::Vorbis => Some("vorbis"),
+        AudioCodec::Pcm => Some("pcm_s16le"),
+        AudioCodec::Flac => Some("flac"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn media_info(json: &str) -> MediaInfo {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn expected(seconds: f64) -> ExpectedOutput {
+        ExpectedOutput {
+            duration: Duration::from_seconds(seconds),
+            has_video: true,
+            has_audio: true,
+        }
+    }
+
+    const PROBED: &str = r#"{
+        "format": {"filename": "out.mp4", "duration": "10.02"},
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "h264",
+             "width": 1920, "height": 1080, "r_frame_rate": "30/1"},
+            {"index": 1, "codec_type": "audio", "codec_name": "aac"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_matching_output_passes() {
+        let config = RenderConfig::new(PathBuf::from("out.mp4"));
+        let report = verify_media_info(&media_info(PROBED), &config, &expected(10.0));
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.checks.len(), 6);
+    }
+
+    #[test]
+    fn test_mismatches_are_reported() {
+        let config = RenderConfig::new(PathBuf::from("out.mp4"))
+            .with_resolution(1280, 720)
+            .with_frame_rate(25.0);
+        let report = verify_media_info(&media_info(PROBED), &config, &expected(12.0));
+
+        let failed: Vec<VerificationItem> = report.failures().map(|check| check.item).collect();
+        assert_eq!(
+            failed,
+            vec![
+                VerificationItem::Duration,
+                VerificationItem::Resolution,
+                VerificationItem::FrameRate
+            ]
+        );
+        assert!(
+            report
+                .to_string()
+                .contains("resolution expected 1280x720, found 1920x1080")
+        );
+    }
+
+    #[test]
+    fn test_verify_output_probes_the_output() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("out.mp4");
+        std::fs::write(&output, b"").unwrap();
+
+        let (ffmpeg, executor) = FFmpeg::recording();
+        executor.respond_media_info(PROBED);
+
+        let config = RenderConfig::new(output.clone())
+            .with_verification(VerificationOptions::enabled(false));
+        let report = verify_output(&ffmpeg, &config, &expected(10.0)).unwrap();
+        assert!(report.passed(), "{report}");
+
+        let calls = executor.calls();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].is_ffprobe());
+        assert!(calls[0].has_arg("-show_streams"));
+        assert_eq!(calls[0].last_arg(), Some(output.to_string_lossy().as_ref()));
+
+        let strict = config
+            .with_resolution(1280, 720)
+            .with_verification(VerificationOptions::enabled(true));
+        let error = verify_output(&ffmpeg, &strict, &expected(10.0)).unwrap_err();
+        assert!(matches!(error, RenderError::VerificationFailed(_)));
+    }
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert!((parse_frame_rate("30000/1001").unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(parse_frame_rate("25"), Some(25.0));
+        assert_eq!(parse_frame_rate("0/0"), None);
+    }
+}
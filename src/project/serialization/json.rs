@@ -10,7 +10,7 @@ use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
 use crate::project::timeline::multi_track::{MultiTrackManager, TrackRelationship};
-use crate::project::timeline::{Clip, Timeline, Track, TrackId, TrackKind};
+use crate::project::timeline::{Clip, Marker, Timeline, Track, TrackId, TrackKind};
 use crate::project::{Project, ProjectId, ProjectMetadata};
 use crate::utility::time::{Duration, TimePosition};
 
@@ -114,6 +114,20 @@ struct SerializedTimeline {
     /// Multi-track relationships.
     #[serde(default)]
     track_relationships: SerializedMultiTrackManager,
+
+    /// Markers on the timeline.
+    #[serde(default)]
+    markers: Vec<SerializedMarker>,
+}
+
+/// Serializable representation of a timeline marker.
+#[derive(Debug, Serialize, Deserialize)]
+struct SerializedMarker {
+    /// Position of the marker in seconds.
+    position: f64,
+
+    /// Label of the marker.
+    label: String,
 }
 
 /// Serializable representation of a track.
@@ -158,6 +172,10 @@ struct SerializedClip {
 
     /// End position in the source asset.
     source_end: f64,
+
+    /// Area of the source frames to keep, as `width:height:x:y`.
+    #[serde(default)]
+    crop: Option<String>,
 }
 
 /// Serializable representation of an asset reference.
@@ -385,10 +403,20 @@ fn convert_to_serialized_timeline(timeline: &Timeline) -> SerializedTimeline {
     // Get the MultiTrackManager from the timeline and serialize it
     let track_relationships = SerializedMultiTrackManager::from(timeline.multi_track_manager());
 
+    let markers = timeline
+        .markers()
+        .iter()
+        .map(|marker| SerializedMarker {
+            position: marker.position.as_seconds(),
+            label: marker.label.clone(),
+        })
+        .collect();
+
     SerializedTimeline {
         tracks: serialized_tracks,
         duration: timeline.duration().as_seconds(),
         track_relationships,
+        markers,
     }
 }
 
@@ -419,6 +447,7 @@ fn convert_to_serialized_clip(clip: &Clip) -> SerializedClip {
         duration: clip.duration().as_seconds(),
         source_start: clip.source_start().as_seconds(),
         source_end: clip.source_end().as_seconds(),
+        crop: clip.crop().map(|crop| crop.to_string()),
     }
 }
 
@@ -516,6 +545,13 @@ fn convert_from_serialized_timeline(serialized: &SerializedTimeline) -> Result<T
         &mut timeline,
     )?;
 
+    for marker in &serialized.markers {
+        timeline.add_marker(Marker::new(
+            TimePosition::from_seconds(marker.position),
+            marker.label.clone(),
+        ));
+    }
+
     Ok(timeline)
 }
 
@@ -713,6 +749,14 @@ fn convert_from_serialized_clip(serialized: &SerializedClip) -> Result<Clip> {
         .parse()
         .map_err(|e| SerializationError::IncompatibleFormat(format!("Invalid asset ID: {e}")))?;
 
+    // Parse crop rectangle
+    let crop = serialized
+        .crop
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| SerializationError::IncompatibleFormat(format!("Invalid clip crop: {e}")))?;
+
     // Create the clip
     Ok(Clip::new(
         id,
@@ -721,7 +765,8 @@ fn convert_from_serialized_clip(serialized: &SerializedClip) -> Result<Clip> {
         Duration::from_seconds(serialized.duration),
         TimePosition::from_seconds(serialized.source_start),
         TimePosition::from_seconds(serialized.source_end),
-    ))
+    )
+    .with_crop(crop))
 }
 
 /// Converts a serialized asset reference to an `AssetReference`.
@@ -872,6 +917,28 @@ mod tests {
         assert_eq!(project.assets[0].id, deserialized_project.assets[0].id);
     }
 
+    #[test]
+    fn test_clip_crop_serialization() {
+        let mut project = create_test_project();
+        let crop: crate::ffmpeg::CropRect = "1920:800:0:140".parse().unwrap();
+        let video_track_id = project.timeline.get_tracks()[0].id();
+        let video_track = project.timeline.get_track_mut(video_track_id).unwrap();
+        video_track.get_clips_mut()[0].set_crop(Some(crop));
+
+        let temp_file = NamedTempFile::new().unwrap();
+        serialize_project(&project, temp_file.path()).unwrap();
+        let deserialized = deserialize_project(temp_file.path()).unwrap();
+
+        let clips: Vec<_> = deserialized
+            .timeline
+            .get_tracks()
+            .iter()
+            .flat_map(|track| track.get_clips())
+            .collect();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].crop(), Some(crop));
+    }
+
     #[test]
     fn test_track_relationship_serialization() {
         let mut project = create_test_project();
@@ -926,6 +993,7 @@ mod tests {
 
                     SerializedMultiTrackManager { relationships }
                 },
+                markers: Vec::new(),
             },
             assets: vec![],
         };
@@ -10,6 +10,7 @@ pub mod history;
 pub mod keyframes;
 pub mod multi_track;
 
+use crate::ffmpeg::CropRect;
 use crate::project::{AssetId, AssetReference, ClipId};
 use crate::utility::time::{Duration, TimePosition};
 use multi_track::TrackRelationship;
@@ -147,6 +148,9 @@ pub struct Clip {
 
     /// End position in the source asset.
     source_end: TimePosition,
+
+    /// Area of the source frames to keep, or `None` for whole frames.
+    crop: Option<CropRect>,
 }
 
 impl Clip {
@@ -176,9 +180,17 @@ impl Clip {
             duration,
             source_start,
             source_end,
+            crop: None,
         }
     }
 
+    /// Sets the area of the source frames to keep.
+    #[must_use]
+    pub fn with_crop(mut self, crop: Option<CropRect>) -> Self {
+        self.crop = crop;
+        self
+    }
+
     /// Gets the ID of the clip.
     #[must_use]
     pub fn id(&self) -> ClipId {
@@ -221,6 +233,12 @@ impl Clip {
         self.source_end
     }
 
+    /// Gets the area of the source frames to keep.
+    #[must_use]
+    pub fn crop(&self) -> Option<CropRect> {
+        self.crop
+    }
+
     /// Sets the position of the clip in the timeline.
     pub fn set_position(&mut self, position: TimePosition) {
         self.position = position;
@@ -241,6 +259,36 @@ impl Clip {
         self.source_end = end;
     }
 
+    /// Sets the area of the source frames to keep.
+    pub fn set_crop(&mut self, crop: Option<CropRect>) {
+        self.crop = crop;
+    }
+
+    /// Maps a time in the source asset to the timeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_time` - The time in the source asset
+    ///
+    /// # Returns
+    ///
+    /// The timeline position showing that time, or `None` if the time is
+    /// outside the used part of the source.
+    #[must_use]
+    pub fn timeline_position(&self, source_time: TimePosition) -> Option<TimePosition> {
+        if source_time < self.source_start || source_time > self.source_end {
+            return None;
+        }
+        let source_length = self.source_end.as_seconds() - self.source_start.as_seconds();
+        if source_length <= 0.0 {
+            return Some(self.position);
+        }
+        // 速度変更されたクリップも考慮し、長さの比で換算する
+        let ratio = self.duration.as_seconds() / source_length;
+        let offset = (source_time.as_seconds() - self.source_start.as_seconds()) * ratio;
+        Some(self.position + Duration::from_seconds(offset))
+    }
+
     /// Checks if this clip overlaps with another clip.
     ///
     /// # Arguments
@@ -456,6 +504,31 @@ impl Track {
     }
 }
 
+/// A named point on the timeline, e.g. a detected scene change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    /// Position of the marker in the timeline.
+    pub position: TimePosition,
+    /// Label of the marker.
+    pub label: String,
+}
+
+impl Marker {
+    /// Creates a new marker.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position of the marker in the timeline
+    /// * `label` - Label of the marker
+    #[must_use]
+    pub fn new(position: TimePosition, label: impl Into<String>) -> Self {
+        Self {
+            position,
+            label: label.into(),
+        }
+    }
+}
+
 /// Timeline data structure.
 #[derive(Debug, Clone)]
 pub struct Timeline {
@@ -470,6 +543,9 @@ pub struct Timeline {
 
     /// Edit history.
     history: history::EditHistory,
+
+    /// Markers, sorted by position.
+    markers: Vec<Marker>,
 }
 
 impl Timeline {
@@ -481,6 +557,7 @@ impl Timeline {
             track_index_map: HashMap::new(),
             multi_track_manager: multi_track::MultiTrackManager::new(),
             history: history::EditHistory::new(),
+            markers: Vec::new(),
         }
     }
 
@@ -840,7 +917,8 @@ impl Timeline {
             second_part_duration,
             source_split_point,
             source_end,
-        );
+        )
+        .with_crop(track.clips[clip_index].crop());
 
         // Modify the original clip (first part)
         let clip = &mut track.clips[clip_index];
@@ -935,6 +1013,12 @@ impl Timeline {
                 ));
             }
 
+            if first_clip.crop() != second_clip.crop() {
+                return Err(TimelineError::InvalidOperation(
+                    "Cannot merge clips with different crops".to_string(),
+                ));
+            }
+
             // Calculate merged duration and collect source end from second clip
             (
                 first_clip.duration() + second_clip.duration(),
@@ -1146,6 +1230,332 @@ impl Timeline {
         result
     }
 
+    /// Splits a clip at several positions.
+    ///
+    /// Positions outside the clip or on its edges are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The ID of the track containing the clip
+    /// * `clip_id` - The ID of the clip to split
+    /// * `positions` - The positions at which to split the clip
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the clips created by the splits, in timeline order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or clip is not found.
+    pub fn split_clip_at(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        positions: &[TimePosition],
+    ) -> Result<Vec<ClipId>> {
+        let track = self
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?;
+        let clip = track.get_clip(clip_id).ok_or(TimelineError::ClipNotFound {
+            track: track_id,
+            clip: clip_id,
+        })?;
+        let (start, end) = (clip.position(), clip.end_position());
+
+        let mut positions: Vec<TimePosition> = positions
+            .iter()
+            .copied()
+            .filter(|position| *position > start && *position < end)
+            .collect();
+        positions.sort();
+        positions.dedup();
+
+        // 前から順に、直前の分割で生まれた後半のクリップを分割する
+        let mut current = clip_id;
+        let mut created = Vec::with_capacity(positions.len());
+        for position in positions {
+            current = self.split_clip(track_id, current, position)?;
+            created.push(current);
+        }
+        Ok(created)
+    }
+
+    /// Splits a clip at several positions and records the split in the history.
+    ///
+    /// The split is recorded as the removal of the clip followed by the
+    /// addition of its parts. Callers splitting several clips wrap the calls
+    /// in a transaction so that a single undo restores all of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The ID of the track containing the clip
+    /// * `clip_id` - The ID of the clip to split
+    /// * `positions` - The positions at which to split the clip
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the clips created by the splits, in timeline order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or clip is not found.
+    pub fn split_clip_at_with_history(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        positions: &[TimePosition],
+    ) -> Result<Vec<ClipId>> {
+        // 複製したトラック上で分割し、結果のクリップを履歴付きで置き換える
+        let mut split = Self::new();
+        let track = self
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?;
+        split.tracks.push(track.clone());
+        split.track_index_map.insert(track_id, 0);
+        let created = split.split_clip_at(track_id, clip_id, positions)?;
+        if created.is_empty() {
+            return Ok(created);
+        }
+
+        let parts: Vec<Clip> = split.tracks[0]
+            .get_clips()
+            .iter()
+            .filter(|clip| clip.id() == clip_id || created.contains(&clip.id()))
+            .cloned()
+            .collect();
+        self.remove_clip_with_history(track_id, clip_id)?;
+        for part in parts {
+            self.add_clip_with_history(track_id, part)?;
+        }
+        Ok(created)
+    }
+
+    /// Removes ranges of a clip's source and closes the gaps they leave.
+    ///
+    /// Each range is shrunk by `padding` on both sides before it is removed,
+    /// so that some of it stays around the kept parts. The clip is replaced by
+    /// the kept parts placed back to back, and the later clips on all tracks
+    /// are moved back by the removed length to keep the tracks in sync. All
+    /// changes are recorded as one transaction, so a single undo restores the
+    /// clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The ID of the track containing the clip
+    /// * `clip_id` - The ID of the clip to cut
+    /// * `ranges` - Start and end of the ranges to remove, in source time
+    /// * `padding` - Length kept at each side of a removed range
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the kept parts in timeline order. The first part keeps the
+    /// ID of the clip.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or clip is not found, a later clip
+    /// cannot be moved back without overlapping another clip, or a
+    /// transaction is already in progress.
+    pub fn remove_source_ranges_with_history(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        ranges: &[(TimePosition, TimePosition)],
+        padding: Duration,
+    ) -> Result<Vec<ClipId>> {
+        let track = self
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?;
+        let clip = track
+            .get_clip(clip_id)
+            .cloned()
+            .ok_or(TimelineError::ClipNotFound {
+                track: track_id,
+                clip: clip_id,
+            })?;
+        // 全トラックの後続クリップを前から順に詰める
+        let mut later: Vec<(TrackId, ClipId, TimePosition)> = self
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                track
+                    .get_clips()
+                    .iter()
+                    .filter(|other| other.position() >= clip.end_position())
+                    .map(|other| (track.id(), other.id(), other.position()))
+            })
+            .collect();
+        later.sort_by_key(|(_, _, position)| *position);
+
+        // 残す区間をソース上で求める
+        let mut removed: Vec<(TimePosition, TimePosition)> = ranges
+            .iter()
+            .map(|(start, end)| {
+                (
+                    (*start + padding).max(clip.source_start()),
+                    (*end - padding).min(clip.source_end()),
+                )
+            })
+            .filter(|(start, end)| start < end)
+            .collect();
+        if removed.is_empty() {
+            return Ok(vec![clip_id]);
+        }
+        removed.sort();
+        let mut kept = Vec::with_capacity(removed.len() + 1);
+        let mut cursor = clip.source_start();
+        for (start, end) in removed {
+            if start > cursor {
+                kept.push((cursor, start));
+            }
+            cursor = cursor.max(end);
+        }
+        if cursor < clip.source_end() {
+            kept.push((cursor, clip.source_end()));
+        }
+
+        // 速度変更されたクリップも考慮し、長さの比で換算する
+        let source_length = (clip.source_end() - clip.source_start()).as_seconds();
+        let ratio = if source_length > 0.0 {
+            clip.duration().as_seconds() / source_length
+        } else {
+            1.0
+        };
+        let mut position = clip.position();
+        let mut parts = Vec::with_capacity(kept.len());
+        for (index, (start, end)) in kept.into_iter().enumerate() {
+            let id = if index == 0 { clip_id } else { ClipId::new() };
+            let duration = Duration::from_seconds((end - start).as_seconds() * ratio);
+            parts.push(
+                Clip::new(id, clip.asset_id(), position, duration, start, end)
+                    .with_crop(clip.crop()),
+            );
+            position += duration;
+        }
+        let shift = clip.end_position() - position;
+
+        self.begin_transaction(Some("Remove source ranges".to_string()))
+            .map_err(|e| TimelineError::InvalidOperation(e.to_string()))?;
+        // 途中で失敗した場合は変更前の状態に戻す
+        let snapshot = self.clone();
+        let result = (|| {
+            self.remove_clip_with_history(track_id, clip_id)?;
+            let ids: Vec<ClipId> = parts.iter().map(Clip::id).collect();
+            for part in parts {
+                self.add_clip_with_history(track_id, part)?;
+            }
+            for (later_track, later_id, later_position) in later {
+                self.move_clip_to_track_with_history(
+                    later_track,
+                    later_track,
+                    later_id,
+                    Some(later_position - shift),
+                )?;
+            }
+            Ok(ids)
+        })();
+
+        match result {
+            Ok(ids) => {
+                self.commit_transaction()
+                    .map_err(|e| TimelineError::InvalidOperation(e.to_string()))?;
+                Ok(ids)
+            }
+            Err(e) => {
+                *self = snapshot;
+                let _ = self.rollback_transaction();
+                Err(e)
+            }
+        }
+    }
+
+    /// Sets the crop of a clip and records the change in history.
+    ///
+    /// The change is recorded as the clip being replaced by a copy with the
+    /// new crop, as one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - The ID of the track containing the clip
+    /// * `clip_id` - The ID of the clip to crop
+    /// * `crop` - The area of the source frames to keep, or `None` to keep
+    ///   whole frames
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the track or clip is not found, or a transaction
+    /// is already in progress.
+    pub fn set_clip_crop_with_history(
+        &mut self,
+        track_id: TrackId,
+        clip_id: ClipId,
+        crop: Option<CropRect>,
+    ) -> Result<()> {
+        let clip = self
+            .get_track(track_id)
+            .ok_or(TimelineError::TrackNotFound(track_id))?
+            .get_clip(clip_id)
+            .cloned()
+            .ok_or(TimelineError::ClipNotFound {
+                track: track_id,
+                clip: clip_id,
+            })?;
+        if clip.crop() == crop {
+            return Ok(());
+        }
+
+        self.begin_transaction(Some("Set clip crop".to_string()))
+            .map_err(|e| TimelineError::InvalidOperation(e.to_string()))?;
+        let snapshot = self.clone();
+        let result = self
+            .remove_clip_with_history(track_id, clip_id)
+            .and_then(|()| self.add_clip_with_history(track_id, clip.with_crop(crop)));
+
+        match result {
+            Ok(()) => self
+                .commit_transaction()
+                .map_err(|e| TimelineError::InvalidOperation(e.to_string())),
+            Err(e) => {
+                *self = snapshot;
+                let _ = self.rollback_transaction();
+                Err(e)
+            }
+        }
+    }
+
+    /// Gets the markers, sorted by position.
+    #[must_use]
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Adds a marker.
+    ///
+    /// # Arguments
+    ///
+    /// * `marker` - The marker to add
+    pub fn add_marker(&mut self, marker: Marker) {
+        let index = self
+            .markers
+            .partition_point(|existing| existing.position <= marker.position);
+        self.markers.insert(index, marker);
+    }
+
+    /// Removes the markers at a position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the markers
+    ///
+    /// # Returns
+    ///
+    /// The number of removed markers.
+    pub fn remove_markers_at(&mut self, position: TimePosition) -> usize {
+        let count = self.markers.len();
+        self.markers.retain(|marker| marker.position != position);
+        count - self.markers.len()
+    }
+
     /// Gets a reference to the edit history.
     #[must_use]
     pub fn history(&self) -> &history::EditHistory {
@@ -1900,6 +2310,203 @@ mod tests {
         assert_eq!(track.get_clips().len(), 2);
     }
 
+    #[test]
+    fn test_split_clip_at_several_positions() {
+        let mut timeline = Timeline::new();
+        let track_id = timeline.add_track(TrackKind::Video);
+        let clip_id = ClipId::new();
+        let clip = Clip::new(
+            clip_id,
+            AssetId::new(),
+            TimePosition::from_seconds(10.0),
+            Duration::from_seconds(10.0),
+            TimePosition::from_seconds(30.0),
+            TimePosition::from_seconds(40.0),
+        );
+        timeline.add_clip(track_id, clip).unwrap();
+
+        // ソース上の時刻をタイムライン上の位置に換算する
+        let clip = timeline
+            .get_track(track_id)
+            .unwrap()
+            .get_clip(clip_id)
+            .unwrap();
+        assert_eq!(
+            clip.timeline_position(TimePosition::from_seconds(32.0)),
+            Some(TimePosition::from_seconds(12.0))
+        );
+        assert_eq!(
+            clip.timeline_position(TimePosition::from_seconds(41.0)),
+            None
+        );
+
+        let positions = [18.0, 12.0, 25.0, 10.0].map(TimePosition::from_seconds);
+        let created = timeline
+            .split_clip_at(track_id, clip_id, &positions)
+            .unwrap();
+        assert_eq!(created.len(), 2);
+
+        let track = timeline.get_track(track_id).unwrap();
+        let starts: Vec<f64> = track
+            .get_clips()
+            .iter()
+            .map(|clip| clip.position().as_seconds())
+            .collect();
+        assert_eq!(starts, vec![10.0, 12.0, 18.0]);
+        let last = track.get_clip(created[1]).unwrap();
+        assert_eq!(last.source_start(), TimePosition::from_seconds(38.0));
+    }
+
+    #[test]
+    fn test_remove_source_ranges_is_one_undo_step() {
+        let mut timeline = Timeline::new();
+        let track_id = timeline.add_track(TrackKind::Audio);
+        let clip_id = ClipId::new();
+        let next_id = ClipId::new();
+        let asset_id = AssetId::new();
+        let seconds = TimePosition::from_seconds;
+        timeline
+            .add_clip(
+                track_id,
+                Clip::new(
+                    clip_id,
+                    asset_id,
+                    seconds(0.0),
+                    Duration::from_seconds(10.0),
+                    seconds(0.0),
+                    seconds(10.0),
+                ),
+            )
+            .unwrap();
+        timeline
+            .add_clip(
+                track_id,
+                Clip::new(
+                    next_id,
+                    asset_id,
+                    seconds(10.0),
+                    Duration::from_seconds(5.0),
+                    seconds(20.0),
+                    seconds(25.0),
+                ),
+            )
+            .unwrap();
+
+        let ranges = [(seconds(2.0), seconds(4.0)), (seconds(6.0), seconds(9.0))];
+        let parts = timeline
+            .remove_source_ranges_with_history(
+                track_id,
+                clip_id,
+                &ranges,
+                Duration::from_seconds(0.5),
+            )
+            .unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], clip_id);
+
+        let spans = |timeline: &Timeline| -> Vec<(f64, f64)> {
+            timeline
+                .get_track(track_id)
+                .unwrap()
+                .get_clips()
+                .iter()
+                .map(|clip| {
+                    (
+                        clip.position().as_seconds(),
+                        clip.source_start().as_seconds(),
+                    )
+                })
+                .collect()
+        };
+        // 2.5〜3.5 と 6.5〜8.5 が詰められ、後続のクリップは 3 秒前に移動する
+        assert_eq!(
+            spans(&timeline),
+            vec![(0.0, 0.0), (2.5, 3.5), (5.5, 8.5), (7.0, 20.0)]
+        );
+
+        timeline.undo().unwrap();
+        assert_eq!(spans(&timeline), vec![(0.0, 0.0), (10.0, 20.0)]);
+        assert!(!timeline.can_undo());
+
+        timeline.redo().unwrap();
+        assert_eq!(spans(&timeline).len(), 4);
+    }
+
+    #[test]
+    fn test_remove_source_ranges_ripples_all_tracks() {
+        let mut timeline = Timeline::new();
+        let audio_track = timeline.add_track(TrackKind::Audio);
+        let video_track = timeline.add_track(TrackKind::Video);
+        let clip_id = ClipId::new();
+        let asset_id = AssetId::new();
+        let seconds = TimePosition::from_seconds;
+        let add = |timeline: &mut Timeline, track_id, id, position: f64, length: f64| {
+            timeline
+                .add_clip(
+                    track_id,
+                    Clip::new(
+                        id,
+                        asset_id,
+                        seconds(position),
+                        Duration::from_seconds(length),
+                        seconds(0.0),
+                        seconds(length),
+                    ),
+                )
+                .unwrap();
+        };
+        add(&mut timeline, audio_track, clip_id, 0.0, 10.0);
+        add(&mut timeline, audio_track, ClipId::new(), 10.0, 5.0);
+        add(&mut timeline, video_track, ClipId::new(), 0.0, 4.0);
+        add(&mut timeline, video_track, ClipId::new(), 12.0, 5.0);
+
+        timeline
+            .remove_source_ranges_with_history(
+                audio_track,
+                clip_id,
+                &[(seconds(2.0), seconds(6.0))],
+                Duration::zero(),
+            )
+            .unwrap();
+
+        let positions = |timeline: &Timeline, track_id| -> Vec<f64> {
+            timeline
+                .get_track(track_id)
+                .unwrap()
+                .get_clips()
+                .iter()
+                .map(|clip| clip.position().as_seconds())
+                .collect()
+        };
+        // 映像トラックの後続クリップも 4 秒前に移動する
+        assert_eq!(positions(&timeline, audio_track), vec![0.0, 2.0, 6.0]);
+        assert_eq!(positions(&timeline, video_track), vec![0.0, 8.0]);
+
+        timeline.undo().unwrap();
+        assert_eq!(positions(&timeline, audio_track), vec![0.0, 10.0]);
+        assert_eq!(positions(&timeline, video_track), vec![0.0, 12.0]);
+    }
+
+    #[test]
+    fn test_markers_stay_sorted() {
+        let mut timeline = Timeline::new();
+        timeline.add_marker(Marker::new(TimePosition::from_seconds(5.0), "b"));
+        timeline.add_marker(Marker::new(TimePosition::from_seconds(2.0), "a"));
+        timeline.add_marker(Marker::new(TimePosition::from_seconds(5.0), "c"));
+
+        let labels: Vec<&str> = timeline
+            .markers()
+            .iter()
+            .map(|m| m.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["a", "b", "c"]);
+        assert_eq!(
+            timeline.remove_markers_at(TimePosition::from_seconds(5.0)),
+            2
+        );
+        assert_eq!(timeline.markers().len(), 1);
+    }
+
     #[test]
     fn test_split_clip() {
         let mut timeline = Timeline::new();
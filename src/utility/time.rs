@@ -5,8 +5,13 @@
 use std::fmt;
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
+use serde::{Deserialize, Serialize};
+
 /// A duration of time.
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// Serialized as a plain number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Duration {
     /// Duration in seconds.
     seconds: f64,
@@ -176,7 +181,10 @@ impl fmt::Display for Duration {
 }
 
 /// A position in time.
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// Serialized as a plain number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TimePosition {
     /// Position in seconds.
     seconds: f64,
@@ -367,6 +375,38 @@ impl TimePosition {
 
         Err(format!("Invalid time format: {}", s))
     }
+
+    /// Creates a time position from a string that may be a timecode.
+    ///
+    /// In addition to the formats accepted by [`TimePosition::parse`], this
+    /// accepts SMPTE-style timecodes ("01:23:45:12"), whose last field is a
+    /// frame number at the given frame rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The string to parse
+    /// * `fps` - The frame rate in frames per second
+    ///
+    /// # Returns
+    ///
+    /// Result containing the parsed TimePosition, or an error if parsing failed.
+    pub fn parse_timecode(s: &str, fps: f64) -> Result<Self, String> {
+        let Some((clock, frames)) = s
+            .rsplit_once(':')
+            .filter(|(clock, _)| clock.matches(':').count() == 2)
+        else {
+            return Self::parse(s);
+        };
+
+        let frames = frames
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid frames: {frames}"))?;
+        if f64::from(frames) >= fps {
+            return Err(format!("Frame {frames} is out of range at {fps} fps"));
+        }
+
+        Ok(Self::parse(clock)? + Duration::from_frames(f64::from(frames), fps))
+    }
 }
 
 impl Add<Duration> for TimePosition {
@@ -493,4 +533,15 @@ mod tests {
         let t2 = TimePosition::from_seconds(3.0);
         assert_eq!((t1 - t2).as_seconds(), 2.0);
     }
+
+    #[test]
+    fn test_time_position_parse_timecode() {
+        let t = TimePosition::parse_timecode("00:01:23:12", 24.0).unwrap();
+        assert_eq!(t.as_seconds(), 83.5);
+
+        let t = TimePosition::parse_timecode("00:01:23.25", 24.0).unwrap();
+        assert_eq!(t.as_seconds(), 83.25);
+
+        assert!(TimePosition::parse_timecode("00:01:23:30", 24.0).is_err());
+    }
 }